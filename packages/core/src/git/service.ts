@@ -1,4 +1,11 @@
-import simpleGit, { SimpleGit, StatusResult, BranchSummary, LogResult, MergeResult } from "simple-git";
+import simpleGit, {
+  SimpleGit,
+  SimpleGitProgressEvent,
+  StatusResult,
+  BranchSummary,
+  LogResult,
+  MergeResult,
+} from "simple-git";
 import type { GitFileStatus, GitStatusSummary, GitBranchInfo, GitCommitInfo } from "./types.js";
 import { STATUS_COLORS, GitStatus } from "./types.js";
 import { logger } from "../tui/utils/logger.js";
@@ -16,12 +23,16 @@ export class GitService {
   /**
    * Creates a new GitService instance
    * @param repoPath - Path to the git repository (defaults to current directory)
+   * @param onProgress - Called with progress of long-running commands (fetch, pull, push, clone)
    */
-  constructor(repoPath: string = process.cwd()) {
+  constructor(
+    repoPath: string = process.cwd(),
+    onProgress?: (event: SimpleGitProgressEvent) => void,
+  ) {
     logger.debug("[GitService] ========== INIT ==========");
     logger.debug("[GitService] Repo path:", repoPath);
     logger.debug("[GitService] CWD:", process.cwd());
-    this.git = simpleGit(repoPath);
+    this.git = simpleGit({ baseDir: repoPath, progress: onProgress });
     this.repoPath = repoPath;
     logger.debug("[GitService] Initialized with simple-git");
   }
@@ -380,6 +391,14 @@ export class GitService {
     await this.git.pull();
   }
 
+  /**
+   * Fetch all remotes, pruning deleted remote branches
+   * @returns Promise<void>
+   */
+  async fetch(): Promise<void> {
+    await this.git.fetch(["--all", "--prune"]);
+  }
+
   /**
    * Push to remote
    * Automatically sets upstream for new branches
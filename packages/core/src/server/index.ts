@@ -2,24 +2,118 @@ import { Elysia } from "elysia";
 import { cors } from "@elysiajs/cors";
 import { GitService } from "../git/index.js";
 import { logger } from "../tui/utils/logger.js";
+import { getVersion } from "../tui/utils/version.js";
+
+/**
+ * Events worth telling the desktop shell about, e.g. to show a notification
+ */
+export type ServerEvent =
+  | { type: "fetch-complete"; scheduled: boolean }
+  | { type: "push-failed"; message: string }
+  | { type: "merge-conflict"; branch: string; files: string[] }
+  | { type: "progress"; method: string; stage: string; progress: number }
+  | { type: "operation-finished"; kind: string };
 
 export interface ServerOptions {
   port?: number;
   hostname?: string;
   repoPath?: string;
+  onEvent?: (event: ServerEvent) => void;
+  /**
+   * Secret every request must carry in the X-OpenTUI-Secret header.
+   * Without one, requests are not checked.
+   */
+  secret?: string;
+  /**
+   * PEM certificate and key paths to serve HTTPS with
+   */
+  tls?: { cert: string; key: string };
+  pairing?: PairingOptions;
+  /**
+   * Most commits returned by one request, to keep large repositories responsive
+   */
+  historyLimit?: number;
+}
+
+/**
+ * LAN pairing for companion devices, handled by the desktop shell
+ */
+export interface PairingOptions {
+  /**
+   * Tokens of paired devices, accepted like the secret. Updated in place.
+   */
+  tokens: Set<string>;
+  /**
+   * Redeem a pairing code for a device token, or null if the code is wrong
+   */
+  pair: (code: string, name: string) => Promise<string | null>;
 }
 
 /**
  * Create the Elysia app with all routes
  */
-export function createApp(repoPath: string = process.cwd()) {
+export function createApp(
+  repoPath: string = process.cwd(),
+  onEvent: (event: ServerEvent) => void = () => {},
+  secret?: string,
+  pairing?: PairingOptions,
+  initialHistoryLimit?: number,
+) {
   logger.debug("[server] Creating app with repoPath:", repoPath);
-  const git = new GitService(repoPath);
+  historyLimit = initialHistoryLimit;
+  const git = new GitService(repoPath, ({ method, stage, progress }) =>
+    onEvent({ type: "progress", method, stage, progress }),
+  );
   logger.debug("[server] GitService created");
 
+  // Long-running operations in flight, reported to the desktop shell before quitting
+  const operations = new Map<number, { kind: string; startedAt: number }>();
+  let nextOperationId = 0;
+  const track = async <T>(kind: string, run: () => Promise<T>): Promise<T> => {
+    const id = nextOperationId++;
+    operations.set(id, { kind, startedAt: Date.now() });
+    try {
+      return await run();
+    } finally {
+      operations.delete(id);
+      onEvent({ type: "operation-finished", kind });
+    }
+  };
+
+  const push = async (run: () => Promise<unknown>) => {
+    try {
+      await track("push", run);
+    } catch (error) {
+      onEvent({ type: "push-failed", message: error instanceof Error ? error.message : String(error) });
+      throw error;
+    }
+  };
+
   const app = new Elysia()
     .use(cors())
+    // Web pages can't read the secret, so they can't drive git through this server
+    .onRequest(({ request, set }) => {
+      if (!secret || request.method === "OPTIONS") return;
+      // Devices without a token yet can only redeem a pairing code
+      if (pairing && new URL(request.url).pathname === "/pair") return;
+      const provided = request.headers.get("x-opentui-secret");
+      if (provided !== secret && !(provided && pairing?.tokens.has(provided))) {
+        set.status = 401;
+        return { error: "Missing or invalid secret" };
+      }
+    })
     .get("/health", () => ({ status: "ok" }))
+    .post("/pair", async ({ body, set }) => {
+      const { code, name } = (body ?? {}) as { code?: string; name?: string };
+      const token = pairing && code ? await pairing.pair(code, name || "Device") : null;
+      if (!token) {
+        set.status = 403;
+        return { error: "Invalid or expired pairing code" };
+      }
+      return { token };
+    })
+    .get("/version", () => ({ version: getVersion() }))
+    .get("/operations", () => ({ operations: [...operations.values()] }))
     
     // Status routes
     .get("/status", async () => {
@@ -50,8 +144,18 @@ export function createApp(repoPath: string = process.cwd()) {
       return { success: true };
     })
     .post("/branches/:name/merge", async ({ params }) => {
-      const result = await git.mergeBranch(params.name);
-      return { success: true, result };
+      try {
+        const result = await track("merge", () => git.mergeBranch(params.name));
+        return { success: true, result };
+      } catch (error) {
+        // simple-git attaches the merge summary to the error on conflicts
+        const conflicts = (error as { git?: { conflicts?: { file: string | null }[] } }).git?.conflicts;
+        if (conflicts?.length) {
+          const files = conflicts.map((c) => c.file).filter((f): f is string => f !== null);
+          onEvent({ type: "merge-conflict", branch: params.name, files });
+        }
+        throw error;
+      }
     })
     .delete("/branches/:name", async ({ params, query }) => {
       const force = query.force === "true";
@@ -71,7 +175,7 @@ export function createApp(repoPath: string = process.cwd()) {
     })
     .post("/tags/:name/push", async ({ params, body }) => {
       const remote = (body as { remote?: string })?.remote || "origin";
-      await git.pushTag(params.name, remote);
+      await push(() => git.pushTag(params.name, remote));
       return { success: true };
     })
     
@@ -107,7 +211,8 @@ export function createApp(repoPath: string = process.cwd()) {
     
     // Commit routes
     .get("/commits", async ({ query }) => {
-      const limit = query.limit ? parseInt(query.limit, 10) : 50;
+      const requested = query.limit ? parseInt(query.limit, 10) : 50;
+      const limit = historyLimit ? Math.min(requested, historyLimit) : requested;
       const commits = await git.getCommits(limit);
       return { commits };
     })
@@ -141,11 +246,16 @@ export function createApp(repoPath: string = process.cwd()) {
     
     // Remote routes
     .post("/remote/pull", async () => {
-      await git.pull();
+      await track("pull", () => git.pull());
+      return { success: true };
+    })
+    .post("/remote/fetch", async ({ query }) => {
+      await track("fetch", () => git.fetch());
+      onEvent({ type: "fetch-complete", scheduled: query.scheduled === "true" });
       return { success: true };
     })
     .post("/remote/push", async () => {
-      await git.push();
+      await push(() => git.push());
       return { success: true };
     })
     
@@ -156,7 +266,7 @@ export function createApp(repoPath: string = process.cwd()) {
       
       // Keep-alive with heartbeat
       while (true) {
-        await new Promise(resolve => setTimeout(resolve, 30000));
+        await new Promise(resolve => setTimeout(resolve, heartbeatMs));
         yield { data: JSON.stringify({ type: "heartbeat", timestamp: Date.now() }) };
       }
     });
@@ -164,6 +274,30 @@ export function createApp(repoPath: string = process.cwd()) {
   return app;
 }
 
+/**
+ * Most commits returned by one request, changed when the desktop shell reloads its config
+ */
+let historyLimit: number | undefined;
+
+/**
+ * Change the most commits returned by one request, or lift the limit
+ */
+export function setHistoryLimit(limit: number | undefined) {
+  historyLimit = limit;
+}
+
+/**
+ * Interval between SSE heartbeats, stretched while the desktop app saves power
+ */
+let heartbeatMs = 30000;
+
+/**
+ * Cut down periodic work while the desktop app saves power, or resume it
+ */
+export function setIdle(idle: boolean) {
+  heartbeatMs = idle ? 300000 : 30000;
+}
+
 // Export the app type for Eden client
 export type App = ReturnType<typeof createApp>;
 
@@ -171,7 +305,7 @@ export type App = ReturnType<typeof createApp>;
  * Start the server
  */
 export async function startServer(options: ServerOptions = {}) {
-  const { port = 5050, hostname = "localhost", repoPath = process.cwd() } = options;
+  const { port = 5050, hostname = "localhost", repoPath = process.cwd(), onEvent, secret, tls, pairing, historyLimit } = options;
   
   logger.debug("[server] ========== SERVER STARTUP ==========");
   logger.debug("[server] Port:", port);
@@ -181,11 +315,15 @@ export async function startServer(options: ServerOptions = {}) {
   logger.debug("[server] CWD:", process.cwd());
   logger.debug("[server] Process ID:", process.pid);
   
-  const app = createApp(repoPath);
+  const app = createApp(repoPath, onEvent, secret, pairing, historyLimit);
   
-  app.listen({ port, hostname });
+  app.listen({
+    port,
+    hostname,
+    tls: tls ? { cert: Bun.file(tls.cert), key: Bun.file(tls.key) } : undefined,
+  });
   
-  console.log(`Server running at http://${hostname}:${port}`);
+  console.log(`Server running at ${tls ? "https" : "http"}://${hostname}:${port}`);
   console.log(`Repository: ${repoPath}`);
   
   logger.debug("[server] Server started successfully");
@@ -5,23 +5,254 @@
  * This creates a standalone HTTP server that can be bundled as a binary
  * and spawned by the Tauri shell plugin.
  */
-import { startServer } from "./server/index.js";
+import { setHistoryLimit, setIdle, startServer } from "./server/index.js";
 import { parseArgs } from "node:util";
+import { setLogLevel, type LogLevel } from "./tui/utils/logger.js";
+import { getVersion } from "./tui/utils/version.js";
 
 const { values } = parseArgs({
   args: Bun.argv.slice(2),
   options: {
     port: { type: "string", short: "p" },
     repo: { type: "string", short: "r" },
+    // Main repository of a linked worktree passed as --repo
+    "main-repo": { type: "string" },
+    // The repository has no working tree; the app offers to add a worktree
+    bare: { type: "boolean" },
+    standby: { type: "boolean" },
+    // Address to listen on; anything but loopback must be allowed in the app's settings
+    host: { type: "string" },
+    // Most commits loaded at a time, set by the shell for large repositories
+    "history-limit": { type: "string" },
+    // Initial log level, changed later with set-log-level control messages
+    "log-level": { type: "string" },
   },
 });
 
+if (values["log-level"]) {
+  setLogLevel(values["log-level"] as LogLevel);
+}
+
 const port = values.port ? parseInt(values.port, 10) : 5050;
-const repoPath = values.repo || process.cwd();
-// Always bind to 127.0.0.1 for Tauri health checks
-const hostname = "127.0.0.1";
+const hostname = values.host || "127.0.0.1";
+const historyLimit = values["history-limit"] ? parseInt(values["history-limit"], 10) : undefined;
+// Required on every request when set, so other local processes and web pages
+// can't use the server
+const secret = process.env.OPENTUI_SERVER_SECRET || undefined;
+// Self-signed certificate from the shell when TLS is turned on
+const tls =
+  process.env.OPENTUI_TLS_CERT && process.env.OPENTUI_TLS_KEY
+    ? { cert: process.env.OPENTUI_TLS_CERT, key: process.env.OPENTUI_TLS_KEY }
+    : undefined;
+
+// Tokens of devices paired over the LAN, kept current by the shell
+const deviceTokens = new Set(
+  (process.env.OPENTUI_DEVICE_TOKENS || "").split(",").filter(Boolean),
+);
+
+let app: Awaited<ReturnType<typeof startServer>> | undefined;
+let boundRepo: string | undefined;
+const startedAt = Date.now();
+
+/**
+ * Version of the JSON-RPC protocol spoken with the desktop shell over stdio
+ */
+const PROTOCOL_VERSION = 1;
+
+/**
+ * Write a JSON-RPC response or notification for the desktop shell
+ */
+function sendRpc(message: Record<string, unknown>) {
+  console.log(`@rpc ${JSON.stringify({ jsonrpc: "2.0", ...message })}`);
+}
+
+/**
+ * Tell the shell the sidecar is up: listening, or standing by for a repository
+ */
+function notifyReady() {
+  sendRpc({
+    method: "ready",
+    params: {
+      pid: process.pid,
+      standby: !app,
+      protocol: PROTOCOL_VERSION,
+      // The address actually bound, checked by the shell
+      host: app?.server?.hostname ?? null,
+    },
+  });
+}
+
+async function bind(repoPath: string) {
+  console.log(`[sidecar] Starting server on port ${port}`);
+  console.log(`[sidecar] Repository path: ${repoPath}`);
+  if (values["main-repo"]) {
+    console.log(`[sidecar] Worktree of: ${values["main-repo"]}`);
+  }
+  if (values.bare) {
+    console.log("[sidecar] Bare repository: working tree views are unavailable");
+  }
+
+  // Events are reported to the desktop shell as prefixed JSON lines on stdout
+  app = await startServer({
+    port,
+    hostname,
+    repoPath,
+    secret,
+    tls,
+    historyLimit,
+    pairing: { tokens: deviceTokens, pair: requestPairing },
+    onEvent: (event) => console.log(`@event ${JSON.stringify(event)}`),
+  });
+  boundRepo = repoPath;
+  notifyReady();
+}
+
+// Standby sidecars are pre-warmed by the shell and bound to a repo later
+if (values.standby) {
+  console.log(`[sidecar] Standing by on port ${port}`);
+  notifyReady();
+} else {
+  await bind(values.repo || process.cwd());
+}
+
+/**
+ * Control messages from the desktop shell, one JSON object per line on stdin
+ */
+type ControlMessage =
+  | { type: "flush-caches" }
+  | { type: "set-log-level"; level: LogLevel }
+  | {
+      type: "reload-config";
+      logLevel: LogLevel;
+      historyLimit: number | null;
+      env: Record<string, string>;
+    }
+  | { type: "bind-repo"; repoPath: string }
+  | { type: "pair-result"; id: number; token: string | null }
+  | { type: "set-device-tokens"; tokens: string[] }
+  | { type: "set-idle"; idle: boolean };
+
+let nextPairRequest = 0;
+const pendingPairings = new Map<number, (token: string | null) => void>();
+
+/**
+ * Ask the desktop shell to redeem a pairing code for a device token.
+ * Resolves to null if the code is wrong or pairing is off.
+ */
+function requestPairing(code: string, name: string): Promise<string | null> {
+  const id = ++nextPairRequest;
+  return new Promise((resolve) => {
+    pendingPairings.set(id, resolve);
+    console.log(`@event ${JSON.stringify({ type: "pair-request", id, code, name })}`);
+  });
+}
+
+async function handleControlMessage(message: ControlMessage) {
+  switch (message.type) {
+    case "flush-caches":
+      Bun.gc(true);
+      console.log("[sidecar] Flushed caches");
+      break;
+    case "set-log-level":
+      setLogLevel(message.level);
+      console.log(`[sidecar] Log level set to ${message.level}`);
+      break;
+    case "reload-config":
+      setLogLevel(message.logLevel);
+      setHistoryLimit(message.historyLimit ?? undefined);
+      // Seen by the git commands the server runs from now on
+      Object.assign(process.env, message.env);
+      console.log("[sidecar] Reloaded config");
+      break;
+    case "bind-repo":
+      if (app) {
+        console.error("[sidecar] Already serving a repository");
+        break;
+      }
+      await bind(message.repoPath);
+      break;
+    case "pair-result": {
+      const resolve = pendingPairings.get(message.id);
+      pendingPairings.delete(message.id);
+      resolve?.(message.token);
+      break;
+    }
+    case "set-device-tokens":
+      deviceTokens.clear();
+      message.tokens.forEach((token) => deviceTokens.add(token));
+      console.log(`[sidecar] ${deviceTokens.size} paired device(s)`);
+      break;
+    case "set-idle":
+      setIdle(message.idle);
+      if (message.idle) Bun.gc(true);
+      console.log(`[sidecar] ${message.idle ? "Idling" : "Resumed from idle"}`);
+      break;
+  }
+}
+
+/**
+ * JSON-RPC request from the desktop shell
+ */
+interface RpcRequest {
+  jsonrpc: "2.0";
+  id: number;
+  method: string;
+  params?: unknown;
+}
+
+const rpcMethods: Record<string, () => Promise<unknown> | unknown> = {
+  ping: () => ({}),
+  status: () => ({
+    pid: process.pid,
+    repoPath: boundRepo ?? null,
+    standby: !app,
+    uptimeMs: Date.now() - startedAt,
+    rssBytes: process.memoryUsage().rss,
+  }),
+  version: () => ({ version: getVersion(), protocol: PROTOCOL_VERSION }),
+  operations: async () => {
+    if (!app) return { operations: [] };
+    const response = await app.handle(new Request("http://localhost/operations"));
+    return response.json();
+  },
+  shutdown: () => {
+    console.log("[sidecar] Shutting down on request");
+    // Answer first, then exit once the response is written
+    setTimeout(async () => {
+      await app?.stop();
+      process.exit(0);
+    }, 0);
+    return {};
+  },
+};
 
-console.log(`[sidecar] Starting server on port ${port}`);
-console.log(`[sidecar] Repository path: ${repoPath}`);
+async function handleRpcRequest(request: RpcRequest) {
+  const method = rpcMethods[request.method];
+  if (!method) {
+    sendRpc({ id: request.id, error: { code: -32601, message: `Unknown method: ${request.method}` } });
+    return;
+  }
+  try {
+    sendRpc({ id: request.id, result: await method() });
+  } catch (error) {
+    sendRpc({
+      id: request.id,
+      error: { code: -32000, message: error instanceof Error ? error.message : String(error) },
+    });
+  }
+}
 
-await startServer({ port, hostname, repoPath });
+for await (const line of console) {
+  if (!line.trim()) continue;
+  try {
+    const message = JSON.parse(line);
+    if (message.jsonrpc === "2.0") {
+      // Not awaited, so a slow query does not hold up control messages
+      void handleRpcRequest(message as RpcRequest);
+    } else {
+      await handleControlMessage(message as ControlMessage);
+    }
+  } catch (error) {
+    console.error("[sidecar] Invalid control message:", error);
+  }
+}
@@ -11,7 +11,7 @@
  * Default is "silent" in production, "info" otherwise
  */
 
-type LogLevel = "debug" | "info" | "warn" | "error" | "silent";
+export type LogLevel = "debug" | "info" | "warn" | "error" | "silent";
 
 const LOG_LEVELS: Record<LogLevel, number> = {
   debug: 0,
@@ -32,7 +32,16 @@ function getLogLevel(): LogLevel {
   return "silent";
 }
 
-const currentLevel = getLogLevel();
+let currentLevel = getLogLevel();
+
+/**
+ * Change the log level at runtime, e.g. on request from the desktop shell
+ */
+export function setLogLevel(level: LogLevel) {
+  if (level in LOG_LEVELS) {
+    currentLevel = level;
+  }
+}
 
 function shouldLog(level: LogLevel): boolean {
   return LOG_LEVELS[level] >= LOG_LEVELS[currentLevel];
@@ -0,0 +1,252 @@
+//! Accessibility preferences from the OS (reduced motion, high contrast and
+//! text scaling) for the UI to honor, injected as `window.__OPENTUI__.a11y`
+//! and re-read whenever the app is activated, and `announce` for reading out
+//! operation results to screen readers.
+
+use serde::Serialize;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+/// What the OS asks apps to do for accessibility
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct A11yPrefs {
+    reduced_motion: bool,
+    high_contrast: bool,
+    /// Multiplier for text sizes, 1.0 when the OS has none
+    font_scale: f64,
+}
+
+impl Default for A11yPrefs {
+    fn default() -> Self {
+        Self {
+            reduced_motion: false,
+            high_contrast: false,
+            font_scale: 1.0,
+        }
+    }
+}
+
+/// Last preferences the OS reported
+#[derive(Clone)]
+pub struct A11yState(Arc<Mutex<A11yPrefs>>);
+
+impl Default for A11yState {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(detect())))
+    }
+}
+
+/// Payload of the `a11y-announce` event, for the window's live region
+#[derive(Debug, Clone, Serialize)]
+struct AnnounceEvent {
+    text: String,
+}
+
+/// How `announce` delivered its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnounceRoute {
+    /// The window's ARIA live region, read by whatever screen reader is running
+    Webview,
+    /// VoiceOver directly, as it ignores live regions of apps in the background
+    VoiceOver,
+}
+
+/// Trimmed stdout of a successful command
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Value from `reg query`, e.g. `    Flags    REG_SZ    126`
+/// or `    TextScaleFactor    REG_DWORD    0x7d`
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn parse_reg_value(output: &str) -> Option<u32> {
+    let value = output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace().skip(1);
+        fields
+            .next()
+            .filter(|kind| kind.starts_with("REG_"))
+            .and(fields.next())
+    })?;
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// A usable text scale: positive, and within what any OS offers
+fn font_scale(scale: Option<f64>) -> f64 {
+    scale
+        .filter(|scale| scale.is_finite())
+        .map_or(1.0, |scale| scale.clamp(0.5, 3.0))
+}
+
+/// Ask the OS for its accessibility preferences
+fn detect() -> A11yPrefs {
+    #[cfg(target_os = "macos")]
+    let prefs = {
+        let enabled = |key: &str| {
+            output("defaults", &["read", "com.apple.universalaccess", key]).as_deref() == Some("1")
+        };
+        A11yPrefs {
+            reduced_motion: enabled("reduceMotion"),
+            high_contrast: enabled("increaseContrast"),
+            // macOS scales text per app, not system-wide
+            font_scale: 1.0,
+        }
+    };
+
+    #[cfg(target_os = "windows")]
+    let prefs = {
+        let value = |key: &str, name: &str| {
+            output("reg", &["query", key, "/v", name])
+                .as_deref()
+                .and_then(parse_reg_value)
+        };
+        A11yPrefs {
+            reduced_motion: value(r"HKCU\Control Panel\Desktop\WindowMetrics", "MinAnimate")
+                == Some(0),
+            // Bit 0 of the flags is HCF_HIGHCONTRASTON
+            high_contrast: value(r"HKCU\Control Panel\Accessibility\HighContrast", "Flags")
+                .is_some_and(|flags| flags & 1 != 0),
+            font_scale: font_scale(
+                value(r"HKCU\Software\Microsoft\Accessibility", "TextScaleFactor")
+                    .map(|percent| f64::from(percent) / 100.0),
+            ),
+        }
+    };
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let prefs = {
+        let get = |schema: &str, key: &str| output("gsettings", &["get", schema, key]);
+        A11yPrefs {
+            reduced_motion: get("org.gnome.desktop.interface", "enable-animations").as_deref()
+                == Some("false"),
+            high_contrast: get("org.gnome.desktop.a11y.interface", "high-contrast").as_deref()
+                == Some("true"),
+            font_scale: font_scale(
+                get("org.gnome.desktop.interface", "text-scaling-factor")
+                    .and_then(|scale| scale.parse().ok()),
+            ),
+        }
+    };
+
+    prefs
+}
+
+/// Current preferences, as injected into `window.__OPENTUI__.a11y`
+pub fn prefs(app: &AppHandle) -> A11yPrefs {
+    app.try_state::<A11yState>()
+        .and_then(|state| state.0.lock().ok().map(|prefs| *prefs))
+        .unwrap_or_else(detect)
+}
+
+/// Re-read the preferences in the background, emitting `a11y-changed` if the
+/// user changed them. There is no portable notification for these, so this
+/// runs whenever the app is activated, e.g. on return from system settings.
+pub fn refresh(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(detected) = tauri::async_runtime::spawn_blocking(detect).await else {
+            return;
+        };
+        let Some(state) = app.try_state::<A11yState>() else {
+            return;
+        };
+        let Ok(mut current) = state.0.lock() else {
+            return;
+        };
+        if *current == detected {
+            return;
+        }
+        *current = detected;
+        drop(current);
+
+        log_info!("OS accessibility preferences changed: {:?}", detected);
+        let _ = app.emit("a11y-changed", detected);
+    });
+}
+
+/// Whether VoiceOver is running
+#[cfg(target_os = "macos")]
+fn voice_over_running() -> bool {
+    Command::new("pgrep")
+        .args(["-x", "VoiceOver"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Have VoiceOver speak the text. Needs "Allow VoiceOver to be controlled with
+/// AppleScript", so failures fall back to the live region.
+#[cfg(target_os = "macos")]
+fn voice_over_output(text: &str) -> bool {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"");
+    Command::new("osascript")
+        .args([
+            "-e",
+            &format!("tell application \"VoiceOver\" to output \"{}\"", escaped),
+        ])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Get the OS accessibility preferences the UI should honor
+#[tauri::command]
+pub fn get_a11y(app: AppHandle) -> A11yPrefs {
+    prefs(&app)
+}
+
+/// Read out an operation result such as "Push complete" to the user's screen
+/// reader. The window's live region is used while it has focus; otherwise,
+/// on macOS, VoiceOver is told directly.
+#[tauri::command]
+pub async fn announce(window: WebviewWindow, text: String) -> AnnounceRoute {
+    #[cfg(target_os = "macos")]
+    if !window.is_focused().unwrap_or(false) {
+        let spoken = tauri::async_runtime::spawn_blocking({
+            let text = text.clone();
+            move || voice_over_running() && voice_over_output(&text)
+        })
+        .await
+        .unwrap_or(false);
+        if spoken {
+            return AnnounceRoute::VoiceOver;
+        }
+    }
+
+    let _ = window.emit_to(window.label(), "a11y-announce", AnnounceEvent { text });
+    AnnounceRoute::Webview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_registry_values() {
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Accessibility\r\n    TextScaleFactor    REG_DWORD    0x7d\r\n\r\n";
+        assert_eq!(parse_reg_value(output), Some(125));
+        let output = "HKEY_CURRENT_USER\\Control Panel\\Accessibility\\HighContrast\n    Flags    REG_SZ    126\n";
+        assert_eq!(parse_reg_value(output), Some(126));
+        assert_eq!(
+            parse_reg_value(
+                "ERROR: The system was unable to find the specified registry key or value."
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_font_scales_sane() {
+        assert_eq!(font_scale(Some(1.25)), 1.25);
+        assert_eq!(font_scale(Some(0.0)), 0.5);
+        assert_eq!(font_scale(Some(f64::NAN)), 1.0);
+        assert_eq!(font_scale(None), 1.0);
+    }
+}
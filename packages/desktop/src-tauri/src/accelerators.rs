@@ -0,0 +1,257 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::menu::{MenuItem, MenuItemBuilder};
+use tauri::{AppHandle, Wry};
+use tauri_plugin_global_shortcut::Shortcut;
+
+use crate::hotkey;
+use crate::i18n;
+use crate::palette;
+use crate::settings::{self, Settings};
+
+/// A shortcut handled natively through the menu bar, so it works even when
+/// an input has captured focus or the webview is unresponsive
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Accelerator {
+    pub id: &'static str,
+    /// English label; the menu shows the `menu.<id>` translation
+    pub label: &'static str,
+    pub accelerator: &'static str,
+}
+
+/// All native accelerators, keyed by menu item id
+pub const ACCELERATORS: &[Accelerator] = &[
+    Accelerator {
+        id: "new-window",
+        label: "New Window",
+        accelerator: "CmdOrCtrl+N",
+    },
+    Accelerator {
+        id: "open-repo",
+        label: "Open Repository…",
+        accelerator: "CmdOrCtrl+O",
+    },
+    Accelerator {
+        id: "close-window",
+        label: "Close Window",
+        accelerator: "CmdOrCtrl+W",
+    },
+    Accelerator {
+        id: "settings",
+        label: "Settings…",
+        accelerator: "CmdOrCtrl+,",
+    },
+    Accelerator {
+        id: "refresh",
+        label: "Refresh",
+        accelerator: "F5",
+    },
+    Accelerator {
+        id: "reload",
+        label: "Reload",
+        accelerator: "CmdOrCtrl+R",
+    },
+    Accelerator {
+        id: "zoom-in",
+        label: "Zoom In",
+        accelerator: "CmdOrCtrl+=",
+    },
+    Accelerator {
+        id: "zoom-out",
+        label: "Zoom Out",
+        accelerator: "CmdOrCtrl+-",
+    },
+    Accelerator {
+        id: "reset-zoom",
+        label: "Actual Size",
+        accelerator: "CmdOrCtrl+0",
+    },
+    Accelerator {
+        id: "toggle-logs",
+        label: "Toggle Logs",
+        accelerator: "CmdOrCtrl+Shift+L",
+    },
+];
+
+/// A user keybinding that is shadowed by a native command or global shortcut
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    /// The user keybinding's action
+    action: String,
+    accelerator: String,
+    /// The native accelerator or global shortcut that wins
+    native_id: &'static str,
+}
+
+/// Build the menu item for a registered accelerator
+pub fn menu_item(app: &AppHandle, id: &str) -> tauri::Result<MenuItem<Wry>> {
+    let entry = ACCELERATORS
+        .iter()
+        .find(|entry| entry.id == id)
+        .unwrap_or_else(|| panic!("accelerator '{}' is not registered", id));
+
+    let label = i18n::t(app, &format!("menu.{}", entry.id));
+    let builder = MenuItemBuilder::with_id(entry.id, label);
+    // The user may have rebound or unbound it
+    match palette::keybinding(&settings::current(app), entry.id) {
+        Some(accelerator) => builder.accelerator(accelerator),
+        None => builder,
+    }
+    .build(app)
+}
+
+/// Ids of the global shortcuts, which the OS delivers even when the app is
+/// in the background
+const GLOBAL_SHORTCUTS: [&str; 2] = ["toggle-window", "switcher"];
+
+/// Id of a native command or global shortcut
+fn native_id(id: &str) -> Option<&'static str> {
+    palette::COMMANDS
+        .iter()
+        .map(|command| command.id)
+        .chain(GLOBAL_SHORTCUTS)
+        .find(|native| *native == id)
+}
+
+/// Every binding in effect with its keys: native commands, the frontend's
+/// keybindings and the global shortcuts. Unparsable ones are left out.
+fn bindings(settings: &Settings) -> Vec<(String, Shortcut)> {
+    let native = palette::COMMANDS.iter().filter_map(|command| {
+        Some((
+            command.id.to_string(),
+            palette::keybinding(settings, command.id)?,
+        ))
+    });
+    let frontend = settings
+        .keybindings
+        .iter()
+        .filter(|(id, accelerator)| native_id(id).is_none() && !accelerator.is_empty())
+        .map(|(id, accelerator)| (id.clone(), accelerator.clone()));
+    let global = GLOBAL_SHORTCUTS
+        .into_iter()
+        .zip([
+            &settings.toggle_window_shortcut,
+            &settings.switcher_shortcut,
+        ])
+        .filter_map(|(id, accelerator)| Some((id.to_string(), accelerator.clone()?)));
+
+    native
+        .chain(frontend)
+        .chain(global)
+        .filter_map(|(id, accelerator)| Some((id, hotkey::parse(&accelerator).ok()?)))
+        .collect()
+}
+
+/// For every binding sharing its keys with another, the ids of the others
+pub fn shared_keys(settings: &Settings) -> HashMap<String, Vec<String>> {
+    let bindings = bindings(settings);
+    let mut shared: HashMap<String, Vec<String>> = HashMap::new();
+    for (id, shortcut) in &bindings {
+        let mut others: Vec<String> = bindings
+            .iter()
+            .filter(|(other, other_shortcut)| other != id && other_shortcut == shortcut)
+            .map(|(other, _)| other.clone())
+            .collect();
+        if !others.is_empty() {
+            others.sort();
+            shared.insert(id.clone(), others);
+        }
+    }
+    shared
+}
+
+/// Find user keybindings that collide with native commands or the global shortcuts
+pub fn conflicts(settings: &Settings) -> Vec<Conflict> {
+    let shared = shared_keys(settings);
+    let mut conflicts: Vec<Conflict> = settings
+        .keybindings
+        .iter()
+        .filter_map(|(action, accelerator)| {
+            // Rebinding a native command replaces it rather than colliding
+            let native = shared
+                .get(action)?
+                .iter()
+                .find_map(|other| native_id(other))?;
+            Some(Conflict {
+                action: action.clone(),
+                accelerator: accelerator.clone(),
+                native_id: native,
+            })
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.action.cmp(&b.action));
+    conflicts
+}
+
+/// Log keybinding conflicts so they show up in the log viewer
+pub fn report_conflicts(settings: &Settings) {
+    for conflict in conflicts(settings) {
+        log_error!(
+            "Keybinding '{}' ({}) is shadowed by native shortcut '{}'",
+            conflict.action,
+            conflict.accelerator,
+            conflict.native_id
+        );
+    }
+}
+
+/// List the native accelerators
+#[tauri::command]
+pub fn get_accelerators() -> Vec<Accelerator> {
+    ACCELERATORS.to_vec()
+}
+
+/// List user keybindings shadowed by native commands or global shortcuts
+#[tauri::command]
+pub fn get_keybinding_conflicts(app: AppHandle) -> Vec<Conflict> {
+    conflicts(&settings::current(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(settings: &mut Settings, action: &str, accelerator: &str) {
+        settings
+            .keybindings
+            .insert(action.to_string(), accelerator.to_string());
+    }
+
+    #[test]
+    fn finds_bindings_sharing_keys() {
+        let mut settings = Settings::default();
+        bind(&mut settings, "stage-all", "CmdOrCtrl+R");
+        let shared = shared_keys(&settings);
+        assert_eq!(shared["reload"], ["stage-all"]);
+        assert_eq!(shared["stage-all"], ["reload"]);
+        assert!(!shared.contains_key("refresh"));
+    }
+
+    #[test]
+    fn finds_keybindings_shadowed_by_natives() {
+        let mut settings = Settings::default();
+        settings.toggle_window_shortcut = Some("CmdOrCtrl+Shift+Space".to_string());
+        settings.switcher_shortcut = Some("CmdOrCtrl+Shift+P".to_string());
+        bind(&mut settings, "stage-all", "CmdOrCtrl+R");
+        bind(&mut settings, "open-palette", "CmdOrCtrl+Shift+P");
+        bind(&mut settings, "hide", "CmdOrCtrl+Shift+Space");
+        // Rebinding a native accelerator moves it instead of colliding
+        bind(&mut settings, "zoom-in", "CmdOrCtrl+=");
+        bind(&mut settings, "commit", "CmdOrCtrl+Enter");
+
+        let found: Vec<_> = conflicts(&settings)
+            .into_iter()
+            .map(|conflict| (conflict.action, conflict.native_id))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("hide".to_string(), "toggle-window"),
+                ("open-palette".to_string(), "switcher"),
+                ("stage-all".to_string(), "reload"),
+            ]
+        );
+    }
+}
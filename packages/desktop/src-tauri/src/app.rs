@@ -0,0 +1,629 @@
+//! Assembling the desktop app. `run` builds it with `AppConfig::default()`;
+//! integration tests can build it without the parts that need a real desktop
+//! session, run it against `tauri::test::mock_context` instead of
+//! `generate_context!`, and start the sidecar themselves.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager, RunEvent, Window, WindowEvent, Wry};
+
+use crate::a11y::{self, A11yState};
+use crate::auto_fetch;
+use crate::background::{self, BackgroundState};
+use crate::broadcast::{self, WindowRegistry};
+use crate::cleanup::{self, CleanupRegistry};
+use crate::close_stash::{self, CloseStashState};
+use crate::config::get_sidecar_port;
+use crate::difftool::ToolFolders;
+use crate::display::{self, DisplayState};
+use crate::ipc::IpcState;
+use crate::large_repo::LargeRepoState;
+use crate::lfs::LfsWarnings;
+use crate::logs::{self, LogState};
+use crate::loopback::ServerSecret;
+use crate::memory::{self, MemoryState};
+use crate::notifications::{self, NotificationState};
+use crate::pairing::PairingState;
+use crate::patch_files::{self, PendingPatches};
+use crate::power::{self, PowerSaver};
+use crate::pty::{self, PtyState};
+use crate::safe_mode::{self, SafeMode};
+use crate::services::{self, ServiceState};
+use crate::settings::{self, CloseBehavior, Settings, SettingsState};
+use crate::shutdown::{self, ShutdownState};
+use crate::sidecar::{is_server_running, ServerState, Sidecar};
+use crate::signing::SigningState;
+use crate::spawn::{self, SpawnState};
+use crate::standby::{self, StandbyPool};
+use crate::startup::{self, StartupError, StartupState};
+use crate::startup_metrics::{self, StartupPhase, StartupTimings};
+use crate::telemetry::{self, Telemetry};
+use crate::theme::{self, ThemeState};
+use crate::timeout::CancelRegistry;
+use crate::tls::{self, TlsState};
+use crate::version::VersionState;
+use crate::watcher::{self, RepoWatchers};
+use crate::window::{self, create_main_window};
+use crate::window_title::{self, TitleState};
+use crate::{
+    accelerators, apply, archive, askpass, badge, clipboard, clone, compact, control,
+    credential_helper, credentials, dev_frontend, diagnostics, difftool, doctor, editor, export,
+    headless, hooks, hotkey, i18n, large_repo, materialize, menu, migrations, native, navigation,
+    objects, onboarding, overview, palette, patch_drag, proxy, recent, remotes, repo_config,
+    repo_state, sessions, shell_integration, sidecar_binary, submodules, switcher, terminal,
+    titlebar, tray, updater, version, worktrees, QuitState,
+};
+
+/// What `build_app` sets up
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Hand later launches over to this instance. Off for tests, which may run
+    /// several apps at once.
+    pub single_instance: bool,
+    /// Install the updater plugin
+    pub updater: bool,
+    /// Register the global shortcut and install the menu
+    pub desktop_integration: bool,
+    /// Run the memory, badge and power monitors (never in safe mode)
+    pub monitors: bool,
+    /// Start the sidecar and open the first window once set up. Off, the app
+    /// only manages its state, leaving the lifecycle to the caller.
+    pub auto_start: bool,
+    /// Repository to start with instead of the one from the command line
+    pub repo_path: Option<String>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            single_instance: true,
+            updater: true,
+            desktop_integration: true,
+            monitors: true,
+            auto_start: true,
+            repo_path: None,
+        }
+    }
+}
+
+/// A later launch passed its arguments to this instance: open the patch or
+/// repository it names, or just bring a window forward
+fn handle_second_launch(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    if let Some(patch) = patch_files::patch_arg(&argv, Path::new(&cwd)) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move { patch_files::open(&app, patch).await });
+        return;
+    }
+    let Some(repo_path) = crate::config::repo_arg(&argv, Path::new(&cwd)) else {
+        crate::window::show_main_window(app);
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::window::open_repo_in_window(&app, &repo_path).await {
+            log_error!("Failed to open {}: {}", repo_path, e);
+        }
+    });
+}
+
+fn handle_window_event(window: &Window, event: &WindowEvent) {
+    match event {
+        // Route window close through the quit confirmation
+        WindowEvent::CloseRequested { api, .. } => {
+            let app = window.app_handle().clone();
+            if let Some(webview) = app.get_webview_window(window.label()) {
+                window::remember_size(&webview);
+            }
+            if crate::is_quit_confirmed(&app) {
+                return;
+            }
+            if close_stash::intercept(&app, window) {
+                api.prevent_close();
+                return;
+            }
+
+            // Only closing the last window applies the on-close behavior
+            // The switcher stays around hidden, so it doesn't count
+            if app
+                .webview_windows()
+                .keys()
+                .filter(|label| *label != switcher::WINDOW_LABEL)
+                .count()
+                > 1
+            {
+                return;
+            }
+
+            match settings::current(&app).on_close {
+                CloseBehavior::Quit => {
+                    api.prevent_close();
+                    tauri::async_runtime::spawn(crate::request_quit(app));
+                }
+                // Keep the window alive but hidden for instant reopen
+                CloseBehavior::Tray => {
+                    api.prevent_close();
+                    let _ = window.hide();
+                    tray::ensure(&app);
+                }
+                // Let the window close, keeping only the sidecar and fetches
+                CloseBehavior::Background => {
+                    tray::ensure(&app);
+                    background::start(&app);
+                }
+            }
+        }
+        WindowEvent::ThemeChanged(theme) => {
+            theme::handle_os_change(window.app_handle(), *theme);
+            // Switching to high contrast changes the theme too
+            a11y::refresh(window.app_handle());
+        }
+        // Re-apply per-monitor zoom when the window lands on another display
+        WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged { .. } => {
+            if let Some(webview) = window.app_handle().get_webview_window(window.label()) {
+                display::update(&webview);
+            }
+        }
+        // The switcher is dismissed by clicking elsewhere
+        WindowEvent::Focused(false) if window.label() == switcher::WINDOW_LABEL => {
+            let _ = window.hide();
+        }
+        // Treat activation shortly after a notification as clicking it
+        WindowEvent::Focused(true) => {
+            power::wake(window.app_handle());
+            a11y::refresh(window.app_handle());
+            notifications::focus_pending(window.app_handle());
+        }
+        WindowEvent::Destroyed => {
+            let app = window.app_handle();
+            display::forget(app, window.label());
+            pty::close_window(app, window.label());
+            let repo_path = app.state::<WindowRegistry>().repo_for(window.label());
+            broadcast::unregister(app, window.label());
+            if let Some(repo_path) = repo_path {
+                watcher::release(app, &repo_path);
+                crate::sidecar::release_sidecar_if_unused(app, &repo_path);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Manage the state every part of the app expects to find
+fn manage_state(app: &AppHandle) {
+    app.manage(LogState::default());
+    logs::start(app);
+    app.manage(Telemetry::default());
+    telemetry::install_panic_hook(app);
+    app.manage(QuitState::default());
+    app.manage(ServerState::default());
+    app.manage(IpcState::default());
+    app.manage(PairingState::default());
+    app.manage(WindowRegistry::default());
+    app.manage(DisplayState::default());
+    app.manage(ThemeState::default());
+    app.manage(A11yState::default());
+    app.manage(VersionState::default());
+    app.manage(BackgroundState::default());
+    app.manage(NotificationState::default());
+    app.manage(StandbyPool::default());
+    app.manage(PendingPatches::default());
+    app.manage(LfsWarnings::default());
+    app.manage(LargeRepoState::default());
+    app.manage(CloseStashState::default());
+    app.manage(ToolFolders::default());
+    app.manage(ServiceState::default());
+    app.manage(TitleState::default());
+    app.manage(CancelRegistry::default());
+    app.manage(CleanupRegistry::default());
+    app.manage(PtyState::default());
+    app.manage(RepoWatchers::default());
+    app.manage(SigningState::default());
+    app.manage(SpawnState::default());
+    app.manage(StartupState::default());
+    app.manage(ShutdownState::default());
+    app.manage(StartupTimings::default());
+    app.manage(PowerSaver::default());
+    app.manage(MemoryState::default());
+}
+
+/// Start the server for the launch repository and open the main window
+async fn start_primary(app: &AppHandle, repo_path: &str) -> Result<(), StartupError> {
+    let started = Instant::now();
+    let port = get_sidecar_port(app, repo_path).map_err(StartupError::Port)?;
+    startup_metrics::record(app, StartupPhase::PortSelection, started);
+
+    log_info!("Starting server on port {}", port);
+    log_info!("Repository path: {}", repo_path);
+
+    // Check if server is already running (for development)
+    let should_spawn = !is_server_running(port).await;
+
+    let child = if should_spawn {
+        if !sidecar_binary::check(app, false).await {
+            return Err(StartupError::MissingBinary);
+        }
+        doctor::check(app, repo_path).await;
+        large_repo::prescan(app, repo_path, false).await;
+        let child = spawn::start(app, port, repo_path).await?;
+        Some(child)
+    } else {
+        log_info!("Server already running on port {}", port);
+        None
+    };
+
+    // Store the child process for cleanup
+    let sidecar = Sidecar {
+        child: Arc::new(Mutex::new(child)),
+        port,
+        repo_path: repo_path.to_string(),
+        restarts: Arc::default(),
+    };
+    app.state::<ServerState>().insert(sidecar.clone());
+    recent::add(app, repo_path);
+
+    // Refuse to continue against a server this app can't talk to
+    let started = Instant::now();
+    version::handshake(app, &sidecar)
+        .await
+        .map_err(StartupError::Incompatible)?;
+    startup_metrics::record(app, StartupPhase::Handshake, started);
+
+    let started = Instant::now();
+    let to_tray = settings::current(app).window.start_minimized_to_tray;
+    create_main_window(app, !to_tray).map_err(StartupError::Window)?;
+    if to_tray {
+        tray::ensure(app);
+    }
+    startup_metrics::record(app, StartupPhase::WindowCreation, started);
+    log_info!("Window created successfully");
+    Ok(())
+}
+
+/// Start the server for the launch repository (or the ones from before an
+/// update restart) and open their windows
+async fn start(app: AppHandle, repo_path: Option<String>) {
+    if headless::requested() {
+        headless::run(&app).await;
+        return;
+    }
+    let startup_patch = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| patch_files::patch_arg(&std::env::args().collect::<Vec<_>>(), &cwd));
+
+    // Reopen the repos from before an update restart, otherwise resolve
+    // the repo from env var, arguments, .repo-path file, default repo or current dir
+    let mut restored_repos = updater::take_restart_repos(&app);
+    let restarting = !restored_repos.is_empty();
+    // A first run without a repository to open starts with onboarding
+    let first_run = !restarting && repo_path.is_none() && migrations::needs_onboarding(&app);
+    let repo_path = if restored_repos.is_empty() {
+        repo_path.unwrap_or_else(|| crate::config::get_repo_path(&app))
+    } else {
+        restored_repos.remove(0)
+    };
+
+    // Booting the server against a folder that is not a repository
+    // leaves a broken session, so let the user pick or create one
+    let location = (!first_run)
+        .then(|| worktrees::locate(&repo_path))
+        .flatten();
+    let Some(location) = location else {
+        if first_run {
+            log_info!("First run, showing onboarding");
+        } else {
+            log_info!("{} is not a git repository, showing onboarding", repo_path);
+        }
+        let started = Instant::now();
+        if let Err(e) = onboarding::show(&app, &repo_path) {
+            startup::fail(&app, StartupError::Window(e)).await;
+            return;
+        }
+        startup_metrics::record(&app, StartupPhase::WindowCreation, started);
+        safe_mode::mark_started(&app);
+        startup_metrics::finish(&app);
+        if !app.state::<SafeMode>().0 {
+            standby::fill(&app);
+            sessions::restore_last(&app).await;
+        }
+        if let Some(patch) = startup_patch {
+            patch_files::open(&app, patch).await;
+        }
+        return;
+    };
+    if let Err(error) = start_primary(&app, &location.workdir).await {
+        startup::fail(&app, error).await;
+        return;
+    }
+    safe_mode::mark_started(&app);
+    startup_metrics::finish(&app);
+    telemetry::record_startup(&app);
+
+    // Warm the standby pool once the main window is up
+    if !app.state::<SafeMode>().0 {
+        standby::fill(&app);
+    }
+
+    for repo_path in restored_repos {
+        if let Err(e) = crate::window::open_repo_in_window(&app, &repo_path).await {
+            log_error!("Failed to reopen {}: {}", repo_path, e);
+        }
+    }
+    if !restarting && !app.state::<SafeMode>().0 {
+        sessions::restore_last(&app).await;
+    }
+
+    if let Some(patch) = startup_patch {
+        patch_files::open(&app, patch).await;
+    }
+}
+
+/// The app with its plugins, commands, event handlers and setup, ready to be
+/// built with a context
+pub fn build_app(config: AppConfig) -> tauri::Builder<Wry> {
+    let mut builder = tauri::Builder::default();
+    if config.single_instance {
+        // Must be registered first so a second launch exits before doing any work
+        builder = builder.plugin(tauri_plugin_single_instance::init(handle_second_launch));
+    }
+    builder
+        .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .invoke_handler(tauri::generate_handler![
+            crate::sidecar::kill_sidecar,
+            crate::config::set_default_repo,
+            crate::config::get_default_repo,
+            crate::config::clear_default_repo,
+            logs::get_logs_v2,
+            logs::get_log_stats,
+            logs::clear_logs,
+            logs::open_log_window,
+            crate::request_quit,
+            sessions::save_session,
+            sessions::load_session,
+            sessions::list_sessions,
+            settings::get_settings,
+            settings::set_settings,
+            settings::open_settings,
+            memory::get_memory_pressure,
+            safe_mode::is_safe_mode,
+            updater::check_for_updates,
+            updater::install_update,
+            broadcast::broadcast_repo_event,
+            broadcast::is_repo_leader,
+            version::get_versions,
+            version::show_about,
+            display::get_monitors,
+            display::move_to_monitor,
+            doctor::run_doctor,
+            display::zoom_in,
+            display::zoom_out,
+            display::set_zoom,
+            compact::toggle_compact_mode,
+            export::export_pdf,
+            clipboard::copy_text,
+            clipboard::copy_commit_sha,
+            clipboard::copy_file_path,
+            clipboard::copy_patch,
+            clipboard::paste_patch,
+            patch_drag::begin_patch_drag,
+            patch_files::take_pending_patch,
+            apply::apply_patch,
+            archive::export_archive,
+            lfs::get_lfs_status,
+            hooks::list_hooks,
+            i18n::get_locale,
+            submodules::list_submodules,
+            submodules::open_submodule,
+            telemetry::record_feature_usage,
+            telemetry::export_telemetry,
+            diagnostics::create_diagnostics_bundle,
+            accelerators::get_accelerators,
+            accelerators::get_keybinding_conflicts,
+            notifications::notify,
+            badge::set_badge,
+            shell_integration::install_shell_integration,
+            shell_integration::uninstall_shell_integration,
+            shell_integration::is_shell_integration_installed,
+            timeout::cancel_command,
+            objects::inspect_object,
+            objects::stream_blob,
+            editor::open_in_editor,
+            difftool::open_in_difftool,
+            difftool::open_in_mergetool,
+            materialize::materialize_blob,
+            terminal::open_in_terminal,
+            repo_state::get_repo_state,
+            overview::get_multi_repo_overview,
+            pty::pty_spawn,
+            pty::pty_write,
+            pty::pty_resize,
+            pty::pty_kill,
+            native::native_status,
+            native::native_branches,
+            native::native_head,
+            worktrees::list_worktrees,
+            worktrees::add_worktree,
+            onboarding::validate_repo_path,
+            onboarding::init_repo,
+            onboarding::complete_onboarding,
+            clone::clone_repo,
+            credentials::store_credential,
+            credential_helper::install_credential_helper,
+            credentials::get_credential,
+            credentials::delete_credential,
+            proxy::get_proxy_config,
+            theme::get_theme,
+            a11y::get_a11y,
+            a11y::announce,
+            titlebar::minimize,
+            titlebar::toggle_maximize,
+            titlebar::close_window,
+            titlebar::start_drag,
+            titlebar::titlebar_double_click,
+            crate::sidecar::restart_sidecar,
+            crate::sidecar::get_server_info,
+            startup::get_startup_error,
+            startup_metrics::get_startup_metrics,
+            tls::get_tls_info,
+            tls::rotate_tls_certificate,
+            pairing::start_pairing,
+            pairing::list_paired_devices,
+            pairing::revoke_device,
+            control::send_sidecar_control,
+            crate::window::open_repo,
+            crate::window::open_repo_dialog,
+            recent::get_recent_repos,
+            remotes::check_remotes,
+            switcher::get_switcher_repos,
+            switcher::activate_switcher_repo,
+            switcher::close_switcher,
+            switcher::set_repo_bookmarked,
+            palette::list_commands,
+            palette::invoke_command,
+            palette::set_keybinding,
+            repo_config::get_effective_config,
+            repo_config::trust_repo_config,
+            large_repo::get_repo_size,
+            services::list_services,
+            services::restart_service,
+            navigation::open_external,
+            logs::set_sidecar_log_level,
+            window_title::set_title_info
+        ])
+        .on_menu_event(|app, event| menu::handle(app, event.id().as_ref()))
+        .on_window_event(handle_window_event)
+        .setup(move |app| {
+            let app_handle = app.handle().clone();
+
+            manage_state(&app_handle);
+            // Without a secret the sidecars would serve any local process
+            let secret_error = match ServerSecret::generate() {
+                Ok(secret) => {
+                    app_handle.manage(secret);
+                    None
+                }
+                Err(e) => Some(StartupError::Secret(e)),
+            };
+            startup_metrics::record(
+                &app_handle,
+                StartupPhase::Setup,
+                startup_metrics::launched_at(),
+            );
+            if let Err(e) = askpass::start(&app_handle) {
+                log_error!("{}", e);
+            }
+            if config.updater {
+                updater::register(&app_handle)?;
+            }
+            dev_frontend::allow_ipc(&app_handle);
+
+            let safe_mode = safe_mode::detect(&app_handle);
+            app_handle.manage(safe_mode);
+
+            // Safe mode ignores the settings file and skips background monitors
+            if !safe_mode.0 {
+                migrations::prepare(&app_handle);
+            }
+            let settings = if safe_mode.0 {
+                Settings::default()
+            } else {
+                settings::load(&app_handle)
+            };
+            accelerators::report_conflicts(&settings);
+            app_handle.manage(TlsState::new(settings.tls));
+            // While no sidecar serves the certificate, it can still be replaced
+            tls::prepare(&app_handle);
+            app_handle.manage(SettingsState(Arc::new(Mutex::new(settings))));
+            logs::refresh(&app_handle);
+
+            if config.desktop_integration {
+                if let Err(e) = hotkey::register(&app_handle) {
+                    log_error!("{}", e);
+                }
+            }
+
+            if config.monitors && !safe_mode.0 {
+                memory::start_monitor(app_handle.clone());
+                memory::start_sidecar_monitor(app_handle.clone());
+                badge::start_auto(app_handle.clone());
+                power::start_monitor(app_handle.clone());
+                auto_fetch::start(app_handle.clone());
+            }
+
+            if let Some(error) = secret_error {
+                let app = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    if headless::requested() {
+                        startup::exit(&app, error);
+                    } else {
+                        startup::fail(&app, error).await;
+                    }
+                });
+            } else if config.auto_start {
+                tauri::async_runtime::spawn(start(app_handle.clone(), config.repo_path.clone()));
+                // Helper services are extensions, which safe mode is for ruling out
+                if !safe_mode.0 {
+                    services::start(&app_handle);
+                }
+            }
+
+            if config.desktop_integration {
+                if let Err(e) = menu::install(&app_handle) {
+                    log_error!("Failed to create menu: {}", e);
+                }
+            }
+
+            Ok(())
+        })
+}
+
+/// React to app-wide events: confirm quitting, reopen from the dock, open
+/// patch files from Finder, and stop the sidecars on exit
+pub fn handle_run_event(app: &AppHandle, event: RunEvent) {
+    match event {
+        // Only user-initiated exits carry no code; programmatic exits go through
+        RunEvent::ExitRequested {
+            code: None, api, ..
+        } => {
+            if !crate::is_quit_confirmed(app) {
+                api.prevent_exit();
+                if settings::current(app).on_close == CloseBehavior::Quit {
+                    tauri::async_runtime::spawn(crate::request_quit(app.clone()));
+                }
+            }
+        }
+        // Clicking the dock icon with no visible windows
+        #[cfg(target_os = "macos")]
+        RunEvent::Reopen {
+            has_visible_windows: false,
+            ..
+        } => {
+            if !notifications::focus_pending(app) {
+                crate::window::show_main_window(app);
+            }
+        }
+        // Patch files opened from Finder arrive as URLs rather than arguments
+        #[cfg(target_os = "macos")]
+        RunEvent::Opened { urls } => {
+            for path in urls.iter().filter_map(|url| url.to_file_path().ok()) {
+                if patch_files::is_patch_file(&path) {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move { patch_files::open(&app, path).await });
+                }
+            }
+        }
+        RunEvent::Exit => {
+            shutdown::log_exit(app);
+            safe_mode::mark_exited(app);
+            // Subsystems first, while the sidecars they may talk to are up
+            cleanup::run(app);
+            crate::sidecar::shutdown_sidecars(app);
+        }
+        _ => {}
+    }
+}
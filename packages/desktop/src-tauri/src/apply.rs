@@ -0,0 +1,177 @@
+//! Apply patches from the clipboard or an opened `.patch` file with libgit2,
+//! checking each file first so the UI can preview what applies and what
+//! conflicts.
+
+use git2::{ApplyLocation, ApplyOptions, Delta, Diff, Patch, Repository};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::native;
+
+/// Where a patch is applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApplyTarget {
+    /// The working tree, like `git apply`
+    #[default]
+    Workdir,
+    /// The index only, like `git apply --cached`
+    Index,
+    /// Both, like `git apply --index`
+    Both,
+}
+
+/// Options for `apply_patch`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ApplyPatchOptions {
+    pub target: ApplyTarget,
+    /// Only check whether the patch applies, changing nothing
+    pub dry_run: bool,
+}
+
+/// One file of a patch and whether it applies
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchFileReport {
+    path: String,
+    /// Previous path of a renamed file
+    old_path: Option<String>,
+    /// "added", "deleted", "modified", "renamed", ...
+    status: &'static str,
+    additions: usize,
+    deletions: usize,
+    /// Why the file does not apply, `None` if it does
+    conflict: Option<String>,
+}
+
+/// Result of `apply_patch`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyReport {
+    /// Whether the patch was applied; always false for a dry run
+    applied: bool,
+    files: Vec<PatchFileReport>,
+}
+
+fn status_name(delta: Delta) -> &'static str {
+    match delta {
+        Delta::Added => "added",
+        Delta::Deleted => "deleted",
+        Delta::Modified => "modified",
+        Delta::Renamed => "renamed",
+        Delta::Copied => "copied",
+        Delta::Typechange => "typechange",
+        _ => "unmodified",
+    }
+}
+
+/// Patch contents from a file path or the patch text itself
+fn read_patch(path_or_text: &str) -> CommandResult<Vec<u8>> {
+    let looks_like_path = !path_or_text.contains('\n') && Path::new(path_or_text).is_file();
+    if !looks_like_path {
+        return Ok(path_or_text.as_bytes().to_vec());
+    }
+    std::fs::read(path_or_text).map_err(|e| {
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("Failed to read patch {}: {}", path_or_text, e),
+        )
+    })
+}
+
+/// Check each file of the patch on its own, so one conflict does not hide
+/// whether the others apply
+fn check_files(
+    repo: &Repository,
+    diff: &Diff,
+    location: ApplyLocation,
+) -> Result<Vec<PatchFileReport>, git2::Error> {
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let path_of = |file: git2::DiffFile| {
+            file.path()
+                .map(|path| path.to_string_lossy().replace('\\', "/"))
+        };
+        let new_path = path_of(delta.new_file());
+        let old_path = path_of(delta.old_file());
+        let (additions, deletions) = Patch::from_diff(diff, index)?
+            .map(|patch| patch.line_stats())
+            .transpose()?
+            .map(|(_, additions, deletions)| (additions, deletions))
+            .unwrap_or_default();
+
+        let mut current = 0;
+        let mut options = ApplyOptions::new();
+        options.check(true);
+        options.delta_callback(move |_| {
+            let selected = current == index;
+            current += 1;
+            selected
+        });
+        let conflict = repo
+            .apply(diff, location, Some(&mut options))
+            .err()
+            .map(|e| e.message().to_string());
+
+        files.push(PatchFileReport {
+            path: new_path
+                .clone()
+                .or_else(|| old_path.clone())
+                .unwrap_or_default(),
+            old_path: (delta.status() == Delta::Renamed)
+                .then_some(old_path)
+                .flatten(),
+            status: status_name(delta.status()),
+            additions,
+            deletions,
+            conflict,
+        });
+    }
+    Ok(files)
+}
+
+/// Apply a patch, given as a file path or as text, to the working tree or
+/// index of the window's repository, or of `repo_path`. Nothing is applied
+/// unless every file applies cleanly.
+#[tauri::command]
+pub async fn apply_patch(
+    app: AppHandle,
+    window: WebviewWindow,
+    path_or_text: String,
+    options: Option<ApplyPatchOptions>,
+    repo_path: Option<String>,
+) -> CommandResult<ApplyReport> {
+    let options = options.unwrap_or_default();
+    let patch = read_patch(&path_or_text)?;
+    let location = match options.target {
+        ApplyTarget::Workdir => ApplyLocation::WorkDir,
+        ApplyTarget::Index => ApplyLocation::Index,
+        ApplyTarget::Both => ApplyLocation::Both,
+    };
+
+    let report = native::with_repo(&app, &window, repo_path, move |repo| {
+        let diff = Diff::from_buffer(&patch)?;
+        let files = check_files(repo, &diff, location)?;
+        let applies = files.iter().all(|file| file.conflict.is_none());
+        if options.dry_run || !applies {
+            return Ok(ApplyReport {
+                applied: false,
+                files,
+            });
+        }
+        repo.apply(&diff, location, None)?;
+        Ok(ApplyReport {
+            applied: true,
+            files,
+        })
+    })
+    .await?;
+
+    if report.applied {
+        log_info!("Applied patch to {} files", report.files.len());
+    }
+    Ok(report)
+}
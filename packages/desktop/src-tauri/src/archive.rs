@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, WebviewWindow};
+use tauri_plugin_dialog::DialogExt;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+use crate::i18n;
+use crate::timeout::{self, CommandClass};
+
+/// Archive formats `git archive` writes natively
+const FORMATS: &[&str] = &["zip", "tar", "tar.gz", "tgz"];
+
+/// Payload of the `archive-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveProgressEvent {
+    dest: String,
+    /// File just added to the archive
+    path: String,
+    current: u64,
+    total: u64,
+}
+
+/// Format for a destination's extension, e.g. `tar.gz` for `x.tar.gz`
+fn format_for(dest: &str) -> Option<&'static str> {
+    let dest = dest.to_lowercase();
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| dest.ends_with(&format!(".{}", format)))
+}
+
+/// Ask where to save the archive. Returns `None` if the dialog was cancelled.
+async fn pick_destination(app: &AppHandle, file_name: &str, format: &str) -> Option<String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title(i18n::t(app, "title.export-archive"))
+        .set_file_name(file_name)
+        .add_filter(format, &[format])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let path = rx.await.ok().flatten()?.into_path().ok()?;
+    Some(path.to_string_lossy().to_string())
+}
+
+/// Run `git archive -v`, which names each file on stderr as it is added.
+/// The archive is written to `output`; progress is reported against `dest`.
+async fn run_archive(
+    app: &AppHandle,
+    repo_path: &str,
+    commit: &str,
+    format: &str,
+    prefix: &str,
+    dest: &str,
+    output: &str,
+) -> CommandResult<()> {
+    let listing = git::run(repo_path, &["ls-tree", "-r", "--name-only", commit]).await?;
+    let total = listing.iter().filter(|byte| **byte == b'\n').count() as u64;
+
+    let format_arg = format!("--format={}", format);
+    let prefix_arg = format!("--prefix={}/", prefix);
+    let mut child = git::command(
+        repo_path,
+        &[
+            "archive",
+            "-v",
+            &format_arg,
+            &prefix_arg,
+            "-o",
+            output,
+            commit,
+        ],
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true)
+    .spawn()
+    .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+    let stderr = child.stderr.take().ok_or("Failed to read git output")?;
+
+    let mut lines = BufReader::new(stderr).lines();
+    let mut current = 0;
+    let mut last_line = String::new();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| format!("Failed to read git output: {}", e))?
+    {
+        // Directories are listed too; only count what ls-tree counted
+        if !line.ends_with('/') {
+            current += 1;
+        }
+        let _ = app.emit(
+            "archive-progress",
+            ArchiveProgressEvent {
+                dest: dest.to_string(),
+                path: line
+                    .strip_prefix(&format!("{}/", prefix))
+                    .unwrap_or(&line)
+                    .to_string(),
+                current: current.min(total),
+                total,
+            },
+        );
+        last_line = line;
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for git: {}", e))?;
+    if !status.success() {
+        return Err(AppError::from(format!("git archive: {}", last_line)));
+    }
+    Ok(())
+}
+
+/// Export the window's repository at a commit as a zip or tar archive, like
+/// `git archive`. Without `dest` a save dialog asks where to put it, and
+/// without `format` it follows the destination's extension, defaulting to zip.
+/// Returns the archive's path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_archive(
+    app: AppHandle,
+    window: WebviewWindow,
+    commitish: String,
+    format: Option<String>,
+    dest: Option<String>,
+    request_id: Option<String>,
+) -> CommandResult<Option<String>> {
+    git::validate_rev(&commitish)?;
+    let repo_path = git::repo_for_window(&app, &window)?;
+    if let Some(format) = &format {
+        if !FORMATS.contains(&format.as_str()) {
+            return Err(AppError::new(
+                ErrorCode::InvalidInput,
+                format!("Unsupported archive format: {}", format),
+            ));
+        }
+    }
+
+    let short = git::run(
+        &repo_path,
+        &["rev-parse", "--short", "--verify", &commitish],
+    )
+    .await
+    .map(|output| String::from_utf8_lossy(&output).trim().to_string())?;
+    let repo_name = Path::new(&repo_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "archive".to_string());
+    let prefix = format!("{}-{}", repo_name, short);
+
+    let dest = match dest {
+        Some(dest) => dest,
+        None => {
+            let format = format.as_deref().unwrap_or("zip");
+            match pick_destination(&app, &format!("{}.{}", prefix, format), format).await {
+                Some(dest) => dest,
+                None => return Ok(None),
+            }
+        }
+    };
+    let format = format.unwrap_or_else(|| format_for(&dest).unwrap_or("zip").to_string());
+
+    // Write next to the destination and move it into place once complete, so
+    // a failed or cancelled export never touches a file that was already there
+    let partial = format!("{}.partial", dest);
+    log_info!("Archiving {} of {} to {}", commitish, repo_path, dest);
+    let result = timeout::run(&app, CommandClass::Long, request_id, |_| {
+        run_archive(
+            &app, &repo_path, &commitish, &format, &prefix, &dest, &partial,
+        )
+    })
+    .await
+    .and_then(|()| {
+        std::fs::rename(&partial, &dest).map_err(|e| {
+            AppError::new(
+                ErrorCode::Internal,
+                format!("Failed to save archive to {}: {}", dest, e),
+            )
+        })
+    });
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    log_info!("Archived {} to {}", commitish, dest);
+    Ok(Some(dest))
+}
@@ -0,0 +1,330 @@
+//! Answers git and ssh credential prompts from the GUI.
+//!
+//! The sidecar is spawned with `GIT_ASKPASS`/`SSH_ASKPASS` pointing at this
+//! binary. Run that way, it relays the prompt to the running app over a local
+//! socket and prints the answer, which the app takes from the keychain or asks
+//! for in a native dialog.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::process::Command;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as AsyncBufReader};
+
+use crate::{credential_helper, credentials, signing};
+
+/// Set in the environment of helper runs: the port the app listens on
+const PORT_VAR: &str = "OPENTUI_GIT_ASKPASS_PORT";
+/// Set in the environment of helper runs: proves the request comes from our sidecar
+const TOKEN_VAR: &str = "OPENTUI_GIT_ASKPASS_TOKEN";
+const DIALOG_TITLE: &str = "opentui-git";
+
+/// Address and secret of the app's prompt listener
+#[derive(Clone, Default)]
+pub struct AskpassState {
+    port: u16,
+    token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PromptRequest {
+    token: String,
+    #[serde(flatten)]
+    kind: PromptKind,
+}
+
+/// What a helper run asks the app for
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum PromptKind {
+    /// A prompt from git or ssh
+    Askpass { prompt: String },
+    /// The passphrase of a commit signing key
+    SigningPassphrase { key: String },
+    /// The passphrase given for a signing key was wrong
+    ForgetPassphrase { key: String },
+    /// Whether to save a credential git accepted in the keychain
+    SaveCredential { host: String, username: String },
+    /// Whether to remove a credential git rejected from the keychain
+    RemoveCredential { host: String, username: String },
+}
+
+#[derive(Serialize, Deserialize)]
+struct PromptResponse {
+    /// `None` if the user cancelled
+    answer: Option<String>,
+}
+
+/// What git or ssh is asking for
+enum Prompt {
+    /// `Username for 'https://host': `
+    Username { host: String },
+    /// `Password for 'https://user@host': `
+    Password { host: String },
+    /// Anything else, e.g. an SSH key passphrase or a host key confirmation
+    Other { hidden: bool },
+}
+
+/// Hard-to-guess token for authenticating local connections: 32 bytes from
+/// the OS random number generator, hex-encoded
+pub fn random_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| format!("OS random number generator unavailable: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Host inside the quoted URL of a git prompt
+fn prompt_host(prompt: &str) -> Option<String> {
+    let url = prompt.split('\'').nth(1)?;
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let host = rest.split('/').next()?;
+    Some(
+        host.rsplit_once('@')
+            .map_or(host, |(_, host)| host)
+            .to_string(),
+    )
+}
+
+fn classify(prompt: &str) -> Prompt {
+    let lower = prompt.to_lowercase();
+    if lower.starts_with("username for") {
+        if let Some(host) = prompt_host(prompt) {
+            return Prompt::Username { host };
+        }
+    }
+    if lower.starts_with("password for") {
+        if let Some(host) = prompt_host(prompt) {
+            return Prompt::Password { host };
+        }
+    }
+    Prompt::Other {
+        hidden: lower.contains("password") || lower.contains("passphrase"),
+    }
+}
+
+/// Show a native text input dialog. Returns `None` if cancelled.
+pub fn native_prompt(message: &str, hidden: bool) -> Option<String> {
+    #[cfg(target_os = "macos")]
+    let output = {
+        let escaped = message.replace('\\', "\\\\").replace('"', "\\\"");
+        let script = format!(
+            "display dialog \"{}\" default answer \"\"{} with title \"{}\" with icon caution",
+            escaped,
+            if hidden { " with hidden answer" } else { "" },
+            DIALOG_TITLE
+        );
+        Command::new("osascript")
+            .args(["-e", &script, "-e", "text returned of result"])
+            .output()
+    };
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let output = {
+        let zenity = Command::new("zenity")
+            .args([
+                if hidden { "--password" } else { "--entry" },
+                "--title",
+                DIALOG_TITLE,
+                "--text",
+                message,
+            ])
+            .output();
+        match zenity {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Command::new("kdialog")
+                .args(["--title", DIALOG_TITLE])
+                .args(if hidden {
+                    ["--password", message]
+                } else {
+                    ["--inputbox", message]
+                })
+                .output(),
+            other => other,
+        }
+    };
+
+    #[cfg(target_os = "windows")]
+    let output = {
+        let escaped = message.replace('\'', "''");
+        let script = if hidden {
+            format!(
+                "$c = Get-Credential -UserName ' ' -Message '{}'; \
+                 if ($c) {{ $c.GetNetworkCredential().Password }} else {{ exit 1 }}",
+                escaped
+            )
+        } else {
+            format!(
+                "Add-Type -AssemblyName Microsoft.VisualBasic; \
+                 $a = [Microsoft.VisualBasic.Interaction]::InputBox('{}', '{}'); \
+                 if ($a) {{ $a }} else {{ exit 1 }}",
+                escaped, DIALOG_TITLE
+            )
+        };
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .output()
+    };
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let answer = String::from_utf8_lossy(&output.stdout);
+            Some(answer.trim_end_matches(['\r', '\n']).to_string())
+        }
+        Ok(_) => None,
+        Err(e) => {
+            log_error!("Failed to show credential prompt: {}", e);
+            None
+        }
+    }
+}
+
+/// Answer a prompt from the keychain, falling back to a dialog
+fn answer(prompt: &str) -> Option<String> {
+    let stored = |host: &str| credentials::lookup(host).ok().flatten();
+    match classify(prompt) {
+        Prompt::Username { host } => stored(&host)
+            .map(|credential| credential.username)
+            .or_else(|| native_prompt(prompt, false)),
+        Prompt::Password { host } => stored(&host)
+            .map(|credential| credential.secret)
+            .or_else(|| native_prompt(prompt, true)),
+        Prompt::Other { hidden } => native_prompt(prompt, hidden),
+    }
+}
+
+/// Serve one helper connection
+async fn handle_connection(app: AppHandle, stream: tokio::net::TcpStream, token: String) {
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    if AsyncBufReader::new(reader)
+        .read_line(&mut line)
+        .await
+        .is_err()
+    {
+        return;
+    }
+    let Ok(request) = serde_json::from_str::<PromptRequest>(&line) else {
+        return;
+    };
+    if request.token != token {
+        log_error!("Rejected credential prompt with a bad token");
+        return;
+    }
+
+    let answer = tauri::async_runtime::spawn_blocking(move || match request.kind {
+        PromptKind::Askpass { prompt } => {
+            log_info!("Credential prompt: {}", prompt.trim());
+            answer(&prompt)
+        }
+        PromptKind::SigningPassphrase { key } => signing::passphrase(&app, &key),
+        PromptKind::ForgetPassphrase { key } => {
+            signing::forget(&app, &key);
+            None
+        }
+        kind @ (PromptKind::SaveCredential { .. } | PromptKind::RemoveCredential { .. }) => {
+            credential_helper::confirm(&app, &kind).then(String::new)
+        }
+    })
+    .await
+    .ok()
+    .flatten();
+
+    let Ok(mut response) = serde_json::to_string(&PromptResponse { answer }) else {
+        return;
+    };
+    response.push('\n');
+    let _ = writer.write_all(response.as_bytes()).await;
+}
+
+/// Listen for prompts from helper runs on a local port
+pub fn start(app: &AppHandle) -> Result<(), String> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| format!("Failed to bind askpass listener: {}", e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure askpass listener: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read askpass address: {}", e))?
+        .port();
+
+    let token = random_token()?;
+    app.manage(AskpassState {
+        port,
+        token: token.clone(),
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log_error!("Failed to start askpass listener: {}", e);
+                return;
+            }
+        };
+        while let Ok((stream, _)) = listener.accept().await {
+            tauri::async_runtime::spawn(handle_connection(app.clone(), stream, token.clone()));
+        }
+    });
+    log_info!("Listening for credential prompts on port {}", port);
+    Ok(())
+}
+
+/// Environment that routes git and ssh prompts to this app
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    let Some(state) = app.try_state::<AskpassState>() else {
+        return Vec::new();
+    };
+    let Ok(exe) = std::env::current_exe() else {
+        return Vec::new();
+    };
+    let exe = exe.to_string_lossy().to_string();
+    vec![
+        ("GIT_ASKPASS".to_string(), exe.clone()),
+        ("SSH_ASKPASS".to_string(), exe),
+        // Use SSH_ASKPASS even when ssh has a terminal or no DISPLAY
+        ("SSH_ASKPASS_REQUIRE".to_string(), "force".to_string()),
+        (PORT_VAR.to_string(), state.port.to_string()),
+        (TOKEN_VAR.to_string(), state.token.clone()),
+    ]
+}
+
+/// Ask the running app for an answer from a helper run.
+/// Returns `None` if the user cancelled.
+pub fn relay(kind: PromptKind) -> std::io::Result<Option<String>> {
+    let (Ok(port), Ok(token)) = (std::env::var(PORT_VAR), std::env::var(TOKEN_VAR)) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Not started by opentui-git",
+        ));
+    };
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", port.parse().unwrap_or(0)))?;
+    let mut request = serde_json::to_string(&PromptRequest { token, kind })?;
+    request.push('\n');
+    stream.write_all(request.as_bytes())?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: PromptResponse = serde_json::from_str(&line)?;
+    Ok(response.answer)
+}
+
+/// When git or ssh started this binary as its askpass helper, relay the
+/// prompt to the running app and return the exit code; otherwise `None`.
+pub fn run_helper() -> Option<i32> {
+    std::env::var(PORT_VAR).ok()?;
+    let prompt = std::env::args().nth(1).unwrap_or_default();
+
+    match relay(PromptKind::Askpass { prompt }) {
+        Ok(Some(answer)) => {
+            println!("{}", answer);
+            Some(0)
+        }
+        Ok(None) => Some(1),
+        Err(e) => {
+            eprintln!("opentui-git askpass: {}", e);
+            Some(1)
+        }
+    }
+}
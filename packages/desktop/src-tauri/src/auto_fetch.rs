@@ -0,0 +1,331 @@
+//! Fetching the remotes of open repositories on a schedule, so the UI can
+//! show incoming commits without the user fetching by hand. Fetches go
+//! through each repository's sidecar and are skipped on metered connections
+//! and while the OS is saving battery. When the current branch's upstream
+//! moves, the repository's windows get `remote-updated`.
+
+use git2::{Branch, Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::broadcast::{self, WindowRegistry};
+use crate::sidecar::{ServerState, Sidecar};
+use crate::{background, notifications, power, repo_config, settings};
+
+/// How often the schedule is checked
+const TICK_SECS: u64 = 60;
+/// Shortest interval allowed, to spare the remotes
+const MIN_INTERVAL_SECS: u64 = 60;
+
+/// When the remotes of open repositories are fetched
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AutoFetchSettings {
+    pub enabled: bool,
+    /// Time between fetches of each repository
+    pub interval_secs: u64,
+    /// Intervals for particular repositories, keyed by path; 0 turns fetching
+    /// off for that repository
+    pub repo_intervals: HashMap<String, u64>,
+    /// Don't fetch while the connection is metered
+    pub skip_on_metered: bool,
+    /// Don't fetch while the OS is saving battery
+    pub skip_on_battery_saver: bool,
+    /// Show a notification when new upstream commits arrive while the app is
+    /// in the background
+    pub notify: bool,
+}
+
+impl Default for AutoFetchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: 10 * 60,
+            repo_intervals: HashMap::new(),
+            skip_on_metered: true,
+            skip_on_battery_saver: true,
+            notify: false,
+        }
+    }
+}
+
+impl AutoFetchSettings {
+    /// Interval for a repository, `None` if it isn't fetched. The setting for
+    /// the repository wins over the one its `.opentui.toml` asks for.
+    fn interval_for(&self, repo_path: &str, requested: Option<u64>) -> Option<Duration> {
+        let key = broadcast::repo_key(repo_path);
+        let secs = self
+            .repo_intervals
+            .iter()
+            .find(|(path, _)| broadcast::repo_key(path) == key)
+            .map(|(_, secs)| *secs)
+            .or(requested)
+            .unwrap_or(self.interval_secs);
+        (secs > 0).then(|| Duration::from_secs(secs.max(MIN_INTERVAL_SECS)))
+    }
+}
+
+/// Payload of the `remote-updated` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteUpdated {
+    repo_path: String,
+    branch: String,
+    upstream: String,
+    /// Commits the upstream gained with this fetch
+    new_commits: usize,
+    /// Commits the branch is now behind its upstream
+    behind: usize,
+}
+
+/// The current branch and where its upstream points
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tracking {
+    branch: String,
+    upstream: String,
+    tip: Oid,
+}
+
+fn tracking(repo: &Repository) -> Option<Tracking> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let branch = Branch::wrap(head);
+    let upstream = branch.upstream().ok()?;
+    Some(Tracking {
+        branch: branch.name().ok()??.to_string(),
+        upstream: upstream.name().ok()??.to_string(),
+        tip: upstream.get().target()?,
+    })
+}
+
+/// Tracking of a repository, read off the async runtime
+async fn read_tracking(repo_path: &str) -> Option<Tracking> {
+    let repo_path = repo_path.to_string();
+    tauri::async_runtime::spawn_blocking(move || tracking(&Repository::open(repo_path).ok()?))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// What changed upstream between two readings, `None` if nothing did
+async fn compare(repo_path: &str, before: &Tracking, after: &Tracking) -> Option<RemoteUpdated> {
+    if before.upstream != after.upstream || before.tip == after.tip {
+        return None;
+    }
+    let repo_path = repo_path.to_string();
+    let (old_tip, new_tip) = (before.tip, after.tip);
+    let after = after.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo = Repository::open(&repo_path).ok()?;
+        let (new_commits, _) = repo.graph_ahead_behind(new_tip, old_tip).ok()?;
+        let local = repo.head().ok()?.target()?;
+        let (_, behind) = repo.graph_ahead_behind(local, new_tip).ok()?;
+        Some(RemoteUpdated {
+            repo_path,
+            branch: after.branch,
+            upstream: after.upstream,
+            new_commits,
+            behind,
+        })
+    })
+    .await
+    .ok()
+    .flatten()
+    .filter(|update| update.new_commits > 0)
+}
+
+/// Whether NetworkManager's `Metered` property, as printed by `busctl`
+/// (`u 1`), says yes or guesses yes
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn nm_metered(output: &str) -> bool {
+    matches!(
+        output
+            .split_whitespace()
+            .last()
+            .and_then(|value| value.parse().ok()),
+        Some(1 | 3)
+    )
+}
+
+/// Whether the connection is metered, where that can be detected
+fn metered() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        std::process::Command::new("busctl")
+            .args([
+                "get-property",
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ])
+            .output()
+            .is_ok_and(|output| {
+                output.status.success() && nm_metered(&String::from_utf8_lossy(&output.stdout))
+            })
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let script = "[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime] | Out-Null; \
+            [Windows.Networking.Connectivity.NetworkInformation]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType";
+        std::process::Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .output()
+            .is_ok_and(|output| {
+                matches!(
+                    String::from_utf8_lossy(&output.stdout).trim(),
+                    "Fixed" | "Variable"
+                )
+            })
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        false
+    }
+}
+
+/// Why fetching should wait, if it should
+async fn skip_reason(settings: &AutoFetchSettings) -> Option<&'static str> {
+    let (check_metered, check_saver) = (settings.skip_on_metered, settings.skip_on_battery_saver);
+    tauri::async_runtime::spawn_blocking(move || {
+        if check_metered && metered() {
+            Some("the connection is metered")
+        } else if check_saver && power::os_power_saving() {
+            Some("the OS is saving battery")
+        } else {
+            None
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Fetch a repository through its sidecar and report new upstream commits
+async fn fetch(app: &AppHandle, sidecar: &Sidecar, notify: bool) {
+    let before = read_tracking(&sidecar.repo_path).await;
+    if let Err(e) = background::fetch(app, sidecar.port).await {
+        log_error!("Scheduled fetch failed for {}: {}", sidecar.repo_path, e);
+        return;
+    }
+    log_info!("Scheduled fetch complete for {}", sidecar.repo_path);
+
+    let (Some(before), Some(after)) = (before, read_tracking(&sidecar.repo_path).await) else {
+        return;
+    };
+    let Some(update) = compare(&sidecar.repo_path, &before, &after).await else {
+        return;
+    };
+    log_info!(
+        "{} has {} new commits on {}",
+        sidecar.repo_path,
+        update.new_commits,
+        update.upstream
+    );
+    if notify {
+        notifications::remote_updated(
+            app,
+            &sidecar.repo_path,
+            &update.upstream,
+            update.new_commits,
+        );
+    }
+    broadcast::emit_to_repo(app, &sidecar.repo_path, "remote-updated", update);
+}
+
+/// Fetch the repositories that have windows open whenever their interval is up
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_fetched: HashMap<String, Instant> = HashMap::new();
+        let mut skipping = None;
+        loop {
+            tokio::time::sleep(Duration::from_secs(TICK_SECS)).await;
+
+            let settings = settings::current(&app).auto_fetch;
+            if !settings.enabled {
+                continue;
+            }
+            let registry = app.state::<WindowRegistry>();
+            let due: Vec<Sidecar> = app
+                .state::<ServerState>()
+                .all()
+                .into_iter()
+                .filter(|sidecar| !registry.windows_for(&sidecar.repo_path).is_empty())
+                .filter(|sidecar| {
+                    settings
+                        .interval_for(
+                            &sidecar.repo_path,
+                            repo_config::fetch_interval(&sidecar.repo_path),
+                        )
+                        .is_some_and(|interval| {
+                            last_fetched
+                                .get(&broadcast::repo_key(&sidecar.repo_path))
+                                .is_none_or(|fetched| fetched.elapsed() >= interval)
+                        })
+                })
+                .collect();
+            if due.is_empty() {
+                continue;
+            }
+
+            let reason = skip_reason(&settings).await;
+            if reason != skipping {
+                if let Some(reason) = reason {
+                    log_info!("Pausing scheduled fetches, {}", reason);
+                }
+                skipping = reason;
+            }
+            if reason.is_some() {
+                continue;
+            }
+
+            for sidecar in due {
+                last_fetched.insert(broadcast::repo_key(&sidecar.repo_path), Instant::now());
+                fetch(&app, &sidecar, settings.notify).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_repository_interval() {
+        let settings = AutoFetchSettings {
+            interval_secs: 300,
+            repo_intervals: HashMap::from([
+                ("/nonexistent/quiet".to_string(), 0),
+                ("/nonexistent/busy/".to_string(), 5),
+            ]),
+            ..AutoFetchSettings::default()
+        };
+        assert_eq!(
+            settings.interval_for("/nonexistent/other", None),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(settings.interval_for("/nonexistent/quiet", Some(120)), None);
+        assert_eq!(
+            settings.interval_for("/nonexistent/busy", None),
+            Some(Duration::from_secs(MIN_INTERVAL_SECS))
+        );
+        assert_eq!(
+            settings.interval_for("/nonexistent/other", Some(120)),
+            Some(Duration::from_secs(120))
+        );
+    }
+
+    #[test]
+    fn reads_network_manager_metering() {
+        assert!(nm_metered("u 1\n"));
+        assert!(nm_metered("u 3\n"));
+        assert!(!nm_metered("u 2\n"));
+        assert!(!nm_metered("u 4\n"));
+        assert!(!nm_metered(""));
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::sidecar::ServerState;
+use crate::{loopback, settings, tls};
+
+const FETCH_TIMEOUT_SECS: u64 = 120;
+
+/// Whether the app is running headless with background fetches
+#[derive(Clone, Default)]
+pub struct BackgroundState(Arc<AtomicBool>);
+
+/// Ask the server to fetch all remotes on a timer, which doesn't notify when
+/// it completes
+pub async fn fetch(app: &AppHandle, port: u32) -> Result<(), String> {
+    let client = loopback::client(app, Duration::from_secs(FETCH_TIMEOUT_SECS))?;
+
+    let response = client
+        .post(format!("{}/remote/fetch", tls::base_url(app, port)))
+        .query(&[("scheduled", true)])
+        .send()
+        .await
+        .map_err(|e| format!("Fetch request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Fetch failed with status {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Keep fetching periodically while no window is open
+pub fn start(app: &AppHandle) {
+    let state = app.state::<BackgroundState>().inner().clone();
+    if state.0.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    log_info!("Running in the background");
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = settings::current(&app)
+                .background_fetch_interval_secs
+                .max(30);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            if !state.0.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let sidecars = app.state::<ServerState>().all();
+            for sidecar in sidecars {
+                match fetch(&app, sidecar.port).await {
+                    Ok(()) => log_info!("Background fetch complete for {}", sidecar.repo_path),
+                    Err(e) => {
+                        log_error!("Background fetch failed for {}: {}", sidecar.repo_path, e)
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Stop background fetching once a window is open again
+pub fn stop(app: &AppHandle) {
+    if let Some(state) = app.try_state::<BackgroundState>() {
+        state.0.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,169 @@
+use serde::Deserialize;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::broadcast::WindowRegistry;
+use crate::sidecar::ServerState;
+use crate::{loopback, power, settings, tls};
+
+const POLL_INTERVAL_SECS: u64 = 10;
+const STATUS_TIMEOUT_SECS: u64 = 5;
+
+/// Response of the server's `/status` endpoint, only the parts the badge needs
+#[derive(Debug, Deserialize)]
+struct StatusResponse {
+    #[serde(default)]
+    files: Vec<serde_json::Value>,
+}
+
+/// Set or clear the dock badge (macOS/Linux) or taskbar overlay (Windows)
+fn apply(app: &AppHandle, count: Option<u32>) {
+    let count = count.filter(|count| *count > 0);
+
+    for window in app.webview_windows().values() {
+        #[cfg(not(target_os = "windows"))]
+        let result = window.set_badge_count(count.map(i64::from));
+        #[cfg(target_os = "windows")]
+        let result = window.set_overlay_icon(count.map(overlay::icon));
+
+        if let Err(e) = result {
+            log_error!("Failed to set badge on {}: {}", window.label(), e);
+        }
+    }
+}
+
+/// Ask the server how many files have changes
+async fn changed_files(app: &AppHandle, port: u32) -> Result<u32, String> {
+    let client = loopback::client(app, Duration::from_secs(STATUS_TIMEOUT_SECS))?;
+
+    let status: StatusResponse = client
+        .get(format!("{}/status", tls::base_url(app, port)))
+        .send()
+        .await
+        .map_err(|e| format!("Status request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid status response: {}", e))?;
+
+    Ok(status.files.len() as u32)
+}
+
+/// Port of the sidecar for the focused window's repo, falling back to the primary
+fn active_port(app: &AppHandle) -> Option<u32> {
+    let server_state = app.state::<ServerState>();
+    app.webview_windows()
+        .values()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .and_then(|window| app.state::<WindowRegistry>().repo_for(window.label()))
+        .and_then(|repo_path| server_state.for_repo(&repo_path))
+        .or_else(|| server_state.primary())
+        .map(|sidecar| sidecar.port)
+}
+
+/// Keep the badge in sync with the active repo's change count while enabled
+pub fn start_auto(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last = None;
+
+        loop {
+            let interval = power::interval(&app, Duration::from_secs(POLL_INTERVAL_SECS));
+            tokio::time::sleep(interval).await;
+
+            if !settings::current(&app).auto_badge {
+                if last.take().is_some() {
+                    apply(&app, None);
+                }
+                continue;
+            }
+
+            let Some(port) = active_port(&app) else {
+                continue;
+            };
+            match changed_files(&app, port).await {
+                Ok(count) if last != Some(count) => {
+                    apply(&app, Some(count));
+                    last = Some(count);
+                }
+                Ok(_) => {}
+                Err(e) => log_error!("Failed to update badge: {}", e),
+            }
+        }
+    });
+}
+
+/// Show a count on the dock/taskbar icon; zero or None clears it
+#[tauri::command]
+pub fn set_badge(app: AppHandle, count: Option<u32>) {
+    apply(&app, count);
+}
+
+/// Windows has no badge API, so the count is drawn into a taskbar overlay icon
+#[cfg(target_os = "windows")]
+mod overlay {
+    use tauri::image::Image;
+
+    const SIZE: usize = 16;
+    const SCALE: usize = 2;
+    const BACKGROUND: [u8; 4] = [0xe5, 0x3e, 0x3e, 0xff];
+    const FOREGROUND: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+    /// 3x5 bitmap digits, one row per byte, high bit on the left
+    const DIGITS: [[u8; 5]; 10] = [
+        [0b111, 0b101, 0b101, 0b101, 0b111],
+        [0b010, 0b110, 0b010, 0b010, 0b111],
+        [0b111, 0b001, 0b111, 0b100, 0b111],
+        [0b111, 0b001, 0b111, 0b001, 0b111],
+        [0b101, 0b101, 0b111, 0b001, 0b001],
+        [0b111, 0b100, 0b111, 0b001, 0b111],
+        [0b111, 0b100, 0b111, 0b101, 0b111],
+        [0b111, 0b001, 0b001, 0b001, 0b001],
+        [0b111, 0b101, 0b111, 0b101, 0b111],
+        [0b111, 0b101, 0b111, 0b001, 0b111],
+    ];
+
+    /// Red circle with the count (capped at 99) in white
+    pub fn icon(count: u32) -> Image<'static> {
+        let mut rgba = vec![0u8; SIZE * SIZE * 4];
+        let mut set = |x: usize, y: usize, color: [u8; 4]| {
+            let offset = (y * SIZE + x) * 4;
+            rgba[offset..offset + 4].copy_from_slice(&color);
+        };
+
+        let center = (SIZE as f32 - 1.0) / 2.0;
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (dx, dy) = (x as f32 - center, y as f32 - center);
+                if dx * dx + dy * dy <= center * center + 1.0 {
+                    set(x, y, BACKGROUND);
+                }
+            }
+        }
+
+        let text = count.min(99).to_string();
+        let width = text.len() * 3 * SCALE + (text.len() - 1) * SCALE;
+        let left = (SIZE - width) / 2;
+        let top = (SIZE - 5 * SCALE) / 2;
+        for (i, digit) in text.bytes().enumerate() {
+            let glyph = DIGITS[(digit - b'0') as usize];
+            let glyph_left = left + i * 4 * SCALE;
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    for sy in 0..SCALE {
+                        for sx in 0..SCALE {
+                            set(
+                                glyph_left + col * SCALE + sx,
+                                top + row * SCALE + sy,
+                                FOREGROUND,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Image::new_owned(rgba, SIZE as u32, SIZE as u32)
+    }
+}
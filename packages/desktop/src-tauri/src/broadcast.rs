@@ -0,0 +1,162 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+
+/// Payload of the `repo-leader-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderChangedEvent {
+    repo_path: String,
+    leader: bool,
+}
+
+/// Which repository each window shows, in registration order per repo.
+/// The first window for a repo is its leader and the only one that runs
+/// watchers and indexing for it.
+#[derive(Clone, Default)]
+pub struct WindowRegistry(Arc<Mutex<HashMap<String, Vec<String>>>>);
+
+/// Normalize a repo path so different spellings of the same repo compare equal
+pub fn repo_key(repo_path: &str) -> String {
+    Path::new(repo_path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| repo_path.trim_end_matches(['/', '\\']).to_string())
+}
+
+/// Key a window's saved state is stored under: its repository, since window
+/// labels change across restarts, or the label for windows without one
+pub fn window_key(window: &WebviewWindow) -> String {
+    window
+        .app_handle()
+        .state::<WindowRegistry>()
+        .repo_for(window.label())
+        .map(|repo_path| repo_key(&repo_path))
+        .unwrap_or_else(|| window.label().to_string())
+}
+
+impl WindowRegistry {
+    /// Windows showing the given repo, leader first
+    pub fn windows_for(&self, repo_path: &str) -> Vec<String> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|map| map.get(&repo_key(repo_path)).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Repo shown by the given window
+    pub fn repo_for(&self, label: &str) -> Option<String> {
+        let map = self.0.lock().ok()?;
+        map.iter()
+            .find(|(_, labels)| labels.iter().any(|l| l == label))
+            .map(|(repo, _)| repo.clone())
+    }
+
+    /// Record that a window shows the given repo, after the windows already on it
+    pub fn add(&self, label: &str, repo_path: &str) {
+        let Ok(mut map) = self.0.lock() else {
+            return;
+        };
+        let labels = map.entry(repo_key(repo_path)).or_default();
+        if !labels.iter().any(|l| l == label) {
+            labels.push(label.to_string());
+        }
+    }
+
+    /// Whether the window is the leader for its repo
+    pub fn is_leader(&self, label: &str) -> bool {
+        self.repo_for(label)
+            .map(|repo| self.windows_for(&repo).first().is_some_and(|l| l == label))
+            .unwrap_or(false)
+    }
+}
+
+/// Record that a window shows the given repo
+pub fn register(app: &AppHandle, label: &str, repo_path: &str) {
+    app.state::<WindowRegistry>().add(label, repo_path);
+}
+
+/// Forget a closed window, handing leadership to the next window on its repo
+pub fn unregister(app: &AppHandle, label: &str) {
+    let registry = app.state::<WindowRegistry>();
+    let new_leader = {
+        let Ok(mut map) = registry.0.lock() else {
+            return;
+        };
+        let Some((repo, labels)) = map
+            .iter_mut()
+            .find(|(_, labels)| labels.iter().any(|l| l == label))
+        else {
+            return;
+        };
+        let repo = repo.clone();
+        let was_leader = labels.first().is_some_and(|l| l == label);
+        labels.retain(|l| l != label);
+
+        let new_leader = if was_leader {
+            labels.first().cloned().map(|next| (repo.clone(), next))
+        } else {
+            None
+        };
+        if labels.is_empty() {
+            map.remove(&repo);
+        }
+        new_leader
+    };
+
+    if let Some((repo_path, next)) = new_leader {
+        log_info!("Window {} is now leader for {}", next, repo_path);
+        let _ = app.emit_to(
+            next.as_str(),
+            "repo-leader-changed",
+            LeaderChangedEvent {
+                repo_path,
+                leader: true,
+            },
+        );
+    }
+}
+
+/// Emit an event to every window showing the given repo
+pub fn emit_to_repo<S: Serialize + Clone>(
+    app: &AppHandle,
+    repo_path: &str,
+    event: &str,
+    payload: S,
+) {
+    for label in app.state::<WindowRegistry>().windows_for(repo_path) {
+        let _ = app.emit_to(label.as_str(), event, payload.clone());
+    }
+}
+
+/// Re-emit an event from one window to every window showing the same repo
+#[tauri::command]
+pub fn broadcast_repo_event(
+    app: AppHandle,
+    window: WebviewWindow,
+    event: String,
+    payload: serde_json::Value,
+) -> CommandResult<()> {
+    let repo_path = app
+        .state::<WindowRegistry>()
+        .repo_for(window.label())
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::NotFound,
+                "Window is not attached to a repository",
+            )
+        })?;
+    emit_to_repo(&app, &repo_path, &event, payload);
+    Ok(())
+}
+
+/// Whether the calling window runs watchers and indexing for its repo
+#[tauri::command]
+pub fn is_repo_leader(app: AppHandle, window: WebviewWindow) -> bool {
+    app.state::<WindowRegistry>().is_leader(window.label())
+}
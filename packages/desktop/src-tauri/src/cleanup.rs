@@ -0,0 +1,143 @@
+//! Teardown when the app exits. Subsystems register what has to be undone on
+//! quit, such as child processes to stop, watchers to drop or temp files to
+//! remove, as they start it. `run` drains the registry on `RunEvent::Exit`
+//! with all steps at once and a deadline, so one that hangs can't keep the
+//! app from exiting, before the sidecars are shut down.
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// How long exiting waits for the steps to finish
+const DEADLINE: Duration = Duration::from_secs(3);
+
+type Step = Box<dyn FnOnce() + Send>;
+
+/// Teardown steps by name, in the order they were registered
+#[derive(Clone, Default)]
+pub struct CleanupRegistry(Arc<Mutex<Vec<(&'static str, Step)>>>);
+
+/// Have `step` run when the app exits. Registering a name again replaces
+/// its step, so subsystems can register each time they start something.
+pub fn register(
+    app: &AppHandle,
+    name: &'static str,
+    step: impl FnOnce(&AppHandle) + Send + 'static,
+) {
+    let Some(registry) = app.try_state::<CleanupRegistry>() else {
+        return;
+    };
+    let Ok(mut steps) = registry.0.lock() else {
+        return;
+    };
+    let app = app.clone();
+    steps.retain(|(registered, _)| *registered != name);
+    steps.push((name, Box::new(move || step(&app))));
+}
+
+/// Run the steps, each on its own thread, and wait for them until the
+/// deadline. Returns the names of those that didn't finish, including any
+/// that panicked.
+fn drain(steps: Vec<(&'static str, Step)>, deadline: Duration) -> Vec<&'static str> {
+    let (tx, rx) = mpsc::channel();
+    let mut pending = Vec::new();
+    for (name, step) in steps {
+        let tx = tx.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("cleanup-{}", name))
+            .spawn(move || {
+                step();
+                let _ = tx.send(name);
+            });
+        match spawned {
+            Ok(_) => pending.push(name),
+            Err(e) => log_error!("Failed to start cleanup of {}: {}", name, e),
+        }
+    }
+    // Only the steps' senders are left, so a panicked step can't be waited on
+    drop(tx);
+
+    let until = Instant::now() + deadline;
+    while !pending.is_empty() {
+        let Some(left) = until.checked_duration_since(Instant::now()) else {
+            break;
+        };
+        match rx.recv_timeout(left) {
+            Ok(name) => pending.retain(|pending| *pending != name),
+            Err(_) => break,
+        }
+    }
+    pending
+}
+
+/// Run a single step now, waiting for it until the same deadline. Returns
+/// whether it finished in time.
+pub fn run_now(name: &'static str, step: impl FnOnce() + Send + 'static) -> bool {
+    let step: Step = Box::new(step);
+    let unfinished = drain(vec![(name, step)], DEADLINE);
+    if !unfinished.is_empty() {
+        log_error!("Exiting before cleanup of {} finished", name);
+    }
+    unfinished.is_empty()
+}
+
+/// Run every registered teardown step. Called once, when the app exits.
+pub fn run(app: &AppHandle) {
+    let Some(registry) = app.try_state::<CleanupRegistry>() else {
+        return;
+    };
+    let steps = match registry.0.lock() {
+        Ok(mut steps) => std::mem::take(&mut *steps),
+        Err(_) => return,
+    };
+    if steps.is_empty() {
+        return;
+    }
+
+    let started = Instant::now();
+    let count = steps.len();
+    let unfinished = drain(steps, DEADLINE);
+    if unfinished.is_empty() {
+        log_info!(
+            "Cleaned up {} subsystem(s) in {} ms",
+            count,
+            started.elapsed().as_millis()
+        );
+    } else {
+        log_error!(
+            "Exiting before cleanup of {} finished",
+            unfinished.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn reports_steps_past_the_deadline() {
+        let ran = Arc::new(AtomicUsize::new(0));
+        let counted = |ran: &Arc<AtomicUsize>| -> Step {
+            let ran = ran.clone();
+            Box::new(move || {
+                ran.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let steps: Vec<(&'static str, Step)> = vec![
+            ("first", counted(&ran)),
+            (
+                "hung",
+                Box::new(|| std::thread::sleep(Duration::from_secs(5))),
+            ),
+            ("panics", Box::new(|| panic!("cleanup failed"))),
+            ("second", counted(&ran)),
+        ];
+        let mut unfinished = drain(steps, Duration::from_millis(200));
+        unfinished.sort();
+        assert_eq!(unfinished, ["hung", "panics"]);
+        assert_eq!(ran.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,109 @@
+//! Clipboard access for git workflows, done natively so copying works the
+//! same on every platform regardless of webview clipboard permissions.
+
+use std::path::Path;
+use tauri::{AppHandle, WebviewWindow};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+
+fn clipboard_error(e: tauri_plugin_clipboard_manager::Error) -> AppError {
+    AppError::new(
+        ErrorCode::Internal,
+        format!("Failed to access clipboard: {}", e),
+    )
+}
+
+fn write(app: &AppHandle, text: String) -> CommandResult<String> {
+    app.clipboard()
+        .write_text(text.clone())
+        .map_err(clipboard_error)?;
+    Ok(text)
+}
+
+/// Copy text as is
+#[tauri::command]
+pub fn copy_text(app: AppHandle, text: String) -> CommandResult<()> {
+    write(&app, text)?;
+    Ok(())
+}
+
+/// Copy the full or abbreviated hash of a commit. Returns the copied hash.
+#[tauri::command]
+pub async fn copy_commit_sha(
+    app: AppHandle,
+    window: WebviewWindow,
+    rev: String,
+    short: Option<bool>,
+) -> CommandResult<String> {
+    git::validate_rev(&rev)?;
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let spec = format!("{}^{{commit}}", rev);
+    let mut args = vec!["rev-parse", "--verify"];
+    if short.unwrap_or(false) {
+        args.push("--short");
+    }
+    args.push(&spec);
+
+    let output = git::run(&repo_path, &args).await?;
+    write(&app, String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Copy a file's path, absolute or relative to the repository.
+/// Returns the copied path.
+#[tauri::command]
+pub fn copy_file_path(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    relative: Option<bool>,
+) -> CommandResult<String> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let absolute = Path::new(&repo_path).join(&path);
+    let text = if relative.unwrap_or(false) {
+        absolute
+            .strip_prefix(&repo_path)
+            .unwrap_or(Path::new(&path))
+            .to_string_lossy()
+            .replace('\\', "/")
+    } else {
+        absolute.to_string_lossy().to_string()
+    };
+    write(&app, text)
+}
+
+/// Copy a patch: a commit as `git format-patch` output when `rev` is given,
+/// otherwise the working tree's (or with `staged`, the index's) diff of `paths`.
+/// Returns the copied patch.
+#[tauri::command]
+pub async fn copy_patch(
+    app: AppHandle,
+    window: WebviewWindow,
+    rev: Option<String>,
+    paths: Option<Vec<String>>,
+    staged: Option<bool>,
+) -> CommandResult<String> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let patch = git::patch(
+        &repo_path,
+        rev.as_deref(),
+        &paths.unwrap_or_default(),
+        staged.unwrap_or(false),
+    )
+    .await?;
+    write(&app, String::from_utf8_lossy(&patch).to_string())
+}
+
+/// Clipboard text for the apply-patch flow
+#[tauri::command]
+pub fn paste_patch(app: AppHandle) -> CommandResult<String> {
+    let text = app.clipboard().read_text().map_err(clipboard_error)?;
+    if !text.contains("diff --git") && !text.contains("\n+++ ") && !text.starts_with("--- ") {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            "Clipboard does not contain a patch",
+        ));
+    }
+    Ok(text)
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter};
+use tokio::io::AsyncReadExt;
+use tokio::process::{ChildStderr, Command};
+
+use crate::askpass;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::remotes;
+use crate::timeout::{self, CommandClass};
+use crate::window::open_repo_in_window;
+
+/// Lines of git's error output kept for the error message
+const MAX_ERROR_LINES: usize = 5;
+
+/// Options for `clone_repo`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CloneOptions {
+    /// Branch to check out instead of the remote's default
+    pub branch: Option<String>,
+    /// Create a shallow clone with this many commits
+    pub depth: Option<u32>,
+    pub recurse_submodules: bool,
+}
+
+/// Payload of the `clone-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CloneProgressEvent {
+    dest: String,
+    /// Stage as git reports it, e.g. "Receiving objects"
+    stage: String,
+    percent: Option<u32>,
+    current: Option<u64>,
+    total: Option<u64>,
+}
+
+/// Parse a progress line such as `Receiving objects:  45% (450/1000), 1.2 MiB`
+fn parse_progress(dest: &str, line: &str) -> Option<CloneProgressEvent> {
+    let line = line.strip_prefix("remote: ").unwrap_or(line);
+    let (stage, rest) = line.split_once(':')?;
+    let (percent, rest) = rest.trim_start().split_once('%')?;
+    let percent = percent.trim().parse().ok()?;
+    let counts = rest
+        .trim_start()
+        .strip_prefix('(')
+        .and_then(|rest| rest.split_once(')'))
+        .and_then(|(counts, _)| counts.split_once('/'));
+
+    Some(CloneProgressEvent {
+        dest: dest.to_string(),
+        stage: stage.trim().to_string(),
+        percent: Some(percent),
+        current: counts.and_then(|(current, _)| current.parse().ok()),
+        total: counts.and_then(|(_, total)| total.parse().ok()),
+    })
+}
+
+/// Read git's `--progress` output, emitting each update as `event`.
+/// Returns the other lines git printed, for the error message if it fails.
+pub async fn read_progress(
+    app: &AppHandle,
+    event: &str,
+    dest: &str,
+    mut stderr: ChildStderr,
+) -> CommandResult<Vec<String>> {
+    // Progress lines end in \r while they update and \n once done
+    let mut errors = Vec::new();
+    let mut line = Vec::new();
+    let mut buffer = [0u8; 4096];
+    loop {
+        let n = stderr
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read git output: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        for &byte in &buffer[..n] {
+            if byte != b'\r' && byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            let text = String::from_utf8_lossy(&line).trim().to_string();
+            line.clear();
+            if text.is_empty() {
+                continue;
+            }
+            match parse_progress(dest, &text) {
+                Some(progress) => {
+                    let _ = app.emit(event, progress);
+                }
+                None if byte == b'\n' && !text.starts_with("Cloning into") => {
+                    errors.push(text);
+                }
+                None => {}
+            }
+        }
+    }
+    Ok(errors)
+}
+
+/// Last few lines of git's error output
+pub fn error_tail(errors: &[String]) -> String {
+    let start = errors.len().saturating_sub(MAX_ERROR_LINES);
+    errors[start..].join("\n")
+}
+
+/// Run `git clone`, emitting `clone-progress` as it goes
+async fn run_clone(
+    app: &AppHandle,
+    url: &str,
+    dest: &str,
+    options: &CloneOptions,
+) -> CommandResult<()> {
+    let mut command = Command::new("git");
+    command.args(["clone", "--progress"]);
+    if let Some(branch) = &options.branch {
+        command.args(["--branch", branch]);
+    }
+    if let Some(depth) = options.depth {
+        command.arg(format!("--depth={}", depth));
+    }
+    if options.recurse_submodules {
+        command.arg("--recurse-submodules");
+    }
+    // Never prompt on a terminal the user cannot see; ask through the app instead
+    command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .envs(askpass::env(app))
+        .arg("--")
+        .args([url, dest])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+    let stderr = child.stderr.take().ok_or("Failed to read git output")?;
+    let errors = read_progress(app, "clone-progress", dest, stderr).await?;
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for git: {}", e))?;
+    if !status.success() {
+        return Err(AppError::from(format!(
+            "git clone: {}",
+            error_tail(&errors)
+        )));
+    }
+    Ok(())
+}
+
+/// Remove everything inside `dir` but keep the directory itself.
+fn empty_dir(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let _ = if path.is_dir() && !path.is_symlink() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+    }
+}
+
+/// Clone a repository into `dest` and open it in a new window.
+/// Cancelling or failing removes the partial clone.
+#[tauri::command]
+pub async fn clone_repo(
+    app: AppHandle,
+    url: String,
+    dest: String,
+    options: Option<CloneOptions>,
+    request_id: Option<String>,
+) -> CommandResult<()> {
+    let options = options.unwrap_or_default();
+    if url.trim().is_empty() || url.starts_with('-') {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid repository URL: {:?}", remotes::redact(&url)),
+        ));
+    }
+
+    let dest_path = Path::new(&dest);
+    let existed = dest_path.exists();
+    if existed
+        && std::fs::read_dir(dest_path)
+            .map(|mut entries| entries.next().is_some())
+            .unwrap_or(true)
+    {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            format!("Destination is not empty: {}", dest),
+        )
+        .with_metadata(serde_json::json!({ "dest": dest })));
+    }
+
+    log_info!("Cloning {} into {}", remotes::redact(&url), dest);
+    let result = timeout::run(&app, CommandClass::Long, request_id, |_| {
+        run_clone(&app, &url, &dest, &options)
+    })
+    .await;
+
+    if let Err(e) = result {
+        // Killed clones leave a partial checkout behind
+        if existed {
+            empty_dir(dest_path);
+        } else {
+            let _ = std::fs::remove_dir_all(dest_path);
+        }
+        return Err(e);
+    }
+
+    log_info!("Cloned {}", remotes::redact(&url));
+    open_repo_in_window(&app, &dest).await?;
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! Stashing uncommitted changes when a window is closed. With the
+//! `stashOnClose` setting, closing a window whose working tree is dirty asks
+//! whether to stash first, so an experiment left mid-way isn't lost to a
+//! later checkout or reset.
+
+use git2::{Repository, Signature, StashFlags};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, Window};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::broadcast::WindowRegistry;
+use crate::{i18n, native, settings};
+
+/// Message of stashes made on close. git prefixes it with the branch, giving
+/// e.g. `On main: Stashed when closing opentui-git`.
+const STASH_MESSAGE: &str = "Stashed when closing opentui-git";
+
+/// Windows whose close was already confirmed, by label
+#[derive(Clone, Default)]
+pub struct CloseStashState(Arc<Mutex<HashSet<String>>>);
+
+/// Stash everything, untracked files included
+fn stash(repo_path: &str) -> Result<(), git2::Error> {
+    let mut repo = Repository::open(repo_path)?;
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("opentui-git", "opentui-git@localhost"))?;
+    repo.stash_save(
+        &signature,
+        STASH_MESSAGE,
+        Some(StashFlags::INCLUDE_UNTRACKED),
+    )?;
+    Ok(())
+}
+
+/// Ask whether to stash a dirty working tree and stash if so. Returns false
+/// if stashing failed, so the changes can be dealt with before closing.
+fn prompt(app: &AppHandle, repo_path: &str) -> bool {
+    let dirty = Repository::open(repo_path).and_then(|repo| native::is_dirty(&repo));
+    let stash_first = match dirty {
+        Ok(false) => false,
+        Ok(true) => app
+            .dialog()
+            .message(i18n::tr(app, "close-stash.message", &[("repo", repo_path)]))
+            .title(i18n::t(app, "close-stash.title"))
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                i18n::t(app, "close-stash.stash"),
+                i18n::t(app, "close-stash.discard"),
+            ))
+            .blocking_show(),
+        // Not being able to tell must not keep the window open
+        Err(e) => {
+            log_error!("Failed to read the status of {}: {}", repo_path, e);
+            false
+        }
+    };
+
+    if !stash_first {
+        return true;
+    }
+    match stash(repo_path) {
+        Ok(()) => {
+            log_info!("Stashed the changes in {} on close", repo_path);
+            true
+        }
+        Err(e) => {
+            log_error!("Failed to stash {} on close: {}", repo_path, e);
+            app.dialog()
+                .message(i18n::tr(
+                    app,
+                    "close-stash.failed",
+                    &[("error", e.message())],
+                ))
+                .title(i18n::t(app, "close-stash.title"))
+                .kind(MessageDialogKind::Error)
+                .blocking_show();
+            false
+        }
+    }
+}
+
+/// Run the stash prompt for a window's repository, and close the window
+/// again once done
+fn confirm(app: AppHandle, window: Window, repo_path: String) {
+    // Keep the window open so the changes can be dealt with
+    if !prompt(&app, &repo_path) {
+        return;
+    }
+
+    if let Ok(mut confirmed) = app.state::<CloseStashState>().0.lock() {
+        confirmed.insert(window.label().to_string());
+    }
+    let _ = window.close();
+}
+
+/// Repository the window is the last one open on. Other windows on the same
+/// repository keep using its working tree, so closing one of them doesn't stash.
+fn last_window_on(registry: &WindowRegistry, label: &str) -> Option<String> {
+    let repo_path = registry.repo_for(label)?;
+    (registry.windows_for(&repo_path) == [label]).then_some(repo_path)
+}
+
+/// Whether closing the window has to wait for the stash prompt. If so, the
+/// prompt runs in the background and closes the window again afterwards.
+pub fn intercept(app: &AppHandle, window: &Window) -> bool {
+    let state = app.state::<CloseStashState>();
+    let Ok(mut confirmed) = state.0.lock() else {
+        return false;
+    };
+    if confirmed.remove(window.label()) || !settings::current(app).stash_on_close {
+        return false;
+    }
+    drop(confirmed);
+
+    let Some(repo_path) = last_window_on(&app.state::<WindowRegistry>(), window.label()) else {
+        return false;
+    };
+    let (app, window) = (app.clone(), window.clone());
+    tauri::async_runtime::spawn_blocking(move || confirm(app, window, repo_path));
+    true
+}
+
+/// Run the stash prompt for every open repository before quitting, since
+/// windows closed by quitting skip `intercept`. Returns false if a stash
+/// failed and the app should stay open.
+pub async fn before_quit(app: &AppHandle) -> bool {
+    if !settings::current(app).stash_on_close {
+        return true;
+    }
+    let registry = app.state::<WindowRegistry>().inner().clone();
+    let mut repos = Vec::new();
+    for label in app.webview_windows().keys() {
+        if let Some(repo_path) = registry.repo_for(label) {
+            if !repos.contains(&repo_path) {
+                repos.push(repo_path);
+            }
+        }
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        repos.iter().all(|repo_path| prompt(&app, repo_path))
+    })
+    .await
+    .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_last_window_on_a_repo_stashes() {
+        let registry = WindowRegistry::default();
+        registry.add("main", "/work/app");
+        registry.add("repo-1", "/work/app");
+        registry.add("repo-2", "/work/lib");
+
+        assert_eq!(last_window_on(&registry, "main"), None);
+        assert_eq!(last_window_on(&registry, "repo-1"), None);
+        assert_eq!(
+            last_window_on(&registry, "repo-2").as_deref(),
+            Some("/work/lib")
+        );
+        assert_eq!(last_window_on(&registry, "settings"), None);
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use tauri::{
+    Emitter, EventTarget, LogicalSize, PhysicalPosition, PhysicalSize, Size, WebviewWindow,
+};
+
+use crate::broadcast;
+use crate::display::{self, MonitorArea};
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::settings;
+
+/// Size of the compact panel, enough for status and a quick commit
+const COMPACT_SIZE: LogicalSize<f64> = LogicalSize {
+    width: 360.0,
+    height: 240.0,
+};
+
+/// Window geometry to restore when leaving compact mode, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl WindowGeometry {
+    /// Current position and size of a window
+    pub fn of(window: &WebviewWindow) -> tauri::Result<Self> {
+        let position = window.outer_position()?;
+        let size = window.inner_size()?;
+        Ok(Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        })
+    }
+
+    /// Move and resize a window to this geometry, brought back onto the
+    /// monitors if they changed since it was saved
+    pub fn apply(&self, window: &WebviewWindow) -> tauri::Result<()> {
+        let fitted = self.fit(&display::monitor_areas(window.app_handle()));
+        if fitted != *self {
+            log_info!(
+                "Moved window {} back on screen ({:?} -> {:?})",
+                window.label(),
+                self,
+                fitted
+            );
+        }
+        window.set_size(PhysicalSize::new(fitted.width, fitted.height))?;
+        window.set_position(PhysicalPosition::new(fitted.x, fitted.y))
+    }
+
+    /// Pixels of this geometry on a monitor
+    fn overlap(&self, area: MonitorArea) -> i64 {
+        let left = i64::from(self.x).max(i64::from(area.x));
+        let top = i64::from(self.y).max(i64::from(area.y));
+        let right = (i64::from(self.x) + i64::from(self.width))
+            .min(i64::from(area.x) + i64::from(area.width));
+        let bottom = (i64::from(self.y) + i64::from(self.height))
+            .min(i64::from(area.y) + i64::from(area.height));
+        (right - left).max(0) * (bottom - top).max(0)
+    }
+
+    /// This geometry shrunk to fit a monitor and pushed inside its edges
+    fn clamp_to(self, area: MonitorArea) -> Self {
+        let width = self.width.min(area.width);
+        let height = self.height.min(area.height);
+        let max_x = i64::from(area.x) + i64::from(area.width - width);
+        let max_y = i64::from(area.y) + i64::from(area.height - height);
+        Self {
+            x: i64::from(self.x).clamp(i64::from(area.x), max_x) as i32,
+            y: i64::from(self.y).clamp(i64::from(area.y), max_y) as i32,
+            width,
+            height,
+        }
+    }
+
+    /// This size, shrunk to fit if needed, centered on a monitor
+    pub fn center_on(self, area: MonitorArea) -> Self {
+        let width = self.width.min(area.width);
+        let height = self.height.min(area.height);
+        Self {
+            x: area.x + ((area.width - width) / 2) as i32,
+            y: area.y + ((area.height - height) / 2) as i32,
+            width,
+            height,
+        }
+    }
+
+    /// Keep a geometry that lies on one monitor; clamp one that spills over
+    /// onto the monitor it mostly covers, and center one on no monitor at all
+    /// (e.g. after undocking) on the first, primary monitor
+    pub fn fit(self, monitors: &[MonitorArea]) -> Self {
+        let Some(primary) = monitors.first() else {
+            return self;
+        };
+        let area = |geometry: &Self| i64::from(geometry.width) * i64::from(geometry.height);
+        if monitors
+            .iter()
+            .any(|monitor| self.overlap(*monitor) == area(&self))
+        {
+            return self;
+        }
+        match monitors
+            .iter()
+            .copied()
+            .filter(|monitor| self.overlap(*monitor) > 0)
+            .max_by_key(|monitor| self.overlap(*monitor))
+        {
+            Some(monitor) => self.clamp_to(monitor),
+            None => self.center_on(*primary),
+        }
+    }
+}
+
+/// Payload of the `compact-mode-changed` event
+#[derive(Debug, Clone, Serialize)]
+struct CompactModeEvent {
+    compact: bool,
+}
+
+fn window_error(e: tauri::Error) -> AppError {
+    AppError::new(
+        ErrorCode::Internal,
+        format!("Failed to resize window: {}", e),
+    )
+}
+
+/// Shrink a window to the always-on-top panel
+fn enter(window: &WebviewWindow) -> tauri::Result<()> {
+    window.unmaximize()?;
+    window.set_min_size(None::<Size>)?;
+    window.set_size(COMPACT_SIZE)?;
+    window.set_always_on_top(true)
+}
+
+/// Put a window back where it was before compact mode
+fn leave(window: &WebviewWindow, geometry: WindowGeometry) -> tauri::Result<()> {
+    window.set_always_on_top(false)?;
+    window.set_min_size(Some(
+        settings::current(window.app_handle()).window.min_size(),
+    ))?;
+    geometry.apply(window)
+}
+
+/// Re-enter compact mode for a window that was compact when the app quit
+pub fn restore(window: &WebviewWindow) {
+    let key = broadcast::window_key(window);
+    if !settings::current(window.app_handle())
+        .compact_windows
+        .contains_key(&key)
+    {
+        return;
+    }
+    if let Err(e) = enter(window) {
+        log_error!(
+            "Failed to restore compact mode for {}: {}",
+            window.label(),
+            e
+        );
+    }
+}
+
+/// Toggle the calling window between its regular size and a small
+/// always-on-top panel. Returns whether the window is now compact.
+#[tauri::command]
+pub fn toggle_compact_mode(window: WebviewWindow) -> CommandResult<bool> {
+    let app = window.app_handle();
+    let key = broadcast::window_key(&window);
+    let saved = settings::current(app).compact_windows.get(&key).copied();
+
+    let compact = match saved {
+        Some(geometry) => {
+            leave(&window, geometry).map_err(window_error)?;
+            settings::update(app, |settings| {
+                settings.compact_windows.remove(&key);
+            })?;
+            false
+        }
+        None => {
+            let geometry = WindowGeometry::of(&window).map_err(window_error)?;
+            enter(&window).map_err(window_error)?;
+            settings::update(app, |settings| {
+                settings.compact_windows.insert(key, geometry);
+            })?;
+            true
+        }
+    };
+
+    log_info!(
+        "Window {} {} compact mode",
+        window.label(),
+        if compact { "entered" } else { "left" }
+    );
+    let _ = window.emit_to(
+        EventTarget::webview_window(window.label()),
+        "compact-mode-changed",
+        CompactModeEvent { compact },
+    );
+    Ok(compact)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAPTOP: MonitorArea = MonitorArea {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+    const EXTERNAL: MonitorArea = MonitorArea {
+        x: 1920,
+        y: 0,
+        width: 2560,
+        height: 1440,
+    };
+
+    fn geometry(x: i32, y: i32, width: u32, height: u32) -> WindowGeometry {
+        WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn keeps_geometry_on_a_monitor() {
+        let saved = geometry(2200, 100, 1200, 800);
+        assert_eq!(saved.fit(&[LAPTOP, EXTERNAL]), saved);
+        assert_eq!(saved.fit(&[]), saved);
+    }
+
+    #[test]
+    fn centers_windows_left_on_a_disconnected_monitor() {
+        let saved = geometry(2200, 100, 1200, 800);
+        assert_eq!(saved.fit(&[LAPTOP]), geometry(360, 140, 1200, 800));
+        // Too big for the remaining monitor as well
+        let saved = geometry(2000, 0, 2400, 1400);
+        assert_eq!(saved.fit(&[LAPTOP]), geometry(0, 0, 1920, 1080));
+    }
+
+    #[test]
+    fn clamps_windows_spilling_off_screen() {
+        let saved = geometry(1500, 600, 1200, 800);
+        assert_eq!(saved.fit(&[LAPTOP]), geometry(720, 280, 1200, 800));
+        // Mostly on the external monitor, so it stays there
+        let saved = geometry(1800, -50, 1200, 800);
+        assert_eq!(saved.fit(&[LAPTOP, EXTERNAL]), geometry(1920, 0, 1200, 800));
+    }
+}
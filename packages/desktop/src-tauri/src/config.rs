@@ -0,0 +1,226 @@
+//! Where the app starts from: the launch repository, the launch port and the
+//! user's shell. The launch repository is resolved at runtime, so packaged
+//! builds find it the same way as dev builds.
+
+use std::net::TcpListener;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::{dev_frontend, mock_server, patch_files, ports, settings, worktrees};
+
+/// Port for the launch repository's server: `OPENTUI_PORT` if set, otherwise one from
+/// the configured range
+pub fn get_sidecar_port(app: &AppHandle, repo_path: &str) -> Result<u32, String> {
+    // Check for environment variable first
+    if let Ok(port_str) = std::env::var("OPENTUI_PORT") {
+        if let Ok(port) = port_str.parse::<u32>() {
+            return Ok(port);
+        }
+    }
+
+    ports::pick(app, Some(repo_path), &[])
+}
+
+/// Ask the OS for a free port
+pub fn find_free_port() -> Result<u32, String> {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .map_err(|e| format!("Failed to bind to find free port: {}", e))?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to get local address: {}", e))?;
+    Ok(address.port() as u32)
+}
+
+/// Get the user's shell (for macOS/Linux)
+pub fn get_user_shell() -> String {
+    std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+}
+
+/// Check if the shell is fish (which uses different flags)
+pub fn is_fish_shell(shell: &str) -> bool {
+    shell.ends_with("/fish") || shell == "fish"
+}
+
+/// Get the appropriate shell flags for login/interactive mode
+pub fn get_shell_flags(shell: &str) -> Vec<&'static str> {
+    if is_fish_shell(shell) {
+        // fish uses -l for login, doesn't support -i the same way
+        vec!["-l", "-c"]
+    } else {
+        // bash, zsh, sh all support -il -c
+        vec!["-il", "-c"]
+    }
+}
+
+/// Repository passed as the first positional command-line argument,
+/// resolved against the directory the command was run from
+pub fn repo_arg(args: &[String], cwd: &Path) -> Option<String> {
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        // Skip the values of flags that take one
+        if arg == dev_frontend::URL_FLAG || arg == mock_server::SCRIPT_FLAG {
+            args.next();
+            continue;
+        }
+        if !arg.starts_with('-') && !patch_files::is_patch_file(Path::new(arg)) {
+            return Some(cwd.join(arg).to_string_lossy().to_string());
+        }
+    }
+    None
+}
+
+/// Folders searched for a `.repo-path` file: the executable's own and, for
+/// dev builds in `src-tauri/target/<profile>`, the two above it where the
+/// predev script writes it
+const REPO_PATH_FILE_DEPTH: usize = 3;
+
+/// Repository named in a `.repo-path` file next to the executable or in
+/// `src-tauri`, written by the predev script. Only dev builds read it;
+/// packaged builds pin a repository with `set_default_repo` instead.
+fn repo_path_file(exe_dir: &Path) -> Option<String> {
+    exe_dir
+        .ancestors()
+        .take(REPO_PATH_FILE_DEPTH)
+        .filter_map(|dir| std::fs::read_to_string(dir.join(".repo-path")).ok())
+        .map(|contents| contents.trim().to_string())
+        .find(|path| !path.is_empty())
+}
+
+/// Get the repository path
+/// Priority:
+/// 1. OPENTUI_REPO environment variable
+/// 2. Folder passed on the command line
+/// 3. .repo-path file (written by predev script, dev builds only)
+/// 4. Default repository set with `set_default_repo`, if it still exists
+/// 5. Current working directory (fallback)
+pub fn get_repo_path(app: &AppHandle) -> String {
+    // Check for environment variable
+    if let Ok(repo) = std::env::var("OPENTUI_REPO") {
+        if !repo.is_empty() {
+            return repo;
+        }
+    }
+
+    // A folder given on the command line, e.g. from the file manager context menu
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(repo) = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| repo_arg(&args, &cwd))
+    {
+        return repo;
+    }
+
+    // Check for .repo-path file written by predev script
+    if let Some(repo) = std::env::current_exe()
+        .ok()
+        .filter(|_| cfg!(debug_assertions))
+        .and_then(|exe| repo_path_file(exe.parent()?))
+    {
+        return repo;
+    }
+
+    if let Some(repo) = settings::current(app).default_repo {
+        if Path::new(&repo).is_dir() {
+            return repo;
+        }
+        log_error!("Default repository {} no longer exists", repo);
+    }
+
+    // Fallback to current directory
+    std::env::current_dir()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| ".".to_string())
+}
+
+/// Set the repository opened when the app is launched without one. Returns
+/// the path stored, the top of the working tree.
+#[tauri::command]
+pub fn set_default_repo(app: AppHandle, repo_path: String) -> CommandResult<String> {
+    let workdir = worktrees::locate(&repo_path)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::NotFound,
+                format!("{} is not a git repository", repo_path),
+            )
+        })?
+        .workdir;
+    settings::update(&app, |settings| {
+        settings.default_repo = Some(workdir.clone())
+    })?;
+    log_info!("Default repository set to {}", workdir);
+    Ok(workdir)
+}
+
+/// The repository opened when the app is launched without one, if set
+#[tauri::command]
+pub fn get_default_repo(app: AppHandle) -> Option<String> {
+    settings::current(&app).default_repo
+}
+
+/// Go back to opening the current directory when launched without a
+/// repository
+#[tauri::command]
+pub fn clear_default_repo(app: AppHandle) -> CommandResult<()> {
+    settings::update(&app, |settings| settings.default_repo = None)?;
+    log_info!("Default repository cleared");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_the_repo_argument_against_cwd() {
+        let cwd = Path::new("/home/me");
+        assert_eq!(
+            repo_arg(&args(&["opentui-git", "projects/app"]), cwd),
+            Some("/home/me/projects/app".to_string())
+        );
+        assert_eq!(
+            repo_arg(&args(&["opentui-git", "/srv/repo"]), cwd),
+            Some("/srv/repo".to_string())
+        );
+        assert_eq!(repo_arg(&args(&["opentui-git"]), cwd), None);
+    }
+
+    #[test]
+    fn skips_flags_and_patch_files() {
+        let cwd = Path::new("/home/me");
+        assert_eq!(
+            repo_arg(
+                &args(&[
+                    "opentui-git",
+                    "--headless",
+                    "--dev-frontend-url",
+                    "http://localhost:1420",
+                    "--mock-server",
+                    "script.json",
+                    "fix.patch",
+                    "repo",
+                ]),
+                cwd
+            ),
+            Some("/home/me/repo".to_string())
+        );
+        assert_eq!(
+            repo_arg(&args(&["opentui-git", "--mock-server", "script.json"]), cwd),
+            None
+        );
+    }
+
+    #[test]
+    fn uses_login_flags_for_the_shell() {
+        assert!(is_fish_shell("/usr/local/bin/fish"));
+        assert!(is_fish_shell("fish"));
+        assert!(!is_fish_shell("/bin/zsh"));
+        assert!(!is_fish_shell("/usr/bin/selfish"));
+        assert_eq!(get_shell_flags("/opt/homebrew/bin/fish"), vec!["-l", "-c"]);
+        assert_eq!(get_shell_flags("/bin/bash"), vec!["-il", "-c"]);
+    }
+}
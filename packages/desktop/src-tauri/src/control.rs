@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::logs::LogLevel;
+use crate::sidecar::{ServerState, Sidecar};
+use crate::window::sidecar_for_window;
+
+/// A secret sent to the sidecar, kept out of the control message log
+#[derive(Clone, Serialize)]
+#[serde(transparent)]
+pub struct Secret(pub String);
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+/// Shell-initiated actions sent to the sidecar as JSON lines on its stdin
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum ControlMessage {
+    /// Release memory held by the server
+    FlushCaches,
+    /// Change how verbosely the server logs
+    SetLogLevel { level: LogLevel },
+    /// Apply the configuration the server would now be spawned with: its
+    /// log level, history limit, and the environment its git commands see.
+    /// Variables dropped from the configuration stay set until a restart.
+    #[serde(rename_all = "camelCase")]
+    ReloadConfig {
+        log_level: LogLevel,
+        history_limit: Option<u32>,
+        /// Values may hold credentials, e.g. in proxy URLs
+        env: HashMap<String, Secret>,
+    },
+    /// Start serving a repository (standby sidecars only)
+    BindRepo {
+        #[serde(rename = "repoPath")]
+        repo_path: String,
+    },
+    /// Answer to a `pair-request` event; `None` if the code was refused
+    PairResult { id: u64, token: Option<Secret> },
+    /// Replace the device tokens the server accepts besides its secret
+    SetDeviceTokens { tokens: Vec<Secret> },
+    /// Cut down background work while the app saves power, or resume it
+    SetIdle { idle: bool },
+}
+
+/// Prefix of stdout lines carrying a sidecar event
+const EVENT_PREFIX: &str = "@event ";
+
+/// Server-side events reported by the sidecar as prefixed JSON lines on its stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum SidecarEvent {
+    FetchComplete {
+        /// Started by the fetch schedule rather than the user
+        #[serde(default)]
+        scheduled: bool,
+    },
+    PushFailed {
+        message: String,
+    },
+    MergeConflict {
+        branch: String,
+        files: Vec<String>,
+    },
+    /// Progress of a fetch, pull or push stage, in percent
+    Progress {
+        method: String,
+        stage: String,
+        progress: u64,
+    },
+    OperationFinished {
+        kind: String,
+    },
+    /// A LAN device redeeming a pairing code, answered with `ControlMessage::PairResult`
+    PairRequest {
+        id: u64,
+        code: String,
+        name: String,
+    },
+}
+
+/// Parse a sidecar stdout line as an event
+pub fn parse_event(line: &str) -> Option<SidecarEvent> {
+    let json = line.trim().strip_prefix(EVENT_PREFIX)?;
+    match serde_json::from_str(json) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            log_error!("Invalid sidecar event {}: {}", json, e);
+            None
+        }
+    }
+}
+
+/// Write a line of JSON to a sidecar's stdin
+pub fn write_line(sidecar: &Sidecar, json: &str) -> Result<(), String> {
+    let mut guard = sidecar
+        .child
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on server state: {}", e))?;
+    let child = guard.as_mut().ok_or("Sidecar not managed by this app")?;
+    child
+        .write(format!("{}\n", json).as_bytes())
+        .map_err(|e| format!("Failed to write to sidecar stdin: {}", e))
+}
+
+/// Write a control message to one sidecar's stdin
+pub fn send_to(sidecar: &Sidecar, message: &ControlMessage) -> Result<(), String> {
+    let line = serde_json::to_string(message)
+        .map_err(|e| format!("Failed to serialize control message: {}", e))?;
+    write_line(sidecar, &line)?;
+    log_info!(
+        "Sent control message to sidecar for {}: {:?}",
+        sidecar.repo_path,
+        message
+    );
+    Ok(())
+}
+
+/// Write a control message to every sidecar's stdin
+pub fn send(app: &AppHandle, message: &ControlMessage) -> Result<(), String> {
+    let sidecars = app.state::<ServerState>().all();
+    if sidecars.is_empty() {
+        return Err("Server not running".to_string());
+    }
+
+    sidecars
+        .iter()
+        .map(|sidecar| send_to(sidecar, message))
+        .fold(Ok(()), |result, sent| result.and(sent))
+}
+
+/// Control messages the UI may send; the rest are the shell's own business
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum UiControlMessage {
+    FlushCaches,
+    ReloadConfig,
+    SetLogLevel { level: LogLevel },
+}
+
+impl UiControlMessage {
+    /// The message to send a sidecar, filled in from the current configuration
+    fn resolve(self, app: &AppHandle, sidecar: &Sidecar) -> ControlMessage {
+        match self {
+            UiControlMessage::FlushCaches => ControlMessage::FlushCaches,
+            UiControlMessage::ReloadConfig => crate::sidecar::current_config(app, sidecar),
+            UiControlMessage::SetLogLevel { level } => ControlMessage::SetLogLevel { level },
+        }
+    }
+}
+
+/// Send a control message to the sidecar serving the calling window's
+/// repository, e.g. to reload its config or change its log level without
+/// restarting it
+#[tauri::command]
+pub fn send_sidecar_control(
+    app: AppHandle,
+    window: WebviewWindow,
+    message: UiControlMessage,
+) -> CommandResult<()> {
+    let sidecar = sidecar_for_window(&app, window.label())
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "No server for this window"))?;
+    send_to(&sidecar, &message.resolve(&app, &sidecar))?;
+    Ok(())
+}
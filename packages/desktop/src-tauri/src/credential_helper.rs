@@ -0,0 +1,270 @@
+//! A git credential helper backed by the OS keychain.
+//!
+//! Run as `opentui-git credential <get|store|erase>`, this binary speaks git's
+//! credential helper protocol over the same keychain entries as the
+//! credentials commands. The `credential-opentui` git alias points at it, so
+//! `credential.helper = opentui` works for the sidecar's git, which gets the
+//! alias through its environment, and in a terminal once
+//! `install_credential_helper` has added it to the global config. Saving a
+//! credential git accepted, or removing one it rejected, is confirmed in the
+//! running app when the helper was started by its sidecar.
+
+use std::collections::HashMap;
+use std::io::Read;
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::askpass::{self, PromptKind};
+use crate::credentials::{self, Credential};
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::{i18n, launch};
+
+/// First argument selecting helper mode
+const SUBCOMMAND: &str = "credential";
+/// Helper name for `credential.helper`
+const HELPER_NAME: &str = "opentui";
+
+/// Attributes git passes on stdin, one `key=value` per line until a blank line
+fn parse_request(input: &str) -> HashMap<String, String> {
+    input
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Answer to `get` for git to read from stdout
+fn format_response(credential: &Credential) -> String {
+    format!(
+        "username={}\npassword={}\n",
+        credential.username, credential.secret
+    )
+}
+
+/// Host a request is for, from `host` or else `url`
+fn request_host(request: &HashMap<String, String>) -> Option<&str> {
+    request
+        .get("host")
+        .or_else(|| request.get("url"))
+        .map(String::as_str)
+        .filter(|host| !host.is_empty())
+}
+
+/// Credential a `store` or `erase` request describes
+fn request_credential(request: &HashMap<String, String>) -> Option<Credential> {
+    Some(Credential {
+        username: request.get("username")?.clone(),
+        secret: request.get("password")?.clone(),
+    })
+}
+
+/// Ask the running app to confirm a change to the keychain. Without an app
+/// to ask, e.g. in a terminal, the change is made as git asked.
+fn confirmed(kind: PromptKind) -> bool {
+    match askpass::relay(kind) {
+        Ok(answer) => answer.is_some(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(e) => {
+            eprintln!("opentui-git credential: {}", e);
+            false
+        }
+    }
+}
+
+fn get(host: &str, request: &HashMap<String, String>) -> CommandResult<()> {
+    let Some(stored) = credentials::lookup(host)? else {
+        return Ok(());
+    };
+    // git may already know whose credential it wants
+    if request
+        .get("username")
+        .is_some_and(|username| *username != stored.username)
+    {
+        return Ok(());
+    }
+    print!("{}", format_response(&stored));
+    Ok(())
+}
+
+fn store(host: &str, request: &HashMap<String, String>) -> CommandResult<()> {
+    let Some(credential) = request_credential(request) else {
+        return Ok(());
+    };
+    // git stores every credential that worked, including the one `get` gave it
+    if credentials::lookup(host)?.as_ref() == Some(&credential) {
+        return Ok(());
+    }
+    if confirmed(PromptKind::SaveCredential {
+        host: host.to_string(),
+        username: credential.username.clone(),
+    }) {
+        credentials::save(host, &credential)?;
+    }
+    Ok(())
+}
+
+fn erase(host: &str, request: &HashMap<String, String>) -> CommandResult<()> {
+    let Some(stored) = credentials::lookup(host)? else {
+        return Ok(());
+    };
+    // Only the credential that was rejected, not one stored since
+    if request_credential(request).is_some_and(|rejected| rejected != stored) {
+        return Ok(());
+    }
+    if confirmed(PromptKind::RemoveCredential {
+        host: host.to_string(),
+        username: stored.username,
+    }) {
+        credentials::remove(host)?;
+    }
+    Ok(())
+}
+
+/// When git started this binary as its credential helper, answer its request
+/// and return the exit code; otherwise `None`.
+pub fn run_helper() -> Option<i32> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() != Some(SUBCOMMAND) {
+        return None;
+    }
+    let action = args.next().unwrap_or_default();
+
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("opentui-git credential: {}", e);
+        return Some(1);
+    }
+    let request = parse_request(&input);
+    let Some(host) = request_host(&request) else {
+        return Some(0);
+    };
+
+    let result = match action.as_str() {
+        "get" => get(host, &request),
+        "store" => store(host, &request),
+        "erase" => erase(host, &request),
+        // Helpers ignore actions they don't know, as git may add more
+        _ => Ok(()),
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("opentui-git credential: {}", e.message);
+            Some(1)
+        }
+    }
+}
+
+/// Confirm saving or removing a credential in a dialog, on a blocking thread
+pub fn confirm(app: &AppHandle, kind: &PromptKind) -> bool {
+    let (title, message, ok, cancel) = match kind {
+        PromptKind::SaveCredential { host, username } => (
+            "credential-helper.save.title",
+            i18n::tr(
+                app,
+                "credential-helper.save.message",
+                &[("host", host.as_str()), ("username", username.as_str())],
+            ),
+            "credential-helper.save",
+            "credential-helper.dont-save",
+        ),
+        PromptKind::RemoveCredential { host, username } => (
+            "credential-helper.remove.title",
+            i18n::tr(
+                app,
+                "credential-helper.remove.message",
+                &[("host", host.as_str()), ("username", username.as_str())],
+            ),
+            "credential-helper.remove",
+            "credential-helper.keep",
+        ),
+        _ => return false,
+    };
+    app.dialog()
+        .message(message)
+        .title(i18n::t(app, title))
+        .kind(MessageDialogKind::Info)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            i18n::t(app, ok),
+            i18n::t(app, cancel),
+        ))
+        .blocking_show()
+}
+
+/// The `credential-opentui` alias running this binary in helper mode
+fn alias() -> Option<(String, String)> {
+    let exe = std::env::current_exe().ok()?;
+    Some((
+        format!("alias.credential-{}", HELPER_NAME),
+        format!(
+            "!{} {}",
+            launch::shell_quote(&exe.to_string_lossy()),
+            SUBCOMMAND
+        ),
+    ))
+}
+
+/// Git config that lets the sidecar's git use `credential.helper = opentui`
+pub fn git_config() -> Vec<(String, String)> {
+    alias().into_iter().collect()
+}
+
+/// Add the `credential-opentui` alias to the user's global git config, so
+/// `credential.helper = opentui` works outside the app too. Returns the
+/// helper name to set.
+#[tauri::command]
+pub async fn install_credential_helper() -> CommandResult<String> {
+    let (key, value) = alias()
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "Failed to locate the app executable"))?;
+    let output = tokio::process::Command::new("git")
+        .args(["config", "--global", &key, &value])
+        .output()
+        .await
+        .map_err(|e| AppError::new(ErrorCode::NotFound, format!("Failed to run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(AppError::from(format!(
+            "git config: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    log_info!("Installed the {} credential helper", HELPER_NAME);
+    Ok(HELPER_NAME.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_git_requests() {
+        let request = parse_request(
+            "protocol=https\nhost=github.com\nusername=ada\npassword=s3=cret\n\nignored=1\n",
+        );
+        assert_eq!(request_host(&request), Some("github.com"));
+        assert_eq!(
+            request_credential(&request),
+            Some(Credential {
+                username: "ada".to_string(),
+                secret: "s3=cret".to_string(),
+            })
+        );
+        assert!(!request.contains_key("ignored"));
+
+        let request = parse_request("url=https://example.com/repo.git\n");
+        assert_eq!(request_host(&request), Some("https://example.com/repo.git"));
+        assert_eq!(request_credential(&request), None);
+    }
+
+    #[test]
+    fn answers_with_username_and_password() {
+        let credential = Credential {
+            username: "ada".to_string(),
+            secret: "token".to_string(),
+        };
+        assert_eq!(
+            format_response(&credential),
+            "username=ada\npassword=token\n"
+        );
+    }
+}
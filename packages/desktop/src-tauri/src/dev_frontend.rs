@@ -0,0 +1,78 @@
+//! Loading the UI from a running dev server (`--dev-frontend-url` or
+//! `OPENTUI_DEV_URL`) instead of the bundled assets, so the frontend can be
+//! iterated on with hot reload while this shell still manages the real
+//! sidecar, injects its port and serves the commands.
+
+use tauri::ipc::CapabilityBuilder;
+use tauri::{AppHandle, Manager, Url, WebviewUrl};
+
+/// Command-line flag pointing the windows at a dev server
+pub const URL_FLAG: &str = "--dev-frontend-url";
+/// Environment variable doing the same, for when the flag is awkward to pass
+const URL_VAR: &str = "OPENTUI_DEV_URL";
+
+/// Value of `--dev-frontend-url <url>` or `--dev-frontend-url=<url>`
+fn flag_value() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == URL_FLAG {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(URL_FLAG).and_then(|v| v.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// The dev server to load the UI from, the flag taking precedence over the
+/// environment. Anything but an http(s) URL is ignored.
+fn url() -> Option<Url> {
+    let value = flag_value().or_else(|| std::env::var(URL_VAR).ok())?;
+    match Url::parse(value.trim()) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Some(url),
+        _ => {
+            log_error!("Ignoring invalid dev frontend URL: {}", value);
+            None
+        }
+    }
+}
+
+/// Where a window loads the UI from: the dev server if one is configured,
+/// otherwise the bundled assets
+pub fn webview_url() -> WebviewUrl {
+    match url() {
+        Some(url) => WebviewUrl::External(url),
+        None => WebviewUrl::App("/".into()),
+    }
+}
+
+/// Whether a URL is on the dev server the UI is loaded from
+pub fn serves(url: &Url) -> bool {
+    self::url().is_some_and(|dev| dev.origin() == url.origin())
+}
+
+/// Let pages from the dev server call the app's commands, which are
+/// otherwise only available to the bundled assets
+pub fn allow_ipc(app: &AppHandle) {
+    let Some(url) = url() else {
+        return;
+    };
+    log_info!("Loading the UI from {}", url);
+
+    let mut pattern = url.clone();
+    pattern.set_path("/*");
+    pattern.set_query(None);
+    pattern.set_fragment(None);
+    let capability = CapabilityBuilder::new("dev-frontend")
+        .remote(pattern.to_string())
+        .window("*")
+        .permission("core:default")
+        .permission("shell:default")
+        .permission("dialog:default")
+        .permission("opener:default")
+        .permission("notification:default");
+    if let Err(e) = app.add_capability(capability) {
+        log_error!("Failed to allow the dev frontend to call commands: {}", e);
+    }
+}
@@ -0,0 +1,503 @@
+//! Comparing and resolving files in the user's external diff and merge tools.
+//! The versions involved are written to temp files from the object database,
+//! the tool runs until it exits, and `external-tool-finished` tells the
+//! repository's windows to refresh. Temp files of tools still open when the
+//! app exits are removed then.
+
+use git2::{ErrorClass, Repository};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::launch::split_words;
+use crate::{broadcast, cleanup, git, launch, native, settings};
+
+/// Folder in the system temp directory holding the versions handed to tools
+const TEMP_DIR: &str = "opentui-git-tools";
+
+/// Counter keeping the temp folders of concurrent launches apart
+static NEXT_LAUNCH_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Temp folders of tools that haven't exited yet
+#[derive(Clone, Default)]
+pub struct ToolFolders(Arc<Mutex<HashSet<PathBuf>>>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolKind {
+    Diff,
+    Merge,
+}
+
+impl ToolKind {
+    /// Git config section of the tool's settings
+    fn section(self) -> &'static str {
+        match self {
+            ToolKind::Diff => "difftool",
+            ToolKind::Merge => "mergetool",
+        }
+    }
+}
+
+/// Files handed to a tool, named as in git's `difftool.<tool>.cmd`
+#[derive(Debug)]
+struct ToolFiles {
+    local: String,
+    remote: String,
+    base: String,
+    merged: String,
+}
+
+/// A tool ready to run
+struct Prepared {
+    program: String,
+    words: Vec<String>,
+    /// Folder of the temp files, removed once the tool exits
+    temp_dir: PathBuf,
+    merged: PathBuf,
+}
+
+/// Payload of the `external-tool-finished` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolFinished {
+    kind: ToolKind,
+    file: String,
+    exit_code: Option<i32>,
+    /// For merges, whether the file was left without conflict markers
+    resolved: Option<bool>,
+}
+
+/// Program and arguments of the tools git knows by name
+fn builtin(tool: &str, kind: ToolKind) -> Option<(&'static str, &'static [&'static str])> {
+    let beyond_compare = if cfg!(target_os = "windows") {
+        "bcomp"
+    } else {
+        "bcompare"
+    };
+    Some(match (tool, kind) {
+        ("vscode", ToolKind::Diff) => ("code", &["--wait", "--diff", "$LOCAL", "$REMOTE"]),
+        ("vscode", ToolKind::Merge) => (
+            "code",
+            &["--wait", "--merge", "$REMOTE", "$LOCAL", "$BASE", "$MERGED"],
+        ),
+        ("meld", ToolKind::Diff) => ("meld", &["$LOCAL", "$REMOTE"]),
+        ("meld", ToolKind::Merge) => ("meld", &["--output=$MERGED", "$LOCAL", "$BASE", "$REMOTE"]),
+        ("kdiff3", ToolKind::Diff) => ("kdiff3", &["$LOCAL", "$REMOTE"]),
+        ("kdiff3", ToolKind::Merge) => ("kdiff3", &["$BASE", "$LOCAL", "$REMOTE", "-o", "$MERGED"]),
+        ("p4merge", ToolKind::Diff) => ("p4merge", &["$LOCAL", "$REMOTE"]),
+        ("p4merge", ToolKind::Merge) => ("p4merge", &["$BASE", "$LOCAL", "$REMOTE", "$MERGED"]),
+        ("bc" | "bc3" | "bc4", ToolKind::Diff) => (beyond_compare, &["$LOCAL", "$REMOTE"]),
+        ("bc" | "bc3" | "bc4", ToolKind::Merge) => (
+            beyond_compare,
+            &["$LOCAL", "$REMOTE", "$BASE", "-mergeoutput=$MERGED"],
+        ),
+        _ => return None,
+    })
+}
+
+/// Fill in `$LOCAL`, `${LOCAL}` and the other variables of a command word
+fn substitute(word: &str, files: &ToolFiles) -> String {
+    [
+        ("LOCAL", &files.local),
+        ("REMOTE", &files.remote),
+        ("BASE", &files.base),
+        ("MERGED", &files.merged),
+    ]
+    .iter()
+    .fold(word.to_string(), |word, (name, value)| {
+        word.replace(&format!("${{{}}}", name), value)
+            .replace(&format!("${}", name), value)
+    })
+}
+
+/// Whether a merged file still has conflict markers
+fn has_conflict_markers(contents: &str) -> bool {
+    let mut lines = contents.lines();
+    lines.any(|line| line.starts_with("<<<<<<<")) && lines.any(|line| line.starts_with(">>>>>>>"))
+}
+
+fn not_found(message: String) -> git2::Error {
+    git2::Error::new(git2::ErrorCode::NotFound, ErrorClass::Config, message)
+}
+
+/// Program and arguments of a configured command line, which must not be empty
+fn split_command(command: &str, source: &str) -> Result<(String, Vec<String>), git2::Error> {
+    let words = split_words(command);
+    let (program, args) = words
+        .split_first()
+        .ok_or_else(|| not_found(format!("The command in {} is empty", source)))?;
+    Ok((program.clone(), args.to_vec()))
+}
+
+/// Program and arguments of the tool: the setting, then git's
+/// `<section>.<tool>.cmd`, then a tool git knows by name, at
+/// `<section>.<tool>.path` if set
+fn command(
+    repo: &Repository,
+    template: Option<String>,
+    kind: ToolKind,
+) -> Result<(String, Vec<String>), git2::Error> {
+    if let Some(template) = template.filter(|template| !template.trim().is_empty()) {
+        return split_command(&template, "settings");
+    }
+
+    let config = repo.config()?.snapshot()?;
+    let tool = match kind {
+        // Like git, compare with the merge tool if no diff tool is set
+        ToolKind::Diff => config
+            .get_string("diff.tool")
+            .or_else(|_| config.get_string("merge.tool")),
+        ToolKind::Merge => config.get_string("merge.tool"),
+    }
+    .map_err(|_| {
+        let name = match kind {
+            ToolKind::Diff => "diff",
+            ToolKind::Merge => "merge",
+        };
+        not_found(format!(
+            "No {} tool is configured. Set {}.tool in git or a {} tool command in settings.",
+            name, name, name
+        ))
+    })?;
+
+    let section = kind.section();
+    let key = format!("{}.{}.cmd", section, tool);
+    if let Ok(cmd) = config.get_string(&key) {
+        return split_command(&cmd, &key);
+    }
+    let (program, args) = builtin(&tool, kind).ok_or_else(|| {
+        not_found(format!(
+            "Don't know how to run {}. Set {}.{}.cmd in git.",
+            tool, section, tool
+        ))
+    })?;
+    let program = config
+        .get_string(&format!("{}.{}.path", section, tool))
+        .ok()
+        .filter(|path| !path.trim().is_empty())
+        .unwrap_or_else(|| program.to_string());
+    Ok((program, args.iter().map(|arg| arg.to_string()).collect()))
+}
+
+/// Write a version of `file` to the temp folder, named like git does, e.g.
+/// `main_LOCAL.rs`, so tools still recognize the file type
+fn write_version(
+    temp_dir: &Path,
+    file: &str,
+    label: &str,
+    contents: &[u8],
+) -> Result<String, git2::Error> {
+    let path = Path::new(file);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let version = temp_dir.join(format!("{}_{}{}", stem, label, extension));
+    std::fs::write(&version, contents).map_err(|e| {
+        git2::Error::from_str(&format!("Failed to write {}: {}", version.display(), e))
+    })?;
+    Ok(version.to_string_lossy().to_string())
+}
+
+/// Contents of `file` at a revision
+fn blob_at(repo: &Repository, rev: &str, file: &str) -> Result<Vec<u8>, git2::Error> {
+    let entry = repo
+        .revparse_single(rev)?
+        .peel_to_tree()?
+        .get_path(Path::new(file))?;
+    Ok(repo.find_blob(entry.id())?.content().to_vec())
+}
+
+/// Remove the temp folders of tools still open. Runs when the app exits.
+fn remove_all(app: &AppHandle) {
+    let folders = match app.state::<ToolFolders>().0.lock() {
+        Ok(mut folders) => std::mem::take(&mut *folders),
+        Err(_) => return,
+    };
+    for folder in folders {
+        let _ = std::fs::remove_dir_all(&folder);
+    }
+}
+
+fn create_temp_dir() -> Result<PathBuf, git2::Error> {
+    let temp_dir = std::env::temp_dir().join(TEMP_DIR).join(format!(
+        "{}-{}",
+        std::process::id(),
+        NEXT_LAUNCH_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&temp_dir)
+        .map_err(|e| git2::Error::from_str(&format!("Failed to create temp folder: {}", e)))?;
+    Ok(temp_dir)
+}
+
+/// Versions for comparing `file` between revisions, or against the working
+/// tree when `theirs` is `None`
+fn prepare_diff(
+    repo: &Repository,
+    template: Option<String>,
+    file: &str,
+    ours: &str,
+    theirs: Option<&str>,
+    base: Option<&str>,
+) -> Result<Prepared, git2::Error> {
+    let (program, args) = command(repo, template, ToolKind::Diff)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Bare repositories have no files to compare"))?;
+    let merged = workdir.join(file);
+    let temp_dir = create_temp_dir()?;
+
+    let files = ToolFiles {
+        local: write_version(&temp_dir, file, "LOCAL", &blob_at(repo, ours, file)?)?,
+        remote: match theirs {
+            Some(theirs) => {
+                write_version(&temp_dir, file, "REMOTE", &blob_at(repo, theirs, file)?)?
+            }
+            // Edits made in the tool land in the working tree
+            None => merged.to_string_lossy().to_string(),
+        },
+        base: match base {
+            Some(base) => write_version(&temp_dir, file, "BASE", &blob_at(repo, base, file)?)?,
+            None => String::new(),
+        },
+        merged: merged.to_string_lossy().to_string(),
+    };
+    Ok(Prepared {
+        words: std::iter::once(program.clone())
+            .chain(args.iter().map(|arg| substitute(arg, &files)))
+            .collect(),
+        program,
+        temp_dir,
+        merged,
+    })
+}
+
+/// The three sides of a conflicted `file` from the index, the merge result
+/// going to the working tree file. Sides that don't exist, such as the base
+/// of a file added on both sides, are empty.
+fn prepare_merge(
+    repo: &Repository,
+    template: Option<String>,
+    file: &str,
+) -> Result<Prepared, git2::Error> {
+    let (program, args) = command(repo, template, ToolKind::Merge)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Bare repositories have no conflicts to resolve"))?;
+    let conflict = repo
+        .index()?
+        .conflicts()?
+        .filter_map(Result::ok)
+        .find(|conflict| {
+            [&conflict.our, &conflict.their, &conflict.ancestor]
+                .iter()
+                .any(|entry| {
+                    entry
+                        .as_ref()
+                        .is_some_and(|entry| entry.path == file.as_bytes())
+                })
+        })
+        .ok_or_else(|| not_found(format!("{} has no conflicts", file)))?;
+
+    let temp_dir = create_temp_dir()?;
+    let side = |entry: &Option<git2::IndexEntry>, label: &str| -> Result<String, git2::Error> {
+        let contents = match entry {
+            Some(entry) => repo.find_blob(entry.id)?.content().to_vec(),
+            None => Vec::new(),
+        };
+        write_version(&temp_dir, file, label, &contents)
+    };
+    let merged = workdir.join(file);
+    let files = ToolFiles {
+        local: side(&conflict.our, "LOCAL")?,
+        remote: side(&conflict.their, "REMOTE")?,
+        base: side(&conflict.ancestor, "BASE")?,
+        merged: merged.to_string_lossy().to_string(),
+    };
+    Ok(Prepared {
+        words: std::iter::once(program.clone())
+            .chain(args.iter().map(|arg| substitute(arg, &files)))
+            .collect(),
+        program,
+        temp_dir,
+        merged,
+    })
+}
+
+/// Launch the tool and, once it exits, clean up and tell the repository's windows
+fn run_tool(
+    app: &AppHandle,
+    repo_path: &str,
+    kind: ToolKind,
+    file: String,
+    prepared: Prepared,
+) -> CommandResult<()> {
+    let Prepared {
+        program,
+        words,
+        temp_dir,
+        merged,
+    } = prepared;
+    let mut child = launch::spawn_gui(&words, repo_path).map_err(|e| {
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("Failed to launch {}: {}", program, e),
+        )
+    })?;
+    log_info!("Opened {} in {}", file, program);
+    if let Ok(mut folders) = app.state::<ToolFolders>().0.lock() {
+        folders.insert(temp_dir.clone());
+    }
+    cleanup::register(app, "difftool", remove_all);
+
+    let app = app.clone();
+    let repo_path = repo_path.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let exit_code = child.wait().ok().and_then(|status| status.code());
+        if let Ok(mut folders) = app.state::<ToolFolders>().0.lock() {
+            folders.remove(&temp_dir);
+        }
+        let _ = std::fs::remove_dir_all(&temp_dir);
+        let resolved = (kind == ToolKind::Merge).then(|| {
+            exit_code == Some(0)
+                && std::fs::read_to_string(&merged)
+                    .is_ok_and(|contents| !has_conflict_markers(&contents))
+        });
+        log_info!("{} exited with {:?}", program, exit_code);
+        broadcast::emit_to_repo(
+            &app,
+            &repo_path,
+            "external-tool-finished",
+            ToolFinished {
+                kind,
+                file,
+                exit_code,
+                resolved,
+            },
+        );
+    });
+    Ok(())
+}
+
+/// Compare a file of the window's repository in the user's diff tool, between
+/// `ours` (HEAD by default) and `theirs` (the working tree by default), with
+/// `base` for tools that take one. `external-tool-finished` follows when the
+/// tool exits.
+#[tauri::command]
+pub async fn open_in_difftool(
+    app: AppHandle,
+    window: WebviewWindow,
+    file: String,
+    ours: Option<String>,
+    theirs: Option<String>,
+    base: Option<String>,
+) -> CommandResult<()> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let ours = ours.unwrap_or_else(|| "HEAD".to_string());
+    for rev in std::iter::once(&ours).chain(&theirs).chain(&base) {
+        git::validate_rev(rev)?;
+    }
+    let template = settings::current(&app).diff_tool_command;
+    let prepared = native::with_repo(&app, &window, Some(repo_path.clone()), {
+        let file = file.clone();
+        move |repo| {
+            prepare_diff(
+                repo,
+                template,
+                &file,
+                &ours,
+                theirs.as_deref(),
+                base.as_deref(),
+            )
+        }
+    })
+    .await?;
+    run_tool(&app, &repo_path, ToolKind::Diff, file, prepared)
+}
+
+/// Resolve a conflicted file of the window's repository in the user's merge
+/// tool. `external-tool-finished` follows when the tool exits, saying whether
+/// the file looks resolved; staging it is left to the UI.
+#[tauri::command]
+pub async fn open_in_mergetool(
+    app: AppHandle,
+    window: WebviewWindow,
+    file: String,
+) -> CommandResult<()> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let template = settings::current(&app).merge_tool_command;
+    let prepared = native::with_repo(&app, &window, Some(repo_path.clone()), {
+        let file = file.clone();
+        move |repo| prepare_merge(repo, template, &file)
+    })
+    .await?;
+    run_tool(&app, &repo_path, ToolKind::Merge, file, prepared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_git_variables() {
+        let files = ToolFiles {
+            local: "/tmp/a_LOCAL.rs".to_string(),
+            remote: "/tmp/a_REMOTE.rs".to_string(),
+            base: "/tmp/a_BASE.rs".to_string(),
+            merged: "/repo/a.rs".to_string(),
+        };
+        assert_eq!(substitute("$LOCAL", &files), "/tmp/a_LOCAL.rs");
+        assert_eq!(substitute("${REMOTE}", &files), "/tmp/a_REMOTE.rs");
+        assert_eq!(
+            substitute("--output=$MERGED", &files),
+            "--output=/repo/a.rs"
+        );
+        assert_eq!(
+            substitute("-mergeoutput=${MERGED}", &files),
+            "-mergeoutput=/repo/a.rs"
+        );
+        assert_eq!(substitute("--wait", &files), "--wait");
+    }
+
+    #[test]
+    fn knows_common_tools() {
+        assert_eq!(
+            builtin("vscode", ToolKind::Diff),
+            Some(("code", &["--wait", "--diff", "$LOCAL", "$REMOTE"][..]))
+        );
+        assert!(builtin("meld", ToolKind::Merge).is_some_and(|(_, args)| args.contains(&"$BASE")));
+        assert_eq!(builtin("nonesuch", ToolKind::Merge), None);
+    }
+
+    #[test]
+    fn detects_leftover_conflict_markers() {
+        assert!(has_conflict_markers(
+            "a\n<<<<<<< ours\nb\n=======\nc\n>>>>>>> theirs\n"
+        ));
+        assert!(!has_conflict_markers("a\nb\n"));
+        assert!(!has_conflict_markers("<<<<<<< only an opening marker\n"));
+    }
+
+    #[test]
+    fn rejects_empty_commands() {
+        assert!(split_command("", "difftool.vim.cmd").is_err());
+        assert!(split_command("   ", "difftool.vim.cmd").is_err());
+        assert_eq!(
+            split_command("meld $LOCAL $REMOTE", "settings").unwrap(),
+            (
+                "meld".to_string(),
+                vec!["$LOCAL".to_string(), "$REMOTE".to_string()]
+            )
+        );
+    }
+}
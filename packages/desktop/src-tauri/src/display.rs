@@ -0,0 +1,290 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, Monitor, WebviewWindow};
+
+use crate::broadcast;
+use crate::compact::WindowGeometry;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::settings;
+
+/// Steps the zoom commands move through, like a browser's
+const ZOOM_LEVELS: &[f64] = &[
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+const MIN_ZOOM: f64 = 0.25;
+const MAX_ZOOM: f64 = 5.0;
+
+/// Monitor a window is currently on
+#[derive(Debug, Clone, PartialEq)]
+struct Placement {
+    monitor: String,
+    scale_factor: f64,
+}
+
+/// State tracking which monitor each window is on
+#[derive(Clone, Default)]
+pub struct DisplayState(Arc<Mutex<HashMap<String, Placement>>>);
+
+/// Payload of the `scale-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScaleChangedEvent {
+    monitor: String,
+    scale_factor: f64,
+    zoom: f64,
+}
+
+/// A connected monitor and the zoom applied on it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorInfo {
+    name: String,
+    scale_factor: f64,
+    width: u32,
+    height: u32,
+    zoom: f64,
+}
+
+/// Where a monitor sits on the desktop, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorArea {
+    fn of(monitor: &Monitor) -> Self {
+        Self {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        }
+    }
+}
+
+/// Areas of the connected monitors, the primary one first
+pub fn monitor_areas(app: &AppHandle) -> Vec<MonitorArea> {
+    let mut areas: Vec<MonitorArea> = app
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(MonitorArea::of)
+        .collect();
+    if let Ok(Some(primary)) = app.primary_monitor() {
+        let primary = MonitorArea::of(&primary);
+        if let Some(index) = areas.iter().position(|area| *area == primary) {
+            areas[..=index].rotate_right(1);
+        }
+    }
+    areas
+}
+
+/// Stable name for a monitor; unnamed monitors are identified by position
+fn monitor_name(monitor: &Monitor) -> String {
+    monitor.name().cloned().unwrap_or_else(|| {
+        let position = monitor.position();
+        format!("monitor@{},{}", position.x, position.y)
+    })
+}
+
+/// Configured zoom for a monitor, defaulting to 1.0
+fn zoom_for(app: &AppHandle, monitor: &str) -> f64 {
+    settings::current(app)
+        .monitor_zoom
+        .get(monitor)
+        .copied()
+        .filter(|zoom| zoom.is_finite() && *zoom > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Zoom chosen for a window with the zoom commands, defaulting to 1.0
+fn window_zoom(window: &WebviewWindow) -> f64 {
+    settings::current(window.app_handle())
+        .window_zoom
+        .get(&broadcast::window_key(window))
+        .copied()
+        .filter(|zoom| zoom.is_finite() && *zoom > 0.0)
+        .unwrap_or(1.0)
+}
+
+/// Apply the zoom for the window's current monitor if it changed
+///
+/// Called on creation, move and scale change so dragging a window between
+/// a HiDPI and a standard display keeps the UI at a sensible size.
+pub fn update(window: &WebviewWindow) {
+    let Ok(Some(monitor)) = window.current_monitor() else {
+        return;
+    };
+
+    let app = window.app_handle();
+    let placement = Placement {
+        monitor: monitor_name(&monitor),
+        scale_factor: monitor.scale_factor(),
+    };
+
+    {
+        let state = app.state::<DisplayState>();
+        let Ok(mut placements) = state.0.lock() else {
+            return;
+        };
+        if placements.get(window.label()) == Some(&placement) {
+            return;
+        }
+        placements.insert(window.label().to_string(), placement.clone());
+    }
+
+    let zoom = zoom_for(app, &placement.monitor) * window_zoom(window);
+    if let Err(e) = window.set_zoom(zoom) {
+        log_error!("Failed to set zoom on {}: {}", window.label(), e);
+    }
+
+    log_info!(
+        "Window {} on {} (scale {}, zoom {})",
+        window.label(),
+        placement.monitor,
+        placement.scale_factor,
+        zoom
+    );
+    let _ = window.emit_to(
+        EventTarget::webview_window(window.label()),
+        "scale-changed",
+        ScaleChangedEvent {
+            monitor: placement.monitor,
+            scale_factor: placement.scale_factor,
+            zoom,
+        },
+    );
+}
+
+/// Re-apply zoom to every window, e.g. after the zoom settings changed
+pub fn refresh(app: &AppHandle) {
+    if let Ok(mut placements) = app.state::<DisplayState>().0.lock() {
+        placements.clear();
+    }
+    for window in app.webview_windows().values() {
+        update(window);
+    }
+}
+
+/// Forget a closed window
+pub fn forget(app: &AppHandle, label: &str) {
+    if let Ok(mut placements) = app.state::<DisplayState>().0.lock() {
+        placements.remove(label);
+    }
+}
+
+/// Save a window's zoom and apply it on top of its monitor's zoom
+fn store_zoom(window: &WebviewWindow, zoom: f64) -> CommandResult<f64> {
+    if !zoom.is_finite() {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid zoom: {}", zoom),
+        ));
+    }
+    let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    let key = broadcast::window_key(window);
+    settings::update(window.app_handle(), |settings| {
+        if zoom == 1.0 {
+            settings.window_zoom.remove(&key);
+        } else {
+            settings.window_zoom.insert(key, zoom);
+        }
+    })?;
+
+    forget(window.app_handle(), window.label());
+    update(window);
+    Ok(zoom)
+}
+
+/// Zoom the calling window in one step. Returns the new zoom.
+#[tauri::command]
+pub fn zoom_in(window: WebviewWindow) -> CommandResult<f64> {
+    let current = window_zoom(&window);
+    let next = ZOOM_LEVELS
+        .iter()
+        .copied()
+        .find(|level| *level > current + f64::EPSILON)
+        .unwrap_or(current);
+    store_zoom(&window, next)
+}
+
+/// Zoom the calling window out one step. Returns the new zoom.
+#[tauri::command]
+pub fn zoom_out(window: WebviewWindow) -> CommandResult<f64> {
+    let current = window_zoom(&window);
+    let next = ZOOM_LEVELS
+        .iter()
+        .rev()
+        .copied()
+        .find(|level| *level < current - f64::EPSILON)
+        .unwrap_or(current);
+    store_zoom(&window, next)
+}
+
+/// Set the calling window's zoom, 1.0 being the default. Returns the zoom
+/// after clamping.
+#[tauri::command]
+pub fn set_zoom(window: WebviewWindow, zoom: f64) -> CommandResult<f64> {
+    store_zoom(&window, zoom)
+}
+
+/// List connected monitors with their scale factor and configured zoom
+#[tauri::command]
+pub fn get_monitors(app: AppHandle) -> CommandResult<Vec<MonitorInfo>> {
+    let monitors = app
+        .available_monitors()
+        .map_err(|e| format!("Failed to list monitors: {}", e))?;
+
+    Ok(monitors
+        .iter()
+        .map(|monitor| {
+            let name = monitor_name(monitor);
+            MonitorInfo {
+                zoom: zoom_for(&app, &name),
+                scale_factor: monitor.scale_factor(),
+                width: monitor.size().width,
+                height: monitor.size().height,
+                name,
+            }
+        })
+        .collect())
+}
+
+/// Move the calling window to the center of a monitor, numbered as in
+/// `get_monitors`, for placing windows from the keyboard
+#[tauri::command]
+pub fn move_to_monitor(window: WebviewWindow, index: usize) -> CommandResult<()> {
+    let monitors = window
+        .available_monitors()
+        .map_err(|e| format!("Failed to list monitors: {}", e))?;
+    let Some(monitor) = monitors.get(index) else {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("No monitor {} ({} connected)", index, monitors.len()),
+        )
+        .with_metadata(serde_json::json!({ "index": index })));
+    };
+
+    let window_error = |e: tauri::Error| format!("Failed to move window: {}", e);
+    // A maximized window has to be restored to move, then fills the new monitor
+    let maximized = window.is_maximized().unwrap_or(false);
+    if maximized {
+        window.unmaximize().map_err(window_error)?;
+    }
+    WindowGeometry::of(&window)
+        .and_then(|geometry| geometry.center_on(MonitorArea::of(monitor)).apply(&window))
+        .map_err(window_error)?;
+    if maximized {
+        window.maximize().map_err(window_error)?;
+    }
+    log_info!(
+        "Moved window {} to {}",
+        window.label(),
+        monitor_name(monitor)
+    );
+    Ok(())
+}
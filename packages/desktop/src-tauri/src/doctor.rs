@@ -0,0 +1,321 @@
+//! Startup check of the environment the sidecar will run in: whether `git` is
+//! on its PATH, recent enough, and has the basics configured. Problems are
+//! explained before spawning instead of surfacing later as obscure server
+//! errors.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tokio::process::Command;
+
+use crate::{environment, i18n, mock_server, profiles, settings};
+
+/// Oldest git the server is tested against
+const MIN_GIT_VERSION: semver::Version = semver::Version::new(2, 25, 0);
+
+/// What a problem is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DoctorCheck {
+    GitMissing,
+    GitOutdated,
+    CredentialHelper,
+    UserName,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The server cannot work properly
+    Error,
+    /// Some operations will fail or prompt
+    Warning,
+}
+
+/// A problem found, with how to fix it on this OS
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorIssue {
+    check: DoctorCheck,
+    severity: Severity,
+    message: String,
+    fix: String,
+}
+
+/// Result of `run_doctor`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    /// Where `git` was found on the sidecar's PATH
+    git_path: Option<String>,
+    git_version: Option<String>,
+    /// The sidecar's PATH, one directory per entry
+    search_path: Vec<String>,
+    issues: Vec<DoctorIssue>,
+}
+
+impl DoctorReport {
+    fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
+}
+
+/// What was learned about the sidecar's git
+#[derive(Debug, Default)]
+struct GitFacts {
+    /// `None` if `git --version` could not be understood
+    version: Option<semver::Version>,
+    credential_helper: Option<String>,
+    user_name: Option<String>,
+}
+
+fn install_hint() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Install the Command Line Tools with `xcode-select --install`, or git with `brew install git`."
+    } else if cfg!(target_os = "windows") {
+        "Install Git for Windows from https://git-scm.com/download/win and restart the app."
+    } else {
+        "Install git with your package manager, e.g. `sudo apt install git` or `sudo dnf install git`."
+    }
+}
+
+fn credential_hint() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Run `git config --global credential.helper osxkeychain` to keep credentials in the keychain."
+    } else if cfg!(target_os = "windows") {
+        "Run `git config --global credential.helper manager` to use Git Credential Manager."
+    } else {
+        "Run `git config --global credential.helper libsecret`, or `cache` to keep credentials in memory for a while."
+    }
+}
+
+/// Version from `git --version`, e.g. `git version 2.39.3 (Apple Git-145)` or
+/// `git version 2.45.1.windows.1`
+fn parse_git_version(output: &str) -> Option<semver::Version> {
+    let version = output
+        .trim()
+        .strip_prefix("git version ")?
+        .split_whitespace()
+        .next()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next().and_then(|part| part.parse().ok()).unwrap_or(0);
+    Some(semver::Version::new(major, minor, patch))
+}
+
+/// Problems with the sidecar's git, `None` meaning it was not found
+fn issues(git: Option<&GitFacts>) -> Vec<DoctorIssue> {
+    let Some(git) = git else {
+        return vec![DoctorIssue {
+            check: DoctorCheck::GitMissing,
+            severity: Severity::Error,
+            message: "git was not found on the server's PATH.".to_string(),
+            fix: install_hint().to_string(),
+        }];
+    };
+
+    let mut issues = Vec::new();
+    match &git.version {
+        Some(version) if *version >= MIN_GIT_VERSION => {}
+        found => issues.push(DoctorIssue {
+            check: DoctorCheck::GitOutdated,
+            severity: Severity::Error,
+            message: format!(
+                "git {} is older than the required {}.",
+                found
+                    .as_ref()
+                    .map_or_else(|| "of an unknown version".to_string(), ToString::to_string),
+                MIN_GIT_VERSION
+            ),
+            fix: install_hint().to_string(),
+        }),
+    }
+    if git.credential_helper.is_none() {
+        issues.push(DoctorIssue {
+            check: DoctorCheck::CredentialHelper,
+            severity: Severity::Warning,
+            message: "No credential helper is configured, so pushing and fetching over HTTPS will ask for a password every time.".to_string(),
+            fix: credential_hint().to_string(),
+        });
+    }
+    if git.user_name.is_none() {
+        issues.push(DoctorIssue {
+            check: DoctorCheck::UserName,
+            severity: Severity::Warning,
+            message: "user.name is not set, so committing will fail.".to_string(),
+            fix: "Run `git config --global user.name \"Your Name\"` and `git config --global user.email you@example.com`.".to_string(),
+        });
+    }
+    issues
+}
+
+/// Trimmed stdout of a successful git run, `None` if it failed or printed nothing
+async fn git_output(
+    git: &Path,
+    repo_path: Option<&str>,
+    env: &[(String, String)],
+    args: &[&str],
+) -> Option<String> {
+    let mut command = Command::new(git);
+    command
+        .args(args)
+        .envs(env.iter().cloned())
+        .kill_on_drop(true);
+    if let Some(repo_path) = repo_path {
+        command.current_dir(repo_path);
+    }
+    let output = command.output().await.ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !stdout.is_empty()).then_some(stdout)
+}
+
+/// Check git as the sidecar for a repository would see it
+async fn report(app: &AppHandle, repo_path: Option<&str>) -> DoctorReport {
+    let dirs: Vec<PathBuf> = environment::sidecar_path(app, repo_path).await;
+    let git_path = environment::find_program(&dirs, "git");
+    let env: Vec<(String, String)> = environment::extra(app)
+        .into_iter()
+        .chain(repo_path.map_or_else(HashMap::new, |repo_path| {
+            profiles::for_repo(app, repo_path).env
+        }))
+        .collect();
+
+    let mut git_version = None;
+    let facts = match &git_path {
+        Some(git) => {
+            git_version = git_output(git, None, &env, &["--version"]).await;
+            Some(GitFacts {
+                version: git_version.as_deref().and_then(parse_git_version),
+                credential_helper: git_output(
+                    git,
+                    repo_path,
+                    &env,
+                    &["config", "--get", "credential.helper"],
+                )
+                .await,
+                user_name: git_output(git, repo_path, &env, &["config", "--get", "user.name"])
+                    .await,
+            })
+        }
+        None => None,
+    };
+
+    DoctorReport {
+        git_path: git_path.map(|path| path.to_string_lossy().to_string()),
+        git_version,
+        search_path: dirs
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect(),
+        issues: issues(facts.as_ref()),
+    }
+}
+
+/// Check the environment before the sidecar for `repo_path` is spawned. Errors
+/// are explained in a dialog on every launch, warnings only the first time.
+pub async fn check(app: &AppHandle, repo_path: &str) {
+    // The mock server doesn't run git, and its tests shouldn't meet a dialog
+    if mock_server::enabled() {
+        return;
+    }
+    let report = report(app, Some(repo_path)).await;
+    for issue in &report.issues {
+        log_error!("Doctor: {}", issue.message);
+    }
+    if report.issues.is_empty() {
+        return;
+    }
+    let warnings_shown = settings::current(app).doctor_warnings_shown;
+    if warnings_shown && !report.has_errors() {
+        return;
+    }
+    if !warnings_shown {
+        if let Err(e) = settings::update(app, |settings| settings.doctor_warnings_shown = true) {
+            log_error!("Failed to save settings: {}", e);
+        }
+    }
+
+    let details: Vec<String> = report
+        .issues
+        .iter()
+        .map(|issue| format!("• {}\n  {}", issue.message, issue.fix))
+        .collect();
+    app.dialog()
+        .message(format!(
+            "{}\n\n{}",
+            i18n::t(app, "doctor.message"),
+            details.join("\n\n")
+        ))
+        .title(i18n::t(app, "doctor.title"))
+        .kind(if report.has_errors() {
+            MessageDialogKind::Error
+        } else {
+            MessageDialogKind::Warning
+        })
+        .show(|_| {});
+}
+
+/// Check git in the environment the sidecar for a repository (or any, without
+/// one) runs in
+#[tauri::command]
+pub async fn run_doctor(app: AppHandle, repo_path: Option<String>) -> DoctorReport {
+    report(&app, repo_path.as_deref()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checks(issues: &[DoctorIssue]) -> Vec<DoctorCheck> {
+        issues.iter().map(|issue| issue.check).collect()
+    }
+
+    #[test]
+    fn parses_git_versions() {
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-145)\n"),
+            Some(semver::Version::new(2, 39, 3))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.45.1.windows.1"),
+            Some(semver::Version::new(2, 45, 1))
+        );
+        assert_eq!(
+            parse_git_version("git version 2.40"),
+            Some(semver::Version::new(2, 40, 0))
+        );
+        assert_eq!(parse_git_version("hub version 2.14.2"), None);
+    }
+
+    #[test]
+    fn reports_missing_and_outdated_git() {
+        assert_eq!(checks(&issues(None)), vec![DoctorCheck::GitMissing]);
+        let old = GitFacts {
+            version: Some(semver::Version::new(2, 17, 1)),
+            credential_helper: Some("cache".to_string()),
+            user_name: Some("Ada".to_string()),
+        };
+        assert_eq!(checks(&issues(Some(&old))), vec![DoctorCheck::GitOutdated]);
+    }
+
+    #[test]
+    fn warns_about_missing_configuration() {
+        let unconfigured = GitFacts {
+            version: Some(semver::Version::new(2, 43, 0)),
+            ..GitFacts::default()
+        };
+        let found = issues(Some(&unconfigured));
+        assert_eq!(
+            checks(&found),
+            vec![DoctorCheck::CredentialHelper, DoctorCheck::UserName]
+        );
+        assert!(found
+            .iter()
+            .all(|issue| issue.severity == Severity::Warning));
+    }
+}
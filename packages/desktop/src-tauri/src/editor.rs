@@ -0,0 +1,116 @@
+use std::path::Path;
+use tauri::{AppHandle, WebviewWindow};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::launch::{find_executable, program_name, split_words};
+use crate::{git, launch, repo_config, settings, terminal};
+
+/// GUI editors to look for when nothing is configured, in order of preference
+const DETECTED_EDITORS: &[&str] = &["code", "cursor", "zed", "subl"];
+
+/// Editors that need a terminal to run in
+const TERMINAL_EDITORS: &[&str] = &["vi", "vim", "nvim", "nano", "emacs", "micro", "hx", "kak"];
+
+/// Arguments that open a file at a line for a known editor
+fn editor_args(program: &str, file: &str, line: u32) -> Vec<String> {
+    match program_name(program).as_str() {
+        "code" | "code-insiders" | "codium" | "cursor" => {
+            vec!["--goto".to_string(), format!("{}:{}", file, line)]
+        }
+        "subl" | "zed" | "hx" => vec![format!("{}:{}", file, line)],
+        name if TERMINAL_EDITORS.contains(&name) => vec![format!("+{}", line), file.to_string()],
+        _ => vec![file.to_string()],
+    }
+}
+
+/// Command line for a user-configured template with `{file}` and `{line}` placeholders
+fn from_template(template: &str, file: &str, line: u32) -> Vec<String> {
+    let mut words = split_words(template);
+    if !template.contains("{file}") {
+        words.push(file.to_string());
+    }
+    words
+        .into_iter()
+        .map(|word| {
+            word.replace("{file}", file)
+                .replace("{line}", &line.to_string())
+        })
+        .collect()
+}
+
+/// Pick the editor command line: settings, then the repository's
+/// `.opentui.toml`, then $VISUAL/$EDITOR, then a detected GUI editor
+fn resolve(app: &AppHandle, repo_path: &str, file: &str, line: u32) -> Option<Vec<String>> {
+    if let Some(template) = settings::current(app)
+        .editor_command
+        .filter(|template| !template.trim().is_empty())
+        .or_else(|| repo_config::for_repo(app, repo_path).editor)
+        .filter(|template| !template.trim().is_empty())
+    {
+        return Some(from_template(&template, file, line));
+    }
+
+    let from_env = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty());
+    if let Some(editor) = from_env {
+        let mut words = split_words(&editor);
+        let program = words.first()?.clone();
+        words.extend(editor_args(&program, file, line));
+        return Some(words);
+    }
+
+    DETECTED_EDITORS.iter().find_map(|name| {
+        let program = find_executable(name)?.to_string_lossy().to_string();
+        let mut words = vec![program.clone()];
+        words.extend(editor_args(&program, file, line));
+        Some(words)
+    })
+}
+
+/// Open a file in the user's editor, optionally at a line.
+/// Relative paths are resolved against the window's repository.
+#[tauri::command]
+pub fn open_in_editor(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    line: Option<u32>,
+) -> CommandResult<()> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let file = Path::new(&repo_path).join(&path);
+    if !file.exists() {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("File not found: {}", path),
+        ));
+    }
+    let file = file.to_string_lossy().to_string();
+    let line = line.unwrap_or(1).max(1);
+
+    let Some(words) = resolve(&app, &repo_path, &file, line) else {
+        // No editor found; fall back to the system's default app for the file
+        return app.opener().open_path(&file, None::<&str>).map_err(|e| {
+            AppError::new(ErrorCode::Internal, format!("Failed to open file: {}", e))
+        });
+    };
+
+    let in_terminal = TERMINAL_EDITORS.contains(&program_name(&words[0]).as_str());
+    let child = if in_terminal {
+        terminal::run_in(&words, &repo_path)
+    } else {
+        launch::spawn_gui(&words, &repo_path)
+    }
+    .map_err(|e| {
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("Failed to launch {}: {}", words[0], e),
+        )
+    })?;
+
+    log_info!("Opened {} with {}", file, words[0]);
+    launch::reap(child);
+    Ok(())
+}
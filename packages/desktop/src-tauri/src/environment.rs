@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_shell::process::Command;
+use tauri_plugin_shell::ShellExt;
+
+use crate::{portable, profiles, settings};
+
+/// Variables the sidecar needs to run at all, kept even when not allowlisted
+const ESSENTIAL: &[&str] = &[
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "PATH",
+    "TMPDIR",
+    "LANG",
+    "SYSTEMROOT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "TEMP",
+    "TMP",
+];
+
+/// Environment settings for the sidecar
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EnvironmentSettings {
+    /// Variables set for the sidecar, e.g. `GIT_SSH_COMMAND` or `SSH_AUTH_SOCK`
+    pub vars: HashMap<String, String>,
+    /// Directories put in front of the inherited PATH
+    pub path_prepend: Vec<String>,
+    /// If set, only these inherited variables are passed on, besides essential ones
+    pub inherit_only: Option<Vec<String>>,
+    /// Inherited variables never passed on
+    pub exclude: Vec<String>,
+}
+
+/// Windows variable names are case-insensitive
+fn same_name(a: &str, b: &str) -> bool {
+    if cfg!(windows) {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+impl EnvironmentSettings {
+    fn filters_inherited(&self) -> bool {
+        self.inherit_only.is_some() || !self.exclude.is_empty()
+    }
+
+    fn inherits(&self, name: &str) -> bool {
+        if self
+            .exclude
+            .iter()
+            .any(|excluded| same_name(excluded, name))
+        {
+            return false;
+        }
+        match &self.inherit_only {
+            Some(allowed) => allowed
+                .iter()
+                .map(String::as_str)
+                .chain(ESSENTIAL.iter().copied())
+                .any(|allowed| same_name(allowed, name)),
+            None => true,
+        }
+    }
+}
+
+/// Apply the inherited-variable filters to a sidecar command.
+/// Call before setting any other variables, which clearing would drop.
+pub fn inherit(app: &AppHandle, command: Command) -> Command {
+    let settings = settings::current(app).environment;
+    if !settings.filters_inherited() {
+        return command;
+    }
+    let inherited: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| settings.inherits(name))
+        .collect();
+    command.env_clear().envs(inherited)
+}
+
+/// Variables configured for the sidecar, applied after the app's own
+pub fn extra(app: &AppHandle) -> Vec<(String, String)> {
+    let settings = settings::current(app).environment;
+    let mut vars: Vec<(String, String)> = settings.vars.into_iter().collect();
+
+    if !settings.path_prepend.is_empty() {
+        let mut paths: Vec<std::path::PathBuf> =
+            settings.path_prepend.iter().map(Into::into).collect();
+        if let Some(path) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&path));
+        }
+        match std::env::join_paths(paths) {
+            Ok(path) => vars.push(("PATH".to_string(), path.to_string_lossy().to_string())),
+            Err(e) => log_error!("Ignoring invalid PATH additions: {}", e),
+        }
+    }
+    vars
+}
+
+/// Marks the PATH line in the login shell's output, which may also contain
+/// whatever the user's profile prints
+const PATH_MARKER: &str = "__OPENTUI_PATH__";
+
+/// PATH as the sidecar for a repository sees it: the login shell's PATH on
+/// macOS/Linux, with the configured and per-repository variables applied
+pub async fn sidecar_path(app: &AppHandle, repo_path: Option<&str>) -> Vec<PathBuf> {
+    let overrides: Vec<(String, String)> = extra(app)
+        .into_iter()
+        .chain(repo_path.map_or_else(HashMap::new, |repo_path| {
+            profiles::for_repo(app, repo_path).env
+        }))
+        .collect();
+    let configured = overrides
+        .iter()
+        .rev()
+        .find(|(name, _)| same_name(name, "PATH"))
+        .map(|(_, path)| path.clone());
+
+    #[cfg(target_os = "windows")]
+    let path = configured.or_else(|| std::env::var("PATH").ok());
+
+    #[cfg(not(target_os = "windows"))]
+    let path = {
+        let shell = crate::config::get_user_shell();
+        let mut args = crate::config::get_shell_flags(&shell);
+        let script = format!("printf '\\n{}%s\\n' \"$PATH\"", PATH_MARKER);
+        args.push(&script);
+        let output = inherit(app, app.shell().command(&shell))
+            .args(&args)
+            .envs(overrides)
+            .output()
+            .await;
+        match output {
+            Ok(output) => parse_path_output(&String::from_utf8_lossy(&output.stdout)),
+            Err(e) => {
+                log_error!("Failed to read the login shell's PATH: {}", e);
+                configured.or_else(|| std::env::var("PATH").ok())
+            }
+        }
+    };
+
+    path.map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+/// PATH from the login shell's output, skipping whatever the profile printed
+#[cfg(not(target_os = "windows"))]
+fn parse_path_output(stdout: &str) -> Option<String> {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix(PATH_MARKER).map(str::to_string))
+}
+
+/// Where the login shell's environment is kept for launching without it
+const LOGIN_ENV_FILE: &str = "login-env.json";
+/// Marks the start of `env` output, after whatever the user's profile prints
+const ENV_MARKER: &str = "__OPENTUI_ENV__";
+
+/// Variables from `env` output following `ENV_MARKER`. Continuation lines of
+/// multi-line values don't look like assignments and are skipped.
+fn parse_env_output(stdout: &str) -> HashMap<&str, &str> {
+    stdout
+        .lines()
+        .skip_while(|line| *line != ENV_MARKER)
+        .skip(1)
+        .filter_map(|line| line.split_once('='))
+        .filter(|(name, _)| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .collect()
+}
+
+/// Save the login shell's environment, to launch sidecars with if the shell
+/// later breaks. Multi-line values are dropped.
+pub async fn cache_login_env(app: &AppHandle) {
+    let Ok(dir) = portable::data_dir(app) else {
+        return;
+    };
+    let shell = crate::config::get_user_shell();
+    let mut args = crate::config::get_shell_flags(&shell);
+    let script = format!("printf '\\n{}\\n'; env", ENV_MARKER);
+    args.push(&script);
+    let output = match app.shell().command(&shell).args(&args).output().await {
+        Ok(output) if output.status.success() => output,
+        Ok(_) => return,
+        Err(e) => {
+            log_error!("Failed to read the login shell's environment: {}", e);
+            return;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let vars = parse_env_output(&stdout);
+    if vars.is_empty() {
+        return;
+    }
+    let result = serde_json::to_string(&vars)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| {
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            std::fs::write(dir.join(LOGIN_ENV_FILE), contents).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log_error!("Failed to cache the login environment: {}", e);
+    }
+}
+
+/// The cached login shell environment, minus variables the settings keep
+/// from the sidecar. Empty if the shell never started a sidecar.
+pub fn cached_login_env(app: &AppHandle) -> Vec<(String, String)> {
+    let settings = settings::current(app).environment;
+    portable::data_dir(app)
+        .ok()
+        .and_then(|dir| std::fs::read_to_string(dir.join(LOGIN_ENV_FILE)).ok())
+        .and_then(|contents| serde_json::from_str::<HashMap<String, String>>(&contents).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(name, _)| settings.inherits(name))
+        .collect()
+}
+
+/// First executable named `name` in `dirs`, like `which`
+pub fn find_program(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    let names: Vec<String> = if cfg!(windows) {
+        ["exe", "cmd", "bat"]
+            .iter()
+            .map(|ext| format!("{}.{}", name, ext))
+            .collect()
+    } else {
+        vec![name.to_string()]
+    };
+    dirs.iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_output_skips_profile_noise() {
+        let stdout = format!(
+            "Welcome back!\nFOO=ignored\n{}\nHOME=/home/me\nPATH=/usr/bin:/bin\n",
+            ENV_MARKER
+        );
+        let vars = parse_env_output(&stdout);
+        assert_eq!(vars.len(), 2);
+        assert_eq!(vars["HOME"], "/home/me");
+        assert_eq!(vars["PATH"], "/usr/bin:/bin");
+    }
+
+    #[test]
+    fn env_output_drops_continuation_lines() {
+        let stdout = format!(
+            "{}\nMESSAGE=first line\nsecond line\nnot a name=x\nEMPTY=\n",
+            ENV_MARKER
+        );
+        let vars = parse_env_output(&stdout);
+        assert_eq!(vars.len(), 2);
+        assert_eq!(vars["MESSAGE"], "first line");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn env_output_without_marker_is_empty() {
+        assert!(parse_env_output("HOME=/home/me\n").is_empty());
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn path_output_finds_marked_line() {
+        let stdout = format!("motd\n\n{}/opt/bin:/usr/bin\n", PATH_MARKER);
+        assert_eq!(
+            parse_path_output(&stdout).as_deref(),
+            Some("/opt/bin:/usr/bin")
+        );
+        assert_eq!(parse_path_output("no marker here"), None);
+    }
+}
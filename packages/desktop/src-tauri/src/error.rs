@@ -0,0 +1,107 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Kind of failure, so the frontend can branch without matching on messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    /// Credentials were missing or rejected
+    Auth,
+    /// A remote or the local server could not be reached
+    Network,
+    /// The request conflicts with the current state
+    Conflict,
+    /// A resource is busy, such as a repository with operations in flight
+    Lock,
+    /// A repository, window or server does not exist
+    NotFound,
+    /// An argument is malformed, such as a path, URL or shortcut
+    InvalidInput,
+    /// The command ran longer than its configured timeout
+    Timeout,
+    /// The command was cancelled by the caller
+    Cancelled,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Best-effort classification of an untyped error message, e.g. one from git
+    fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if [
+            "authentication failed",
+            "permission denied",
+            "could not read username",
+        ]
+        .iter()
+        .any(|needle| message.contains(needle))
+        {
+            ErrorCode::Auth
+        } else if [
+            "could not resolve host",
+            "unable to access",
+            "connection refused",
+            "timed out",
+        ]
+        .iter()
+        .any(|needle| message.contains(needle))
+        {
+            ErrorCode::Network
+        } else if message.contains("conflict") {
+            ErrorCode::Conflict
+        } else if message.contains(".lock") {
+            ErrorCode::Lock
+        } else {
+            ErrorCode::Internal
+        }
+    }
+}
+
+/// Error returned by every fallible command, serialized as `{ code, message, metadata? }`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            metadata: None,
+        }
+    }
+
+    /// Attach structured details, e.g. the operations that blocked a request
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::classify(&message), message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// Result type of fallible commands
+pub type CommandResult<T> = Result<T, AppError>;
@@ -0,0 +1,124 @@
+//! Export the current view, e.g. a diff or commit, as a PDF for sharing.
+//!
+//! On Linux WebKitGTK prints straight to the file picked in a save dialog.
+//! Elsewhere the webview has no print-to-file API Tauri exposes, so the native
+//! print dialog is shown instead, whose "Save as PDF" (macOS) or
+//! "Microsoft Print to PDF" (Windows) does the same.
+
+use serde::Deserialize;
+use tauri::WebviewWindow;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::i18n;
+
+/// Options for `export_pdf`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExportPdfOptions {
+    /// Name suggested in the save dialog, e.g. `abc1234.pdf`
+    pub file_name: Option<String>,
+    pub landscape: bool,
+}
+
+fn print_error(e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorCode::Internal, format!("Failed to export PDF: {}", e))
+}
+
+/// Ask where to save the PDF. Returns `None` if the dialog was cancelled.
+#[cfg(target_os = "linux")]
+async fn pick_destination(
+    window: &WebviewWindow,
+    options: &ExportPdfOptions,
+) -> Option<std::path::PathBuf> {
+    use tauri::Manager;
+    use tauri_plugin_dialog::DialogExt;
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let mut dialog = window
+        .app_handle()
+        .dialog()
+        .file()
+        .set_title(i18n::t(window.app_handle(), "title.export-pdf"))
+        .add_filter("PDF", &["pdf"]);
+    if let Some(name) = &options.file_name {
+        dialog = dialog.set_file_name(name);
+    }
+    dialog.save_file(move |path| {
+        let _ = tx.send(path);
+    });
+    rx.await.ok().flatten()?.into_path().ok()
+}
+
+/// Print the webview's current page to a PDF file with WebKitGTK
+#[cfg(target_os = "linux")]
+async fn print_to_file(
+    window: &WebviewWindow,
+    path: &std::path::Path,
+    landscape: bool,
+) -> CommandResult<()> {
+    use std::sync::{Arc, Mutex};
+    use webkit2gtk::PrintOperationExt;
+
+    let uri = gtk::glib::filename_to_uri(path, None).map_err(print_error)?;
+    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
+    // Whichever of finished and failed fires first reports the outcome
+    let tx = Arc::new(Mutex::new(Some(tx)));
+    let report = move |result: Result<(), String>| {
+        if let Some(tx) = tx.lock().ok().and_then(|mut tx| tx.take()) {
+            let _ = tx.send(result);
+        }
+    };
+
+    window
+        .with_webview(move |webview| {
+            let operation = webkit2gtk::PrintOperation::new(&webview.inner());
+            let settings = gtk::PrintSettings::new();
+            settings.set_printer("Print to File");
+            settings.set("output-file-format", Some("pdf"));
+            settings.set("output-uri", Some(uri.as_str()));
+            operation.set_print_settings(&settings);
+            if landscape {
+                let setup = gtk::PageSetup::new();
+                setup.set_orientation(gtk::PageOrientation::Landscape);
+                operation.set_page_setup(&setup);
+            }
+
+            let on_failed = report.clone();
+            operation.connect_failed(move |_, error| on_failed(Err(error.to_string())));
+            operation.connect_finished(move |_| report(Ok(())));
+            operation.print();
+        })
+        .map_err(print_error)?;
+
+    rx.await
+        .map_err(|_| print_error("print operation was dropped"))?
+        .map_err(print_error)
+}
+
+/// Export the calling window's current view as a PDF.
+/// Returns the saved path, or `None` if cancelled or handed to the print dialog.
+#[tauri::command]
+pub async fn export_pdf(
+    window: WebviewWindow,
+    options: Option<ExportPdfOptions>,
+) -> CommandResult<Option<String>> {
+    let options = options.unwrap_or_default();
+
+    #[cfg(target_os = "linux")]
+    {
+        let Some(path) = pick_destination(&window, &options).await else {
+            return Ok(None);
+        };
+        print_to_file(&window, &path, options.landscape).await?;
+        log_info!("Exported PDF to {}", path.display());
+        Ok(Some(path.to_string_lossy().to_string()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        // The print dialog has its own orientation and file name controls
+        let _ = options;
+        window.print().map_err(print_error)?;
+        Ok(None)
+    }
+}
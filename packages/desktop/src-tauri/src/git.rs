@@ -0,0 +1,107 @@
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tokio::process::Command;
+
+use crate::broadcast::WindowRegistry;
+use crate::error::{AppError, CommandResult, ErrorCode};
+
+/// Repository shown in a window
+pub fn repo_for_window(app: &AppHandle, window: &WebviewWindow) -> CommandResult<String> {
+    app.state::<WindowRegistry>()
+        .repo_for(window.label())
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::NotFound,
+                "Window is not attached to a repository",
+            )
+        })
+}
+
+/// A `git` command running in a repository
+pub fn command(repo_path: &str, args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    command.current_dir(repo_path).args(args);
+    command
+}
+
+/// Run git in a repository and return its stdout
+pub async fn run(repo_path: &str, args: &[&str]) -> CommandResult<Vec<u8>> {
+    let output = command(repo_path, args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(AppError::from(format!(
+            "git {}: {}",
+            args.join(" "),
+            stderr
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Environment passing config entries to git through `GIT_CONFIG_COUNT`,
+/// appended to any the user already passes that way
+pub fn config_env(entries: &[(String, String)]) -> Vec<(String, String)> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+    let first = std::env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut vars = vec![(
+        "GIT_CONFIG_COUNT".to_string(),
+        (first + entries.len()).to_string(),
+    )];
+    for (offset, (key, value)) in entries.iter().enumerate() {
+        vars.push((format!("GIT_CONFIG_KEY_{}", first + offset), key.clone()));
+        vars.push((
+            format!("GIT_CONFIG_VALUE_{}", first + offset),
+            value.clone(),
+        ));
+    }
+    vars
+}
+
+/// Reject revisions that git would parse as options
+pub fn validate_rev(rev: &str) -> CommandResult<()> {
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid object name: {:?}", rev),
+        ));
+    }
+    Ok(())
+}
+
+/// A commit as `git format-patch` output when `rev` is given, otherwise the
+/// working tree's (or with `staged`, the index's) diff of `paths`
+pub async fn patch(
+    repo_path: &str,
+    rev: Option<&str>,
+    paths: &[String],
+    staged: bool,
+) -> CommandResult<Vec<u8>> {
+    let output = match rev {
+        Some(rev) => {
+            validate_rev(rev)?;
+            run(repo_path, &["format-patch", "-1", "--stdout", rev]).await?
+        }
+        None => {
+            let mut args = vec!["diff", "--binary"];
+            if staged {
+                args.push("--cached");
+            }
+            args.push("--");
+            args.extend(paths.iter().map(String::as_str));
+            run(repo_path, &args).await?
+        }
+    };
+    if output.iter().all(u8::is_ascii_whitespace) {
+        return Err(AppError::new(ErrorCode::NotFound, "No changes in patch"));
+    }
+    Ok(output)
+}
@@ -0,0 +1,102 @@
+//! Headless mode (`--no-window`): run the sidecar supervisor without any
+//! webview and print the server URL, so the UI can be used from a regular
+//! browser or over a forwarded port on a remote machine.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::power;
+use crate::shutdown::{self, ExitReason};
+use crate::sidecar::{ServerState, Sidecar};
+use crate::startup::{self, StartupError};
+use crate::startup_metrics::{self, StartupPhase};
+use crate::{ipc, large_repo, loopback, recent, sidecar_binary, spawn, tls, version, worktrees};
+
+/// Command-line flag selecting headless mode
+const NO_WINDOW_FLAG: &str = "--no-window";
+/// Interval between health checks of the sidecar
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Whether the app was launched with `--no-window`
+pub fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == NO_WINDOW_FLAG)
+}
+
+/// Start the sidecar for the launch repository and keep it running,
+/// respawning it whenever it stops answering. Never returns until the app exits.
+pub async fn run(app: &AppHandle) {
+    let repo_path = crate::config::get_repo_path(app);
+    let Some(location) = worktrees::locate(&repo_path) else {
+        startup::exit(app, StartupError::NotARepository(repo_path));
+        return;
+    };
+    let repo_path = location.workdir;
+    let started = Instant::now();
+    let port = match crate::config::get_sidecar_port(app, &repo_path) {
+        Ok(port) => port,
+        Err(e) => {
+            startup::exit(app, StartupError::Port(e));
+            return;
+        }
+    };
+    startup_metrics::record(app, StartupPhase::PortSelection, started);
+
+    log_info!("Running headless on port {}", port);
+    log_info!("Repository path: {}", repo_path);
+
+    if !sidecar_binary::check(app, true).await {
+        startup::exit(app, StartupError::MissingBinary);
+        return;
+    }
+    large_repo::prescan(app, &repo_path, true).await;
+    let child = match spawn::start(app, port, &repo_path).await {
+        Ok(child) => child,
+        Err(e) => {
+            startup::exit(app, e.into());
+            return;
+        }
+    };
+    let sidecar = Sidecar {
+        child: Arc::new(Mutex::new(Some(child))),
+        port,
+        repo_path: repo_path.clone(),
+        restarts: Arc::default(),
+    };
+    app.state::<ServerState>().insert(sidecar.clone());
+    recent::add(app, &repo_path);
+
+    let started = Instant::now();
+    if let Err(message) = version::handshake(app, &sidecar).await {
+        startup::exit(app, StartupError::Incompatible(message));
+        return;
+    }
+    startup_metrics::record(app, StartupPhase::Handshake, started);
+
+    // Without windows nothing else asks to quit, and exiting stops the sidecar
+    let exit_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            shutdown::exit(&exit_app, ExitReason::Interrupted);
+        }
+    });
+
+    startup_metrics::finish(app);
+    log_info!("Server ready, connect the UI to:");
+    println!("{}", tls::base_url(app, port));
+    // Never logged: the shell log ends up in diagnostics bundles
+    if let Some(secret) = loopback::secret(app) {
+        println!("Open the UI with ?secret={} to authenticate", secret);
+    }
+
+    loop {
+        tokio::time::sleep(power::interval(app, HEALTH_CHECK_INTERVAL)).await;
+        if ipc::ping(app, &sidecar).await.is_ok() {
+            continue;
+        }
+        log_error!("Server for {} stopped answering, respawning", repo_path);
+        if crate::sidecar::respawn_sidecar(app, &sidecar).await {
+            log_info!("Server back up at {}", tls::base_url(app, port));
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Hook diagnostics. Hooks run with the sidecar's environment, not the
+//! user's terminal, so a hook whose interpreter is missing from the sidecar's
+//! PATH fails only when committing from the app.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::CommandResult;
+use crate::{environment, native, watcher};
+
+/// An installed hook and the interpreter it runs with
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookInfo {
+    /// Hook name, e.g. `pre-commit`
+    name: String,
+    path: String,
+    /// Whether git will run it; git skips hooks that are not executable
+    executable: bool,
+    /// Interpreter from the shebang line, e.g. `python3` or `/bin/bash`.
+    /// `None` for hooks without one, which git runs with `sh`.
+    interpreter: Option<String>,
+    /// Where the interpreter was found for the sidecar, `None` if it wasn't
+    interpreter_path: Option<String>,
+}
+
+/// Hooks of a repository and where they were read from
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookList {
+    /// Hooks directory: `core.hooksPath` if set, else `.git/hooks`
+    hooks_dir: String,
+    /// Whether the directory comes from `core.hooksPath`
+    custom_hooks_path: bool,
+    hooks: Vec<HookInfo>,
+    /// The sidecar's PATH, one directory per entry
+    search_path: Vec<String>,
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    // Git for Windows runs hooks through its own sh regardless of permissions
+    true
+}
+
+/// Interpreter named by a script's shebang line, looking through `env`
+fn interpreter(path: &Path) -> Option<String> {
+    let contents = std::fs::read(path).ok()?;
+    let first_line = contents.split(|byte| *byte == b'\n').next()?;
+    let shebang = String::from_utf8_lossy(first_line)
+        .strip_prefix("#!")?
+        .trim()
+        .to_string();
+    let mut words = shebang.split_whitespace();
+    let program = words.next()?;
+    if Path::new(program)
+        .file_name()
+        .is_some_and(|name| name == "env")
+    {
+        // `#!/usr/bin/env -S node --flag` runs the first non-option word
+        return words
+            .find(|word| !word.starts_with('-'))
+            .map(str::to_string);
+    }
+    Some(program.to_string())
+}
+
+/// Where an interpreter resolves for the sidecar
+fn resolve(interpreter: &str, dirs: &[PathBuf]) -> Option<PathBuf> {
+    let path = Path::new(interpreter);
+    if path.is_absolute() && path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    // Git for Windows maps `/bin/bash` and friends onto its own install,
+    // so look absolute paths up by name as well
+    let name = path.file_name()?.to_string_lossy();
+    environment::find_program(dirs, &name)
+}
+
+/// Hooks directory of a repository and whether it comes from `core.hooksPath`
+fn hooks_dir(repo_path: &str, hooks_path: Option<PathBuf>) -> Option<(PathBuf, bool)> {
+    if let Some(hooks_path) = hooks_path {
+        // Relative paths are relative to where hooks run, the working tree
+        return Some((Path::new(repo_path).join(hooks_path), true));
+    }
+    let (_, common_dir) = watcher::git_dirs(repo_path)?;
+    Some((common_dir.join("hooks"), false))
+}
+
+/// List the hooks of the window's repository, or of `repo_path`, with the
+/// interpreters they need and whether the sidecar can find them
+#[tauri::command]
+pub async fn list_hooks(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo_path: Option<String>,
+) -> CommandResult<HookList> {
+    let (repo_path, hooks_path) = native::with_repo(&app, &window, repo_path, |repo| {
+        let workdir = repo
+            .workdir()
+            .unwrap_or_else(|| repo.path())
+            .to_string_lossy()
+            .to_string();
+        // `get_path` expands a leading `~/` like git does
+        let hooks_path = repo.config()?.get_path("core.hooksPath").ok();
+        Ok((workdir, hooks_path))
+    })
+    .await?;
+
+    let dirs = environment::sidecar_path(&app, Some(&repo_path)).await;
+    let mut hooks = Vec::new();
+    let (hooks_dir, custom_hooks_path) = match hooks_dir(&repo_path, hooks_path) {
+        Some(found) => found,
+        None => (PathBuf::new(), false),
+    };
+    if let Ok(entries) = std::fs::read_dir(&hooks_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !path.is_file() || name.ends_with(".sample") || name.starts_with('.') {
+                continue;
+            }
+            let interpreter = interpreter(&path);
+            let interpreter_path = resolve(interpreter.as_deref().unwrap_or("sh"), &dirs)
+                .map(|path| path.to_string_lossy().to_string());
+            hooks.push(HookInfo {
+                executable: is_executable(&path),
+                path: path.to_string_lossy().to_string(),
+                name,
+                interpreter,
+                interpreter_path,
+            });
+        }
+    }
+    hooks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(HookList {
+        hooks_dir: hooks_dir.to_string_lossy().to_string(),
+        custom_hooks_path,
+        hooks,
+        search_path: dirs
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect(),
+    })
+}
@@ -0,0 +1,102 @@
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::window::show_main_window;
+use crate::{settings, switcher};
+
+pub const DEFAULT_TOGGLE_SHORTCUT: &str = "CommandOrControl+Shift+G";
+pub const DEFAULT_SWITCHER_SHORTCUT: &str = "CommandOrControl+Shift+O";
+
+/// Parse an accelerator string such as `CommandOrControl+Shift+G`
+pub fn parse(accelerator: &str) -> Result<Shortcut, String> {
+    accelerator
+        .parse::<Shortcut>()
+        .map_err(|e| format!("Invalid shortcut '{}': {}", accelerator, e))
+}
+
+/// Hide the main window if it is in front, otherwise bring it forward
+fn toggle_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let visible = window.is_visible().unwrap_or(false);
+        let focused = window.is_focused().unwrap_or(false);
+        if visible && focused {
+            let _ = window.hide();
+            return;
+        }
+    }
+    show_main_window(app);
+}
+
+/// A global shortcut that couldn't be registered, naming its settings field
+#[derive(Debug)]
+pub struct ShortcutError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for ShortcutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Bind one global shortcut, if it is set
+fn bind(
+    app: &AppHandle,
+    field: &'static str,
+    name: &str,
+    accelerator: Option<&str>,
+    action: fn(&AppHandle),
+) -> Result<(), ShortcutError> {
+    let Some(accelerator) = accelerator else {
+        log_info!("Global {} shortcut disabled", name);
+        return Ok(());
+    };
+    let error = |message| ShortcutError { field, message };
+
+    let shortcut = parse(accelerator).map_err(error)?;
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |app, _shortcut, event| {
+            if event.state() == ShortcutState::Pressed {
+                action(app);
+            }
+        })
+        .map_err(|e| {
+            error(format!(
+                "Failed to register shortcut '{}': {}",
+                accelerator, e
+            ))
+        })?;
+
+    log_info!("Registered global {} shortcut {}", name, accelerator);
+    Ok(())
+}
+
+/// Register the show/hide and switcher hotkeys from settings, replacing any
+/// previous bindings
+pub fn register(app: &AppHandle) -> Result<(), ShortcutError> {
+    app.global_shortcut()
+        .unregister_all()
+        .map_err(|e| ShortcutError {
+            field: "toggleWindowShortcut",
+            message: format!("Failed to unregister global shortcuts: {}", e),
+        })?;
+
+    let settings = settings::current(app);
+    let toggle = bind(
+        app,
+        "toggleWindowShortcut",
+        "show/hide",
+        settings.toggle_window_shortcut.as_deref(),
+        toggle_main_window,
+    );
+    // One shortcut taken by another app shouldn't cost the other one
+    let switcher = bind(
+        app,
+        "switcherShortcut",
+        "switcher",
+        settings.switcher_shortcut.as_deref(),
+        switcher::toggle,
+    );
+    toggle.and(switcher)
+}
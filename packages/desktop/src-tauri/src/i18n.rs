@@ -0,0 +1,189 @@
+//! Translations of the strings the shell shows itself: menus, the tray,
+//! dialogs and notifications. Translations are bundled from `locales/` and
+//! chosen by the `locale` setting or else the OS language, falling back to
+//! English for anything missing. `get_locale` tells the frontend which one is
+//! in use so both speak the same language.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+use crate::{menu, settings, tray};
+
+/// Language every key is guaranteed to exist in
+const FALLBACK: &str = "en";
+
+/// Bundled translations, by language tag
+const BUNDLED: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("de", include_str!("../locales/de.json")),
+    ("fr", include_str!("../locales/fr.json")),
+];
+
+/// Result of `get_locale`, also the payload of the `locale-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// Language in use, one of `available`
+    locale: &'static str,
+    /// Language reported by the OS, as is
+    system: Option<String>,
+    available: Vec<&'static str>,
+}
+
+fn translations() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    static TRANSLATIONS: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+    TRANSLATIONS.get_or_init(|| {
+        BUNDLED
+            .iter()
+            .filter_map(|(locale, contents)| match serde_json::from_str(contents) {
+                Ok(strings) => Some((*locale, strings)),
+                Err(e) => {
+                    log_error!("Invalid translations for {}: {}", locale, e);
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// The OS language, e.g. `de_DE.UTF-8` or `fr-FR`. Windows is not asked, so
+/// it uses the setting or English.
+fn system_locale() -> Option<String> {
+    let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX");
+    #[cfg(target_os = "macos")]
+    let from_env = from_env.or_else(|| {
+        std::process::Command::new("defaults")
+            .args(["read", "-g", "AppleLocale"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    });
+    from_env
+}
+
+/// Bundled language matching a requested one, by full tag or by language:
+/// `de_DE.UTF-8` and `de-AT` both resolve to `de`
+fn resolve(requested: &str) -> Option<&'static str> {
+    let tag = requested
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-")
+        .to_lowercase();
+    let language = tag.split('-').next().unwrap_or_default();
+    BUNDLED
+        .iter()
+        .map(|(locale, _)| *locale)
+        .find(|locale| *locale == tag)
+        .or_else(|| {
+            BUNDLED
+                .iter()
+                .map(|(locale, _)| *locale)
+                .find(|locale| *locale == language)
+        })
+}
+
+/// Language in use: the setting if it names a bundled one, else the OS's
+pub fn current(app: &AppHandle) -> &'static str {
+    settings::current(app)
+        .locale
+        .as_deref()
+        .and_then(resolve)
+        .or_else(|| system_locale().as_deref().and_then(resolve))
+        .unwrap_or(FALLBACK)
+}
+
+/// A string in the given language with `{name}` placeholders filled in,
+/// falling back to English, then to the key itself
+pub fn translate(locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+    let translations = translations();
+    let text = [locale, FALLBACK]
+        .iter()
+        .find_map(|locale| translations.get(locale)?.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string());
+    args.iter().fold(text, |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// A string in the app's language
+pub fn t(app: &AppHandle, key: &str) -> String {
+    translate(current(app), key, &[])
+}
+
+/// A string in the app's language with `{name}` placeholders filled in
+pub fn tr(app: &AppHandle, key: &str, args: &[(&str, &str)]) -> String {
+    translate(current(app), key, args)
+}
+
+fn info(app: &AppHandle) -> LocaleInfo {
+    LocaleInfo {
+        locale: current(app),
+        system: system_locale(),
+        available: BUNDLED.iter().map(|(locale, _)| *locale).collect(),
+    }
+}
+
+/// Rebuild the menu and tray in the current language after the settings changed
+pub fn refresh(app: &AppHandle) {
+    if app.menu().is_some() {
+        if let Err(e) = menu::install(app) {
+            log_error!("Failed to refresh menu: {}", e);
+        }
+    }
+    tray::refresh(app);
+    let _ = app.emit("locale-changed", info(app));
+}
+
+/// Language of the native menus and dialogs
+#[tauri::command]
+pub fn get_locale(app: AppHandle) -> LocaleInfo {
+    info(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_bundled_language_has_every_key() {
+        let translations = translations();
+        assert_eq!(translations.len(), BUNDLED.len());
+        let english = &translations[FALLBACK];
+        for (locale, strings) in translations {
+            for key in english.keys() {
+                assert!(strings.contains_key(key), "{} is missing {}", locale, key);
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_os_locales() {
+        assert_eq!(resolve("de_DE.UTF-8"), Some("de"));
+        assert_eq!(resolve("fr-CA"), Some("fr"));
+        assert_eq!(resolve("en_US@euro"), Some("en"));
+        assert_eq!(resolve("EN"), Some("en"));
+        assert_eq!(resolve("ja_JP.UTF-8"), None);
+    }
+
+    #[test]
+    fn fills_placeholders_and_falls_back() {
+        assert_eq!(
+            translate(
+                "de",
+                "quit.confirm",
+                &[("operations", "Ein rebase läuft noch")]
+            ),
+            "Ein rebase läuft noch — trotzdem beenden?"
+        );
+        assert_eq!(translate("ja", "tray.quit", &[]), "Quit");
+        assert_eq!(translate("en", "no.such.key", &[]), "no.such.key");
+    }
+}
@@ -0,0 +1,311 @@
+//! JSON-RPC 2.0 between the shell and its sidecars over the sidecar's stdio.
+//! Requests are written to stdin as JSON lines, next to the control messages;
+//! responses and notifications come back on stdout as `@rpc `-prefixed lines.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+use crate::control;
+use crate::sidecar::Sidecar;
+
+/// Version of the protocol this build speaks
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Prefix of stdout lines carrying a JSON-RPC message
+const RPC_PREFIX: &str = "@rpc ";
+/// How long a request may wait for its response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+struct RpcRequest<'a, P> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: P,
+}
+
+/// A response or notification from a sidecar
+#[derive(Deserialize)]
+struct RpcMessage {
+    id: Option<u64>,
+    method: Option<String>,
+    params: Option<Value>,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// Error answer to a request
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// Params of the `ready` notification, sent once the sidecar is listening and
+/// again when a standby sidecar binds a repository
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyParams {
+    pub pid: u32,
+    pub standby: bool,
+    pub protocol: u32,
+    /// Address the server is listening on, `None` while standing by
+    #[serde(default)]
+    pub host: Option<String>,
+}
+
+/// Result of the `status` method
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    pub pid: u32,
+    pub uptime_ms: u64,
+    /// Resident memory; missing from servers that predate the memory limit
+    #[serde(default)]
+    pub rss_bytes: Option<u64>,
+}
+
+/// Result of the `version` method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerVersion {
+    pub version: String,
+    pub protocol: u32,
+}
+
+/// A git operation in flight, from the `operations` method
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub kind: String,
+}
+
+#[derive(Deserialize)]
+struct OperationsResult {
+    operations: Vec<Operation>,
+}
+
+/// Empty result of `ping` and `shutdown`
+#[derive(Deserialize)]
+struct Empty {}
+
+type Reply = Result<Value, RpcError>;
+
+#[derive(Default)]
+struct Inner {
+    next_id: u64,
+    /// Requests waiting for a response, with the port they were sent to
+    pending: HashMap<u64, (u32, oneshot::Sender<Reply>)>,
+    /// Latest `ready` notification per port
+    ready: HashMap<u32, ReadyParams>,
+    /// Number of sidecars started on each port, telling a process that has
+    /// since been replaced apart from the current one
+    launches: HashMap<u32, u64>,
+    /// Ports whose current sidecar process has exited
+    exited: HashSet<u32>,
+}
+
+/// Requests in flight and the sidecars that reported ready
+#[derive(Clone, Default)]
+pub struct IpcState(Arc<Mutex<Inner>>);
+
+/// Handle a sidecar stdout line if it is a JSON-RPC message.
+/// Returns whether it was one.
+pub fn handle_line(app: &AppHandle, port: u32, line: &str) -> bool {
+    let Some(json) = line.trim().strip_prefix(RPC_PREFIX) else {
+        return false;
+    };
+    let message: RpcMessage = match serde_json::from_str(json) {
+        Ok(message) => message,
+        Err(e) => {
+            log_error!("Invalid sidecar RPC message {}: {}", json, e);
+            return true;
+        }
+    };
+    let Some(state) = app.try_state::<IpcState>() else {
+        return true;
+    };
+    let Ok(mut inner) = state.0.lock() else {
+        return true;
+    };
+
+    if let Some(id) = message.id {
+        if let Some((_, reply)) = inner.pending.remove(&id) {
+            let _ = reply.send(match message.error {
+                Some(error) => Err(error),
+                None => Ok(message.result.unwrap_or(Value::Null)),
+            });
+        }
+        return true;
+    }
+
+    if message.method.as_deref() == Some("ready") {
+        match message.params.map(serde_json::from_value::<ReadyParams>) {
+            Some(Ok(ready)) => {
+                if ready.protocol != PROTOCOL_VERSION {
+                    log_error!(
+                        "Sidecar on port {} speaks protocol {}, expected {}",
+                        port,
+                        ready.protocol,
+                        PROTOCOL_VERSION
+                    );
+                }
+                if !ready.standby {
+                    log_info!("Sidecar {} serving on port {}", ready.pid, port);
+                }
+                inner.ready.insert(port, ready);
+            }
+            _ => log_error!("Invalid ready notification from port {}", port),
+        }
+    }
+    true
+}
+
+/// Latest `ready` notification from the sidecar on a port
+pub fn ready_info(app: &AppHandle, port: u32) -> Option<ReadyParams> {
+    app.try_state::<IpcState>()?
+        .0
+        .lock()
+        .ok()?
+        .ready
+        .get(&port)
+        .cloned()
+}
+
+/// Forget a port's sidecar before another is started on it: its ready state
+/// is cleared and its pending requests fail. Returns the new launch's number,
+/// for `mark_exited`.
+pub fn forget(app: &AppHandle, port: u32) -> u64 {
+    let Some(state) = app.try_state::<IpcState>() else {
+        return 0;
+    };
+    let Ok(mut inner) = state.0.lock() else {
+        return 0;
+    };
+    inner.ready.remove(&port);
+    inner.exited.remove(&port);
+    inner
+        .pending
+        .retain(|_, (pending_port, _)| *pending_port != port);
+    let launch = inner.launches.entry(port).or_default();
+    *launch += 1;
+    *launch
+}
+
+/// Record that a launch on a port exited, so nothing keeps waiting for it.
+/// Ignored if another sidecar has been started on the port since.
+pub fn mark_exited(app: &AppHandle, port: u32, launch: u64) {
+    if let Some(state) = app.try_state::<IpcState>() {
+        if let Ok(mut inner) = state.0.lock() {
+            if inner.launches.get(&port) == Some(&launch) {
+                inner.exited.insert(port);
+            }
+        }
+    }
+}
+
+fn has_exited(app: &AppHandle, port: u32) -> bool {
+    app.try_state::<IpcState>()
+        .and_then(|state| Some(state.0.lock().ok()?.exited.contains(&port)))
+        .unwrap_or(false)
+}
+
+/// Wait until the sidecar on a port reports it is serving a repository.
+/// Gives up early if the process exits, e.g. a login shell failing on its rc files.
+pub async fn wait_ready(app: &AppHandle, port: u32, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if ready_info(app, port).is_some_and(|ready| !ready.standby) {
+            return true;
+        }
+        if has_exited(app, port) {
+            log_error!("Server on port {} exited before it was ready", port);
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    false
+}
+
+/// Call a method on a sidecar and wait for its result
+pub async fn call<P: Serialize, R: DeserializeOwned>(
+    app: &AppHandle,
+    sidecar: &Sidecar,
+    method: &str,
+    params: P,
+) -> Result<R, String> {
+    let state = app.state::<IpcState>();
+    let (tx, rx) = oneshot::channel();
+    let id = {
+        let mut inner = state
+            .0
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock on IPC state: {}", e))?;
+        inner.next_id += 1;
+        let id = inner.next_id;
+        inner.pending.insert(id, (sidecar.port, tx));
+        id
+    };
+
+    let request = serde_json::to_string(&RpcRequest {
+        jsonrpc: "2.0",
+        id,
+        method,
+        params,
+    })
+    .map_err(|e| format!("Failed to serialize {} request: {}", method, e))?;
+    let remove_pending = || {
+        if let Ok(mut inner) = state.0.lock() {
+            inner.pending.remove(&id);
+        }
+    };
+    if let Err(e) = control::write_line(sidecar, &request) {
+        remove_pending();
+        return Err(e);
+    }
+
+    let reply = match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+        Ok(Ok(reply)) => reply,
+        Ok(Err(_)) => return Err(format!("Sidecar exited before answering {}", method)),
+        Err(_) => {
+            remove_pending();
+            return Err(format!("Sidecar did not answer {} in time", method));
+        }
+    };
+    let result = reply.map_err(|e| format!("{} failed: {} ({})", method, e.message, e.code))?;
+    serde_json::from_value(result).map_err(|e| format!("Invalid {} result: {}", method, e))
+}
+
+/// Check that a sidecar answers
+pub async fn ping(app: &AppHandle, sidecar: &Sidecar) -> Result<(), String> {
+    call::<_, Empty>(app, sidecar, "ping", ()).await.map(|_| ())
+}
+
+/// Process and uptime of a sidecar
+pub async fn status(app: &AppHandle, sidecar: &Sidecar) -> Result<ServerStatus, String> {
+    call(app, sidecar, "status", ()).await
+}
+
+/// Version of the sidecar and the protocol it speaks
+pub async fn version(app: &AppHandle, sidecar: &Sidecar) -> Result<ServerVersion, String> {
+    call(app, sidecar, "version", ()).await
+}
+
+/// Git operations the sidecar is running
+pub async fn operations(app: &AppHandle, sidecar: &Sidecar) -> Result<Vec<Operation>, String> {
+    call::<_, OperationsResult>(app, sidecar, "operations", ())
+        .await
+        .map(|result| result.operations)
+}
+
+/// Ask a sidecar to stop serving and exit
+pub async fn shutdown(app: &AppHandle, sidecar: &Sidecar) -> Result<(), String> {
+    call::<_, Empty>(app, sidecar, "shutdown", ())
+        .await
+        .map(|_| ())
+}
@@ -0,0 +1,249 @@
+//! A quick size estimate of a repository before its sidecar starts. Past the
+//! thresholds in settings, the working tree goes unwatched and the sidecar
+//! loads history in smaller pages, and the user is told why, instead of the
+//! app pegging the CPU for minutes on a monorepo.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::broadcast::repo_key;
+use crate::error::CommandResult;
+use crate::{git, i18n, settings, watcher};
+
+/// Magic and version of a pack index with a fanout table
+const IDX_V2_HEADER: [u8; 8] = [0xff, b't', b'O', b'c', 0, 0, 0, 2];
+/// Loose object directory counted to estimate them all, as `git gc --auto` does
+const SAMPLE_DIR: &str = "17";
+
+/// When a repository counts as large and what changes for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LargeRepoSettings {
+    pub enabled: bool,
+    /// Objects in the object database, packed and loose
+    pub max_objects: u64,
+    /// Files tracked in the index
+    pub max_files: usize,
+    /// Most commits the sidecar loads at a time for a large repository
+    pub history_limit: u32,
+}
+
+impl Default for LargeRepoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_objects: 2_000_000,
+            max_files: 100_000,
+            history_limit: 1000,
+        }
+    }
+}
+
+/// Estimated size of a repository
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoSize {
+    objects: u64,
+    files: usize,
+    large: bool,
+    /// How long the estimate took
+    scan_ms: u64,
+}
+
+impl RepoSize {
+    pub fn is_large(&self) -> bool {
+        self.large
+    }
+}
+
+/// Sizes found by the pre-scan, keyed by repo key, and the repositories
+/// already warned about this session
+#[derive(Clone, Default)]
+pub struct LargeRepoState(Arc<Mutex<HashMap<String, (RepoSize, bool)>>>);
+
+/// Object count in a pack index, from the last fanout entry
+fn idx_object_count(header: &[u8]) -> Option<u64> {
+    if header.get(..8)? != IDX_V2_HEADER {
+        return None;
+    }
+    let last = 8 + 255 * 4;
+    let count = header.get(last..last + 4)?;
+    Some(u32::from_be_bytes(count.try_into().ok()?).into())
+}
+
+fn packed_objects(objects_dir: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(objects_dir.join("pack")) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+        .filter_map(|path| {
+            let mut header = [0; 8 + 256 * 4];
+            std::fs::File::open(path)
+                .and_then(|mut file| file.read_exact(&mut header))
+                .ok()?;
+            idx_object_count(&header)
+        })
+        .sum()
+}
+
+fn loose_objects(objects_dir: &Path) -> u64 {
+    let sampled = std::fs::read_dir(objects_dir.join(SAMPLE_DIR))
+        .map(|entries| entries.count() as u64)
+        .unwrap_or_default();
+    sampled * 256
+}
+
+fn is_large(settings: &LargeRepoSettings, objects: u64, files: usize) -> bool {
+    settings.enabled && (objects > settings.max_objects || files > settings.max_files)
+}
+
+/// Estimate the size of a repository from its pack indexes, a sample of its
+/// loose objects and its index, without walking either
+fn scan(repo_path: &str, settings: &LargeRepoSettings) -> RepoSize {
+    let started = Instant::now();
+    let objects = watcher::git_dirs(repo_path)
+        .map(|(_, common_dir)| {
+            let objects_dir = common_dir.join("objects");
+            packed_objects(&objects_dir) + loose_objects(&objects_dir)
+        })
+        .unwrap_or_default();
+    // Bare repositories have no index
+    let files = git2::Repository::open(repo_path)
+        .and_then(|repo| repo.index())
+        .map(|index| index.len())
+        .unwrap_or_default();
+    RepoSize {
+        objects,
+        files,
+        large: is_large(settings, objects, files),
+        scan_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn warn(app: &AppHandle, repo_path: &str, size: RepoSize, settings: &LargeRepoSettings) {
+    app.dialog()
+        .message(i18n::tr(
+            app,
+            "large-repo.message",
+            &[
+                ("repo", repo_path),
+                ("objects", size.objects.to_string().as_str()),
+                ("files", size.files.to_string().as_str()),
+                ("limit", settings.history_limit.to_string().as_str()),
+            ],
+        ))
+        .title(i18n::t(app, "large-repo.title"))
+        .kind(MessageDialogKind::Info)
+        .show(|_| {});
+}
+
+/// Estimate a repository's size before its sidecar is spawned, remembering
+/// the result for the spawn flags and the watcher, and, unless headless, warn
+/// once per session if it is large
+pub async fn prescan(app: &AppHandle, repo_path: &str, headless: bool) -> RepoSize {
+    let settings = settings::current(app).large_repo;
+    let size = tauri::async_runtime::spawn_blocking({
+        let (repo_path, settings) = (repo_path.to_string(), settings.clone());
+        move || scan(&repo_path, &settings)
+    })
+    .await
+    .unwrap_or_default();
+
+    let first_warning = app
+        .state::<LargeRepoState>()
+        .0
+        .lock()
+        .is_ok_and(|mut sizes| {
+            let warned = sizes
+                .get(&repo_key(repo_path))
+                .is_some_and(|(_, warned)| *warned);
+            sizes.insert(repo_key(repo_path), (size, warned || size.large));
+            size.large && !warned
+        });
+    if size.large {
+        log_info!(
+            "{} is large ({} objects, {} files, scanned in {} ms); not watching the working tree",
+            repo_path,
+            size.objects,
+            size.files,
+            size.scan_ms
+        );
+    }
+    if first_warning && !headless {
+        warn(app, repo_path, size, &settings);
+    }
+    size
+}
+
+/// Whether the pre-scan found the repository large
+pub fn is_large_repo(app: &AppHandle, repo_path: &str) -> bool {
+    app.try_state::<LargeRepoState>().is_some_and(|state| {
+        state.0.lock().is_ok_and(|sizes| {
+            sizes
+                .get(&repo_key(repo_path))
+                .is_some_and(|(size, _)| size.large)
+        })
+    })
+}
+
+/// Extra sidecar arguments for a large repository
+pub fn sidecar_args(app: &AppHandle, repo_path: &str) -> Vec<String> {
+    if !is_large_repo(app, repo_path) {
+        return Vec::new();
+    }
+    vec![
+        "--history-limit".to_string(),
+        settings::current(app).large_repo.history_limit.to_string(),
+    ]
+}
+
+/// Size estimate of the window's repository from when its sidecar started
+#[tauri::command]
+pub fn get_repo_size(app: AppHandle, window: WebviewWindow) -> CommandResult<Option<RepoSize>> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    Ok(app
+        .state::<LargeRepoState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|sizes| sizes.get(&repo_key(&repo_path)).map(|(size, _)| *size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_object_count_of_pack_indexes() {
+        let mut header = vec![0; 8 + 256 * 4];
+        header[..8].copy_from_slice(&IDX_V2_HEADER);
+        header[8 + 255 * 4..].copy_from_slice(&1_234_567u32.to_be_bytes());
+        assert_eq!(idx_object_count(&header), Some(1_234_567));
+
+        header[7] = 1;
+        assert_eq!(idx_object_count(&header), None);
+        assert_eq!(idx_object_count(&IDX_V2_HEADER), None);
+    }
+
+    #[test]
+    fn applies_thresholds() {
+        let settings = LargeRepoSettings::default();
+        assert!(!is_large(&settings, 1000, 100));
+        assert!(is_large(&settings, 3_000_000, 100));
+        assert!(is_large(&settings, 1000, 150_000));
+        let off = LargeRepoSettings {
+            enabled: false,
+            ..settings
+        };
+        assert!(!is_large(&off, 3_000_000, 150_000));
+    }
+}
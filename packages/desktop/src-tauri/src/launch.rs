@@ -0,0 +1,184 @@
+//! Helpers for launching external programs such as editors and terminals
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+
+/// Install locations GUI launches often miss from PATH
+#[cfg(target_os = "macos")]
+const EXTRA_DIRS: &[&str] = &[
+    "/usr/local/bin",
+    "/opt/homebrew/bin",
+    "/Applications/Visual Studio Code.app/Contents/Resources/app/bin",
+    "/Applications/Cursor.app/Contents/Resources/app/bin",
+    "/Applications/Sublime Text.app/Contents/SharedSupport/bin",
+];
+#[cfg(not(target_os = "macos"))]
+const EXTRA_DIRS: &[&str] = &["/usr/local/bin", "/snap/bin"];
+
+/// Split a command template into words, honoring single and double quotes
+pub fn split_words(template: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+
+    for c in template.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Find an executable on PATH or in common install locations
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    if name.contains(std::path::MAIN_SEPARATOR) {
+        return Path::new(name).is_file().then(|| PathBuf::from(name));
+    }
+
+    let extensions: &[&str] = if cfg!(target_os = "windows") {
+        &[".exe", ".cmd", ".bat", ""]
+    } else {
+        &[""]
+    };
+    let path_dirs = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    path_dirs
+        .into_iter()
+        .chain(EXTRA_DIRS.iter().map(PathBuf::from))
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |ext| dir.join(format!("{}{}", name, ext)))
+        })
+        .find(|candidate| candidate.is_file())
+}
+
+/// Base name of a program, without directory or extension
+pub fn program_name(program: &str) -> String {
+    Path::new(program)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| program.to_string())
+}
+
+/// Quote a word for a POSIX shell
+pub fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// Quote a word for a cmd.exe command line: quoted for the program's own
+/// argument parsing, then every character cmd treats specially escaped with
+/// `^`, so file names such as `a&calc.txt` can't run commands
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn cmd_quote(word: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in word.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        // Backslashes are only special before a quote
+        let escapes = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.push_str(&"\\".repeat(escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+
+    let mut escaped = String::new();
+    for c in quoted.chars() {
+        if "^&|<>()%!\"".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Run a GUI program such as an editor or diff tool. On macOS/Linux it goes
+/// through the login shell so programs installed via the user's PATH are
+/// found; the returned child is the program itself.
+pub fn spawn_gui(words: &[String], cwd: &str) -> std::io::Result<Child> {
+    #[cfg(not(target_os = "windows"))]
+    {
+        let shell = crate::config::get_user_shell();
+        let script = format!(
+            "exec {}",
+            words
+                .iter()
+                .map(|w| shell_quote(w))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        Command::new(&shell)
+            .args(crate::config::get_shell_flags(&shell))
+            .arg(script)
+            .current_dir(cwd)
+            .spawn()
+    }
+
+    // Run directly rather than through cmd, which would run metacharacters
+    // in file names. Resolving the program finds .cmd shims such as
+    // code.cmd, whose arguments the standard library escapes.
+    #[cfg(target_os = "windows")]
+    {
+        let (program, args) = words.split_first().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "Empty command")
+        })?;
+        let program = find_executable(program).unwrap_or_else(|| PathBuf::from(program));
+        Command::new(program).args(args).current_dir(cwd).spawn()
+    }
+}
+
+/// Reap a launched process in the background so it does not linger as a zombie
+pub fn reap(child: Child) {
+    std::thread::spawn(move || {
+        let mut child = child;
+        let _ = child.wait();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmd_quote_escapes_metacharacters() {
+        assert_eq!(cmd_quote("a&calc.txt"), r#"^"a^&calc.txt^""#);
+        assert_eq!(cmd_quote("100%!"), r#"^"100^%^!^""#);
+        assert_eq!(cmd_quote("(x)|y>z<w"), r#"^"^(x^)^|y^>z^<w^""#);
+    }
+
+    #[test]
+    fn cmd_quote_escapes_quotes_for_the_program() {
+        assert_eq!(cmd_quote(r#"say "hi""#), r#"^"say \^"hi\^"^""#);
+        assert_eq!(cmd_quote(r"C:\dir\"), r#"^"C:\dir\\^""#);
+    }
+}
@@ -0,0 +1,132 @@
+//! Git LFS detection. Without `git-lfs` on the sidecar's PATH, checkouts of
+//! an LFS repository silently leave pointer files in place of the real
+//! contents, so warn as soon as such a repository is opened.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewWindow};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::error::CommandResult;
+use crate::{broadcast, environment, git, i18n, watcher};
+
+/// Repositories already warned about this session, keyed by repo key
+#[derive(Clone, Default)]
+pub struct LfsWarnings(Arc<Mutex<HashSet<String>>>);
+
+/// Whether a repository uses LFS and whether the sidecar can run it.
+/// Also the payload of the `lfs-missing` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LfsStatus {
+    repo_path: String,
+    /// Whether `.gitattributes` routes any files through the LFS filter
+    uses_lfs: bool,
+    /// Where `git-lfs` was found on the sidecar's PATH
+    lfs_path: Option<String>,
+    /// The sidecar's PATH, one directory per entry
+    search_path: Vec<String>,
+}
+
+impl LfsStatus {
+    fn is_missing(&self) -> bool {
+        self.uses_lfs && self.lfs_path.is_none()
+    }
+}
+
+fn install_hint() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Install it with `brew install git-lfs`, then run `git lfs install`."
+    } else if cfg!(target_os = "windows") {
+        "Install it from https://git-lfs.com (it ships with Git for Windows), then run `git lfs install`."
+    } else {
+        "Install it with your package manager, e.g. `sudo apt install git-lfs`, then run `git lfs install`."
+    }
+}
+
+/// Whether any attributes file of the repository sets `filter=lfs`
+fn uses_lfs(repo_path: &str) -> bool {
+    let mut files = vec![Path::new(repo_path).join(".gitattributes")];
+    if let Some((_, common_dir)) = watcher::git_dirs(repo_path) {
+        files.push(common_dir.join("info").join("attributes"));
+    }
+    files.iter().any(|file| {
+        std::fs::read_to_string(file).is_ok_and(|contents| {
+            contents.lines().any(|line| {
+                !line.trim_start().starts_with('#')
+                    && line.split_whitespace().any(|attr| attr == "filter=lfs")
+            })
+        })
+    })
+}
+
+async fn status(app: &AppHandle, repo_path: &str) -> LfsStatus {
+    let uses_lfs = uses_lfs(repo_path);
+    let dirs = if uses_lfs {
+        environment::sidecar_path(app, Some(repo_path)).await
+    } else {
+        Vec::new()
+    };
+    LfsStatus {
+        repo_path: repo_path.to_string(),
+        uses_lfs,
+        lfs_path: environment::find_program(&dirs, "git-lfs")
+            .map(|path| path.to_string_lossy().to_string()),
+        search_path: dirs
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect(),
+    }
+}
+
+/// Check a newly opened repository in the background and, once per session,
+/// warn its window if it uses LFS but `git-lfs` can't be found
+pub fn check(app: &AppHandle, window: &WebviewWindow, repo_path: &str) {
+    let app = app.clone();
+    let window = window.clone();
+    let repo_path = repo_path.to_string();
+    tauri::async_runtime::spawn(async move {
+        let status = status(&app, &repo_path).await;
+        if !status.is_missing() {
+            return;
+        }
+        let first_warning = app
+            .state::<LfsWarnings>()
+            .0
+            .lock()
+            .is_ok_and(|mut warned| warned.insert(broadcast::repo_key(&repo_path)));
+        if !first_warning {
+            return;
+        }
+
+        log_error!(
+            "{} uses Git LFS but git-lfs is not on the sidecar's PATH",
+            repo_path
+        );
+        let _ = window.emit_to(
+            EventTarget::webview_window(window.label()),
+            "lfs-missing",
+            status.clone(),
+        );
+        app.dialog()
+            .message(format!(
+                "{}\n\n{}\n\n{}\n{}",
+                i18n::tr(&app, "lfs.message", &[("repo", repo_path.as_str())]),
+                install_hint(),
+                i18n::t(&app, "lfs.searched-path"),
+                status.search_path.join("\n")
+            ))
+            .title(i18n::t(&app, "lfs.title"))
+            .kind(MessageDialogKind::Warning)
+            .show(|_| {});
+    });
+}
+
+/// LFS status of the window's repository
+#[tauri::command]
+pub async fn get_lfs_status(app: AppHandle, window: WebviewWindow) -> CommandResult<LfsStatus> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    Ok(status(&app, &repo_path).await)
+}
@@ -0,0 +1,698 @@
+//! Sidecar output and the shell's own log lines, kept in a ring buffer and
+//! streamed to the log window so packaged builds can show it without a
+//! terminal. Release builds on Windows have no console at all unless launched
+//! with `--attach-console` from one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewWindow};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::control::{self, ControlMessage};
+use crate::error::CommandResult;
+use crate::{dev_frontend, i18n, navigation, portable, settings, theme};
+
+/// Lines queued between the sidecar readers and the buffer
+const LOG_CHANNEL_CAPACITY: usize = 1024;
+/// Smallest line length cap, so lines keep enough text to be useful
+const MIN_LINE_BYTES: usize = 256;
+/// Prefix of the per-session log files in the app log directory
+const LOG_FILE_PREFIX: &str = "sidecar-";
+/// Entries returned by one `get_logs_v2` call unless a smaller limit is given
+const MAX_PAGE_SIZE: usize = 500;
+/// Label of the log window
+const WINDOW_LABEL: &str = "logs";
+/// Port recorded for the shell's own lines
+pub const SHELL_PORT: u32 = 0;
+/// Shell lines kept from before the pipeline starts
+const MAX_EARLY_LINES: usize = 500;
+/// Command-line flag attaching to the console of the terminal the app was
+/// launched from
+const ATTACH_CONSOLE_FLAG: &str = "--attach-console";
+
+/// How much sidecar output is kept, in memory and on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LogSettings {
+    /// Lines kept in memory for the log window and diagnostics
+    pub max_entries: usize,
+    /// Longer lines, e.g. whole diffs printed on one line, are cut to this
+    pub max_line_bytes: usize,
+    /// Also write each session's output to a file in the app log directory
+    pub write_to_disk: bool,
+    /// Session log files kept; older ones are deleted
+    pub retained_files: usize,
+    /// Log files older than this are deleted; 0 keeps them regardless of age
+    pub retention_days: u64,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            max_entries: 2000,
+            max_line_bytes: 8 * 1024,
+            write_to_disk: true,
+            retained_files: 10,
+            retention_days: 14,
+        }
+    }
+}
+
+/// Output stream a line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// Severity of a line, as far as it can be told from its text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Level by its name, as in `--log-level debug`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+
+    /// Level named near the start of a line, e.g. `ERROR ...` or `[warn] ...`
+    fn detect(line: &str) -> Self {
+        let head: String = line.chars().take(40).collect::<String>().to_lowercase();
+        let words: Vec<&str> = head
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let has = |names: &[&str]| words.iter().any(|word| names.contains(word));
+        if has(&["error", "err", "fatal", "panic", "panicked", "exception"]) {
+            LogLevel::Error
+        } else if has(&["warn", "warning"]) {
+            LogLevel::Warn
+        } else if has(&["debug", "trace"]) {
+            LogLevel::Debug
+        } else {
+            LogLevel::Info
+        }
+    }
+}
+
+/// A line of sidecar output. Also the payload of the `log-entry` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// Increasing id, unique for the app's lifetime
+    id: u64,
+    /// Milliseconds since the Unix epoch
+    time: u128,
+    /// Port of the sidecar that printed it, `SHELL_PORT` for the shell's own
+    port: u32,
+    /// Name of the helper service that printed it, if not a sidecar
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<String>,
+    stream: LogStream,
+    level: LogLevel,
+    line: String,
+}
+
+#[derive(Default)]
+struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    next_id: u64,
+}
+
+/// A line on its way from a sidecar reader to the buffer
+#[derive(Debug, Clone)]
+struct RawLine {
+    port: u32,
+    service: Option<String>,
+    stream: LogStream,
+    /// Known for the shell's lines, detected from the text for sidecar output
+    level: Option<LogLevel>,
+    line: String,
+}
+
+/// State to collect sidecar logs for debugging. Lines pass through a bounded
+/// channel to a single task that stores them, so a sidecar flooding its
+/// output loses its oldest unstored lines instead of stalling the runtime.
+#[derive(Clone)]
+pub struct LogState {
+    buffer: Arc<Mutex<LogBuffer>>,
+    sender: broadcast::Sender<RawLine>,
+    /// Lines dropped because the channel was full
+    dropped: Arc<AtomicU64>,
+    /// Lines cut short for exceeding the line length cap
+    truncated: Arc<AtomicU64>,
+    /// Limits from `LogSettings`, read on every line
+    max_entries: Arc<AtomicUsize>,
+    max_line_bytes: Arc<AtomicUsize>,
+    write_to_disk: Arc<AtomicBool>,
+}
+
+impl Default for LogState {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(LOG_CHANNEL_CAPACITY);
+        let defaults = LogSettings::default();
+        Self {
+            buffer: Arc::default(),
+            sender,
+            dropped: Arc::default(),
+            truncated: Arc::default(),
+            max_entries: Arc::new(AtomicUsize::new(defaults.max_entries)),
+            max_line_bytes: Arc::new(AtomicUsize::new(defaults.max_line_bytes)),
+            write_to_disk: Arc::new(AtomicBool::new(defaults.write_to_disk)),
+        }
+    }
+}
+
+/// Counters of the log pipeline
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogStats {
+    /// Entries currently buffered
+    buffered: usize,
+    /// Most entries the buffer holds
+    capacity: usize,
+    /// Lines received since startup, including dropped ones
+    total: u64,
+    dropped: u64,
+    truncated: u64,
+}
+
+/// Cut a line down to `max_bytes`, on a character boundary.
+/// Returns whether it was cut.
+fn cap_line(line: &mut String, max_bytes: usize) -> bool {
+    if line.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    let removed = line.len() - end;
+    line.truncate(end);
+    line.push_str(&format!(" … [{} bytes truncated]", removed));
+    true
+}
+
+/// Queue a line of sidecar output. Never blocks; when the channel is full the
+/// oldest queued line is dropped.
+pub fn push(app: &AppHandle, port: u32, stream: LogStream, line: &str) {
+    queue(app, port, None, stream, line);
+}
+
+/// Queue a line of a helper service's output, like `push`
+pub fn push_service(app: &AppHandle, service: &str, port: u32, stream: LogStream, line: &str) {
+    queue(app, port, Some(service.to_string()), stream, line);
+}
+
+fn queue(app: &AppHandle, port: u32, service: Option<String>, stream: LogStream, line: &str) {
+    let Some(state) = app.try_state::<LogState>() else {
+        return;
+    };
+    let mut line = line.trim_end_matches(['\r', '\n']).to_string();
+    if cap_line(&mut line, state.max_line_bytes.load(Ordering::Relaxed)) {
+        state.truncated.fetch_add(1, Ordering::Relaxed);
+    }
+    let _ = state.sender.send(RawLine {
+        port,
+        service,
+        stream,
+        level: None,
+        line,
+    });
+}
+
+/// Where shell lines go once `start` has run, and those logged before
+struct ShellSink {
+    sender: Option<broadcast::Sender<RawLine>>,
+    early: Vec<RawLine>,
+}
+
+static SHELL_SINK: Mutex<ShellSink> = Mutex::new(ShellSink {
+    sender: None,
+    early: Vec::new(),
+});
+
+/// Print a line of the shell's own to the console, if there is one, and queue
+/// it with the sidecar output. Use `log_info!` and `log_error!` instead.
+pub fn shell_line(stream: LogStream, line: String) {
+    let level = match stream {
+        LogStream::Stdout => {
+            println!("[tauri] {}", line);
+            LogLevel::Info
+        }
+        LogStream::Stderr => {
+            eprintln!("[tauri] {}", line);
+            LogLevel::Error
+        }
+    };
+    let raw = RawLine {
+        port: SHELL_PORT,
+        service: None,
+        stream,
+        level: Some(level),
+        line,
+    };
+    let Ok(mut sink) = SHELL_SINK.lock() else {
+        return;
+    };
+    match &sink.sender {
+        Some(sender) => {
+            let _ = sender.send(raw);
+        }
+        None if sink.early.len() < MAX_EARLY_LINES => sink.early.push(raw),
+        None => {}
+    }
+}
+
+/// With `--attach-console`, write to the console of the terminal the app was
+/// launched from. Release builds on Windows are GUI programs without one.
+pub fn attach_console() {
+    if !std::env::args()
+        .skip(1)
+        .any(|arg| arg == ATTACH_CONSOLE_FLAG)
+    {
+        return;
+    }
+    #[cfg(target_os = "windows")]
+    {
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn AttachConsole(process_id: u32) -> i32;
+        }
+        /// `ATTACH_PARENT_PROCESS`
+        const PARENT_PROCESS: u32 = u32::MAX;
+        // SAFETY: AttachConsole takes no pointers and only fails if the
+        // parent has no console or one is already attached
+        if unsafe { AttachConsole(PARENT_PROCESS) } != 0 {
+            // Start below the prompt the terminal already printed
+            println!();
+        }
+    }
+}
+
+/// This session's log file, named after the time the app started
+fn session_log_path(app: &AppHandle) -> Option<PathBuf> {
+    static STARTED: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+    let started = STARTED.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default()
+    });
+    portable::log_dir(app)
+        .ok()
+        .map(|dir| dir.join(format!("{}{}.log", LOG_FILE_PREFIX, started)))
+}
+
+/// Delete session log files beyond the retention limits, keeping `current`
+fn prune_log_files(dir: &Path, current: Option<&Path>, settings: &LogSettings) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<(PathBuf, SystemTime)> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with(LOG_FILE_PREFIX) && name.ends_with(".log")
+        })
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.modified().ok()?)))
+        .filter(|(path, _)| Some(path.as_path()) != current)
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let max_age = Duration::from_secs(settings.retention_days * 24 * 60 * 60);
+    // The current session's file counts towards the limit
+    let keep = settings.retained_files.saturating_sub(1);
+    for (index, (path, modified)) in files.iter().enumerate() {
+        let expired = settings.retention_days > 0
+            && SystemTime::now()
+                .duration_since(*modified)
+                .is_ok_and(|age| age > max_age);
+        if index >= keep || expired {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Append an entry to this session's log file, opening it on first use
+fn write_to_file(app: &AppHandle, file: &mut Option<File>, entry: &LogEntry) {
+    if file.is_none() {
+        let Some(path) = session_log_path(app) else {
+            return;
+        };
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+        {
+            Ok(opened) => *file = Some(opened),
+            Err(e) => {
+                // Not `log_error!`, which would queue another line to write
+                eprintln!("[tauri] Failed to open log file {}: {}", path.display(), e);
+                return;
+            }
+        }
+    }
+    let stream = match entry.stream {
+        LogStream::Stdout => "stdout",
+        LogStream::Stderr => "stderr",
+    };
+    if let Some(file) = file {
+        let source = match (&entry.service, entry.port) {
+            (Some(service), _) => service.clone(),
+            (None, SHELL_PORT) => "shell".to_string(),
+            (None, port) => port.to_string(),
+        };
+        let _ = writeln!(
+            file,
+            "{} [{}:{}] {}",
+            entry.time, source, stream, entry.line
+        );
+    }
+}
+
+/// Store a line in the buffer and stream it to the log window
+fn store(app: &AppHandle, state: &LogState, file: &mut Option<File>, raw: RawLine) {
+    let Ok(mut buffer) = state.buffer.lock() else {
+        return;
+    };
+    let entry = LogEntry {
+        id: buffer.next_id,
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default(),
+        port: raw.port,
+        service: raw.service,
+        stream: raw.stream,
+        level: raw.level.unwrap_or_else(|| LogLevel::detect(&raw.line)),
+        line: raw.line,
+    };
+    buffer.next_id += 1;
+    buffer.entries.push_back(entry.clone());
+    let max_entries = state.max_entries.load(Ordering::Relaxed);
+    while buffer.entries.len() > max_entries {
+        buffer.entries.pop_front();
+    }
+    drop(buffer);
+
+    if state.write_to_disk.load(Ordering::Relaxed) {
+        write_to_file(app, file, &entry);
+    } else {
+        *file = None;
+    }
+
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.emit_to(
+            EventTarget::webview_window(WINDOW_LABEL),
+            "log-entry",
+            entry,
+        );
+    }
+}
+
+/// Apply the log settings: shrinking the buffer trims it right away, and
+/// log files beyond the retention limits are deleted
+pub fn refresh(app: &AppHandle) {
+    let settings = settings::current(app).logs;
+    let Some(state) = app.try_state::<LogState>() else {
+        return;
+    };
+    state
+        .max_entries
+        .store(settings.max_entries, Ordering::Relaxed);
+    state.max_line_bytes.store(
+        settings.max_line_bytes.max(MIN_LINE_BYTES),
+        Ordering::Relaxed,
+    );
+    state
+        .write_to_disk
+        .store(settings.write_to_disk, Ordering::Relaxed);
+    trim(app, settings.max_entries);
+
+    let current = session_log_path(app);
+    if let Some(dir) = current.as_deref().and_then(Path::parent) {
+        prune_log_files(dir, current.as_deref(), &settings);
+    }
+}
+
+/// Start the task moving queued lines into the buffer, along with the shell
+/// lines logged so far
+pub fn start(app: &AppHandle) {
+    let state = app.state::<LogState>().inner().clone();
+    let mut receiver = state.sender.subscribe();
+    if let Ok(mut sink) = SHELL_SINK.lock() {
+        // The first app of the process keeps the shell's lines
+        if sink.sender.is_none() {
+            for raw in sink.early.drain(..) {
+                let _ = state.sender.send(raw);
+            }
+            sink.sender = Some(state.sender.clone());
+        }
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut file = None;
+        loop {
+            match receiver.recv().await {
+                Ok(raw) => store(&app, &state, &mut file, raw),
+                Err(RecvError::Lagged(count)) => {
+                    state.dropped.fetch_add(count, Ordering::Relaxed);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Sidecar arguments setting the level it logs at, from the `logLevel`
+/// setting
+pub fn sidecar_args(app: &AppHandle) -> Vec<String> {
+    settings::current(app)
+        .log_level
+        .map(|level| vec!["--log-level".to_string(), level.as_str().to_string()])
+        .unwrap_or_default()
+}
+
+/// Drop all but the newest `keep` entries
+pub fn trim(app: &AppHandle, keep: usize) {
+    let Some(state) = app.try_state::<LogState>() else {
+        return;
+    };
+    if let Ok(mut buffer) = state.buffer.lock() {
+        while buffer.entries.len() > keep {
+            buffer.entries.pop_front();
+        }
+        buffer.entries.shrink_to_fit();
+    }
+}
+
+/// The buffered logs as text, one `[stream] line` per line, the shell's own
+/// marked `[tauri]` as on the console
+pub fn text(app: &AppHandle) -> String {
+    let Some(state) = app.try_state::<LogState>() else {
+        return String::new();
+    };
+    let Ok(buffer) = state.buffer.lock() else {
+        return String::new();
+    };
+    buffer
+        .entries
+        .iter()
+        .map(|entry| {
+            let stream = match entry.stream {
+                LogStream::Stdout => "stdout",
+                LogStream::Stderr => "stderr",
+            };
+            if entry.port == SHELL_PORT {
+                format!("[{}] [tauri] {}\n", stream, entry.line)
+            } else {
+                format!("[{}] {}\n", stream, entry.line)
+            }
+        })
+        .collect()
+}
+
+/// A page of log entries from `get_logs_v2`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogPage {
+    entries: Vec<LogEntry>,
+    /// Pass as `since` to fetch the entries after this page
+    next_cursor: Option<u64>,
+}
+
+/// Collected sidecar logs, oldest first: those after the `since` cursor, at
+/// or above `level`, containing `filter` (case-insensitive), at most `limit`.
+/// Poll with the returned cursor to fetch only new entries.
+#[tauri::command]
+pub fn get_logs_v2(
+    app: AppHandle,
+    since: Option<u64>,
+    limit: Option<usize>,
+    level: Option<LogLevel>,
+    filter: Option<String>,
+) -> LogPage {
+    let limit = limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let filter = filter.map(|filter| filter.to_lowercase());
+    let entries: Vec<LogEntry> = app
+        .try_state::<LogState>()
+        .and_then(|state| {
+            let buffer = state.buffer.lock().ok()?;
+            Some(
+                buffer
+                    .entries
+                    .iter()
+                    .filter(|entry| !matches!(since, Some(since) if entry.id <= since))
+                    .filter(|entry| !matches!(level, Some(level) if entry.level < level))
+                    .filter(|entry| match &filter {
+                        Some(filter) => entry.line.to_lowercase().contains(filter.as_str()),
+                        None => true,
+                    })
+                    .take(limit)
+                    .cloned()
+                    .collect(),
+            )
+        })
+        .unwrap_or_default();
+
+    LogPage {
+        next_cursor: entries.last().map(|entry| entry.id).or(since),
+        entries,
+    }
+}
+
+/// Change the level every running sidecar logs at, e.g. to capture debug
+/// output of a problem without restarting and losing it. Sidecars started
+/// later log at the `logLevel` setting.
+#[tauri::command]
+pub fn set_sidecar_log_level(app: AppHandle, level: LogLevel) -> CommandResult<()> {
+    control::send(&app, &ControlMessage::SetLogLevel { level })?;
+    log_info!("Sidecars now log at {} level", level.as_str());
+    Ok(())
+}
+
+/// Counters of the log pipeline, including lines dropped under load
+#[tauri::command]
+pub fn get_log_stats(app: AppHandle) -> LogStats {
+    let state = app.state::<LogState>();
+    let (buffered, next_id) = state
+        .buffer
+        .lock()
+        .map(|buffer| (buffer.entries.len(), buffer.next_id))
+        .unwrap_or_default();
+    let dropped = state.dropped.load(Ordering::Relaxed);
+    LogStats {
+        buffered,
+        capacity: state.max_entries.load(Ordering::Relaxed),
+        total: next_id + dropped,
+        dropped,
+        truncated: state.truncated.load(Ordering::Relaxed),
+    }
+}
+
+/// Empty the log buffer
+#[tauri::command]
+pub fn clear_logs(app: AppHandle) {
+    trim(&app, 0);
+}
+
+/// Open the log window, or focus it if already open. It starts from
+/// `get_logs_v2` and follows the `log-entry` event.
+#[tauri::command]
+pub async fn open_log_window(app: AppHandle) -> CommandResult<()> {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    WebviewWindow::builder(&app, WINDOW_LABEL, dev_frontend::webview_url())
+        .on_navigation(navigation::handler(&app))
+        .title(i18n::t(&app, "title.logs"))
+        .inner_size(900.0, 600.0)
+        .min_inner_size(480.0, 320.0)
+        .resizable(true)
+        .theme(theme::native(&app))
+        .initialization_script(&format!(
+            r#"
+            window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+            window.__OPENTUI__.view = "logs";
+            window.__OPENTUI__.theme = "{}";
+            "#,
+            theme::name(&app)
+        ))
+        .build()
+        .map_err(|e| format!("Failed to create log window: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_levels_near_the_start() {
+        assert_eq!(
+            LogLevel::detect("ERROR failed to read index"),
+            LogLevel::Error
+        );
+        assert_eq!(LogLevel::detect("[warn] slow fetch"), LogLevel::Warn);
+        assert_eq!(
+            LogLevel::detect("2024-01-01T00:00:00Z DEBUG tick"),
+            LogLevel::Debug
+        );
+        assert_eq!(LogLevel::detect("thread 'main' panicked"), LogLevel::Error);
+        assert_eq!(
+            LogLevel::detect("Uncaught exception in fetch"),
+            LogLevel::Error
+        );
+        assert_eq!(LogLevel::detect("listening on 4096"), LogLevel::Info);
+        // Only the start of the line counts
+        let late = format!("{} error", "x".repeat(60));
+        assert_eq!(LogLevel::detect(&late), LogLevel::Info);
+    }
+
+    #[test]
+    fn caps_long_lines_on_char_boundaries() {
+        let mut short = "short".to_string();
+        assert!(!cap_line(&mut short, 10));
+        assert_eq!(short, "short");
+
+        let mut long = "ééééé".to_string();
+        assert!(cap_line(&mut long, 5));
+        assert_eq!(long, "éé … [6 bytes truncated]");
+    }
+}
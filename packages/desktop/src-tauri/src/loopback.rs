@@ -0,0 +1,182 @@
+//! Defense in depth for the local server, which runs git (and so hooks) on
+//! behalf of whoever talks to it: every request must carry a per-launch secret,
+//! and a sidecar that bound anything but loopback is refused unless LAN exposure
+//! or pairing is turned on in the settings. Also which loopback, IPv4 or IPv6,
+//! sidecars listen on and are reached at.
+
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpListener};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::{askpass, ipc, settings, tls};
+
+/// Header the server checks the secret in
+pub const SECRET_HEADER: &str = "X-OpenTUI-Secret";
+/// Environment variable passing the secret to the sidecar
+const SECRET_VAR: &str = "OPENTUI_SERVER_SECRET";
+const LOOPBACK_HOST: &str = "127.0.0.1";
+const LOOPBACK_HOST_V6: &str = "::1";
+const ANY_HOST: &str = "0.0.0.0";
+const ANY_HOST_V6: &str = "::";
+
+/// Which IP version sidecars listen on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFamily {
+    /// IPv4, unless the system has no IPv4 loopback
+    #[default]
+    Auto,
+    Ipv4,
+    Ipv6,
+}
+
+/// Secret shared with the sidecars started by this launch
+#[derive(Clone)]
+pub struct ServerSecret(String);
+
+impl ServerSecret {
+    pub fn generate() -> Result<Self, String> {
+        askpass::random_token().map(Self)
+    }
+}
+
+/// The secret webviews and HTTP clients must send
+pub fn secret(app: &AppHandle) -> Option<String> {
+    app.try_state::<ServerSecret>()
+        .map(|secret| secret.0.clone())
+}
+
+/// Environment giving the sidecar its secret
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    secret(app)
+        .map(|secret| vec![(SECRET_VAR.to_string(), secret)])
+        .unwrap_or_default()
+}
+
+/// Whether sidecars may listen beyond loopback
+fn lan_allowed(app: &AppHandle) -> bool {
+    let settings = settings::current(app);
+    settings.expose_on_lan || settings.lan_pairing
+}
+
+/// Whether the system has an IPv4 loopback, which IPv6-only systems lack
+fn has_ipv4_loopback() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let available = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).is_ok();
+        if !available {
+            log_info!("No IPv4 loopback, using IPv6 for the servers");
+        }
+        available
+    })
+}
+
+fn uses_ipv6(family: AddressFamily) -> bool {
+    match family {
+        AddressFamily::Auto => !has_ipv4_loopback(),
+        AddressFamily::Ipv4 => false,
+        AddressFamily::Ipv6 => true,
+    }
+}
+
+/// Address the sidecar should listen on
+pub fn host(app: &AppHandle) -> &'static str {
+    let ipv6 = uses_ipv6(settings::current(app).server_address);
+    match (lan_allowed(app), ipv6) {
+        (true, false) => ANY_HOST,
+        (true, true) => ANY_HOST_V6,
+        (false, false) => LOOPBACK_HOST,
+        (false, true) => LOOPBACK_HOST_V6,
+    }
+}
+
+/// Address to reach a server listening on `bound`: the matching loopback for
+/// the unspecified address, otherwise the address itself
+fn connect_address(bound: &str) -> String {
+    match bound.parse::<IpAddr>() {
+        Ok(IpAddr::V4(address)) if address.is_unspecified() => LOOPBACK_HOST.to_string(),
+        Ok(IpAddr::V6(address)) if address.is_unspecified() => LOOPBACK_HOST_V6.to_string(),
+        _ => bound.to_string(),
+    }
+}
+
+/// Host to reach the sidecar on a port at, from the address it reported
+/// binding, or the one it was told to listen on for sidecars started
+/// outside the app
+pub fn connect_host(app: &AppHandle, port: u32) -> String {
+    let bound = ipc::ready_info(app, port)
+        .and_then(|ready| ready.host)
+        .unwrap_or_else(|| host(app).to_string());
+    connect_address(&bound)
+}
+
+/// A host as written in a URL, with IPv6 addresses in brackets
+pub fn url_host(host: &str) -> String {
+    if host.parse::<Ipv6Addr>().is_ok() {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// HTTP client for talking to a sidecar, sending the secret with every request
+pub fn client(app: &AppHandle, timeout: Duration) -> Result<reqwest::Client, String> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(secret) = secret(app) {
+        let value = reqwest::header::HeaderValue::from_str(&secret)
+            .map_err(|e| format!("Invalid server secret: {}", e))?;
+        headers.insert(SECRET_HEADER, value);
+    }
+    let mut builder = reqwest::Client::builder()
+        .timeout(timeout)
+        .default_headers(headers);
+    if let Some(certificate) = tls::root_certificate(app) {
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Check that the sidecar on a port bound loopback only, unless LAN access is on
+pub fn verify_bind(app: &AppHandle, port: u32) -> Result<(), String> {
+    if lan_allowed(app) {
+        return Ok(());
+    }
+    let host = ipc::ready_info(app, port)
+        .and_then(|ready| ready.host)
+        .ok_or_else(|| format!("Server on port {} did not report its address", port))?;
+    let loopback = host == "localhost"
+        || host
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_loopback());
+    if !loopback {
+        return Err(format!(
+            "Server on port {} is listening on {} instead of loopback",
+            port, host
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reaches_unspecified_addresses_over_loopback() {
+        assert_eq!(connect_address("0.0.0.0"), "127.0.0.1");
+        assert_eq!(connect_address("::"), "::1");
+        assert_eq!(connect_address("::1"), "::1");
+        assert_eq!(connect_address("localhost"), "localhost");
+    }
+
+    #[test]
+    fn brackets_ipv6_hosts_in_urls() {
+        assert_eq!(url_host("::1"), "[::1]");
+        assert_eq!(url_host("127.0.0.1"), "127.0.0.1");
+        assert_eq!(url_host("localhost"), "localhost");
+    }
+}
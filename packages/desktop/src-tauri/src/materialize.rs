@@ -0,0 +1,179 @@
+//! Writing versions of files from the object database to a temp folder, so
+//! the UI can hand an old version to an editor or image viewer, which the
+//! webview can't do by itself. Files keep their name under a folder per
+//! commit and are made read-only, since edits to them go nowhere. The folder
+//! is removed when the app exits.
+
+use git2::{ObjectType, Repository};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::{cleanup, git, native};
+
+/// Folder in the system temp directory holding the written versions, with a
+/// folder per app process
+const TEMP_DIR: &str = "opentui-git-blobs";
+/// Folders of other processes older than this are left over from crashes
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Folder of this process's written versions
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir()
+        .join(TEMP_DIR)
+        .join(std::process::id().to_string())
+}
+
+/// Where a file at a commit is written. `None` if the path could lead
+/// outside the folder.
+fn version_path(root: &Path, commit: &str, file: &str) -> Option<PathBuf> {
+    let file = Path::new(file);
+    let relative = !file.as_os_str().is_empty()
+        && file
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    relative.then(|| root.join(commit).join(file))
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> git2::Error {
+    git2::Error::from_str(&format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Write `file` as of `rev` under `root`, unless an earlier call already did
+fn materialize(
+    repo: &Repository,
+    root: &Path,
+    rev: &str,
+    file: &str,
+) -> Result<PathBuf, git2::Error> {
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    let entry = commit.tree()?.get_path(Path::new(file))?;
+    if entry.kind() != Some(ObjectType::Blob) {
+        return Err(git2::Error::from_str(&format!(
+            "{} is not a file at {}",
+            file, rev
+        )));
+    }
+    let short_id = commit.as_object().short_id()?;
+    let short_id = short_id.as_str().unwrap_or_default();
+    let path = version_path(root, short_id, file)
+        .ok_or_else(|| git2::Error::from_str(&format!("Invalid path: {:?}", file)))?;
+    // Versions never change, so one written before can be reused
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let blob = repo.find_blob(entry.id())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| write_error(dir, e))?;
+    }
+    std::fs::write(&path, blob.content()).map_err(|e| write_error(&path, e))?;
+    let mut permissions = std::fs::metadata(&path)
+        .map_err(|e| write_error(&path, e))?
+        .permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&path, permissions).map_err(|e| write_error(&path, e))?;
+    Ok(path)
+}
+
+/// Remove a folder of written versions
+fn remove(dir: &Path) {
+    // Windows refuses to delete read-only files
+    #[cfg(windows)]
+    for file in walk(dir) {
+        if let Ok(metadata) = std::fs::metadata(&file) {
+            let mut permissions = metadata.permissions();
+            #[allow(clippy::permissions_set_readonly_false)]
+            permissions.set_readonly(false);
+            let _ = std::fs::set_permissions(&file, permissions);
+        }
+    }
+    if let Err(e) = std::fs::remove_dir_all(dir) {
+        log_error!("Failed to remove {}: {}", dir.display(), e);
+    }
+}
+
+/// Files under a folder
+#[cfg(windows)]
+fn walk(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .flat_map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(&path)
+            } else {
+                vec![path]
+            }
+        })
+        .collect()
+}
+
+/// Remove this process's written versions, and those other processes left
+/// behind when they crashed. Runs when the app exits.
+fn remove_all() {
+    let own = temp_dir();
+    if own.exists() {
+        remove(&own);
+    }
+    let Ok(entries) = std::fs::read_dir(std::env::temp_dir().join(TEMP_DIR)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > STALE_AFTER));
+        if stale {
+            remove(&entry.path());
+        }
+    }
+}
+
+/// Write a file of the window's repository as of `commitish` to a temp
+/// folder and return its path, for opening it in another app. The file is
+/// read-only and removed when the app exits.
+#[tauri::command]
+pub async fn materialize_blob(
+    app: AppHandle,
+    window: WebviewWindow,
+    commitish: String,
+    path: String,
+) -> CommandResult<String> {
+    git::validate_rev(&commitish)?;
+    if version_path(Path::new(""), "", &path).is_none() {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid path: {:?}", path),
+        ));
+    }
+    cleanup::register(&app, "blob-files", |_| remove_all());
+    let written = native::with_repo(&app, &window, None, move |repo| {
+        materialize(repo, &temp_dir(), &commitish, &path)
+    })
+    .await?;
+    Ok(written.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_versions_inside_the_folder() {
+        let root = Path::new("/tmp/blobs");
+        assert_eq!(
+            version_path(root, "abc1234", "src/main.rs"),
+            Some(root.join("abc1234").join("src/main.rs"))
+        );
+        assert_eq!(version_path(root, "abc1234", "../secret"), None);
+        assert_eq!(version_path(root, "abc1234", "src/../../secret"), None);
+        assert_eq!(version_path(root, "abc1234", "/etc/passwd"), None);
+        assert_eq!(version_path(root, "abc1234", "./src/main.rs"), None);
+        assert_eq!(version_path(root, "abc1234", ""), None);
+    }
+}
@@ -0,0 +1,337 @@
+//! Reacts to OS memory pressure and to sidecars outgrowing their memory limit.
+//!
+//! Pressure comes from PSI on Linux and the kernel memorystatus level on
+//! macOS; under pressure the shell trims its log buffer and asks the sidecar
+//! to drop its caches. Sidecars staying over the configured limit are
+//! restarted once no operation is running.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::control::{self, ControlMessage};
+use crate::sidecar::{ServerState, Sidecar};
+use crate::{broadcast, ipc, logs, power, settings};
+
+const POLL_INTERVAL_SECS: u64 = 5;
+/// How often sidecar memory is sampled against the limit
+const SIDECAR_SAMPLE_SECS: u64 = 30;
+/// How long a sidecar over the limit gets to exit on its own before it is killed
+const SHUTDOWN_TIMEOUT_SECS: u64 = 3;
+const LOG_ENTRIES_UNDER_PRESSURE: usize = 50;
+
+/// PSI `avg10` thresholds (percent of time stalled on memory)
+const WARNING_PSI_AVG10: f64 = 10.0;
+const CRITICAL_PSI_AVG10: f64 = 40.0;
+
+/// Memory pressure level reported by the OS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl PressureLevel {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            2 => PressureLevel::Critical,
+            1 => PressureLevel::Warning,
+            _ => PressureLevel::Normal,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            PressureLevel::Normal => 0,
+            PressureLevel::Warning => 1,
+            PressureLevel::Critical => 2,
+        }
+    }
+}
+
+/// Payload of the `memory-pressure` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemoryPressureEvent {
+    level: PressureLevel,
+    degraded: bool,
+}
+
+/// Payload of the `sidecar-memory-limit` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemoryLimitEvent {
+    port: u32,
+    repo_path: String,
+    rss_bytes: u64,
+    limit_bytes: u64,
+    restarted: bool,
+}
+
+/// State tracking the last observed memory pressure level
+#[derive(Clone, Default)]
+pub struct MemoryState(Arc<AtomicU8>);
+
+impl MemoryState {
+    pub fn level(&self) -> PressureLevel {
+        PressureLevel::from_u8(self.0.load(Ordering::SeqCst))
+    }
+
+    fn swap(&self, level: PressureLevel) -> PressureLevel {
+        PressureLevel::from_u8(self.0.swap(level.as_u8(), Ordering::SeqCst))
+    }
+}
+
+/// Pressure level from the contents of `/proc/pressure/memory`, judged by
+/// the `some` line's `avg10`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_psi(contents: &str) -> Option<PressureLevel> {
+    // Format: "some avg10=1.23 avg60=0.50 avg300=0.10 total=12345"
+    let some = contents.lines().find(|line| line.starts_with("some"))?;
+    let avg10: f64 = some
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()?;
+
+    Some(if avg10 >= CRITICAL_PSI_AVG10 {
+        PressureLevel::Critical
+    } else if avg10 >= WARNING_PSI_AVG10 {
+        PressureLevel::Warning
+    } else {
+        PressureLevel::Normal
+    })
+}
+
+/// Read memory pressure from Linux PSI (`/proc/pressure/memory`)
+#[cfg(target_os = "linux")]
+fn read_pressure() -> Option<PressureLevel> {
+    parse_psi(&std::fs::read_to_string("/proc/pressure/memory").ok()?)
+}
+
+/// Read memory pressure from the macOS kernel memorystatus level
+#[cfg(target_os = "macos")]
+fn read_pressure() -> Option<PressureLevel> {
+    // 1 = normal, 2 = warning, 4 = critical
+    let output = std::process::Command::new("sysctl")
+        .args(["-n", "kern.memorystatus_vm_pressure_level"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(match String::from_utf8_lossy(&output.stdout).trim() {
+        "4" => PressureLevel::Critical,
+        "2" => PressureLevel::Warning,
+        _ => PressureLevel::Normal,
+    })
+}
+
+/// No pressure signal on other platforms
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn read_pressure() -> Option<PressureLevel> {
+    None
+}
+
+/// Shrink in-memory buffers and ask the sidecar to release what it can
+fn respond_to_pressure(app: &AppHandle, level: PressureLevel) {
+    logs::trim(app, LOG_ENTRIES_UNDER_PRESSURE);
+
+    if !settings::current(app).drop_sidecar_caches_on_memory_pressure {
+        return;
+    }
+
+    match control::send(app, &ControlMessage::FlushCaches) {
+        Ok(()) => log_info!("Asked sidecar to drop caches ({:?} pressure)", level),
+        Err(e) => log_error!("Failed to ask sidecar to drop caches: {}", e),
+    }
+}
+
+/// Get the current memory pressure level
+#[tauri::command]
+pub fn get_memory_pressure(app: AppHandle) -> PressureLevel {
+    app.try_state::<MemoryState>()
+        .map(|state| state.level())
+        .unwrap_or(PressureLevel::Normal)
+}
+
+/// Poll the OS memory pressure signal and react to level changes
+pub fn start_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(POLL_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            let level = match tauri::async_runtime::spawn_blocking(read_pressure).await {
+                Ok(Some(level)) => level,
+                Ok(None) => {
+                    log_info!("Memory pressure signal not available on this platform");
+                    return;
+                }
+                Err(_) => continue,
+            };
+
+            let previous = app.state::<MemoryState>().swap(level);
+            if previous == level {
+                continue;
+            }
+
+            log_info!("Memory pressure changed: {:?} -> {:?}", previous, level);
+            let _ = app.emit(
+                "memory-pressure",
+                MemoryPressureEvent {
+                    level,
+                    degraded: level != PressureLevel::Normal,
+                },
+            );
+
+            if level != PressureLevel::Normal {
+                respond_to_pressure(&app, level);
+            }
+        }
+    });
+}
+
+/// Shut a sidecar down cleanly and start it again on the same port
+async fn restart_over_limit(app: &AppHandle, sidecar: &Sidecar) -> bool {
+    if ipc::shutdown(app, sidecar).await.is_ok() {
+        let start = Instant::now();
+        while crate::sidecar::is_server_running(sidecar.port).await
+            && start.elapsed() < Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)
+        {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+    if !crate::sidecar::respawn_sidecar(app, sidecar).await {
+        return false;
+    }
+    crate::sidecar::emit_reconnected(app, sidecar, true);
+    true
+}
+
+/// Sample each sidecar's resident memory and restart one that stays above
+/// the configured limit. Long sessions on huge repositories slowly grow the
+/// server, which used to take restarting the whole app.
+pub fn start_sidecar_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        // Consecutive samples over the limit, by port
+        let mut breaches: HashMap<u32, u32> = HashMap::new();
+
+        loop {
+            let interval = power::interval(&app, Duration::from_secs(SIDECAR_SAMPLE_SECS));
+            tokio::time::sleep(interval).await;
+
+            let settings = settings::current(&app);
+            let Some(limit_mb) = settings.sidecar_memory_limit_mb else {
+                breaches.clear();
+                continue;
+            };
+            let limit_bytes = limit_mb.saturating_mul(1024 * 1024);
+            let samples = settings.sidecar_memory_limit_samples.max(1);
+
+            let sidecars = app.state::<ServerState>().all();
+            breaches.retain(|port, _| sidecars.iter().any(|s| s.port == *port));
+
+            for sidecar in sidecars {
+                // Only sidecars this app spawned can be asked and restarted
+                if !sidecar.is_managed() {
+                    continue;
+                }
+                let Some(rss_bytes) = ipc::status(&app, &sidecar)
+                    .await
+                    .ok()
+                    .and_then(|status| status.rss_bytes)
+                else {
+                    continue;
+                };
+
+                if rss_bytes <= limit_bytes {
+                    breaches.remove(&sidecar.port);
+                    continue;
+                }
+                let count = breaches.entry(sidecar.port).or_default();
+                *count += 1;
+                if *count < samples {
+                    continue;
+                }
+
+                // Wait for a quiet moment rather than cut off a running operation
+                if !crate::sidecar::get_running_operations(&app, &sidecar)
+                    .await
+                    .is_empty()
+                {
+                    log_info!(
+                        "Sidecar for {} over memory limit, waiting for operations to finish",
+                        sidecar.repo_path
+                    );
+                    continue;
+                }
+                breaches.remove(&sidecar.port);
+
+                log_error!(
+                    "Sidecar for {} using {} MB, over the {} MB limit; restarting",
+                    sidecar.repo_path,
+                    rss_bytes / (1024 * 1024),
+                    limit_mb
+                );
+                let restarted = restart_over_limit(&app, &sidecar).await;
+                if !restarted {
+                    log_error!("Sidecar for {} failed to restart", sidecar.repo_path);
+                }
+                broadcast::emit_to_repo(
+                    &app,
+                    &sidecar.repo_path,
+                    "sidecar-memory-limit",
+                    MemoryLimitEvent {
+                        port: sidecar.port,
+                        repo_path: sidecar.repo_path.clone(),
+                        rss_bytes,
+                        limit_bytes,
+                        restarted,
+                    },
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn psi(some_avg10: &str) -> String {
+        format!(
+            "some avg10={} avg60=0.50 avg300=0.10 total=12345\n\
+             full avg10=0.00 avg60=0.00 avg300=0.00 total=678\n",
+            some_avg10
+        )
+    }
+
+    #[test]
+    fn parses_pressure_levels() {
+        assert_eq!(parse_psi(&psi("0.00")), Some(PressureLevel::Normal));
+        assert_eq!(parse_psi(&psi("9.99")), Some(PressureLevel::Normal));
+        assert_eq!(parse_psi(&psi("10.00")), Some(PressureLevel::Warning));
+        assert_eq!(parse_psi(&psi("39.50")), Some(PressureLevel::Warning));
+        assert_eq!(parse_psi(&psi("40.00")), Some(PressureLevel::Critical));
+        assert_eq!(parse_psi(&psi("97.12")), Some(PressureLevel::Critical));
+    }
+
+    #[test]
+    fn rejects_unreadable_pressure() {
+        assert_eq!(
+            parse_psi("full avg10=50.00 avg60=0.00 avg300=0.00 total=678\n"),
+            None
+        );
+        assert_eq!(parse_psi(""), None);
+        assert_eq!(parse_psi(&psi("n/a")), None);
+        assert_eq!(parse_psi("some avg60=0.50 total=12345\n"), None);
+    }
+}
@@ -0,0 +1,220 @@
+use tauri::menu::{Menu, MenuBuilder, MenuItemBuilder, SubmenuBuilder};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewWindow, Wry};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::accelerators::menu_item;
+use crate::broadcast::WindowRegistry;
+use crate::display;
+use crate::sidecar::restart_sidecar;
+use crate::window::{open_repo_dialog, open_repo_in_window, open_window_for_repo};
+use crate::{i18n, recent, settings, version};
+
+const ISSUES_URL: &str = "https://github.com/crob19/opentui-git/issues/new";
+
+/// Build the native menu bar and install it for the app
+pub fn install(app: &AppHandle) -> tauri::Result<()> {
+    app.set_menu(build(app)?)?;
+    Ok(())
+}
+
+fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let recent_repos = recent::list(app);
+    let mut open_recent = SubmenuBuilder::new(app, i18n::t(app, "menu.open-recent"));
+    if recent_repos.is_empty() {
+        open_recent = open_recent.item(
+            &MenuItemBuilder::with_id("recent-none", i18n::t(app, "menu.no-recent"))
+                .enabled(false)
+                .build(app)?,
+        );
+    }
+    for (index, repo_path) in recent_repos.iter().enumerate() {
+        open_recent = open_recent
+            .item(&MenuItemBuilder::with_id(format!("recent-{}", index), repo_path).build(app)?);
+    }
+
+    let file = SubmenuBuilder::new(app, i18n::t(app, "menu.file"))
+        .item(&menu_item(app, "new-window")?)
+        .item(&menu_item(app, "open-repo")?)
+        .item(&open_recent.build()?)
+        .separator()
+        .item(&menu_item(app, "close-window")?);
+    // macOS keeps Settings in the application menu
+    #[cfg(not(target_os = "macos"))]
+    let file = file.separator().item(&menu_item(app, "settings")?);
+    let file = file.build()?;
+
+    // Without an Edit menu, copy/paste shortcuts stop working in the webview on macOS
+    let edit = SubmenuBuilder::new(app, i18n::t(app, "menu.edit"))
+        .undo()
+        .redo()
+        .separator()
+        .cut()
+        .copy()
+        .paste()
+        .select_all()
+        .build()?;
+
+    let view = SubmenuBuilder::new(app, i18n::t(app, "menu.view"))
+        .item(&menu_item(app, "refresh")?)
+        .item(&menu_item(app, "reload")?)
+        .item(
+            &MenuItemBuilder::with_id("restart-server", i18n::t(app, "menu.restart-server"))
+                .build(app)?,
+        )
+        .separator()
+        .item(&menu_item(app, "zoom-in")?)
+        .item(&menu_item(app, "zoom-out")?)
+        .item(&menu_item(app, "reset-zoom")?)
+        .separator()
+        .item(&menu_item(app, "toggle-logs")?)
+        .build()?;
+
+    let help = SubmenuBuilder::new(app, i18n::t(app, "menu.help"))
+        .item(&MenuItemBuilder::with_id("about", i18n::t(app, "menu.about")).build(app)?)
+        .item(
+            &MenuItemBuilder::with_id("report-issue", i18n::t(app, "menu.report-issue"))
+                .build(app)?,
+        )
+        .build()?;
+
+    #[cfg(target_os = "macos")]
+    let menu = MenuBuilder::new(app).item(&app_submenu(app)?);
+    #[cfg(not(target_os = "macos"))]
+    let menu = MenuBuilder::new(app);
+
+    menu.items(&[&file, &edit, &view, &help]).build()
+}
+
+/// The macOS application menu
+#[cfg(target_os = "macos")]
+fn app_submenu(app: &AppHandle) -> tauri::Result<tauri::menu::Submenu<Wry>> {
+    SubmenuBuilder::new(app, "opentui-git")
+        .about(None)
+        .separator()
+        .item(&menu_item(app, "settings")?)
+        .separator()
+        .services()
+        .separator()
+        .hide()
+        .hide_others()
+        .show_all()
+        .separator()
+        .quit()
+        .build()
+}
+
+/// The window menu actions apply to
+fn focused_window(app: &AppHandle) -> Option<WebviewWindow> {
+    app.webview_windows()
+        .into_values()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .or_else(|| app.get_webview_window("main"))
+}
+
+/// Handle a click on a menu item
+pub fn handle(app: &AppHandle, id: &str) {
+    match id {
+        "open-repo" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = open_repo_dialog(app).await {
+                    log_error!("Failed to open repository: {}", e);
+                }
+            });
+        }
+        "new-window" => {
+            let Some(repo_path) = focused_window(app)
+                .and_then(|window| app.state::<WindowRegistry>().repo_for(window.label()))
+            else {
+                return;
+            };
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = open_window_for_repo(&app, &repo_path) {
+                    log_error!("Failed to open new window: {}", e);
+                }
+            });
+        }
+        "close-window" => {
+            if let Some(window) = focused_window(app) {
+                let _ = window.close();
+            }
+        }
+        "settings" => {
+            // Build off the event loop thread to avoid deadlocking webview creation
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = settings::open_window(&app) {
+                    log_error!("{}", e);
+                }
+            });
+        }
+        "refresh" => {
+            if let Some(window) = focused_window(app) {
+                let _ = window.emit_to(EventTarget::webview_window(window.label()), "refresh", ());
+            }
+        }
+        "reload" => {
+            if let Some(window) = focused_window(app) {
+                let _ = window.eval("window.location.reload()");
+            }
+        }
+        "restart-server" => {
+            if let Some(window) = focused_window(app) {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = restart_sidecar(app, window, None).await {
+                        log_error!("{}", e);
+                    }
+                });
+            }
+        }
+        "zoom-in" | "zoom-out" | "reset-zoom" => {
+            let Some(window) = focused_window(app) else {
+                return;
+            };
+            let result = match id {
+                "zoom-in" => display::zoom_in(window),
+                "zoom-out" => display::zoom_out(window),
+                _ => display::set_zoom(window, 1.0),
+            };
+            if let Err(e) = result {
+                log_error!("Failed to zoom: {}", e.message);
+            }
+        }
+        "toggle-logs" => {
+            if let Some(window) = focused_window(app) {
+                let _ = window.emit_to(
+                    EventTarget::webview_window(window.label()),
+                    "toggle-logs",
+                    (),
+                );
+            }
+        }
+        "about" => {
+            tauri::async_runtime::spawn(version::show_about(app.clone()));
+        }
+        "report-issue" => {
+            if let Err(e) = app.opener().open_url(ISSUES_URL, None::<&str>) {
+                log_error!("Failed to open issue tracker: {}", e);
+            }
+        }
+        _ => {
+            let Some(index) = id
+                .strip_prefix("recent-")
+                .and_then(|i| i.parse::<usize>().ok())
+            else {
+                return;
+            };
+            let Some(repo_path) = recent::list(app).get(index).cloned() else {
+                return;
+            };
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = open_repo_in_window(&app, &repo_path).await {
+                    log_error!("Failed to open {}: {}", repo_path, e);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! The installed-version marker and migrations of persisted state. The marker
+//! in the config directory records which version last ran and whether the
+//! user went through onboarding. When a newer version starts, the steps
+//! registered for the versions in between rewrite the settings and sessions
+//! files before anything loads them, after copying the old files aside.
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::{portable, sessions, settings};
+
+const MARKER_FILE: &str = "install.json";
+/// Directory in the config directory holding the files from before each
+/// upgrade, by the version upgraded from
+const BACKUP_DIR: &str = "backups";
+
+/// What the installed-version marker records
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Marker {
+    /// Version that last ran and whose formats the files are in
+    version: String,
+    /// Whether the first-run onboarding was completed or skipped
+    onboarded: bool,
+}
+
+/// A persisted file migrations can rewrite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StateFile {
+    Settings,
+    Sessions,
+}
+
+impl StateFile {
+    const ALL: [StateFile; 2] = [StateFile::Settings, StateFile::Sessions];
+
+    fn path(self, app: &AppHandle) -> Option<PathBuf> {
+        match self {
+            StateFile::Settings => settings::settings_path(app),
+            StateFile::Sessions => sessions::sessions_path(app),
+        }
+    }
+}
+
+/// A change to the format of a persisted file
+struct Migration {
+    /// First version writing the new format
+    version: Version,
+    file: StateFile,
+    description: &'static str,
+    apply: fn(&mut Value) -> Result<(), String>,
+}
+
+/// Migrations in the order they apply. Add a step whenever a release changes
+/// a file in a way serde defaults can't absorb, such as renaming a field or
+/// changing its type; fields that are only added need none.
+const MIGRATIONS: &[Migration] = &[];
+
+fn marker_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::config_dir(app)
+        .ok()
+        .map(|dir| dir.join(MARKER_FILE))
+}
+
+fn read_marker(app: &AppHandle) -> Option<Marker> {
+    let contents = std::fs::read_to_string(marker_path(app)?).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|e| log_error!("Invalid install marker: {}", e))
+        .ok()
+}
+
+fn write_marker(app: &AppHandle, marker: &Marker) {
+    let Some(path) = marker_path(app) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let result = serde_json::to_string_pretty(marker)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| std::fs::write(&path, contents).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        log_error!("Failed to write install marker: {}", e);
+    }
+}
+
+/// Steps for an upgrade from `from` to `to`, in registration order
+fn pending<'a>(migrations: &'a [Migration], from: &Version, to: &Version) -> Vec<&'a Migration> {
+    migrations
+        .iter()
+        .filter(|migration| &migration.version > from && &migration.version <= to)
+        .collect()
+}
+
+/// Run a file's steps on its parsed contents
+fn apply_steps(file: StateFile, value: &mut Value, steps: &[&Migration]) -> Result<(), String> {
+    for step in steps.iter().filter(|step| step.file == file) {
+        log_info!(
+            "Migrating {:?} to {}: {}",
+            file,
+            step.version,
+            step.description
+        );
+        (step.apply)(value).map_err(|e| format!("{}: {}", step.description, e))?;
+    }
+    Ok(())
+}
+
+/// Back up and rewrite the files the steps touch. Every file is migrated in
+/// memory before any is written, so a failing step leaves them all as they
+/// were.
+fn migrate(app: &AppHandle, from: &Version, steps: &[&Migration]) -> Result<(), String> {
+    let mut migrated = Vec::new();
+    for file in StateFile::ALL {
+        if !steps.iter().any(|step| step.file == file) {
+            continue;
+        }
+        // Nothing to migrate if the file was never written
+        let Some(path) = file.path(app).filter(|path| path.is_file()) else {
+            continue;
+        };
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let mut value: Value = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        apply_steps(file, &mut value, steps)?;
+        migrated.push((path, contents, value));
+    }
+    if migrated.is_empty() {
+        return Ok(());
+    }
+
+    let backup_dir = portable::config_dir(app)
+        .map_err(|e| format!("Config directory not available: {}", e))?
+        .join(BACKUP_DIR)
+        .join(from.to_string());
+    std::fs::create_dir_all(&backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    for (path, contents, _) in &migrated {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        std::fs::write(backup_dir.join(name), contents)
+            .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    }
+    for (path, _, value) in &migrated {
+        let contents = serde_json::to_string_pretty(value)
+            .map_err(|e| format!("Failed to serialize {}: {}", path.display(), e))?;
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    log_info!(
+        "Backed up the files from {} to {}",
+        from,
+        backup_dir.display()
+    );
+    Ok(())
+}
+
+/// Bring persisted state up to this version's formats. Called before the
+/// settings are loaded; on the very first run it only writes the marker.
+pub fn prepare(app: &AppHandle) {
+    let current = app.package_info().version.clone();
+    let marker = match read_marker(app) {
+        Some(marker) => marker,
+        // Settings from before the marker existed mean an existing install,
+        // which needs migrating but not onboarding
+        None if StateFile::Settings
+            .path(app)
+            .is_some_and(|path| path.is_file()) =>
+        {
+            Marker {
+                version: Version::new(0, 0, 0).to_string(),
+                onboarded: true,
+            }
+        }
+        None => {
+            log_info!("First run of {}", current);
+            write_marker(
+                app,
+                &Marker {
+                    version: current.to_string(),
+                    onboarded: false,
+                },
+            );
+            return;
+        }
+    };
+
+    let from = Version::parse(&marker.version).unwrap_or(Version::new(0, 0, 0));
+    if from > current {
+        log_error!(
+            "State was written by {}, newer than {}; leaving it as is",
+            from,
+            current
+        );
+        return;
+    }
+    if from < current {
+        let steps = pending(MIGRATIONS, &from, &current);
+        log_info!(
+            "Upgrading from {} to {} with {} migration(s)",
+            from,
+            current,
+            steps.len()
+        );
+        // The marker stays behind, so the upgrade is retried next launch
+        if let Err(e) = migrate(app, &from, &steps) {
+            log_error!(
+                "Migration from {} failed, keeping the old files: {}",
+                from,
+                e
+            );
+            return;
+        }
+        write_marker(
+            app,
+            &Marker {
+                version: current.to_string(),
+                ..marker
+            },
+        );
+    }
+}
+
+/// Whether this is a first run whose onboarding hasn't been completed
+pub fn needs_onboarding(app: &AppHandle) -> bool {
+    read_marker(app).is_some_and(|marker| !marker.onboarded)
+}
+
+/// Record that the user went through onboarding
+pub fn mark_onboarded(app: &AppHandle) {
+    write_marker(
+        app,
+        &Marker {
+            version: app.package_info().version.to_string(),
+            onboarded: true,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_theme(value: &mut Value) -> Result<(), String> {
+        let settings = value.as_object_mut().ok_or("Not an object")?;
+        if let Some(theme) = settings.remove("colorScheme") {
+            settings.insert("theme".to_string(), theme);
+        }
+        Ok(())
+    }
+
+    fn step(version: &str, file: StateFile) -> Migration {
+        Migration {
+            version: Version::parse(version).unwrap(),
+            file,
+            description: "rename colorScheme to theme",
+            apply: rename_theme,
+        }
+    }
+
+    #[test]
+    fn picks_the_steps_between_versions() {
+        let migrations = [
+            step("0.1.2", StateFile::Settings),
+            step("0.1.5", StateFile::Sessions),
+            step("0.2.0", StateFile::Settings),
+        ];
+        let versions = |from: &str, to: &str| {
+            pending(
+                &migrations,
+                &Version::parse(from).unwrap(),
+                &Version::parse(to).unwrap(),
+            )
+            .iter()
+            .map(|step| step.version.to_string())
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(versions("0.1.2", "0.1.7"), ["0.1.5"]);
+        assert_eq!(versions("0.0.0", "0.2.0"), ["0.1.2", "0.1.5", "0.2.0"]);
+        assert!(versions("0.1.7", "0.1.7").is_empty());
+    }
+
+    #[test]
+    fn applies_only_the_files_steps() {
+        let settings_step = step("0.1.2", StateFile::Settings);
+        let sessions_step = Migration {
+            apply: |_| Err("should not run".to_string()),
+            ..step("0.1.3", StateFile::Sessions)
+        };
+        let mut value = serde_json::json!({ "colorScheme": "dark", "telemetry": true });
+        apply_steps(
+            StateFile::Settings,
+            &mut value,
+            &[&settings_step, &sessions_step],
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "theme": "dark", "telemetry": true })
+        );
+    }
+
+    #[test]
+    fn reports_failing_steps() {
+        let mut value = serde_json::json!([]);
+        let error = apply_steps(
+            StateFile::Settings,
+            &mut value,
+            &[&step("0.1.2", StateFile::Settings)],
+        )
+        .unwrap_err();
+        assert!(error.contains("Not an object"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Companion app for iOS and Android. A phone or tablet cannot run the
+//! sidecar, so instead the UI connects to a server started elsewhere (see
+//! `--no-window` and LAN pairing) and the app remembers the servers it has
+//! reached, reconnecting to the last one on launch.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+
+const SERVERS_FILE: &str = "servers.json";
+
+/// A server the app has connected to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownServer {
+    pub url: String,
+    /// Secret or paired device token the server requires
+    pub token: Option<String>,
+    /// Seconds since the Unix epoch
+    pub last_used: u64,
+}
+
+fn servers_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(SERVERS_FILE))
+}
+
+/// Known servers, most recently used first
+fn load(app: &AppHandle) -> Vec<KnownServer> {
+    let mut servers: Vec<KnownServer> = servers_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default();
+    servers.sort_by(|a, b| b.last_used.cmp(&a.last_used));
+    servers
+}
+
+fn write(app: &AppHandle, servers: &[KnownServer]) -> Result<(), String> {
+    let path = servers_path(app).ok_or("Data directory not available")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(servers)
+        .map_err(|e| format!("Failed to serialize servers: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write servers: {}", e))
+}
+
+fn normalize(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_string()
+}
+
+/// Create the only window, pointed at the last server used, if any. The page
+/// shows the connect screen when that server is gone or there is none.
+fn create_main_window(app: &AppHandle) -> tauri::Result<WebviewWindow> {
+    let last = load(app).into_iter().next();
+    WebviewWindow::builder(app, "main", WebviewUrl::App("/".into()))
+        .initialization_script(&format!(
+            r#"
+            window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+            window.__OPENTUI__.mobile = true;
+            window.__OPENTUI__.serverUrl = {};
+            window.__OPENTUI__.secret = {};
+            "#,
+            serde_json::to_string(&last.as_ref().map(|server| &server.url))
+                .unwrap_or_else(|_| "null".to_string()),
+            serde_json::to_string(&last.as_ref().and_then(|server| server.token.as_ref()))
+                .unwrap_or_else(|_| "null".to_string()),
+        ))
+        .build()
+}
+
+/// Servers connected to before, most recently used first
+#[tauri::command]
+fn list_servers(app: AppHandle) -> Vec<KnownServer> {
+    load(&app)
+}
+
+/// Remember a server after connecting to it, replacing its token if it changed
+#[tauri::command]
+fn save_server(app: AppHandle, url: String, token: Option<String>) -> CommandResult<()> {
+    let url = normalize(&url);
+    if url.is_empty() {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            "Server URL cannot be empty",
+        ));
+    }
+    let mut servers = load(&app);
+    servers.retain(|server| server.url != url);
+    servers.insert(
+        0,
+        KnownServer {
+            url,
+            token: token.filter(|token| !token.is_empty()),
+            last_used: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+        },
+    );
+    Ok(write(&app, &servers)?)
+}
+
+/// Forget a server and its token
+#[tauri::command]
+fn forget_server(app: AppHandle, url: String) -> CommandResult<()> {
+    let url = normalize(&url);
+    let mut servers = load(&app);
+    let count = servers.len();
+    servers.retain(|server| server.url != url);
+    if servers.len() == count {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("No saved server {}", url),
+        ));
+    }
+    Ok(write(&app, &servers)?)
+}
+
+/// Run the companion app. Nothing here spawns processes or touches the
+/// desktop-only plugins.
+pub fn run() {
+    tauri::Builder::default()
+        .invoke_handler(tauri::generate_handler![
+            list_servers,
+            save_server,
+            forget_server
+        ])
+        .setup(|app| {
+            create_main_window(app.handle())?;
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
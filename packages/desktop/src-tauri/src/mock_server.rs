@@ -0,0 +1,289 @@
+//! Mock sidecar for end-to-end tests of the shell. With `OPENTUI_MOCK_SERVER=1`
+//! or `--mock-server <script.json>` this binary launches itself in place of the
+//! real server: it speaks the stdio protocol, answers HTTP with canned
+//! responses and can be scripted to start slowly, log, or crash, so spawning,
+//! readiness, restarts and log capture can be tested in CI without Node or a
+//! real repository. `OPENTUI_MOCK_SERVER` may also name a script.
+//!
+//! The mock only serves plain HTTP, so leave the `tls` setting off.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_plugin_shell::process::Command;
+use tauri_plugin_shell::ShellExt;
+
+use crate::ipc;
+
+/// Command-line flag replaying a script
+pub const SCRIPT_FLAG: &str = "--mock-server";
+/// `1` for the built-in responses, or the path of a script
+const MOCK_VAR: &str = "OPENTUI_MOCK_SERVER";
+/// Set on the child to run it as the mock; holds the script path, if any
+const HELPER_VAR: &str = "OPENTUI_MOCK_SIDECAR";
+
+/// What the mock does, all optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct MockScript {
+    /// Wait before reporting ready, e.g. to run into the readiness timeout
+    ready_delay_ms: u64,
+    /// Exit this long after reporting ready, e.g. to test respawning
+    exit_after_ms: Option<u64>,
+    exit_code: i32,
+    /// Lines printed at startup, to test log capture
+    stdout: Vec<String>,
+    stderr: Vec<String>,
+    /// HTTP responses by `METHOD /path` or just `/path`
+    responses: HashMap<String, MockResponse>,
+    /// Results of stdio requests by method, replacing the built-in ones
+    rpc: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MockResponse {
+    #[serde(default = "ok_status")]
+    status: u16,
+    #[serde(default)]
+    body: Value,
+}
+
+fn ok_status() -> u16 {
+    200
+}
+
+/// Value of `--mock-server <path>`
+fn script_arg() -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let index = args.iter().position(|arg| arg == SCRIPT_FLAG)?;
+    args.get(index + 1).cloned()
+}
+
+/// Script to replay, `Some("")` for the built-in responses, `None` when not mocking
+fn script_path() -> Option<String> {
+    if let Some(path) = script_arg() {
+        return Some(path);
+    }
+    match std::env::var(MOCK_VAR).ok()?.trim() {
+        "" | "0" => None,
+        "1" => Some(String::new()),
+        path => Some(path.to_string()),
+    }
+}
+
+/// Whether sidecars are replaced by the mock
+pub fn enabled() -> bool {
+    script_path().is_some()
+}
+
+/// Command launching this binary as the mock sidecar
+pub fn command(app: &AppHandle) -> Result<Command, String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the app binary for the mock server: {}", e))?;
+    // Relative script paths are resolved here, since the child runs elsewhere
+    let script = script_path().unwrap_or_default();
+    let script = if script.is_empty() {
+        script
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(&script))
+            .unwrap_or_else(|_| PathBuf::from(&script))
+            .to_string_lossy()
+            .to_string()
+    };
+    Ok(app.shell().command(exe).env(HELPER_VAR, script))
+}
+
+fn load_script(path: &str) -> Result<MockScript, String> {
+    if path.is_empty() {
+        return Ok(MockScript::default());
+    }
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid mock script {}: {}", path, e))
+}
+
+/// Print a JSON-RPC message on stdout, where the shell picks it up
+fn send_rpc(message: Value) {
+    println!("@rpc {}", message);
+}
+
+fn notify_ready(host: &str, standby: bool) {
+    send_rpc(json!({
+        "jsonrpc": "2.0",
+        "method": "ready",
+        "params": {
+            "pid": std::process::id(),
+            "standby": standby,
+            "protocol": ipc::PROTOCOL_VERSION,
+            "host": (!standby).then_some(host),
+        },
+    }));
+}
+
+/// Built-in answer to an HTTP request, if there is one for the path
+fn builtin_response(path: &str) -> Option<MockResponse> {
+    let body = match path {
+        "/health" => json!({ "status": "ok" }),
+        "/version" => json!({ "version": env!("CARGO_PKG_VERSION") }),
+        "/operations" => json!({ "operations": [] }),
+        _ => return None,
+    };
+    Some(MockResponse { status: 200, body })
+}
+
+/// Answer one HTTP request; the connection is closed afterwards
+fn serve_connection(mut stream: TcpStream, responses: &HashMap<String, MockResponse>) {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let method = words.next().unwrap_or_default();
+    let path = words.next().unwrap_or("/").split('?').next().unwrap_or("/");
+
+    let response = responses
+        .get(&format!("{} {}", method, path))
+        .or_else(|| responses.get(path))
+        .cloned()
+        .or_else(|| builtin_response(path))
+        .unwrap_or(MockResponse {
+            status: 404,
+            body: json!({ "error": "Not found" }),
+        });
+    let body = response.body.to_string();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        body.len(),
+        body
+    );
+}
+
+/// Answer a JSON-RPC request from the shell. Returns whether to exit afterwards.
+fn answer_rpc(request: &Value, script: &MockScript, started: Instant) -> bool {
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return false;
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let result = script.rpc.get(method).cloned().or_else(|| match method {
+        "ping" | "shutdown" => Some(json!({})),
+        "status" => Some(json!({
+            "pid": std::process::id(),
+            "uptimeMs": started.elapsed().as_millis() as u64,
+        })),
+        "version" => Some(json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "protocol": ipc::PROTOCOL_VERSION,
+        })),
+        "operations" => Some(json!({ "operations": [] })),
+        _ => None,
+    });
+    send_rpc(match result {
+        Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        None => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+        }),
+    });
+    method == "shutdown"
+}
+
+/// When the shell started this binary as the mock sidecar, serve until stdin
+/// closes and return the exit code; otherwise `None`.
+pub fn run_helper() -> Option<i32> {
+    let script_path = std::env::var(HELPER_VAR).ok()?;
+    let script = match load_script(&script_path) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("[mock] {}", e);
+            return Some(1);
+        }
+    };
+    let started = Instant::now();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let value = |flag: &str| {
+        let index = args.iter().position(|arg| arg == flag)?;
+        args.get(index + 1).cloned()
+    };
+    let Some(port) = value("--port").and_then(|port| port.parse::<u16>().ok()) else {
+        eprintln!("[mock] Missing or invalid --port");
+        return Some(1);
+    };
+    let host = value("--host").unwrap_or_else(|| "127.0.0.1".to_string());
+    let mut standby = args.iter().any(|arg| arg == "--standby");
+
+    let listener = match TcpListener::bind((host.as_str(), port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("[mock] Failed to listen on {}:{}: {}", host, port, e);
+            return Some(1);
+        }
+    };
+    for line in &script.stdout {
+        println!("{}", line);
+    }
+    for line in &script.stderr {
+        eprintln!("{}", line);
+    }
+
+    let responses = script.responses.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            serve_connection(stream, &responses);
+        }
+    });
+
+    std::thread::sleep(Duration::from_millis(script.ready_delay_ms));
+    println!("[mock] Serving on {}:{}", host, port);
+    notify_ready(&host, standby);
+
+    if let Some(exit_after_ms) = script.exit_after_ms {
+        let code = script.exit_code;
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(exit_after_ms));
+            println!("[mock] Exiting with code {} as scripted", code);
+            std::process::exit(code);
+        });
+    }
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            eprintln!("[mock] Ignoring invalid input: {}", line);
+            continue;
+        };
+        if let Some(kind) = message.get("type").and_then(Value::as_str) {
+            println!("[mock] Control message: {}", kind);
+            if kind == "bind-repo" && standby {
+                standby = false;
+                notify_ready(&host, standby);
+            }
+            continue;
+        }
+        if answer_rpc(&message, &script, started) {
+            println!("[mock] Shutting down on request");
+            return Some(0);
+        }
+    }
+    Some(0)
+}
@@ -0,0 +1,306 @@
+//! Read-only repository state straight from libgit2, shaped like the server's
+//! JSON so the UI can render before the sidecar is up or while it restarts
+
+use git2::{BranchType, Repository, Status, StatusOptions};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+
+/// Colors the server uses for each status, see `STATUS_COLORS` in core
+const COLOR_MODIFIED: &str = "#FFAA00";
+const COLOR_DELETED: &str = "#FF4444";
+const COLOR_UNTRACKED: &str = "#888888";
+const COLOR_ADDED: &str = "#44FF44";
+const COLOR_RENAMED: &str = "#00AAFF";
+const COLOR_UNMERGED: &str = "#FF00FF";
+const COLOR_DEFAULT: &str = "#FFFFFF";
+
+/// Same as the server's `GitFileStatus`
+#[derive(Debug, Clone, Serialize)]
+pub struct FileStatus {
+    path: String,
+    working_dir: &'static str,
+    index: &'static str,
+    staged: bool,
+    #[serde(rename = "statusText")]
+    status_text: &'static str,
+    color: &'static str,
+}
+
+/// Same as the server's `GitStatusSummary` from `/status`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusSummary {
+    current: String,
+    ahead: usize,
+    behind: usize,
+    files: Vec<FileStatus>,
+    is_clean: bool,
+}
+
+/// One entry of the server's `/branches` response
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchSummary {
+    current: bool,
+    name: String,
+    commit: String,
+    label: String,
+}
+
+/// Same as the server's `GitBranchInfo` from `/branches`
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchInfo {
+    current: String,
+    all: Vec<String>,
+    branches: BTreeMap<String, BranchSummary>,
+}
+
+/// The server's `/commits/current` response plus the branch HEAD is on
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadInfo {
+    hash: String,
+    current: String,
+    detached: bool,
+}
+
+fn git_error(e: git2::Error) -> AppError {
+    let code = match e.code() {
+        git2::ErrorCode::NotFound | git2::ErrorCode::UnbornBranch => ErrorCode::NotFound,
+        git2::ErrorCode::Locked => ErrorCode::Lock,
+        _ => ErrorCode::Internal,
+    };
+    AppError::new(code, e.message().to_string())
+}
+
+/// Run a libgit2 read off the main thread on the given repository, or the window's
+pub async fn with_repo<T, F>(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    repo_path: Option<String>,
+    read: F,
+) -> CommandResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Repository) -> Result<T, git2::Error> + Send + 'static,
+{
+    let repo_path = match repo_path {
+        Some(repo_path) => repo_path,
+        None => git::repo_for_window(app, window)?,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let repo = Repository::open(&repo_path).map_err(git_error)?;
+        read(&repo).map_err(git_error)
+    })
+    .await
+    .map_err(|e| AppError::new(ErrorCode::Internal, format!("Git task failed: {}", e)))?
+}
+
+/// Branch HEAD points at, even before its first commit; `None` when detached
+pub fn head_branch(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target()?;
+    Some(
+        target
+            .strip_prefix("refs/heads/")
+            .unwrap_or(target)
+            .to_string(),
+    )
+}
+
+/// Whether the working tree has changes, untracked files included
+pub fn is_dirty(repo: &Repository) -> Result<bool, git2::Error> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    Ok(!repo.statuses(Some(&mut options))?.is_empty())
+}
+
+/// Commits the current branch is ahead of and behind its upstream
+fn ahead_behind(repo: &Repository) -> (usize, usize) {
+    let counts = || -> Result<(usize, usize), git2::Error> {
+        let head = repo.head()?;
+        let branch = git2::Branch::wrap(head);
+        let upstream = branch.upstream()?;
+        let (Some(local), Some(remote)) = (branch.get().target(), upstream.get().target()) else {
+            return Ok((0, 0));
+        };
+        repo.graph_ahead_behind(local, remote)
+    };
+    counts().unwrap_or((0, 0))
+}
+
+/// Server status code for an entry, checked in the server's order of precedence
+fn status_code(status: Status) -> Option<&'static str> {
+    if status.is_conflicted() {
+        Some("U")
+    } else if status.intersects(Status::INDEX_MODIFIED | Status::WT_MODIFIED) {
+        Some("M")
+    } else if status.intersects(Status::INDEX_DELETED | Status::WT_DELETED) {
+        Some("D")
+    } else if status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED) {
+        Some("R")
+    } else if status.contains(Status::INDEX_NEW) {
+        Some("A")
+    } else if status.contains(Status::WT_NEW) {
+        Some("?")
+    } else {
+        None
+    }
+}
+
+fn file_status(path: String, staged: bool, code: &'static str) -> FileStatus {
+    let (status_text, color) = match code {
+        "M" => ("Modified", COLOR_MODIFIED),
+        "A" => ("Added", COLOR_ADDED),
+        "D" => ("Deleted", COLOR_DELETED),
+        "R" => ("Renamed", COLOR_RENAMED),
+        "?" => ("Untracked", COLOR_UNTRACKED),
+        "U" => ("Conflict", COLOR_UNMERGED),
+        _ => ("Unknown", COLOR_DEFAULT),
+    };
+    FileStatus {
+        path,
+        working_dir: code,
+        index: if staged { code } else { " " },
+        staged,
+        status_text,
+        color,
+    }
+}
+
+fn read_status(repo: &Repository) -> Result<StatusSummary, git2::Error> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true);
+
+    let staged_flags = Status::INDEX_NEW
+        | Status::INDEX_MODIFIED
+        | Status::INDEX_DELETED
+        | Status::INDEX_RENAMED
+        | Status::INDEX_TYPECHANGE;
+
+    let mut files = Vec::new();
+    for entry in repo.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+        let Some(code) = status_code(status) else {
+            continue;
+        };
+        // Renames are listed under their new path
+        let path = entry
+            .head_to_index()
+            .and_then(|delta| delta.new_file().path())
+            .map(|path| path.to_string_lossy().to_string())
+            .or_else(|| entry.path().map(str::to_string))
+            .unwrap_or_default();
+        let staged = match code {
+            "R" => true,
+            "U" | "?" => false,
+            _ => status.intersects(staged_flags),
+        };
+        files.push(file_status(path, staged, code));
+    }
+
+    // The server groups files by status in this order
+    let rank = |code: &str| {
+        ["M", "D", "R", "A", "?", "U"]
+            .iter()
+            .position(|c| *c == code)
+    };
+    files.sort_by_key(|file| rank(file.working_dir));
+
+    let (ahead, behind) = ahead_behind(repo);
+    Ok(StatusSummary {
+        current: head_branch(repo).unwrap_or_else(|| "HEAD".to_string()),
+        ahead,
+        behind,
+        is_clean: files.is_empty(),
+        files,
+    })
+}
+
+fn read_branches(repo: &Repository) -> Result<BranchInfo, git2::Error> {
+    let current = head_branch(repo).unwrap_or_default();
+    let mut all = Vec::new();
+    let mut branches = BTreeMap::new();
+
+    for branch in repo.branches(None)? {
+        let (branch, branch_type) = branch?;
+        // Skip symbolic refs such as origin/HEAD
+        if branch.get().symbolic_target().is_some() {
+            continue;
+        }
+        let Some(short) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let name = match branch_type {
+            BranchType::Local => short,
+            BranchType::Remote => format!("remotes/{}", short),
+        };
+        let commit = branch.get().peel_to_commit()?;
+        let hash = commit.id().to_string();
+
+        all.push(name.clone());
+        branches.insert(
+            name.clone(),
+            BranchSummary {
+                current: branch_type == BranchType::Local && name == current,
+                name,
+                commit: hash[..7].to_string(),
+                label: commit.summary().unwrap_or_default().to_string(),
+            },
+        );
+    }
+    all.sort();
+
+    Ok(BranchInfo {
+        current,
+        all,
+        branches,
+    })
+}
+
+fn read_head(repo: &Repository) -> Result<HeadInfo, git2::Error> {
+    let commit = repo.head()?.peel_to_commit()?;
+    let detached = repo.head_detached()?;
+    Ok(HeadInfo {
+        hash: commit.id().to_string()[..7].to_string(),
+        current: head_branch(repo).unwrap_or_else(|| "HEAD".to_string()),
+        detached,
+    })
+}
+
+/// Working tree status, without waiting for the server.
+/// Reads the given repository, or the window's if omitted.
+#[tauri::command]
+pub async fn native_status(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo_path: Option<String>,
+) -> CommandResult<StatusSummary> {
+    with_repo(&app, &window, repo_path, read_status).await
+}
+
+/// Local and remote branches, without waiting for the server
+#[tauri::command]
+pub async fn native_branches(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo_path: Option<String>,
+) -> CommandResult<BranchInfo> {
+    with_repo(&app, &window, repo_path, read_branches).await
+}
+
+/// The commit and branch HEAD points at, without waiting for the server
+#[tauri::command]
+pub async fn native_head(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo_path: Option<String>,
+) -> CommandResult<HeadInfo> {
+    with_repo(&app, &window, repo_path, read_head).await
+}
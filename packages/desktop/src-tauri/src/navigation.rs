@@ -0,0 +1,194 @@
+//! Where the webviews may go. Pages stay on the app's own origin, the dev
+//! server and the local sidecars; links anywhere else, e.g. to an issue
+//! tracker or a remote mentioned in a commit message, open in the system
+//! browser instead, without asking for hosts on the `externalLinkHosts`
+//! allowlist and after a confirmation for the rest.
+
+use std::net::IpAddr;
+use tauri::{AppHandle, Manager, Url};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::sidecar::ServerState;
+use crate::{dev_frontend, i18n, settings};
+
+/// Schemes handed to the system; anything else, e.g. `file:` or
+/// `javascript:`, is never opened
+const EXTERNAL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Whether a URL is one of the app's own pages: bundled assets are served
+/// from `tauri://localhost`, or `http://tauri.localhost` on Windows
+fn is_app_origin(url: &Url) -> bool {
+    url.scheme() == "tauri"
+        || url.host_str() == Some("tauri.localhost")
+        || url.as_str() == "about:blank"
+}
+
+fn is_loopback_host(url: &Url) -> bool {
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    // IPv6 hosts come in brackets
+    host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+/// Whether a URL points at one of this app's sidecars
+fn is_local_server(app: &AppHandle, url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+        && is_loopback_host(url)
+        && url
+            .port()
+            .is_some_and(|port| app.state::<ServerState>().for_port(port.into()).is_some())
+}
+
+/// Whether a host matches an allowlist entry: the host itself, or any
+/// subdomain for an entry like `*.example.com`
+fn host_allowed(allowed: &[String], host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    allowed.iter().any(|entry| {
+        let entry = entry.trim().to_lowercase();
+        match entry.strip_prefix("*.") {
+            Some(domain) => host.ends_with(&format!(".{}", domain)),
+            None => host == entry,
+        }
+    })
+}
+
+/// Ask whether to open a link to a host that isn't on the allowlist
+async fn confirm(app: &AppHandle, url: &Url) -> bool {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(i18n::tr(
+            app,
+            "external-link.message",
+            &[
+                ("host", url.host_str().unwrap_or_default()),
+                ("url", url.as_str()),
+            ],
+        ))
+        .title(i18n::t(app, "external-link.title"))
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            i18n::t(app, "external-link.open"),
+            i18n::t(app, "external-link.cancel"),
+        ))
+        .show(move |open| {
+            let _ = tx.send(open);
+        });
+    rx.await.unwrap_or(false)
+}
+
+/// Open a URL in the system's handler for it, asking first unless its host
+/// is allowlisted
+async fn open(app: &AppHandle, url: Url) -> CommandResult<()> {
+    if !EXTERNAL_SCHEMES.contains(&url.scheme()) {
+        return Err(AppError::new(
+            ErrorCode::Internal,
+            format!("Refusing to open {} links", url.scheme()),
+        ));
+    }
+    // Mail links have no host and only start composing a message
+    let allowed = url.scheme() == "mailto"
+        || url
+            .host_str()
+            .is_some_and(|host| host_allowed(&settings::current(app).external_link_hosts, host));
+    if !allowed && !confirm(app, &url).await {
+        return Err(AppError::new(ErrorCode::Cancelled, "Not opening the link"));
+    }
+
+    log_info!("Opening {} externally", url);
+    app.opener()
+        .open_url(url.as_str(), None::<&str>)
+        .map_err(|e| {
+            AppError::new(
+                ErrorCode::Internal,
+                format!("Failed to open {}: {}", url, e),
+            )
+        })
+}
+
+/// Navigation handler for the app's webviews: allow their own pages and
+/// the local servers, and hand everything else to the system browser
+pub fn handler(app: &AppHandle) -> impl Fn(&Url) -> bool + Send + 'static {
+    let app = app.clone();
+    move |url| {
+        if is_app_origin(url) || dev_frontend::serves(url) || is_local_server(&app, url) {
+            return true;
+        }
+        if EXTERNAL_SCHEMES.contains(&url.scheme()) {
+            let (app, url) = (app.clone(), url.clone());
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = open(&app, url).await {
+                    log_info!("{}", e);
+                }
+            });
+        } else {
+            log_error!("Blocked navigation to {}", url);
+        }
+        false
+    }
+}
+
+/// Open a link in the system browser, or mail client for `mailto:`. Hosts
+/// not in the `externalLinkHosts` setting need the user's confirmation.
+#[tauri::command]
+pub async fn open_external(app: AppHandle, url: String) -> CommandResult<()> {
+    let url = Url::parse(url.trim()).map_err(|e| {
+        AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid URL {}: {}", url, e),
+        )
+    })?;
+    open(&app, url).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(hosts: &[&str]) -> Vec<String> {
+        hosts.iter().map(|host| host.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_hosts_and_wildcard_subdomains() {
+        let allowed = hosts(&["github.com", "*.atlassian.net"]);
+        assert!(host_allowed(&allowed, "github.com"));
+        assert!(host_allowed(&allowed, "GitHub.com."));
+        assert!(!host_allowed(&allowed, "gist.github.com"));
+        assert!(host_allowed(&allowed, "acme.atlassian.net"));
+        assert!(!host_allowed(&allowed, "atlassian.net"));
+        assert!(!host_allowed(&allowed, "evilatlassian.net"));
+    }
+
+    #[test]
+    fn recognizes_the_app_origin() {
+        assert!(is_app_origin(
+            &Url::parse("tauri://localhost/index.html").unwrap()
+        ));
+        assert!(is_app_origin(
+            &Url::parse("http://tauri.localhost/").unwrap()
+        ));
+        assert!(!is_app_origin(&Url::parse("https://github.com/").unwrap()));
+    }
+
+    #[test]
+    fn recognizes_loopback_hosts() {
+        assert!(is_loopback_host(
+            &Url::parse("http://127.0.0.1:4096/").unwrap()
+        ));
+        assert!(is_loopback_host(&Url::parse("http://[::1]:4096/").unwrap()));
+        assert!(is_loopback_host(
+            &Url::parse("http://localhost:4096/").unwrap()
+        ));
+        assert!(!is_loopback_host(
+            &Url::parse("http://192.168.1.2:4096/").unwrap()
+        ));
+    }
+}
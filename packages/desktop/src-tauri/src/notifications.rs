@@ -0,0 +1,198 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::broadcast::{self, WindowRegistry};
+use crate::control::SidecarEvent;
+use crate::error::CommandResult;
+use crate::i18n;
+use crate::repo_state::Operation;
+use crate::window::open_repo_in_window;
+
+/// How long after a notification activating the app counts as clicking it
+const CLICK_WINDOW_SECS: u64 = 60;
+
+/// Repository of the last notification shown while the app was unfocused.
+///
+/// Desktop notifications have no click callback, so the app being activated
+/// shortly after one is treated as a click and focuses that repository.
+#[derive(Clone, Default)]
+pub struct NotificationState(Arc<Mutex<Option<(String, Instant)>>>);
+
+/// Whether any app window has focus
+fn app_focused(app: &AppHandle) -> bool {
+    app.webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Show an OS notification, remembering its repository for click-to-focus
+fn show(app: &AppHandle, title: &str, body: &str, repo_path: Option<&str>) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))?;
+
+    if let Some(repo_path) = repo_path {
+        if let Ok(mut pending) = app.state::<NotificationState>().0.lock() {
+            *pending = Some((repo_path.to_string(), Instant::now()));
+        }
+    }
+    Ok(())
+}
+
+/// Repository name shown in notification titles
+fn repo_name(repo_path: &str) -> &str {
+    std::path::Path::new(repo_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(repo_path)
+}
+
+/// Forward a sidecar event to the repo's windows and notify if the app is in the background
+pub fn handle_sidecar_event(app: &AppHandle, repo_path: &str, event: SidecarEvent) {
+    broadcast::emit_to_repo(app, repo_path, "sidecar-event", event.clone());
+
+    if app_focused(app) {
+        return;
+    }
+
+    let name = repo_name(repo_path);
+    let repo = [("repo", name)];
+    let (title, body) = match event {
+        SidecarEvent::FetchComplete { scheduled: false } => (
+            i18n::tr(app, "notification.fetch-complete", &repo),
+            i18n::t(app, "notification.fetch-complete.body"),
+        ),
+        SidecarEvent::PushFailed { message } => {
+            (i18n::tr(app, "notification.push-failed", &repo), message)
+        }
+        SidecarEvent::MergeConflict { branch, files } => (
+            i18n::tr(app, "notification.merge-conflict", &repo),
+            i18n::tr(
+                app,
+                if files.len() == 1 {
+                    "notification.merge-conflict.one"
+                } else {
+                    "notification.merge-conflict.many"
+                },
+                &[
+                    ("branch", branch.as_str()),
+                    ("count", files.len().to_string().as_str()),
+                ],
+            ),
+        ),
+        // Scheduled fetches only notify about new commits, via `remote_updated`
+        SidecarEvent::FetchComplete { scheduled: true }
+        | SidecarEvent::Progress { .. }
+        | SidecarEvent::OperationFinished { .. }
+        | SidecarEvent::PairRequest { .. } => return,
+    };
+
+    if let Err(e) = show(app, &title, &body, Some(repo_path)) {
+        log_error!("{}", e);
+    }
+}
+
+/// Notify about new upstream commits fetched in the background, unless the app
+/// is in use
+pub fn remote_updated(app: &AppHandle, repo_path: &str, upstream: &str, count: usize) {
+    if app_focused(app) {
+        return;
+    }
+    let title = i18n::tr(
+        app,
+        "notification.remote-updated",
+        &[("repo", repo_name(repo_path))],
+    );
+    let body = i18n::tr(
+        app,
+        if count == 1 {
+            "notification.remote-updated.one"
+        } else {
+            "notification.remote-updated.many"
+        },
+        &[
+            ("upstream", upstream),
+            ("count", count.to_string().as_str()),
+        ],
+    );
+    if let Err(e) = show(app, &title, &body, Some(repo_path)) {
+        log_error!("{}", e);
+    }
+}
+
+/// Notify that a repository is now in the middle of an operation started
+/// outside the app, unless the app is in use
+pub fn operation_started(app: &AppHandle, repo_path: &str, operation: &Operation) {
+    if app_focused(app) {
+        return;
+    }
+    let body = match operation {
+        Operation::Merge { .. } => "notification.repo-state.merge",
+        Operation::Rebase { .. } => "notification.repo-state.rebase",
+        Operation::ApplyMailbox { .. } => "notification.repo-state.apply-mailbox",
+        Operation::CherryPick { .. } => "notification.repo-state.cherry-pick",
+        Operation::Revert { .. } => "notification.repo-state.revert",
+        // Nothing to resolve
+        Operation::Bisect { .. } => return,
+    };
+    let title = i18n::tr(
+        app,
+        "notification.repo-state",
+        &[("repo", repo_name(repo_path))],
+    );
+    if let Err(e) = show(app, &title, &i18n::t(app, body), Some(repo_path)) {
+        log_error!("{}", e);
+    }
+}
+
+/// Focus the repository of a recently shown notification, if any.
+/// Returns true if a window is being focused.
+pub fn focus_pending(app: &AppHandle) -> bool {
+    let pending = app
+        .state::<NotificationState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.take());
+    let Some((repo_path, shown_at)) = pending else {
+        return false;
+    };
+    if shown_at.elapsed() > Duration::from_secs(CLICK_WINDOW_SECS) {
+        return false;
+    }
+
+    // Already looking at it
+    let focused_repo = app
+        .webview_windows()
+        .values()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .and_then(|window| app.state::<WindowRegistry>().repo_for(window.label()));
+    if focused_repo.as_deref() == Some(repo_path.as_str()) {
+        return true;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = open_repo_in_window(&app, &repo_path).await {
+            log_error!("Failed to focus {}: {}", repo_path, e);
+        }
+    });
+    true
+}
+
+/// Show an OS notification from the frontend
+#[tauri::command]
+pub fn notify(
+    app: AppHandle,
+    title: String,
+    body: String,
+    repo_path: Option<String>,
+) -> CommandResult<()> {
+    show(&app, &title, &body, repo_path.as_deref())?;
+    Ok(())
+}
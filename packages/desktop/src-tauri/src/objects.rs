@@ -0,0 +1,284 @@
+use serde::Serialize;
+use std::process::Stdio;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::{AppHandle, WebviewWindow};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+/// Bytes scanned for NUL when deciding whether a blob is binary, as git does
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// A header line of a commit or tag, in order of appearance
+#[derive(Debug, Clone, Serialize)]
+pub struct Header {
+    key: String,
+    value: String,
+}
+
+/// An entry of a tree object
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeEntry {
+    mode: String,
+    kind: String,
+    oid: String,
+    /// Size of blob entries; none for trees and submodules
+    size: Option<u64>,
+    name: String,
+}
+
+/// Typed details of a git object
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ObjectDetails {
+    Commit {
+        oid: String,
+        size: u64,
+        tree: String,
+        parents: Vec<String>,
+        headers: Vec<Header>,
+        message: String,
+    },
+    Tree {
+        oid: String,
+        size: u64,
+        entries: Vec<TreeEntry>,
+    },
+    Blob {
+        oid: String,
+        size: u64,
+        binary: bool,
+    },
+    Tag {
+        oid: String,
+        size: u64,
+        object: String,
+        target_type: String,
+        name: String,
+        headers: Vec<Header>,
+        message: String,
+    },
+}
+
+/// Resolve a revision to its object id, type and size
+async fn batch_check(repo_path: &str, rev: &str) -> CommandResult<(String, String, u64)> {
+    git::validate_rev(rev)?;
+
+    // Passing the name on stdin keeps it from ever being read as an option
+    let mut child = git::command(repo_path, &["cat-file", "--batch-check"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(format!("{}\n", rev).as_bytes())
+            .await
+            .map_err(|e| {
+                AppError::new(ErrorCode::Internal, format!("Failed to query git: {}", e))
+            })?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to query git: {}", e)))?;
+
+    // "<oid> <type> <size>", or "<rev> missing"
+    let line = String::from_utf8_lossy(&output.stdout);
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    match fields.as_slice() {
+        [oid, kind, size] => Ok((
+            oid.to_string(),
+            kind.to_string(),
+            size.parse().unwrap_or_default(),
+        )),
+        _ => Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("Object not found: {}", rev),
+        )),
+    }
+}
+
+/// Split a commit or tag into its headers and message.
+/// Continuation lines (e.g. signatures) are folded into the previous header.
+fn parse_headers(raw: &str) -> (Vec<Header>, String) {
+    let (head, message) = raw.split_once("\n\n").unwrap_or((raw, ""));
+    let mut headers: Vec<Header> = Vec::new();
+    for line in head.lines() {
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if let Some(last) = headers.last_mut() {
+                last.value.push('\n');
+                last.value.push_str(continuation);
+            }
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        headers.push(Header {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+    (headers, message.to_string())
+}
+
+fn header<'a>(headers: &'a [Header], key: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|h| h.key == key)
+        .map(|h| h.value.as_str())
+}
+
+/// Parse `git ls-tree -z --long` output
+fn parse_tree(raw: &[u8]) -> Vec<TreeEntry> {
+    raw.split(|b| *b == 0)
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            // "<mode> <type> <oid> <size>\t<name>"
+            let record = String::from_utf8_lossy(record);
+            let (meta, name) = record.split_once('\t')?;
+            let mut fields = meta.split_whitespace();
+            Some(TreeEntry {
+                mode: fields.next()?.to_string(),
+                kind: fields.next()?.to_string(),
+                oid: fields.next()?.to_string(),
+                size: fields.next().and_then(|size| size.parse().ok()),
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Whether the start of a blob contains a NUL byte
+async fn is_binary(repo_path: &str, oid: &str) -> CommandResult<bool> {
+    let mut child = git::command(repo_path, &["cat-file", "blob", oid])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+    let Some(stdout) = child.stdout.take() else {
+        return Ok(false);
+    };
+
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    stdout
+        .take(BINARY_SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .await
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to read blob: {}", e)))?;
+    Ok(head.contains(&0))
+}
+
+/// Inspect a git object by id or revision in the window's repository
+#[tauri::command]
+pub async fn inspect_object(
+    app: AppHandle,
+    window: WebviewWindow,
+    oid: String,
+) -> CommandResult<ObjectDetails> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let (oid, kind, size) = batch_check(&repo_path, &oid).await?;
+
+    match kind.as_str() {
+        "commit" => {
+            let raw = git::run(&repo_path, &["cat-file", "commit", &oid]).await?;
+            let (headers, message) = parse_headers(&String::from_utf8_lossy(&raw));
+            Ok(ObjectDetails::Commit {
+                tree: header(&headers, "tree").unwrap_or_default().to_string(),
+                parents: headers
+                    .iter()
+                    .filter(|h| h.key == "parent")
+                    .map(|h| h.value.clone())
+                    .collect(),
+                oid,
+                size,
+                headers,
+                message,
+            })
+        }
+        "tag" => {
+            let raw = git::run(&repo_path, &["cat-file", "tag", &oid]).await?;
+            let (headers, message) = parse_headers(&String::from_utf8_lossy(&raw));
+            Ok(ObjectDetails::Tag {
+                object: header(&headers, "object").unwrap_or_default().to_string(),
+                target_type: header(&headers, "type").unwrap_or_default().to_string(),
+                name: header(&headers, "tag").unwrap_or_default().to_string(),
+                oid,
+                size,
+                headers,
+                message,
+            })
+        }
+        "tree" => {
+            let raw = git::run(&repo_path, &["ls-tree", "-z", "--long", &oid]).await?;
+            Ok(ObjectDetails::Tree {
+                entries: parse_tree(&raw),
+                oid,
+                size,
+            })
+        }
+        "blob" => Ok(ObjectDetails::Blob {
+            binary: is_binary(&repo_path, &oid).await?,
+            oid,
+            size,
+        }),
+        other => Err(AppError::new(
+            ErrorCode::Internal,
+            format!("Unknown object type: {}", other),
+        )),
+    }
+}
+
+/// Stream a blob's raw content to the frontend in chunks.
+/// Returns the number of bytes sent.
+#[tauri::command]
+pub async fn stream_blob(
+    app: AppHandle,
+    window: WebviewWindow,
+    oid: String,
+    on_chunk: Channel,
+) -> CommandResult<u64> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let (oid, kind, _) = batch_check(&repo_path, &oid).await?;
+    if kind != "blob" {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("{} is a {}, not a blob", oid, kind),
+        ));
+    }
+
+    let mut child = git::command(&repo_path, &["cat-file", "blob", &oid])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AppError::new(ErrorCode::Internal, "Failed to read blob"))?;
+
+    let mut sent = 0u64;
+    let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+    loop {
+        let read = stdout.read(&mut buffer).await.map_err(|e| {
+            AppError::new(ErrorCode::Internal, format!("Failed to read blob: {}", e))
+        })?;
+        if read == 0 {
+            break;
+        }
+        on_chunk
+            .send(InvokeResponseBody::Raw(buffer[..read].to_vec()))
+            .map_err(|e| {
+                AppError::new(ErrorCode::Internal, format!("Failed to send chunk: {}", e))
+            })?;
+        sent += read as u64;
+    }
+    Ok(sent)
+}
@@ -0,0 +1,206 @@
+//! Window shown when the app starts without a usable repository, e.g. when
+//! launched from the Dock and falling back to a working directory that is not
+//! a repository. No sidecar runs for it; the UI offers to open, init or clone
+//! a repository instead. On the first run it also walks the user through
+//! choosing a theme and whether to keep the telemetry journal.
+
+use git2::{Reference, Repository, RepositoryInitOptions};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::dev_frontend;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+use crate::migrations;
+use crate::navigation;
+use crate::settings;
+use crate::theme::{self, ThemePreference};
+use crate::window::open_repo_in_window;
+use crate::worktrees;
+
+/// Label of the onboarding window, which stands in for the main window
+const LABEL: &str = "main";
+
+/// What a path is, for deciding how to open it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoValidation {
+    path: String,
+    exists: bool,
+    is_repo: bool,
+    is_bare: bool,
+    /// Top of the working tree the path belongs to
+    workdir: Option<String>,
+    /// Main repository when the path is in a linked worktree
+    main_repo: Option<String>,
+}
+
+/// Check what a path is without opening it
+pub fn validate(path: &str) -> RepoValidation {
+    let location = worktrees::locate(path);
+    RepoValidation {
+        path: path.to_string(),
+        exists: Path::new(path).is_dir(),
+        is_repo: location.is_some(),
+        is_bare: location.as_ref().is_some_and(|location| location.bare),
+        workdir: location
+            .as_ref()
+            .filter(|location| !location.bare)
+            .map(|location| location.workdir.clone()),
+        main_repo: location.and_then(|location| location.main_repo),
+    }
+}
+
+/// Show the onboarding window, suggesting `path` as where to create a repository
+pub fn show(app: &AppHandle, path: &str) -> Result<WebviewWindow, String> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(window);
+    }
+
+    WebviewWindow::builder(app, LABEL, dev_frontend::webview_url())
+        .on_navigation(navigation::handler(app))
+        .title("opentui-git")
+        .inner_size(900.0, 600.0)
+        .min_inner_size(600.0, 400.0)
+        .resizable(true)
+        .theme(theme::native(app))
+        .initialization_script(&format!(
+            r#"
+            window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+            window.__OPENTUI__.port = null;
+            window.__OPENTUI__.onboarding = true;
+            window.__OPENTUI__.firstRun = {};
+            window.__OPENTUI__.suggestedPath = {};
+            window.__OPENTUI__.theme = "{}";
+            "#,
+            migrations::needs_onboarding(app),
+            serde_json::to_string(path).unwrap_or_else(|_| "\"\"".to_string()),
+            theme::name(app)
+        ))
+        .build()
+        .map_err(|e| format!("Failed to create window: {}", e))
+}
+
+/// Close the onboarding window once a repository window has replaced it
+pub fn finish(app: &AppHandle) {
+    let Some(window) = app.get_webview_window(LABEL) else {
+        return;
+    };
+    // A main window attached to a repository is not the onboarding window
+    if git::repo_for_window(app, &window).is_err() {
+        let _ = window.close();
+    }
+}
+
+/// Choices made on the first run's onboarding
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OnboardingChoices {
+    /// Theme to use; the current one is kept if unset
+    pub theme: Option<ThemePreference>,
+    /// Whether to record the local telemetry journal
+    pub telemetry: bool,
+}
+
+/// Save the first run's choices and stop showing onboarding on launch. The
+/// repository is opened separately, through `init_repo` or the open and
+/// clone commands.
+#[tauri::command]
+pub fn complete_onboarding(app: AppHandle, choices: OnboardingChoices) -> CommandResult<()> {
+    settings::update(&app, |settings| {
+        if let Some(theme) = choices.theme {
+            settings.theme = theme;
+        }
+        settings.telemetry = choices.telemetry;
+    })?;
+    theme::refresh(&app);
+    migrations::mark_onboarded(&app);
+    log_info!("Onboarding complete");
+    Ok(())
+}
+
+/// Check whether a path can be opened as a repository
+#[tauri::command]
+pub fn validate_repo_path(path: String) -> RepoValidation {
+    validate(&path)
+}
+
+/// Options for `init_repo`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InitOptions {
+    /// Template directory to copy hooks and info from instead of git's default
+    pub template: Option<String>,
+    /// Contents of a `.gitignore` to create in the new repository
+    pub gitignore: Option<String>,
+}
+
+fn init(
+    path: &str,
+    default_branch: Option<&str>,
+    options: &InitOptions,
+) -> Result<(), git2::Error> {
+    let mut init_options = RepositoryInitOptions::new();
+    // Without an explicit branch libgit2 follows init.defaultBranch
+    if let Some(branch) = default_branch {
+        init_options.initial_head(branch);
+    }
+    if let Some(template) = &options.template {
+        init_options.template_path(Path::new(template));
+    }
+    Repository::init_opts(path, &init_options)?;
+
+    if let Some(gitignore) = &options.gitignore {
+        std::fs::write(Path::new(path).join(".gitignore"), gitignore)
+            .map_err(|e| git2::Error::from_str(&format!("Failed to write .gitignore: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Create a repository at `path`, creating missing directories, and open it
+/// in a new window
+#[tauri::command]
+pub async fn init_repo(
+    app: AppHandle,
+    path: String,
+    default_branch: Option<String>,
+    options: Option<InitOptions>,
+) -> CommandResult<()> {
+    let options = options.unwrap_or_default();
+    if let Some(branch) = &default_branch {
+        if !Reference::is_valid_name(&format!("refs/heads/{}", branch)) {
+            return Err(AppError::new(
+                ErrorCode::InvalidInput,
+                format!("Invalid branch name: {:?}", branch),
+            ));
+        }
+    }
+    if Path::new(&path).join(".git").exists() {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            format!("Already a repository: {}", path),
+        )
+        .with_metadata(serde_json::json!({ "path": path })));
+    }
+
+    let init_path = path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        init(&init_path, default_branch.as_deref(), &options)
+    })
+    .await
+    .map_err(|e| AppError::new(ErrorCode::Internal, format!("Init task failed: {}", e)))?
+    .map_err(|e| {
+        AppError::new(
+            ErrorCode::Internal,
+            format!("Failed to create repository: {}", e.message()),
+        )
+        .with_metadata(serde_json::json!({ "path": path }))
+    })?;
+
+    log_info!("Created repository at {}", path);
+    open_repo_in_window(&app, &path).await?;
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::Semaphore;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+use crate::recent;
+use crate::timeout::{self, CommandClass};
+
+/// How many repositories are read at once
+const MAX_CONCURRENT: usize = 8;
+
+/// Branch and working tree summary of one repository
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoOverview {
+    path: String,
+    name: String,
+    /// Current branch, `None` when HEAD is detached
+    branch: Option<String>,
+    upstream: Option<String>,
+    ahead: u32,
+    behind: u32,
+    staged: usize,
+    unstaged: usize,
+    untracked: usize,
+    conflicted: usize,
+    dirty: bool,
+    /// Why the repository could not be read, e.g. it was moved or deleted
+    error: Option<String>,
+}
+
+/// Fill an overview from `git status --porcelain=v2 --branch -z` output
+fn parse_status(output: &[u8], overview: &mut RepoOverview) {
+    let output = String::from_utf8_lossy(output);
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+
+    while let Some(entry) = entries.next() {
+        if let Some(header) = entry.strip_prefix("# ") {
+            let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+            match key {
+                "branch.head" if value != "(detached)" => {
+                    overview.branch = Some(value.to_string());
+                }
+                "branch.upstream" => overview.upstream = Some(value.to_string()),
+                "branch.ab" => {
+                    // Format: "+<ahead> -<behind>"
+                    for count in value.split(' ') {
+                        if let Some(ahead) = count.strip_prefix('+') {
+                            overview.ahead = ahead.parse().unwrap_or(0);
+                        } else if let Some(behind) = count.strip_prefix('-') {
+                            overview.behind = behind.parse().unwrap_or(0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let mut fields = entry.splitn(3, ' ');
+        let kind = fields.next().unwrap_or("");
+        let xy = fields.next().unwrap_or("");
+        match kind {
+            "1" | "2" => {
+                let mut xy = xy.chars();
+                if xy.next().is_some_and(|x| x != '.') {
+                    overview.staged += 1;
+                }
+                if xy.next().is_some_and(|y| y != '.') {
+                    overview.unstaged += 1;
+                }
+                // Renames and copies are followed by the original path
+                if kind == "2" {
+                    entries.next();
+                }
+            }
+            "u" => overview.conflicted += 1,
+            "?" => overview.untracked += 1,
+            _ => {}
+        }
+    }
+
+    overview.dirty =
+        overview.staged + overview.unstaged + overview.untracked + overview.conflicted > 0;
+}
+
+/// Read the overview of one repository, recording failures in it
+async fn read_overview(repo_path: String) -> RepoOverview {
+    let mut overview = RepoOverview {
+        name: Path::new(&repo_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| repo_path.clone()),
+        path: repo_path,
+        ..Default::default()
+    };
+
+    if !Path::new(&overview.path).is_dir() {
+        overview.error = Some("Repository not found".to_string());
+        return overview;
+    }
+
+    // Skip refreshing the index so a dashboard never contends for index.lock
+    let args = [
+        "--no-optional-locks",
+        "status",
+        "--porcelain=v2",
+        "--branch",
+        "-z",
+    ];
+    match git::run(&overview.path, &args).await {
+        Ok(output) => parse_status(&output, &mut overview),
+        Err(e) => overview.error = Some(e.message),
+    }
+    overview
+}
+
+/// Summarize the branch, dirty state and ahead/behind counts of several
+/// repositories at once. Defaults to the recent repositories.
+#[tauri::command]
+pub async fn get_multi_repo_overview(
+    app: AppHandle,
+    paths: Option<Vec<String>>,
+    request_id: Option<String>,
+) -> CommandResult<Vec<RepoOverview>> {
+    let paths = paths.unwrap_or_else(|| recent::list(&app));
+
+    timeout::run(&app, CommandClass::Local, request_id, |_| async move {
+        let limit = Arc::new(Semaphore::new(MAX_CONCURRENT));
+        let tasks: Vec<_> = paths
+            .into_iter()
+            .map(|repo_path| {
+                let limit = limit.clone();
+                tauri::async_runtime::spawn(async move {
+                    let _permit = limit.acquire_owned().await;
+                    read_overview(repo_path).await
+                })
+            })
+            .collect();
+
+        let mut overviews = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            overviews.push(task.await.map_err(|e| {
+                AppError::new(ErrorCode::Internal, format!("Status task failed: {}", e))
+            })?);
+        }
+        Ok(overviews)
+    })
+    .await
+}
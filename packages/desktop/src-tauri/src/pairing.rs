@@ -0,0 +1,260 @@
+//! LAN pairing for companion devices, e.g. a tablet browser used as a second
+//! screen. Strictly opt-in: with `lanPairing` on, sidecars listen on all
+//! interfaces, and a device gets a token by redeeming a short-lived code shown
+//! in the app. Tokens are kept in the settings until revoked.
+
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::control::{self, ControlMessage, Secret};
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::sidecar::Sidecar;
+use crate::window::sidecar_for_window;
+use crate::{askpass, settings, tls};
+
+/// Environment variable passing the accepted device tokens to a sidecar
+const TOKENS_VAR: &str = "OPENTUI_DEVICE_TOKENS";
+/// How long a pairing code can be redeemed
+const CODE_LIFETIME: Duration = Duration::from_secs(120);
+/// Wrong guesses after which the code is thrown away
+const MAX_ATTEMPTS: u32 = 5;
+
+/// A device that redeemed a pairing code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairedDevice {
+    pub id: String,
+    pub name: String,
+    pub token: String,
+    /// Seconds since the Unix epoch
+    pub paired_at: u64,
+}
+
+/// A paired device as shown in the UI, without its token
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    id: String,
+    name: String,
+    paired_at: u64,
+    revoked: bool,
+}
+
+/// A code to show for a device to redeem
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingCode {
+    code: String,
+    /// Server address to open on the device, with the code, for a QR code
+    url: Option<String>,
+    expires_in_secs: u64,
+}
+
+struct PendingCode {
+    code: String,
+    expires: Instant,
+    attempts: u32,
+}
+
+/// The code currently offered for pairing, if any
+#[derive(Clone, Default)]
+pub struct PairingState(Arc<Mutex<Option<PendingCode>>>);
+
+/// Address of this machine on the LAN. Connecting a UDP socket sends nothing,
+/// it only picks the interface a packet would leave through.
+fn lan_address() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Tokens of paired devices that have not been revoked
+fn active_tokens(app: &AppHandle) -> Vec<String> {
+    let settings = settings::current(app);
+    if !settings.lan_pairing {
+        return Vec::new();
+    }
+    settings
+        .paired_devices
+        .into_iter()
+        .filter(|device| !settings.revoked_devices.contains(&device.id))
+        .map(|device| device.token)
+        .collect()
+}
+
+/// Environment giving a sidecar the accepted device tokens
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    vec![(TOKENS_VAR.to_string(), active_tokens(app).join(","))]
+}
+
+/// Tell every sidecar which device tokens to accept
+pub fn push_tokens(app: &AppHandle) {
+    let tokens = active_tokens(app).into_iter().map(Secret).collect();
+    // No sidecar running is fine; new ones read the tokens on start
+    let _ = control::send(app, &ControlMessage::SetDeviceTokens { tokens });
+}
+
+/// Check a code against the one on offer, counting wrong guesses
+fn redeem(app: &AppHandle, code: &str) -> bool {
+    let state = app.state::<PairingState>();
+    let Ok(mut pending) = state.0.lock() else {
+        return false;
+    };
+    let Some(offer) = pending.as_mut() else {
+        return false;
+    };
+    if offer.expires < Instant::now() {
+        *pending = None;
+        return false;
+    }
+    if offer.code != code.trim() {
+        offer.attempts += 1;
+        if offer.attempts >= MAX_ATTEMPTS {
+            log_info!("Too many wrong pairing codes, cancelling pairing");
+            *pending = None;
+        }
+        return false;
+    }
+    *pending = None;
+    true
+}
+
+/// Answer a `pair-request` event from a sidecar with a new device token,
+/// or none if pairing is off or the code is wrong
+pub fn handle_request(app: &AppHandle, sidecar: Sidecar, id: u64, code: String, name: String) {
+    let token = if settings::current(app).lan_pairing && redeem(app, &code) {
+        let (device_id, token) = match (askpass::random_token(), askpass::random_token()) {
+            (Ok(device_id), Ok(token)) => (device_id[..8].to_string(), token),
+            (Err(e), _) | (_, Err(e)) => {
+                log_error!("Failed to pair device {}: {}", name, e);
+                answer(&sidecar, id, None);
+                return;
+            }
+        };
+        let device = PairedDevice {
+            id: device_id,
+            name,
+            token,
+            paired_at: now_secs(),
+        };
+        let token = device.token.clone();
+        let info = DeviceInfo {
+            id: device.id.clone(),
+            name: device.name.clone(),
+            paired_at: device.paired_at,
+            revoked: false,
+        };
+        match settings::update(app, |settings| settings.paired_devices.push(device)) {
+            Ok(()) => {
+                log_info!("Paired device {}", info.name);
+                push_tokens(app);
+                let _ = app.emit("device-paired", info);
+                Some(Secret(token))
+            }
+            Err(e) => {
+                log_error!("Failed to save paired device: {}", e);
+                None
+            }
+        }
+    } else {
+        log_error!("Rejected pairing request from {}", name);
+        None
+    };
+
+    answer(&sidecar, id, token);
+}
+
+/// Send the result of a pairing request back to the sidecar that asked
+fn answer(sidecar: &Sidecar, id: u64, token: Option<Secret>) {
+    if let Err(e) = control::send_to(sidecar, &ControlMessage::PairResult { id, token }) {
+        log_error!("Failed to answer pairing request: {}", e);
+    }
+}
+
+/// Offer a new pairing code for the calling window's server, replacing any
+/// previous one
+#[tauri::command]
+pub fn start_pairing(app: AppHandle, window: WebviewWindow) -> CommandResult<PairingCode> {
+    if !settings::current(&app).lan_pairing {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            "LAN pairing is turned off in the settings",
+        ));
+    }
+    let sidecar = sidecar_for_window(&app, window.label())
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "No server for this window"))?;
+
+    let digits = u64::from_str_radix(&askpass::random_token()?[..12], 16).unwrap_or_default();
+    let code = format!("{:06}", digits % 1_000_000);
+    let url = lan_address().map(|address| {
+        let scheme = if tls::enabled(&app) { "https" } else { "http" };
+        format!("{}://{}:{}/?pair={}", scheme, address, sidecar.port, code)
+    });
+
+    let state = app.state::<PairingState>();
+    let mut pending = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on pairing state: {}", e))?;
+    *pending = Some(PendingCode {
+        code: code.clone(),
+        expires: Instant::now() + CODE_LIFETIME,
+        attempts: 0,
+    });
+
+    Ok(PairingCode {
+        code,
+        url,
+        expires_in_secs: CODE_LIFETIME.as_secs(),
+    })
+}
+
+/// Devices paired so far, including revoked ones
+#[tauri::command]
+pub fn list_paired_devices(app: AppHandle) -> Vec<DeviceInfo> {
+    let settings = settings::current(&app);
+    settings
+        .paired_devices
+        .into_iter()
+        .map(|device| DeviceInfo {
+            revoked: settings.revoked_devices.contains(&device.id),
+            id: device.id,
+            name: device.name,
+            paired_at: device.paired_at,
+        })
+        .collect()
+}
+
+/// Stop accepting a device's token. Its id stays on the revocation list so the
+/// token is refused even if the device entry comes back from a backup.
+#[tauri::command]
+pub fn revoke_device(app: AppHandle, id: String) -> CommandResult<()> {
+    let known = settings::current(&app)
+        .paired_devices
+        .iter()
+        .any(|device| device.id == id);
+    if !known {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("No paired device {}", id),
+        ));
+    }
+    settings::update(&app, |settings| {
+        if !settings.revoked_devices.contains(&id) {
+            settings.revoked_devices.push(id.clone());
+        }
+    })?;
+    push_tokens(&app);
+    log_info!("Revoked device {}", id);
+    Ok(())
+}
@@ -0,0 +1,344 @@
+//! Registry of the shell's own commands for the command palette. Each command
+//! has an id, a translated title, the arguments it takes and a keybinding,
+//! defaulting to its menu accelerator and overridable through the
+//! `keybindings` setting, which also holds the frontend's own actions.
+//! `list_commands` describes them with any keybinding conflicts and
+//! `invoke_command` runs them, so the palette drives shell actions the same
+//! way as the server's.
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::accelerators::{self, ACCELERATORS};
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::settings::{self, Settings};
+use crate::window::open_repo_in_window;
+use crate::{compact, display, hotkey, i18n, menu, switcher};
+
+/// Type of a command argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArgKind {
+    String,
+    Number,
+    Boolean,
+}
+
+/// An argument a command takes, by name
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+/// A native command the palette can run
+#[derive(Debug, Clone, Copy)]
+pub struct CommandSpec {
+    pub id: &'static str,
+    /// Translation key of the title
+    pub title_key: &'static str,
+    pub args: &'static [ArgSpec],
+}
+
+/// All native commands. Those with a menu item share its id, title and
+/// accelerator, and run through the menu handler.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        id: "new-window",
+        title_key: "menu.new-window",
+        args: &[],
+    },
+    CommandSpec {
+        id: "open-repo",
+        title_key: "menu.open-repo",
+        args: &[],
+    },
+    CommandSpec {
+        id: "open-repo-path",
+        title_key: "command.open-repo-path",
+        args: &[ArgSpec {
+            name: "repoPath",
+            kind: ArgKind::String,
+            required: true,
+        }],
+    },
+    CommandSpec {
+        id: "switch-repository",
+        title_key: "command.switch-repository",
+        args: &[],
+    },
+    CommandSpec {
+        id: "close-window",
+        title_key: "menu.close-window",
+        args: &[],
+    },
+    CommandSpec {
+        id: "settings",
+        title_key: "menu.settings",
+        args: &[],
+    },
+    CommandSpec {
+        id: "refresh",
+        title_key: "menu.refresh",
+        args: &[],
+    },
+    CommandSpec {
+        id: "reload",
+        title_key: "menu.reload",
+        args: &[],
+    },
+    CommandSpec {
+        id: "restart-server",
+        title_key: "menu.restart-server",
+        args: &[],
+    },
+    CommandSpec {
+        id: "zoom-in",
+        title_key: "menu.zoom-in",
+        args: &[],
+    },
+    CommandSpec {
+        id: "zoom-out",
+        title_key: "menu.zoom-out",
+        args: &[],
+    },
+    CommandSpec {
+        id: "reset-zoom",
+        title_key: "menu.reset-zoom",
+        args: &[],
+    },
+    CommandSpec {
+        id: "set-zoom",
+        title_key: "command.set-zoom",
+        args: &[ArgSpec {
+            name: "factor",
+            kind: ArgKind::Number,
+            required: true,
+        }],
+    },
+    CommandSpec {
+        id: "toggle-compact",
+        title_key: "command.toggle-compact",
+        args: &[],
+    },
+    CommandSpec {
+        id: "toggle-logs",
+        title_key: "menu.toggle-logs",
+        args: &[],
+    },
+    CommandSpec {
+        id: "about",
+        title_key: "menu.about",
+        args: &[],
+    },
+    CommandSpec {
+        id: "report-issue",
+        title_key: "menu.report-issue",
+        args: &[],
+    },
+];
+
+/// A command as listed in the palette
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandInfo {
+    id: &'static str,
+    title: String,
+    args: &'static [ArgSpec],
+    default_keybinding: Option<&'static str>,
+    /// The keybinding in effect, after the user's
+    keybinding: Option<String>,
+    /// Commands, frontend actions or global shortcuts on the same keys
+    conflicts: Vec<String>,
+}
+
+fn find(id: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|command| command.id == id)
+}
+
+fn default_keybinding(id: &str) -> Option<&'static str> {
+    ACCELERATORS
+        .iter()
+        .find(|entry| entry.id == id)
+        .map(|entry| entry.accelerator)
+}
+
+/// Keybinding of a command: the user's if set, where an empty one unbinds
+/// it, otherwise the default
+pub fn keybinding(settings: &Settings, id: &str) -> Option<String> {
+    match settings.keybindings.get(id) {
+        Some(accelerator) if accelerator.is_empty() => None,
+        Some(accelerator) => Some(accelerator.clone()),
+        None => default_keybinding(id).map(str::to_string),
+    }
+}
+
+/// Check arguments against a command's spec
+fn check_args(command: &CommandSpec, args: &Value) -> Result<(), String> {
+    let empty = serde_json::Map::new();
+    let given = match args {
+        Value::Null => &empty,
+        Value::Object(given) => given,
+        _ => return Err(format!("Arguments of '{}' must be an object", command.id)),
+    };
+
+    if let Some(unknown) = given
+        .keys()
+        .find(|name| !command.args.iter().any(|arg| arg.name == name.as_str()))
+    {
+        return Err(format!("'{}' takes no argument '{}'", command.id, unknown));
+    }
+    for arg in command.args {
+        let valid = match (given.get(arg.name), arg.kind) {
+            (None | Some(Value::Null), _) => !arg.required,
+            (Some(Value::String(_)), ArgKind::String)
+            | (Some(Value::Number(_)), ArgKind::Number)
+            | (Some(Value::Bool(_)), ArgKind::Boolean) => true,
+            _ => false,
+        };
+        if !valid {
+            return Err(format!(
+                "'{}' needs argument '{}' of type {:?}",
+                command.id, arg.name, arg.kind
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// List the native commands with their keybindings
+#[tauri::command]
+pub fn list_commands(app: AppHandle) -> Vec<CommandInfo> {
+    let settings = settings::current(&app);
+    let mut conflicts = accelerators::shared_keys(&settings);
+    COMMANDS
+        .iter()
+        .map(|command| CommandInfo {
+            id: command.id,
+            title: i18n::t(&app, command.title_key),
+            args: command.args,
+            default_keybinding: default_keybinding(command.id),
+            keybinding: keybinding(&settings, command.id),
+            conflicts: conflicts.remove(command.id).unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Run a native command for the calling window
+#[tauri::command]
+pub async fn invoke_command(
+    app: AppHandle,
+    window: WebviewWindow,
+    id: String,
+    args: Option<Value>,
+) -> CommandResult<()> {
+    let command = find(&id)
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("Unknown command '{}'", id)))?;
+    let args = args.unwrap_or(Value::Null);
+    check_args(command, &args).map_err(|e| AppError::new(ErrorCode::InvalidInput, e))?;
+
+    log_info!("Running command {}", command.id);
+    match command.id {
+        "open-repo-path" => {
+            let repo_path = args["repoPath"].as_str().unwrap_or_default();
+            open_repo_in_window(&app, repo_path).await?;
+        }
+        "switch-repository" => switcher::toggle(&app),
+        "set-zoom" => {
+            display::set_zoom(window, args["factor"].as_f64().unwrap_or(1.0))?;
+        }
+        "toggle-compact" => {
+            compact::toggle_compact_mode(window)?;
+        }
+        // The palette lives in the focused window, which menu items act on
+        id => menu::handle(&app, id),
+    }
+    Ok(())
+}
+
+/// Set a command's or frontend action's keybinding. `None` restores the
+/// default and an empty accelerator removes the binding. Returns what else
+/// is bound to the same keys.
+#[tauri::command]
+pub fn set_keybinding(
+    app: AppHandle,
+    id: String,
+    accelerator: Option<String>,
+) -> CommandResult<Vec<String>> {
+    if let Some(accelerator) = accelerator.as_deref().filter(|a| !a.is_empty()) {
+        hotkey::parse(accelerator).map_err(|e| {
+            AppError::new(ErrorCode::InvalidInput, e)
+                .with_metadata(serde_json::json!({ "field": "keybindings", "id": id }))
+        })?;
+    }
+    settings::update(&app, |settings| match accelerator {
+        Some(accelerator) => {
+            settings.keybindings.insert(id.clone(), accelerator);
+        }
+        None => {
+            settings.keybindings.remove(&id);
+        }
+    })?;
+
+    let settings = settings::current(&app);
+    if find(&id).is_some() {
+        // Menu accelerators are fixed when the menu is built
+        if let Err(e) = menu::install(&app) {
+            log_error!("Failed to refresh menu: {}", e);
+        }
+    }
+    settings::emit_changed(&app);
+    Ok(accelerators::shared_keys(&settings)
+        .remove(&id)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_keybindings_override_defaults() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            keybinding(&settings, "zoom-in").as_deref(),
+            Some("CmdOrCtrl+=")
+        );
+        settings
+            .keybindings
+            .insert("zoom-in".to_string(), "CmdOrCtrl+Up".to_string());
+        settings
+            .keybindings
+            .insert("reload".to_string(), String::new());
+        assert_eq!(
+            keybinding(&settings, "zoom-in").as_deref(),
+            Some("CmdOrCtrl+Up")
+        );
+        assert_eq!(keybinding(&settings, "reload"), None);
+        assert_eq!(keybinding(&settings, "toggle-compact"), None);
+    }
+
+    #[test]
+    fn checks_arguments() {
+        let open = find("open-repo-path").unwrap();
+        assert!(check_args(open, &serde_json::json!({ "repoPath": "/tmp/repo" })).is_ok());
+        assert!(check_args(open, &Value::Null).is_err());
+        assert!(check_args(open, &serde_json::json!({ "repoPath": 1 })).is_err());
+        assert!(check_args(
+            open,
+            &serde_json::json!({ "repoPath": "/tmp", "extra": true })
+        )
+        .is_err());
+        assert!(check_args(find("refresh").unwrap(), &Value::Null).is_ok());
+    }
+
+    #[test]
+    fn menu_commands_have_menu_items() {
+        for entry in ACCELERATORS {
+            assert!(find(entry.id).is_some(), "{} has no command", entry.id);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Drag a commit or diff out of the app as a `.patch` file, to drop into an
+//! email, a chat or a file manager.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+
+/// Drag preview image
+const DRAG_ICON: &[u8] = include_bytes!("../icons/32x32.png");
+/// Patches older than this are removed from the temp directory; by then any
+/// drop has long finished reading them
+const MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Directory the dragged patch files are written to
+fn patch_dir() -> std::io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("opentui-git-patches");
+    std::fs::create_dir_all(&dir)?;
+
+    if let Ok(entries) = std::fs::read_dir(&dir) {
+        for entry in entries.flatten() {
+            let stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age > MAX_AGE);
+            if stale {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+    Ok(dir)
+}
+
+/// File name like `abc1234-fix-the-thing.patch`, using git's sanitized subject
+async fn file_name(repo_path: &str, commit: Option<&str>, staged: bool) -> String {
+    let Some(commit) = commit else {
+        return if staged { "staged" } else { "changes" }.to_string() + ".patch";
+    };
+    let name = git::run(repo_path, &["log", "-1", "--format=%h-%f", commit])
+        .await
+        .map(|output| String::from_utf8_lossy(&output).trim().to_string())
+        .unwrap_or_default();
+    if name.is_empty() {
+        format!("{}.patch", commit.replace(['/', '\\'], "-"))
+    } else {
+        format!("{}.patch", name)
+    }
+}
+
+/// Write a commit's patch, or the working tree's or index's diff of `paths`,
+/// to a temp file and start dragging it out of the calling window.
+/// Returns the patch file's path.
+#[tauri::command]
+pub async fn begin_patch_drag(
+    app: AppHandle,
+    window: WebviewWindow,
+    commit: Option<String>,
+    paths: Option<Vec<String>>,
+    staged: Option<bool>,
+) -> CommandResult<String> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let staged = staged.unwrap_or(false);
+    let patch = git::patch(
+        &repo_path,
+        commit.as_deref(),
+        &paths.unwrap_or_default(),
+        staged,
+    )
+    .await?;
+
+    let io_error = |e: std::io::Error| {
+        AppError::new(
+            ErrorCode::Internal,
+            format!("Failed to write patch file: {}", e),
+        )
+    };
+    let path = patch_dir()
+        .map_err(io_error)?
+        .join(file_name(&repo_path, commit.as_deref(), staged).await);
+    std::fs::write(&path, patch).map_err(io_error)?;
+
+    // Native drag sessions must start on the main thread
+    let item = drag::DragItem::Files(vec![path.clone()]);
+    app.run_on_main_thread(move || {
+        #[cfg(target_os = "linux")]
+        let handle = window.gtk_window();
+        #[cfg(not(target_os = "linux"))]
+        let handle = tauri::Result::Ok(window.clone());
+
+        let result = handle.map_err(|e| e.to_string()).and_then(|handle| {
+            drag::start_drag(
+                &handle,
+                item,
+                drag::Image::Raw(DRAG_ICON.to_vec()),
+                |_, _| {},
+                drag::Options::default(),
+            )
+            .map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            log_error!("Failed to start patch drag: {}", e);
+        }
+    })
+    .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to start drag: {}", e)))?;
+
+    Ok(path.to_string_lossy().to_string())
+}
@@ -0,0 +1,134 @@
+//! `.patch` and `.diff` files opened with the app, from the file manager or
+//! the command line. Each is handed to a repository window's apply-patch flow.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, WebviewWindow};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::broadcast::WindowRegistry;
+use crate::window::open_repo_in_window;
+use crate::worktrees;
+
+const EXTENSIONS: &[&str] = &["patch", "diff"];
+
+/// Patches opened for a window that it has not picked up yet, keyed by label.
+/// A window opened for the patch may not be listening for events yet.
+#[derive(Clone, Default)]
+pub struct PendingPatches(Arc<Mutex<HashMap<String, PatchOpened>>>);
+
+/// Payload of the `patch-opened` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchOpened {
+    path: String,
+    repo_path: String,
+}
+
+/// Whether a path looks like a patch file
+pub fn is_patch_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Patch file passed on the command line, resolved against the directory
+/// the command was run from
+pub fn patch_arg(args: &[String], cwd: &Path) -> Option<PathBuf> {
+    args.iter()
+        .skip(1)
+        .map(Path::new)
+        .find(|arg| is_patch_file(arg))
+        .map(|arg| cwd.join(arg))
+}
+
+/// Window a patch should be applied in: the focused repository window, else
+/// the main window, else any repository window
+fn target_window(app: &AppHandle) -> Option<WebviewWindow> {
+    let registry = app.state::<WindowRegistry>();
+    let windows: Vec<WebviewWindow> = app
+        .webview_windows()
+        .into_values()
+        .filter(|window| registry.repo_for(window.label()).is_some())
+        .collect();
+    windows
+        .iter()
+        .find(|window| window.is_focused().unwrap_or(false))
+        .or_else(|| windows.iter().find(|window| window.label() == "main"))
+        .or_else(|| windows.first())
+        .cloned()
+}
+
+/// Ask which repository to apply a patch to and open it.
+/// Returns the repository's first window, or `None` if cancelled.
+async fn pick_repo_window(app: &AppHandle, patch: &Path) -> Option<WebviewWindow> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let title = format!(
+        "Choose a repository to apply {}",
+        patch.file_name()?.to_string_lossy()
+    );
+    app.dialog()
+        .file()
+        .set_title(title)
+        .pick_folder(move |folder| {
+            let _ = tx.send(folder);
+        });
+    let folder = rx.await.ok().flatten()?.into_path().ok()?;
+    let repo_path = worktrees::locate(&folder.to_string_lossy())?.workdir;
+
+    if let Err(e) = open_repo_in_window(app, &repo_path).await {
+        log_error!("Failed to open {}: {}", repo_path, e);
+        return None;
+    }
+    let label = app
+        .state::<WindowRegistry>()
+        .windows_for(&repo_path)
+        .into_iter()
+        .next()?;
+    app.get_webview_window(&label)
+}
+
+/// Hand a patch file to a repository window, asking for the repository if
+/// none is open
+pub async fn open(app: &AppHandle, path: PathBuf) {
+    let window = match target_window(app) {
+        Some(window) => window,
+        None => match pick_repo_window(app, &path).await {
+            Some(window) => window,
+            None => return,
+        },
+    };
+    let Some(repo_path) = app.state::<WindowRegistry>().repo_for(window.label()) else {
+        return;
+    };
+
+    log_info!("Opening patch {} in {}", path.display(), window.label());
+    let payload = PatchOpened {
+        path: path.to_string_lossy().to_string(),
+        repo_path,
+    };
+    if let Ok(mut pending) = app.state::<PendingPatches>().0.lock() {
+        pending.insert(window.label().to_string(), payload.clone());
+    }
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+    let _ = window.emit_to(
+        EventTarget::webview_window(window.label()),
+        "patch-opened",
+        payload,
+    );
+}
+
+/// Take the patch opened for the calling window, if any, so a window that
+/// missed the `patch-opened` event while loading still shows it
+#[tauri::command]
+pub fn take_pending_patch(app: AppHandle, window: WebviewWindow) -> Option<PatchOpened> {
+    app.state::<PendingPatches>()
+        .0
+        .lock()
+        .ok()?
+        .remove(window.label())
+}
@@ -0,0 +1,61 @@
+//! Portable mode: with a `portable` file next to the executable, or when
+//! launched with `--portable`, settings, recent repositories, sessions and logs
+//! live in a folder beside the executable instead of the platform's config
+//! and data directories, so the app can run from a USB stick or a machine
+//! where those are locked down.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager};
+
+/// Command-line flag turning portable mode on
+const FLAG: &str = "--portable";
+/// File next to the executable turning portable mode on
+const MARKER: &str = "portable";
+/// Folder next to the executable holding the app's files
+const DATA_DIR: &str = "opentui-git-data";
+
+/// Folder holding the app's files when portable, `None` otherwise
+fn root() -> Option<&'static PathBuf> {
+    static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+        let requested = std::env::args().skip(1).any(|arg| arg == FLAG);
+        if !requested && !exe_dir.join(MARKER).is_file() {
+            return None;
+        }
+        let root = exe_dir.join(DATA_DIR);
+        log_info!("Portable mode, keeping data in {}", root.display());
+        Some(root)
+    })
+    .as_ref()
+}
+
+/// Whether the app keeps its files beside the executable
+pub fn enabled() -> bool {
+    root().is_some()
+}
+
+/// Where settings are stored
+pub fn config_dir(app: &AppHandle) -> tauri::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root.join("config")),
+        None => app.path().app_config_dir(),
+    }
+}
+
+/// Where recent repositories, sessions and other app state are stored
+pub fn data_dir(app: &AppHandle) -> tauri::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root.join("data")),
+        None => app.path().app_data_dir(),
+    }
+}
+
+/// Where session logs are written
+pub fn log_dir(app: &AppHandle) -> tauri::Result<PathBuf> {
+    match root() {
+        Some(root) => Ok(root.join("logs")),
+        None => app.path().app_log_dir(),
+    }
+}
@@ -0,0 +1,185 @@
+//! Port selection for sidecars. By default the OS picks any free port, but a
+//! range can be configured with `OPENTUI_PORT_RANGE=39000-39100` or in the
+//! settings, and each repository can then keep the same port across launches
+//! so bookmarks, proxies and firewall rules keep working.
+
+use serde::{Deserialize, Serialize};
+use std::net::TcpListener;
+use std::ops::RangeInclusive;
+use tauri::{AppHandle, Manager};
+
+use crate::broadcast::repo_key;
+use crate::config::find_free_port;
+use crate::sidecar::ServerState;
+use crate::{loopback, settings};
+
+/// Environment variable overriding the port range setting
+const RANGE_VAR: &str = "OPENTUI_PORT_RANGE";
+
+/// Ports sidecars are started on, both ends included
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    /// Parse `start-end`, rejecting empty ranges and port 0
+    fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.trim().split_once('-')?;
+        let range = Self {
+            start: start.trim().parse().ok()?,
+            end: end.trim().parse().ok()?,
+        };
+        (range.start > 0 && range.start <= range.end).then_some(range)
+    }
+
+    fn ports(self) -> RangeInclusive<u16> {
+        self.start..=self.end
+    }
+}
+
+/// The configured range, the environment taking precedence over the settings
+fn range(app: &AppHandle) -> Option<PortRange> {
+    if let Ok(value) = std::env::var(RANGE_VAR) {
+        match PortRange::parse(&value) {
+            Some(range) => return Some(range),
+            None => log_error!("Ignoring invalid {}: {}", RANGE_VAR, value),
+        }
+    }
+    settings::current(app)
+        .port_range
+        .filter(|range| range.start > 0 && range.start <= range.end)
+}
+
+/// FNV-1a, which unlike the std hasher is the same on every launch and platform
+fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The first port in the range, starting from the repository's own port if
+/// `stable_key` is given, that is neither taken nor reported busy by `is_free`
+fn choose(
+    range: PortRange,
+    stable_key: Option<&str>,
+    taken: &[u32],
+    is_free: impl Fn(u16) -> bool,
+) -> Option<u16> {
+    let len = range.ports().len();
+    let offset = stable_key.map_or(0, |key| (stable_hash(key) % len as u64) as usize);
+    range
+        .ports()
+        .cycle()
+        .skip(offset)
+        .take(len)
+        .find(|&port| !taken.contains(&(port as u32)) && is_free(port))
+}
+
+fn is_free(host: &str, port: u16) -> bool {
+    TcpListener::bind((host, port)).is_ok()
+}
+
+/// Pick a port for a new sidecar, skipping ports taken by running sidecars and
+/// `exclude`. Within a configured range a repository starts from the same port
+/// every time when stable ports are on, and moves to the next free one if that
+/// is taken. Falls back to any free port if the range is exhausted.
+pub fn pick(app: &AppHandle, repo_path: Option<&str>, exclude: &[u32]) -> Result<u32, String> {
+    let Some(range) = range(app) else {
+        return find_free_port();
+    };
+    let taken: Vec<u32> = app
+        .state::<ServerState>()
+        .all()
+        .iter()
+        .map(|sidecar| sidecar.port)
+        .chain(exclude.iter().copied())
+        .collect();
+
+    let stable_key = repo_path
+        .filter(|_| settings::current(app).stable_ports)
+        .map(repo_key);
+    let host = loopback::host(app);
+    let port = choose(range, stable_key.as_deref(), &taken, |port| {
+        is_free(host, port)
+    });
+
+    match port {
+        Some(port) => Ok(port as u32),
+        None => {
+            log_error!(
+                "No free port in {}-{}, using any free port",
+                range.start,
+                range.end
+            );
+            find_free_port()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: PortRange = PortRange {
+        start: 39000,
+        end: 39009,
+    };
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(PortRange::parse(" 39000 - 39009 "), Some(RANGE));
+        assert_eq!(
+            PortRange::parse("4000-4000"),
+            Some(PortRange {
+                start: 4000,
+                end: 4000
+            })
+        );
+        assert_eq!(PortRange::parse("4001-4000"), None);
+        assert_eq!(PortRange::parse("0-10"), None);
+        assert_eq!(PortRange::parse("4000"), None);
+        assert_eq!(PortRange::parse("4000-70000"), None);
+    }
+
+    #[test]
+    fn picks_first_free_port_without_a_key() {
+        assert_eq!(choose(RANGE, None, &[], |_| true), Some(39000));
+        assert_eq!(choose(RANGE, None, &[39000, 39001], |_| true), Some(39002));
+        assert_eq!(choose(RANGE, None, &[], |port| port > 39004), Some(39005));
+    }
+
+    #[test]
+    fn stable_ports_repeat_and_wrap() {
+        let first = choose(RANGE, Some("/work/repo"), &[], |_| true).unwrap();
+        assert_eq!(
+            choose(RANGE, Some("/work/repo"), &[], |_| true),
+            Some(first)
+        );
+
+        // Taken ports move the repository on to the next one, wrapping around
+        let next = if first == RANGE.end {
+            RANGE.start
+        } else {
+            first + 1
+        };
+        assert_eq!(
+            choose(RANGE, Some("/work/repo"), &[first as u32], |_| true),
+            Some(next)
+        );
+    }
+
+    #[test]
+    fn exhausted_range_picks_nothing() {
+        let taken: Vec<u32> = RANGE.ports().map(u32::from).collect();
+        assert_eq!(choose(RANGE, Some("/work/repo"), &taken, |_| true), None);
+        assert_eq!(choose(RANGE, None, &[], |_| false), None);
+    }
+
+    #[test]
+    fn stable_hash_is_fnv1a() {
+        assert_eq!(stable_hash(""), 0xcbf29ce484222325);
+        assert_eq!(stable_hash("a"), 0xaf63dc4c8601ec8c);
+    }
+}
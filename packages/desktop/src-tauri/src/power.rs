@@ -0,0 +1,187 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Manager};
+
+use crate::control::{self, ControlMessage};
+use crate::{headless, settings, watcher};
+
+const TICK_SECS: u64 = 5;
+/// Wall-clock time beyond the tick that indicates the machine was suspended
+const SUSPEND_THRESHOLD_SECS: u64 = 30;
+/// How much longer periodic checks wait while saving power
+const SAVER_SLOWDOWN: u32 = 6;
+
+/// Power saving: entered once every window has been hidden or minimized for
+/// a while, or the OS is saving battery and no window has focus. Watchers are
+/// paused and periodic checks slowed down until a window is focused again.
+#[derive(Clone, Default)]
+pub struct PowerSaver(Arc<Mutex<SaverState>>);
+
+#[derive(Default)]
+struct SaverState {
+    active: bool,
+    /// When the windows were last seen all hidden
+    hidden_since: Option<Instant>,
+}
+
+/// Whether power saving is on
+pub fn saving(app: &AppHandle) -> bool {
+    app.try_state::<PowerSaver>()
+        .and_then(|saver| Some(saver.0.lock().ok()?.active))
+        .unwrap_or(false)
+}
+
+/// Interval for a periodic check, longer while saving power
+pub fn interval(app: &AppHandle, normal: Duration) -> Duration {
+    if saving(app) {
+        normal * SAVER_SLOWDOWN
+    } else {
+        normal
+    }
+}
+
+/// Whether no window is on screen. With no windows at all the app runs in
+/// the background, which counts too, except headless where remote clients
+/// are using the server.
+fn windows_hidden(app: &AppHandle) -> bool {
+    !headless::requested()
+        && app.webview_windows().values().all(|window| {
+            !window.is_visible().unwrap_or(true) || window.is_minimized().unwrap_or(false)
+        })
+}
+
+fn any_focused(app: &AppHandle) -> bool {
+    app.webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Whether the OS reports it is saving battery, where that can be detected
+pub fn os_power_saving() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string("/sys/firmware/acpi/platform_profile")
+            .is_ok_and(|profile| profile.trim() == "low-power")
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("pmset")
+            .arg("-g")
+            .output()
+            .is_ok_and(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .any(|line| line.split_whitespace().eq(["lowpowermode", "1"]))
+            })
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        false
+    }
+}
+
+fn set_sidecars_idle(app: &AppHandle, idle: bool) {
+    if !settings::current(app).idle_sidecars_when_saving {
+        return;
+    }
+    // No sidecars running is fine, there is nothing to idle
+    let _ = control::send(app, &ControlMessage::SetIdle { idle });
+}
+
+fn enter_saver(app: &AppHandle) {
+    let Some(saver) = app.try_state::<PowerSaver>() else {
+        return;
+    };
+    let Ok(mut state) = saver.0.lock() else {
+        return;
+    };
+    if state.active {
+        return;
+    }
+    state.active = true;
+    drop(state);
+
+    log_info!("Entering power saving");
+    watcher::pause(app);
+    set_sidecars_idle(app, true);
+}
+
+/// Leave power saving, e.g. because a window was focused
+pub fn wake(app: &AppHandle) {
+    let Some(saver) = app.try_state::<PowerSaver>() else {
+        return;
+    };
+    let Ok(mut state) = saver.0.lock() else {
+        return;
+    };
+    state.hidden_since = None;
+    if !state.active {
+        return;
+    }
+    state.active = false;
+    drop(state);
+
+    log_info!("Leaving power saving");
+    watcher::resume(app);
+    set_sidecars_idle(app, false);
+}
+
+/// Enter or leave power saving according to the windows and the OS
+fn update_saver(app: &AppHandle) {
+    let settings = settings::current(app);
+    if !settings.power_saver {
+        wake(app);
+        return;
+    }
+
+    let hidden = windows_hidden(app);
+    let hidden_long = {
+        let Some(saver) = app.try_state::<PowerSaver>() else {
+            return;
+        };
+        let Ok(mut state) = saver.0.lock() else {
+            return;
+        };
+        if hidden {
+            let since = *state.hidden_since.get_or_insert_with(Instant::now);
+            since.elapsed() >= Duration::from_secs(settings.power_saver_delay_secs)
+        } else {
+            state.hidden_since = None;
+            false
+        }
+    };
+
+    // Checked last, since reading the OS state can mean running a program
+    if hidden_long || (!any_focused(app) && os_power_saving()) {
+        enter_saver(app);
+    } else if !hidden && any_focused(app) {
+        wake(app);
+    }
+}
+
+/// Detect suspend/resume and reconnect to the sidecar after waking, and
+/// switch power saving on and off.
+///
+/// The async timer runs on the monotonic clock, which stops while the machine
+/// sleeps, so a wall-clock jump much larger than the tick means we just resumed.
+pub fn start_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let tick = Duration::from_secs(TICK_SECS);
+        let mut last_tick = SystemTime::now();
+
+        loop {
+            tokio::time::sleep(tick).await;
+
+            let now = SystemTime::now();
+            let elapsed = now.duration_since(last_tick).unwrap_or_default();
+            last_tick = now;
+
+            if elapsed > tick + Duration::from_secs(SUSPEND_THRESHOLD_SECS) {
+                log_info!("Resumed after ~{}s suspended", elapsed.as_secs());
+                crate::sidecar::reconnect_sidecar(&app).await;
+            }
+
+            update_saver(&app);
+        }
+    });
+}
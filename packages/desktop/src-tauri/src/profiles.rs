@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::AppHandle;
+
+use crate::{broadcast, repo_config, safe_mode, settings, watcher};
+
+/// Repo-local profile, kept in the git directory so it is never committed
+/// or picked up from a cloned repository
+const PROFILE_FILE: &str = "opentui-git.json";
+
+/// Sidecar options a profile may set. The sidecar exits on any option it
+/// doesn't know, and the others are managed by the shell.
+const PROFILE_OPTIONS: &[&str] = &["history-limit", "log-level"];
+
+/// Extra sidecar arguments and environment for one repository
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RepoProfile {
+    /// Arguments appended to the sidecar's command line
+    pub args: Vec<String>,
+    /// Variables set for the sidecar, e.g. `GIT_DIR`
+    pub env: HashMap<String, String>,
+}
+
+impl RepoProfile {
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty() && self.env.is_empty()
+    }
+
+    /// Check the arguments against the options a profile may set, so a
+    /// bad profile fails the spawn with a clear error instead of the sidecar
+    pub fn check_args(&self) -> Result<(), String> {
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            let option = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("Unexpected sidecar argument \"{}\"", arg))?;
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (option, None),
+            };
+            if !PROFILE_OPTIONS.contains(&name) {
+                return Err(format!(
+                    "Unknown sidecar option --{}, profiles may only set --{}",
+                    name,
+                    PROFILE_OPTIONS.join(", --")
+                ));
+            }
+            if value.is_none() && args.next().is_none() {
+                return Err(format!("Sidecar option --{} needs a value", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Value given last for a sidecar option, as the sidecar reads them
+    pub fn option(&self, name: &str) -> Option<&str> {
+        let mut value = None;
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                continue;
+            };
+            match option.split_once('=') {
+                Some((option, given)) if option == name => value = Some(given),
+                None if option == name => value = args.next().map(String::as_str),
+                _ => {}
+            }
+        }
+        value
+    }
+
+    /// Add another profile's arguments, letting its variables win
+    fn merge(&mut self, other: RepoProfile) {
+        self.args.extend(other.args);
+        self.env.extend(other.env);
+    }
+}
+
+/// Profile from `<git common dir>/opentui-git.json`, if any
+fn from_repo(repo_path: &str) -> Option<RepoProfile> {
+    let (_, common_dir) = watcher::git_dirs(repo_path)?;
+    let contents = std::fs::read_to_string(common_dir.join(PROFILE_FILE)).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(profile) => Some(profile),
+        Err(e) => {
+            log_error!("Ignoring invalid {} in {}: {}", PROFILE_FILE, repo_path, e);
+            None
+        }
+    }
+}
+
+/// Spawn profile for a repository: the trusted `.opentui.toml`, then the
+/// repo-local file, then settings. Safe mode ignores them all.
+pub fn for_repo(app: &AppHandle, repo_path: &str) -> RepoProfile {
+    if safe_mode::is_active(app) {
+        return RepoProfile::default();
+    }
+    let mut profile = repo_config::for_repo(app, repo_path).sidecar;
+    if let Some(local) = from_repo(repo_path) {
+        profile.merge(local);
+    }
+    let key = broadcast::repo_key(repo_path);
+    if let Some(configured) = settings::current(app)
+        .repo_profiles
+        .into_iter()
+        .find(|(path, _)| broadcast::repo_key(path) == key)
+        .map(|(_, profile)| profile)
+    {
+        profile.merge(configured);
+    }
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(args: &[&str]) -> RepoProfile {
+        RepoProfile {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn accepts_the_options_profiles_may_set() {
+        assert!(profile(&["--history-limit", "500"]).check_args().is_ok());
+        assert!(profile(&["--log-level=debug"]).check_args().is_ok());
+    }
+
+    #[test]
+    fn later_options_win() {
+        let profile = profile(&[
+            "--log-level",
+            "warn",
+            "--history-limit=50",
+            "--log-level=debug",
+        ]);
+        assert_eq!(profile.option("log-level"), Some("debug"));
+        assert_eq!(profile.option("history-limit"), Some("50"));
+        assert_eq!(profile.option("port"), None);
+    }
+
+    #[test]
+    fn rejects_options_the_sidecar_does_not_know() {
+        let error = profile(&["--verbose"]).check_args().unwrap_err();
+        assert!(error.contains("--verbose"));
+        assert!(profile(&["--port", "1"]).check_args().is_err());
+        assert!(profile(&["debug"]).check_args().is_err());
+        assert!(profile(&["--log-level"]).check_args().is_err());
+    }
+}
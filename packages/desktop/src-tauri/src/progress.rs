@@ -0,0 +1,28 @@
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+use crate::broadcast::WindowRegistry;
+use crate::control::SidecarEvent;
+
+/// Drive the taskbar/dock progress indicator from git progress reported by a sidecar
+pub fn handle_sidecar_event(app: &AppHandle, repo_path: &str, event: &SidecarEvent) {
+    let (status, progress) = match event {
+        SidecarEvent::Progress { progress, .. } => {
+            (ProgressBarStatus::Normal, Some((*progress).min(100)))
+        }
+        SidecarEvent::OperationFinished { .. } => (ProgressBarStatus::None, None),
+        _ => return,
+    };
+
+    for label in app.state::<WindowRegistry>().windows_for(repo_path) {
+        let Some(window) = app.get_webview_window(&label) else {
+            continue;
+        };
+        if let Err(e) = window.set_progress_bar(ProgressBarState {
+            status: Some(status),
+            progress,
+        }) {
+            log_error!("Failed to set progress on {}: {}", label, e);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tauri::AppHandle;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::settings;
+
+/// Where the sidecar's proxy comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProxyMode {
+    /// Use the environment the app was started with, else the OS settings
+    #[default]
+    System,
+    /// Use the proxies configured in settings
+    Manual,
+    /// Connect directly, even if the OS has a proxy configured
+    Off,
+}
+
+/// Proxy settings, overriding the OS configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    /// Comma-separated hosts that bypass the proxy
+    pub no_proxy: Option<String>,
+}
+
+/// Proxy configuration handed to the sidecar
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    /// "environment", "system", "settings" or "none"
+    source: &'static str,
+    http_proxy: Option<String>,
+    https_proxy: Option<String>,
+    no_proxy: Option<String>,
+}
+
+impl ProxyConfig {
+    fn is_empty(&self) -> bool {
+        self.http_proxy.is_none() && self.https_proxy.is_none()
+    }
+
+    /// Proxy git goes through for an HTTP or HTTPS host, `None` when it
+    /// connects directly because there is no proxy or `no_proxy` bypasses it
+    pub fn for_host(&self, https: bool, host: &str) -> Option<&str> {
+        let proxy = if https {
+            self.https_proxy.as_deref()
+        } else {
+            self.http_proxy.as_deref()
+        }?;
+        let host = host.trim_matches(['[', ']']).to_lowercase();
+        let bypassed = self
+            .no_proxy
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| {
+                let domain = entry.trim_start_matches('*').trim_start_matches('.');
+                entry == "*" || host == domain || host.ends_with(&format!(".{}", domain))
+            });
+        (!bypassed).then_some(proxy)
+    }
+}
+
+/// First non-empty value of an environment variable, in either case
+fn env_var(name: &str) -> Option<String> {
+    [name.to_uppercase(), name.to_lowercase()]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+fn from_environment() -> ProxyConfig {
+    ProxyConfig {
+        source: "environment",
+        http_proxy: env_var("http_proxy"),
+        https_proxy: env_var("https_proxy"),
+        no_proxy: env_var("no_proxy"),
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Read proxies from `scutil --proxy`, which prints keys like `HTTPProxy : host`
+#[cfg(target_os = "macos")]
+fn from_os() -> ProxyConfig {
+    let Some(output) = run("scutil", &["--proxy"]) else {
+        return ProxyConfig::default();
+    };
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (name, value) = line.split_once(" : ")?;
+            (name.trim() == key).then(|| value.trim().to_string())
+        })
+    };
+    let proxy = |prefix: &str| {
+        if value(&format!("{}Enable", prefix)).as_deref() != Some("1") {
+            return None;
+        }
+        let host = value(&format!("{}Proxy", prefix))?;
+        let port = value(&format!("{}Port", prefix));
+        Some(match port {
+            Some(port) => format!("http://{}:{}", host, port),
+            None => format!("http://{}", host),
+        })
+    };
+
+    // ExceptionsList is printed as an array with one "<index> : <host>" line per entry
+    let exceptions: Vec<String> = output
+        .lines()
+        .skip_while(|line| !line.contains("ExceptionsList"))
+        .skip(1)
+        .take_while(|line| !line.trim().starts_with('}'))
+        .filter_map(|line| {
+            line.split_once(" : ")
+                .map(|(_, host)| host.trim().to_string())
+        })
+        .collect();
+
+    ProxyConfig {
+        source: "system",
+        http_proxy: proxy("HTTP"),
+        https_proxy: proxy("HTTPS"),
+        no_proxy: (!exceptions.is_empty()).then(|| exceptions.join(",")),
+    }
+}
+
+/// Read proxies from the WinINet settings in the registry
+#[cfg(target_os = "windows")]
+fn from_os() -> ProxyConfig {
+    let Some(output) = run(
+        "reg",
+        &[
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Internet Settings",
+        ],
+    ) else {
+        return ProxyConfig::default();
+    };
+    // Lines look like "    ProxyServer    REG_SZ    host:port"
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next()? == key).then(|| fields.skip(1).collect::<Vec<_>>().join(" "))
+        })
+    };
+    if value("ProxyEnable").as_deref() != Some("0x1") {
+        return ProxyConfig::default();
+    }
+    let Some(server) = value("ProxyServer") else {
+        return ProxyConfig::default();
+    };
+
+    // Either one "host:port" for all protocols or "http=host:port;https=host:port"
+    let for_protocol = |protocol: &str| {
+        if !server.contains('=') {
+            return Some(format!("http://{}", server));
+        }
+        server.split(';').find_map(|entry| {
+            let (name, address) = entry.split_once('=')?;
+            (name == protocol).then(|| format!("http://{}", address))
+        })
+    };
+    let no_proxy = value("ProxyOverride").map(|bypass| {
+        bypass
+            .split(';')
+            .map(|host| if host == "<local>" { "localhost" } else { host })
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+
+    ProxyConfig {
+        source: "system",
+        http_proxy: for_protocol("http"),
+        https_proxy: for_protocol("https"),
+        no_proxy,
+    }
+}
+
+/// Read proxies from GNOME's settings
+#[cfg(all(unix, not(target_os = "macos")))]
+fn from_os() -> ProxyConfig {
+    let get = |schema: &str, key: &str| {
+        run("gsettings", &["get", schema, key])
+            .map(|value| value.trim().trim_matches('\'').to_string())
+    };
+    if get("org.gnome.system.proxy", "mode").as_deref() != Some("manual") {
+        return ProxyConfig::default();
+    }
+    let proxy = |protocol: &str| {
+        let schema = format!("org.gnome.system.proxy.{}", protocol);
+        let host = get(&schema, "host").filter(|host| !host.is_empty())?;
+        let port = get(&schema, "port").filter(|port| port != "0");
+        Some(match port {
+            Some(port) => format!("http://{}:{}", host, port),
+            None => format!("http://{}", host),
+        })
+    };
+    // Printed as a list like ['localhost', '127.0.0.0/8']
+    let no_proxy = get("org.gnome.system.proxy", "ignore-hosts").map(|hosts| {
+        hosts
+            .trim_matches(['[', ']'])
+            .split(',')
+            .map(|host| host.trim().trim_matches('\''))
+            .filter(|host| !host.is_empty())
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+
+    ProxyConfig {
+        source: "system",
+        http_proxy: proxy("http"),
+        https_proxy: proxy("https"),
+        no_proxy,
+    }
+}
+
+/// Resolve the proxy the sidecar should use
+pub fn resolve(app: &AppHandle) -> ProxyConfig {
+    let proxy = settings::current(app).proxy;
+    let none = ProxyConfig {
+        source: "none",
+        ..Default::default()
+    };
+    match proxy.mode {
+        ProxyMode::Off => none,
+        ProxyMode::Manual => ProxyConfig {
+            source: "settings",
+            http_proxy: proxy.http_proxy,
+            https_proxy: proxy.https_proxy,
+            no_proxy: proxy.no_proxy,
+        },
+        ProxyMode::System => {
+            let environment = from_environment();
+            if !environment.is_empty() {
+                return environment;
+            }
+            let system = from_os();
+            if system.is_empty() {
+                none
+            } else {
+                system
+            }
+        }
+    }
+}
+
+/// Proxy variables for the sidecar's environment, in both cases since tools
+/// disagree on which they read
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    let config = resolve(app);
+    let mut vars = Vec::new();
+    for (name, value) in [
+        ("HTTP_PROXY", config.http_proxy),
+        ("HTTPS_PROXY", config.https_proxy),
+        ("NO_PROXY", config.no_proxy),
+    ] {
+        // Off still clears variables inherited from the app's environment
+        let value = value.unwrap_or_default();
+        vars.push((name.to_string(), value.clone()));
+        vars.push((name.to_lowercase(), value));
+    }
+    vars
+}
+
+/// Get the proxy configuration the sidecar uses and where it came from
+#[tauri::command]
+pub async fn get_proxy_config(app: AppHandle) -> CommandResult<ProxyConfig> {
+    tauri::async_runtime::spawn_blocking(move || resolve(&app))
+        .await
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Proxy lookup failed: {}", e)))
+}
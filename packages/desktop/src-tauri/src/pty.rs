@@ -0,0 +1,278 @@
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::cleanup;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+
+const READ_BUFFER_SIZE: usize = 8192;
+
+/// A shell running in a pseudo-terminal, owned by one window
+struct PtySession {
+    window: String,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+#[derive(Default)]
+struct Sessions {
+    next_id: u32,
+    by_id: HashMap<u32, PtySession>,
+}
+
+/// State holding the open terminal sessions
+#[derive(Clone, Default)]
+pub struct PtyState(Arc<Mutex<Sessions>>);
+
+/// Payload of the `pty-output` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PtyOutputEvent {
+    id: u32,
+    data: String,
+}
+
+/// Payload of the `pty-exit` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PtyExitEvent {
+    id: u32,
+    /// `None` if the shell was killed
+    exit_code: Option<u32>,
+}
+
+fn pty_error(context: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::new(ErrorCode::Internal, format!("{}: {}", context, e))
+}
+
+fn size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Decode the complete UTF-8 prefix of the buffer, keeping a trailing partial
+/// character for the next read
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Not UTF-8 at all; decode what is there rather than stalling
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..valid]).to_string();
+    pending.drain(..valid);
+    text
+}
+
+fn session_error(id: u32) -> AppError {
+    AppError::new(ErrorCode::NotFound, format!("No terminal session {}", id))
+}
+
+/// Forward a session's output to its window until the shell exits
+fn stream_output(app: AppHandle, id: u32, window: String, mut reader: Box<dyn Read + Send>) {
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; READ_BUFFER_SIZE];
+        let mut pending = Vec::new();
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    pending.extend_from_slice(&buffer[..n]);
+                    let data = take_utf8(&mut pending);
+                    if !data.is_empty() {
+                        let _ =
+                            app.emit_to(window.as_str(), "pty-output", PtyOutputEvent { id, data });
+                    }
+                }
+            }
+        }
+
+        // Still registered means the shell exited on its own rather than being killed
+        let session = app
+            .state::<PtyState>()
+            .0
+            .lock()
+            .ok()
+            .and_then(|mut sessions| sessions.by_id.remove(&id));
+        let exit_code = session
+            .and_then(|mut session| session.child.wait().ok())
+            .map(|status| status.exit_code());
+        log_info!("Terminal session {} exited", id);
+        let _ = app.emit_to(window.as_str(), "pty-exit", PtyExitEvent { id, exit_code });
+    });
+}
+
+/// Start a shell in the window's repository, or a subdirectory of it.
+/// Returns the session id used by the other terminal commands and events.
+#[tauri::command]
+pub fn pty_spawn(
+    app: AppHandle,
+    window: WebviewWindow,
+    subdir: Option<String>,
+    cols: u16,
+    rows: u16,
+) -> CommandResult<u32> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let dir = match &subdir {
+        Some(subdir) => Path::new(&repo_path).join(subdir),
+        None => Path::new(&repo_path).to_path_buf(),
+    };
+    if !dir.is_dir() {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("Directory not found: {}", dir.display()),
+        ));
+    }
+
+    let pair = native_pty_system()
+        .openpty(size(cols, rows))
+        .map_err(|e| pty_error("Failed to open terminal", e))?;
+
+    // The user's login shell, or %COMSPEC% on Windows
+    let mut command = CommandBuilder::new_default_prog();
+    command.cwd(&dir);
+    command.env("TERM", "xterm-256color");
+    let child = pair
+        .slave
+        .spawn_command(command)
+        .map_err(|e| pty_error("Failed to start shell", e))?;
+    // Only the child holds the slave side, so reads end when it exits
+    drop(pair.slave);
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| pty_error("Failed to read terminal", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| pty_error("Failed to write terminal", e))?;
+
+    let state = app.state::<PtyState>();
+    let mut sessions = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on terminal sessions: {}", e))?;
+    sessions.next_id += 1;
+    let id = sessions.next_id;
+    sessions.by_id.insert(
+        id,
+        PtySession {
+            window: window.label().to_string(),
+            master: pair.master,
+            writer,
+            child,
+        },
+    );
+    drop(sessions);
+    cleanup::register(&app, "terminals", close_all);
+
+    log_info!("Started terminal session {} in {}", id, dir.display());
+    stream_output(app.clone(), id, window.label().to_string(), reader);
+    Ok(id)
+}
+
+/// Send keyboard input to a terminal session
+#[tauri::command]
+pub fn pty_write(app: AppHandle, id: u32, data: String) -> CommandResult<()> {
+    let state = app.state::<PtyState>();
+    let mut sessions = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on terminal sessions: {}", e))?;
+    let session = sessions
+        .by_id
+        .get_mut(&id)
+        .ok_or_else(|| session_error(id))?;
+    session
+        .writer
+        .write_all(data.as_bytes())
+        .and_then(|_| session.writer.flush())
+        .map_err(|e| pty_error("Failed to write terminal", e))
+}
+
+/// Resize a terminal session to the panel's character grid
+#[tauri::command]
+pub fn pty_resize(app: AppHandle, id: u32, cols: u16, rows: u16) -> CommandResult<()> {
+    let state = app.state::<PtyState>();
+    let sessions = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on terminal sessions: {}", e))?;
+    let session = sessions.by_id.get(&id).ok_or_else(|| session_error(id))?;
+    session
+        .master
+        .resize(size(cols, rows))
+        .map_err(|e| pty_error("Failed to resize terminal", e))
+}
+
+/// End a session, killing its shell
+fn kill(mut session: PtySession) {
+    if let Err(e) = session.child.kill() {
+        log_error!("Failed to kill terminal shell: {}", e);
+    }
+    let _ = session.child.wait();
+}
+
+/// Close a terminal session, killing its shell
+#[tauri::command]
+pub fn pty_kill(app: AppHandle, id: u32) -> CommandResult<()> {
+    let session = app
+        .state::<PtyState>()
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on terminal sessions: {}", e))?
+        .by_id
+        .remove(&id)
+        .ok_or_else(|| session_error(id))?;
+    kill(session);
+    Ok(())
+}
+
+/// Kill every terminal session, when the app exits
+fn close_all(app: &AppHandle) {
+    let Some(state) = app.try_state::<PtyState>() else {
+        return;
+    };
+    let closed: Vec<PtySession> = match state.0.lock() {
+        Ok(mut sessions) => sessions.by_id.drain().map(|(_, session)| session).collect(),
+        Err(_) => return,
+    };
+    for session in closed {
+        kill(session);
+    }
+}
+
+/// Kill the terminal sessions of a closed window
+pub fn close_window(app: &AppHandle, label: &str) {
+    let Some(state) = app.try_state::<PtyState>() else {
+        return;
+    };
+    let closed: Vec<PtySession> = match state.0.lock() {
+        Ok(mut sessions) => {
+            let ids: Vec<u32> = sessions
+                .by_id
+                .iter()
+                .filter(|(_, session)| session.window == label)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.into_iter()
+                .filter_map(|id| sessions.by_id.remove(&id))
+                .collect()
+        }
+        Err(_) => return,
+    };
+    for session in closed {
+        kill(session);
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::broadcast::repo_key;
+use crate::menu;
+use crate::portable;
+
+const RECENT_FILE: &str = "recent-repos.json";
+const MAX_RECENT_REPOS: usize = 10;
+
+fn recent_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::data_dir(app)
+        .ok()
+        .map(|dir| dir.join(RECENT_FILE))
+}
+
+/// Recently opened repositories, most recent first
+pub fn list(app: &AppHandle) -> Vec<String> {
+    recent_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Move a repository to the top of the recent list and refresh the menu
+pub fn add(app: &AppHandle, repo_path: &str) {
+    let key = repo_key(repo_path);
+    let mut repos = list(app);
+    repos.retain(|repo| repo_key(repo) != key);
+    repos.insert(0, repo_path.to_string());
+    repos.truncate(MAX_RECENT_REPOS);
+
+    let Some(path) = recent_path(app) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match serde_json::to_string_pretty(&repos) {
+        Ok(contents) => {
+            if let Err(e) = std::fs::write(&path, contents) {
+                log_error!("Failed to save recent repos: {}", e);
+            }
+        }
+        Err(e) => log_error!("Failed to serialize recent repos: {}", e),
+    }
+
+    if let Err(e) = menu::install(app) {
+        log_error!("Failed to refresh menu: {}", e);
+    }
+}
+
+/// Get recently opened repositories, most recent first
+#[tauri::command]
+pub fn get_recent_repos(app: AppHandle) -> Vec<String> {
+    list(&app)
+}
@@ -0,0 +1,351 @@
+//! Repository configuration committed as `.opentui.toml` at the top of the
+//! working tree, shared by everyone working on the repository:
+//!
+//! ```toml
+//! default_remote = "upstream"
+//! fetch_interval_secs = 300
+//! editor = "code --goto {file}:{line}"
+//!
+//! [sidecar]
+//! args = ["--log-level", "debug"]
+//! env = { GIT_TRACE = "1" }
+//!
+//! [watcher]
+//! exclude = ["target/", "*.log"]
+//! ```
+//!
+//! User settings win over it. As the file arrives with a clone, the parts
+//! that run programs, `sidecar` and `editor`, only apply once the user trusts
+//! the file, and trust lapses whenever its contents change.
+
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::error::CommandResult;
+use crate::profiles::{self, RepoProfile};
+use crate::settings::{self, Settings};
+use crate::sidecar::ServerState;
+use crate::{broadcast, control, git};
+
+pub const CONFIG_FILE: &str = ".opentui.toml";
+
+/// Contents of `.opentui.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Extra sidecar arguments and environment; needs trust
+    pub sidecar: RepoProfile,
+    pub watcher: WatcherConfig,
+    /// Remote the UI offers first, e.g. for pulls
+    pub default_remote: Option<String>,
+    /// Interval of scheduled fetches; 0 turns them off for the repository
+    pub fetch_interval_secs: Option<u64>,
+    /// Editor command, as in the `editorCommand` setting; needs trust
+    pub editor: Option<String>,
+}
+
+impl RepoConfig {
+    /// Keys that run programs and so only apply to a trusted file
+    fn privileged_keys(&self) -> Vec<&'static str> {
+        let mut keys = Vec::new();
+        if !self.sidecar.is_empty() {
+            keys.push("sidecar");
+        }
+        if self.editor.is_some() {
+            keys.push("editor");
+        }
+        keys
+    }
+
+    /// Drop the keys that need trust
+    fn without_privileged(self) -> Self {
+        Self {
+            sidecar: RepoProfile::default(),
+            editor: None,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatcherConfig {
+    /// Gitignore-style patterns of working tree paths not to report
+    pub exclude: Vec<String>,
+}
+
+/// The repository file as read, with its content id for trust
+struct Loaded {
+    config: RepoConfig,
+    hash: String,
+}
+
+/// Where a setting in effect came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Repo,
+    Settings,
+    Default,
+}
+
+/// Repository and global configuration merged, for debugging
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    repo_path: String,
+    /// Path of `.opentui.toml`, if the repository has a valid one
+    file: Option<String>,
+    trusted: bool,
+    /// Keys of the file that aren't applied because it isn't trusted
+    untrusted_keys: Vec<&'static str>,
+    /// Sidecar arguments and environment from the file, the git directory
+    /// profile and settings together
+    sidecar: RepoProfile,
+    watcher_exclude: Vec<String>,
+    default_remote: Option<String>,
+    /// `None` when scheduled fetches are off for the repository
+    fetch_interval_secs: Option<u64>,
+    editor_command: Option<String>,
+    sources: BTreeMap<&'static str, Source>,
+}
+
+/// Parse `.opentui.toml`, if the repository has one
+fn parse(contents: &str) -> Result<RepoConfig, String> {
+    toml::from_str(contents).map_err(|e| e.message().to_string())
+}
+
+fn load(repo_path: &str) -> Option<Loaded> {
+    let contents = std::fs::read_to_string(Path::new(repo_path).join(CONFIG_FILE)).ok()?;
+    match parse(&contents) {
+        Ok(config) => Some(Loaded {
+            config,
+            // The blob id, as git would store it
+            hash: Oid::hash_object(ObjectType::Blob, contents.as_bytes())
+                .map(|oid| oid.to_string())
+                .unwrap_or_default(),
+        }),
+        Err(e) => {
+            log_error!("Ignoring invalid {} in {}: {}", CONFIG_FILE, repo_path, e);
+            None
+        }
+    }
+}
+
+fn is_trusted(settings: &Settings, repo_path: &str, hash: &str) -> bool {
+    let key = broadcast::repo_key(repo_path);
+    settings
+        .trusted_repo_configs
+        .iter()
+        .any(|(path, trusted)| broadcast::repo_key(path) == key && trusted == hash)
+}
+
+/// The repository's configuration, without the parts needing trust unless
+/// the user trusts this version of the file
+pub fn for_repo(app: &AppHandle, repo_path: &str) -> RepoConfig {
+    let Some(loaded) = load(repo_path) else {
+        return RepoConfig::default();
+    };
+    if is_trusted(&settings::current(app), repo_path, &loaded.hash) {
+        return loaded.config;
+    }
+    let untrusted = loaded.config.privileged_keys();
+    if !untrusted.is_empty() {
+        log_info!(
+            "Not applying {} from {} in {} until it is trusted",
+            untrusted.join(", "),
+            CONFIG_FILE,
+            repo_path
+        );
+    }
+    loaded.config.without_privileged()
+}
+
+/// Patterns of working tree paths the watcher skips. These can't do harm,
+/// so they apply without trust.
+pub fn watcher_excludes(repo_path: &str) -> Vec<String> {
+    load(repo_path)
+        .map(|loaded| loaded.config.watcher.exclude)
+        .unwrap_or_default()
+}
+
+/// Interval of scheduled fetches the repository asks for, which like the
+/// excludes applies without trust
+pub fn fetch_interval(repo_path: &str) -> Option<u64> {
+    load(repo_path).and_then(|loaded| loaded.config.fetch_interval_secs)
+}
+
+/// The user's value if set, else the repository's, else the default
+fn pick<T>(user: Option<T>, repo: Option<T>, default: Option<T>) -> (Option<T>, Source) {
+    match (user, repo) {
+        (Some(user), _) => (Some(user), Source::Settings),
+        (None, Some(repo)) => (Some(repo), Source::Repo),
+        (None, None) => (default, Source::Default),
+    }
+}
+
+fn effective(app: &AppHandle, repo_path: &str) -> EffectiveConfig {
+    let settings = settings::current(app);
+    let loaded = load(repo_path);
+    let trusted = loaded
+        .as_ref()
+        .is_some_and(|loaded| is_trusted(&settings, repo_path, &loaded.hash));
+    let untrusted_keys = match &loaded {
+        Some(loaded) if !trusted => loaded.config.privileged_keys(),
+        _ => Vec::new(),
+    };
+    let file = loaded.as_ref().map(|_| {
+        Path::new(repo_path)
+            .join(CONFIG_FILE)
+            .to_string_lossy()
+            .to_string()
+    });
+    let repo = loaded.map_or_else(RepoConfig::default, |loaded| {
+        if trusted {
+            loaded.config
+        } else {
+            loaded.config.without_privileged()
+        }
+    });
+
+    let mut sources = BTreeMap::new();
+    let key = broadcast::repo_key(repo_path);
+    let (fetch_interval_secs, source) = pick(
+        settings
+            .auto_fetch
+            .repo_intervals
+            .iter()
+            .find(|(path, _)| broadcast::repo_key(path) == key)
+            .map(|(_, secs)| *secs),
+        repo.fetch_interval_secs,
+        Some(settings.auto_fetch.interval_secs),
+    );
+    sources.insert("fetchIntervalSecs", source);
+    let (editor_command, source) = pick(
+        settings
+            .editor_command
+            .clone()
+            .filter(|command| !command.trim().is_empty()),
+        repo.editor.clone(),
+        None,
+    );
+    sources.insert("editorCommand", source);
+    let (default_remote, source) = pick(None, repo.default_remote.clone(), None);
+    sources.insert("defaultRemote", source);
+
+    EffectiveConfig {
+        repo_path: repo_path.to_string(),
+        file,
+        trusted,
+        untrusted_keys,
+        sidecar: profiles::for_repo(app, repo_path),
+        watcher_exclude: repo.watcher.exclude,
+        default_remote,
+        fetch_interval_secs: fetch_interval_secs.filter(|secs| *secs > 0),
+        editor_command,
+        sources,
+    }
+}
+
+/// Show how the window repository's `.opentui.toml` and the global settings
+/// combine
+#[tauri::command]
+pub fn get_effective_config(
+    app: AppHandle,
+    window: WebviewWindow,
+) -> CommandResult<EffectiveConfig> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    Ok(effective(&app, &repo_path))
+}
+
+/// Trust the current contents of the window repository's `.opentui.toml`,
+/// letting its `sidecar` and `editor` keys apply, or withdraw trust. Sidecar
+/// changes apply when it is next started.
+#[tauri::command]
+pub fn trust_repo_config(
+    app: AppHandle,
+    window: WebviewWindow,
+    trusted: bool,
+) -> CommandResult<EffectiveConfig> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let hash = load(&repo_path).map(|loaded| loaded.hash);
+    let key = broadcast::repo_key(&repo_path);
+    settings::update(&app, |settings| {
+        settings
+            .trusted_repo_configs
+            .retain(|path, _| broadcast::repo_key(path) != key);
+        if let Some(hash) = hash.filter(|_| trusted) {
+            settings
+                .trusted_repo_configs
+                .insert(repo_path.clone(), hash);
+        }
+    })?;
+    log_info!(
+        "{} {} in {}",
+        if trusted {
+            "Trusted"
+        } else {
+            "Stopped trusting"
+        },
+        CONFIG_FILE,
+        repo_path
+    );
+    if let Some(sidecar) = app.state::<ServerState>().for_repo(&repo_path) {
+        let message = crate::sidecar::current_config(&app, &sidecar);
+        if let Err(e) = control::send_to(&sidecar, &message) {
+            log_error!("Failed to reload config of {}: {}", repo_path, e);
+        }
+    }
+    Ok(effective(&app, &repo_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_documented_keys() {
+        let config = parse(
+            r#"
+            default_remote = "upstream"
+            fetch_interval_secs = 300
+            editor = "code --goto {file}:{line}"
+
+            [sidecar]
+            args = ["--log-level", "debug"]
+            env = { GIT_TRACE = "1" }
+
+            [watcher]
+            exclude = ["target/", "*.log"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.default_remote.as_deref(), Some("upstream"));
+        assert_eq!(config.fetch_interval_secs, Some(300));
+        assert_eq!(config.sidecar.args, ["--log-level", "debug"]);
+        assert!(config.sidecar.check_args().is_ok());
+        assert_eq!(config.sidecar.env["GIT_TRACE"], "1");
+        assert_eq!(config.watcher.exclude, ["target/", "*.log"]);
+        assert_eq!(config.privileged_keys(), ["sidecar", "editor"]);
+
+        let config = config.without_privileged();
+        assert!(config.privileged_keys().is_empty());
+        assert_eq!(config.default_remote.as_deref(), Some("upstream"));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(parse("fetch_interval = 5").is_err());
+        assert!(parse("[watcher]\nignore = []").is_err());
+    }
+
+    #[test]
+    fn prefers_user_settings() {
+        assert_eq!(pick(Some(1), Some(2), Some(3)), (Some(1), Source::Settings));
+        assert_eq!(pick(None, Some(2), Some(3)), (Some(2), Source::Repo));
+        assert_eq!(pick(None::<u32>, None, Some(3)), (Some(3), Source::Default));
+    }
+}
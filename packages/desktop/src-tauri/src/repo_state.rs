@@ -0,0 +1,192 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::CommandResult;
+use crate::git;
+
+/// What HEAD points at
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HeadState {
+    Branch { name: String },
+    Detached { oid: String },
+}
+
+/// A multi-step operation left in progress in the repository
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum Operation {
+    Merge {
+        heads: Vec<String>,
+    },
+    Rebase {
+        interactive: bool,
+        step: Option<u32>,
+        total: Option<u32>,
+        head_name: Option<String>,
+        onto: Option<String>,
+    },
+    /// `git am` applying patches from a mailbox
+    ApplyMailbox {
+        step: Option<u32>,
+        total: Option<u32>,
+    },
+    CherryPick {
+        head: String,
+        /// Commits left to pick, including the current one
+        remaining: usize,
+    },
+    Revert {
+        head: String,
+        remaining: usize,
+    },
+    Bisect {
+        /// Branch or commit bisect was started from
+        start: Option<String>,
+        good: usize,
+        bad: usize,
+    },
+}
+
+impl Operation {
+    /// Commands that resolve the operation, e.g. `git rebase --continue`
+    fn actions(&self) -> Vec<&'static str> {
+        match self {
+            Operation::Merge { .. } => vec!["continue", "abort"],
+            Operation::Bisect { .. } => vec!["reset"],
+            _ => vec!["continue", "skip", "abort"],
+        }
+    }
+}
+
+/// HEAD and in-progress operation state of a repository
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoState {
+    head: HeadState,
+    operation: Option<Operation>,
+    actions: Vec<&'static str>,
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|contents| contents.trim().to_string())
+        .filter(|contents| !contents.is_empty())
+}
+
+fn read_number(path: &Path) -> Option<u32> {
+    read_trimmed(path)?.parse().ok()
+}
+
+/// Number of pending entries in a sequencer todo file
+fn count_todo(path: &Path) -> usize {
+    std::fs::read_to_string(path)
+        .map(|contents| {
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .count()
+        })
+        .unwrap_or_default()
+}
+
+fn head_state(git_dir: &Path) -> HeadState {
+    let head = read_trimmed(&git_dir.join("HEAD")).unwrap_or_default();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => HeadState::Branch {
+            name: reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        },
+        None => HeadState::Detached { oid: head },
+    }
+}
+
+/// Operation in progress in a git directory, read from the files git keeps
+/// while it runs
+pub fn operation(git_dir: &Path) -> Option<Operation> {
+    let rebase_merge = git_dir.join("rebase-merge");
+    if rebase_merge.is_dir() {
+        return Some(Operation::Rebase {
+            interactive: rebase_merge.join("interactive").exists(),
+            step: read_number(&rebase_merge.join("msgnum")),
+            total: read_number(&rebase_merge.join("end")),
+            head_name: read_trimmed(&rebase_merge.join("head-name")),
+            onto: read_trimmed(&rebase_merge.join("onto")),
+        });
+    }
+
+    let rebase_apply = git_dir.join("rebase-apply");
+    if rebase_apply.is_dir() {
+        let step = read_number(&rebase_apply.join("next"));
+        let total = read_number(&rebase_apply.join("last"));
+        if rebase_apply.join("applying").exists() {
+            return Some(Operation::ApplyMailbox { step, total });
+        }
+        return Some(Operation::Rebase {
+            interactive: false,
+            step,
+            total,
+            head_name: read_trimmed(&rebase_apply.join("head-name")),
+            onto: read_trimmed(&rebase_apply.join("onto")),
+        });
+    }
+
+    if let Some(merge_head) = read_trimmed(&git_dir.join("MERGE_HEAD")) {
+        return Some(Operation::Merge {
+            heads: merge_head.lines().map(str::to_string).collect(),
+        });
+    }
+
+    // A single pick has no sequencer directory, so it counts as one remaining
+    let remaining = count_todo(&git_dir.join("sequencer").join("todo")).max(1);
+    if let Some(head) = read_trimmed(&git_dir.join("CHERRY_PICK_HEAD")) {
+        return Some(Operation::CherryPick { head, remaining });
+    }
+    if let Some(head) = read_trimmed(&git_dir.join("REVERT_HEAD")) {
+        return Some(Operation::Revert { head, remaining });
+    }
+
+    if git_dir.join("BISECT_LOG").exists() {
+        let log = std::fs::read_to_string(git_dir.join("BISECT_LOG")).unwrap_or_default();
+        let count = |term: &str| {
+            log.lines()
+                .filter(|line| line.starts_with(&format!("git bisect {}", term)))
+                .count()
+        };
+        return Some(Operation::Bisect {
+            start: read_trimmed(&git_dir.join("BISECT_START")),
+            good: count("good"),
+            bad: count("bad"),
+        });
+    }
+
+    None
+}
+
+/// Report detached HEAD and any merge, rebase, cherry-pick, revert or bisect
+/// in progress in the window's repository
+#[tauri::command]
+pub async fn get_repo_state(app: AppHandle, window: WebviewWindow) -> CommandResult<RepoState> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let output = git::run(&repo_path, &["rev-parse", "--absolute-git-dir"]).await?;
+    let git_dir = PathBuf::from(String::from_utf8_lossy(&output).trim());
+
+    let operation = operation(&git_dir);
+    Ok(RepoState {
+        head: head_state(&git_dir),
+        actions: operation
+            .as_ref()
+            .map(Operation::actions)
+            .unwrap_or_default(),
+        operation,
+    })
+}
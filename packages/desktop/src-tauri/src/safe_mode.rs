@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::{portable, shutdown};
+
+const STARTUP_ATTEMPTS_FILE: &str = "startup-attempts";
+/// Consecutive unfinished startups before safe mode kicks in automatically
+const MAX_FAILED_STARTUPS: u32 = 3;
+/// How long the app must stay up before a startup counts as successful
+const STARTUP_STABLE_SECS: u64 = 10;
+
+/// Whether the app is running in safe mode: default settings, no background
+/// monitors, only the bare sidecar and window
+#[derive(Clone, Copy)]
+pub struct SafeMode(pub bool);
+
+/// Path of the file counting startups that never reached a stable state
+fn attempts_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::data_dir(app)
+        .ok()
+        .map(|dir| dir.join(STARTUP_ATTEMPTS_FILE))
+}
+
+fn read_attempts(app: &AppHandle) -> u32 {
+    attempts_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| contents.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+fn write_attempts(app: &AppHandle, attempts: u32) {
+    let Some(path) = attempts_path(app) else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if let Err(e) = std::fs::write(&path, attempts.to_string()) {
+        log_error!("Failed to record startup attempt: {}", e);
+    }
+}
+
+/// Whether safe mode was requested via `--safe-mode` or `OPENTUI_SAFE_MODE`
+fn requested() -> bool {
+    std::env::args().any(|arg| arg == "--safe-mode")
+        || std::env::var("OPENTUI_SAFE_MODE").is_ok_and(|v| v == "1" || v == "true")
+}
+
+/// Record this startup attempt and decide whether to run in safe mode
+pub fn detect(app: &AppHandle) -> SafeMode {
+    let attempts = read_attempts(app);
+    write_attempts(app, attempts + 1);
+
+    if requested() {
+        log_info!("Safe mode requested");
+        return SafeMode(true);
+    }
+
+    if attempts >= MAX_FAILED_STARTUPS {
+        log_info!(
+            "{} previous startups did not complete, starting in safe mode",
+            attempts
+        );
+        return SafeMode(true);
+    }
+
+    SafeMode(false)
+}
+
+/// Reset the failed-startup counter once the app has stayed up for a while
+pub fn mark_started(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(STARTUP_STABLE_SECS)).await;
+        write_attempts(&app, 0);
+    });
+}
+
+/// Reset the failed-startup counter when the app exits cleanly, which a quit
+/// within the first seconds otherwise leaves counted as a failed startup.
+/// Exits for startup failures keep it, since those are what it counts.
+pub fn mark_exited(app: &AppHandle) {
+    let failed = shutdown::reason(app).is_some_and(|reason| reason.code() != 0);
+    if !failed {
+        write_attempts(app, 0);
+    }
+}
+
+/// Whether the app is running in safe mode
+pub fn is_active(app: &AppHandle) -> bool {
+    app.try_state::<SafeMode>().is_some_and(|mode| mode.0)
+}
+
+/// Whether the app is running in safe mode
+#[tauri::command]
+pub fn is_safe_mode(app: AppHandle) -> bool {
+    is_active(&app)
+}
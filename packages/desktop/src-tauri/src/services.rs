@@ -0,0 +1,586 @@
+//! Optional helper processes run alongside the sidecars, e.g. an LFS proxy or
+//! a commit message helper, declared in `services.json` in the config
+//! directory:
+//!
+//! ```json
+//! {
+//!   "lfs-proxy": {
+//!     "command": "lfs-proxy --listen 127.0.0.1:{port}",
+//!     "listen": "port"
+//!   },
+//!   "commit-helper": {
+//!     "command": "commit-helper --socket {socket}",
+//!     "listen": "socket",
+//!     "env": { "MODEL": "small" },
+//!     "restart": "always"
+//!   }
+//! }
+//! ```
+//!
+//! Each gets its own port or socket, its output goes to the log window under
+//! its name, and it is restarted with a backoff when it dies, the way the
+//! sidecars are supervised, so the shell can be extended without changing
+//! the server.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::logs::{self, LogStream, SHELL_PORT};
+use crate::{cleanup, environment, launch, portable, ports, proxy, telemetry};
+
+const MANIFEST_FILE: &str = "services.json";
+/// Folder in the data directory holding the services' sockets
+const SOCKET_DIR: &str = "services";
+/// A service up for this long before dying counts as having started fine
+const STABLE_SECS: u64 = 30;
+/// Crashes in a row, each before `STABLE_SECS`, after which a service is
+/// left stopped
+const MAX_FAILURES: u32 = 5;
+/// Longest wait before restarting a crashed service
+const MAX_BACKOFF_SECS: u64 = 30;
+
+/// How a service is reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Listen {
+    /// Nothing to connect to, e.g. a service only watching files
+    #[default]
+    None,
+    /// A free port, passed as `{port}`
+    Port,
+    /// A socket path in the data directory, passed as `{socket}`
+    Socket,
+}
+
+/// When a service that exited is started again
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Never,
+    /// Only when it exited with an error or was killed
+    #[default]
+    OnFailure,
+    Always,
+}
+
+/// A service as declared in the manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceSpec {
+    /// Program and arguments, split like a shell would. `{port}` and
+    /// `{socket}` are replaced here and in `env`.
+    pub command: String,
+    pub env: HashMap<String, String>,
+    pub listen: Listen,
+    pub restart: RestartPolicy,
+    pub enabled: bool,
+}
+
+impl Default for ServiceSpec {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            env: HashMap::new(),
+            listen: Listen::None,
+            restart: RestartPolicy::OnFailure,
+            enabled: true,
+        }
+    }
+}
+
+/// Where a service is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceStatus {
+    Running,
+    /// Died and waiting for its backoff to restart
+    Restarting,
+    /// Exited and not to be restarted under its policy
+    Exited,
+    /// Failed to start, or crashed too often in a row
+    Failed,
+    Stopped,
+    Disabled,
+}
+
+struct Service {
+    spec: ServiceSpec,
+    child: Option<CommandChild>,
+    port: Option<u32>,
+    socket: Option<PathBuf>,
+    status: ServiceStatus,
+    exit_code: Option<i32>,
+    started_at: Option<Instant>,
+    /// Times the service was started after the first
+    restarts: usize,
+    /// Crashes in a row, reset once it stays up for `STABLE_SECS`
+    failures: u32,
+    /// Bumped on every start, so an old process's exit isn't taken for the
+    /// current one's
+    generation: u64,
+}
+
+impl Service {
+    fn new(spec: ServiceSpec) -> Self {
+        let status = if spec.enabled {
+            ServiceStatus::Stopped
+        } else {
+            ServiceStatus::Disabled
+        };
+        Self {
+            spec,
+            child: None,
+            port: None,
+            socket: None,
+            status,
+            exit_code: None,
+            started_at: None,
+            restarts: 0,
+            failures: 0,
+            generation: 0,
+        }
+    }
+
+    fn kill(&mut self) {
+        if let Some(child) = self.child.take() {
+            let _ = child.kill();
+        }
+    }
+}
+
+/// The helper services by name
+#[derive(Clone, Default)]
+pub struct ServiceState(Arc<Mutex<BTreeMap<String, Service>>>);
+
+/// A service as listed by `list_services`. Also the payload of the
+/// `service-status` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceInfo {
+    name: String,
+    command: String,
+    status: ServiceStatus,
+    pid: Option<u32>,
+    port: Option<u32>,
+    socket: Option<String>,
+    uptime_ms: Option<u64>,
+    restarts: usize,
+    exit_code: Option<i32>,
+}
+
+fn info(name: &str, service: &Service) -> ServiceInfo {
+    let running = service.status == ServiceStatus::Running;
+    ServiceInfo {
+        name: name.to_string(),
+        command: service.spec.command.clone(),
+        status: service.status,
+        pid: service.child.as_ref().map(CommandChild::pid),
+        port: service.port,
+        socket: service
+            .socket
+            .as_ref()
+            .map(|socket| socket.to_string_lossy().to_string()),
+        uptime_ms: service
+            .started_at
+            .filter(|_| running)
+            .map(|started| started.elapsed().as_millis() as u64),
+        restarts: service.restarts,
+        exit_code: service.exit_code,
+    }
+}
+
+fn parse_manifest(contents: &str) -> Result<BTreeMap<String, ServiceSpec>, String> {
+    serde_json::from_str(contents).map_err(|e| e.to_string())
+}
+
+/// Services declared in the manifest; none if it is missing or invalid
+fn load_manifest(app: &AppHandle) -> BTreeMap<String, ServiceSpec> {
+    let Ok(path) = portable::config_dir(app).map(|dir| dir.join(MANIFEST_FILE)) else {
+        return BTreeMap::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return BTreeMap::new();
+    };
+    parse_manifest(&contents).unwrap_or_else(|e| {
+        log_error!("Ignoring invalid {}: {}", path.display(), e);
+        BTreeMap::new()
+    })
+}
+
+/// Replace `{port}` and `{socket}` in a word of the command or a variable
+fn expand(word: &str, port: Option<u32>, socket: Option<&PathBuf>) -> String {
+    let mut word = word.to_string();
+    if let Some(port) = port {
+        word = word.replace("{port}", &port.to_string());
+    }
+    if let Some(socket) = socket {
+        word = word.replace("{socket}", &socket.to_string_lossy());
+    }
+    word
+}
+
+/// Whether a service that exited with `code` is started again. Killed
+/// processes have no code.
+fn should_restart(policy: RestartPolicy, code: Option<i32>) -> bool {
+    match policy {
+        RestartPolicy::Never => false,
+        RestartPolicy::OnFailure => code != Some(0),
+        RestartPolicy::Always => true,
+    }
+}
+
+/// Wait before the restart after `failures` crashes in a row: 1s, doubling
+fn backoff(failures: u32) -> Duration {
+    let secs = 1u64 << failures.saturating_sub(1).min(6);
+    Duration::from_secs(secs.min(MAX_BACKOFF_SECS))
+}
+
+fn emit_status(app: &AppHandle, name: &str, service: &Service) {
+    let _ = app.emit("service-status", info(name, service));
+}
+
+/// Spawn a service's process, forwarding its output to the logs
+fn spawn(
+    app: &AppHandle,
+    name: &str,
+    service: &Service,
+    generation: u64,
+) -> Result<CommandChild, String> {
+    let words: Vec<String> = launch::split_words(&service.spec.command)
+        .iter()
+        .map(|word| expand(word, service.port, service.socket.as_ref()))
+        .collect();
+    let (program, args) = words.split_first().ok_or("The command is empty")?;
+
+    let mut env: Vec<(String, String)> =
+        vec![("OPENTUI_SERVICE_NAME".to_string(), name.to_string())];
+    if let Some(port) = service.port {
+        env.push(("OPENTUI_SERVICE_PORT".to_string(), port.to_string()));
+    }
+    if let Some(socket) = &service.socket {
+        env.push((
+            "OPENTUI_SERVICE_SOCKET".to_string(),
+            socket.to_string_lossy().to_string(),
+        ));
+    }
+    env.extend(service.spec.env.iter().map(|(key, value)| {
+        (
+            key.clone(),
+            expand(value, service.port, service.socket.as_ref()),
+        )
+    }));
+
+    let (mut rx, child) = environment::inherit(app, app.shell().command(program))
+        .envs(environment::cached_login_env(app))
+        .envs(proxy::env(app))
+        .envs(environment::extra(app))
+        .envs(env)
+        .args(args)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let (app, name, port) = (
+        app.clone(),
+        name.to_string(),
+        service.port.unwrap_or(SHELL_PORT),
+    );
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    let line = String::from_utf8_lossy(&line);
+                    logs::push_service(&app, &name, port, LogStream::Stdout, &line);
+                }
+                CommandEvent::Stderr(line) => {
+                    let line = String::from_utf8_lossy(&line);
+                    logs::push_service(&app, &name, port, LogStream::Stderr, &line);
+                }
+                CommandEvent::Error(err) => {
+                    log_error!("Service {} error: {}", name, err);
+                }
+                CommandEvent::Terminated(status) => {
+                    exited(&app, &name, generation, status.code).await;
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+    Ok(child)
+}
+
+/// Ports given to services, which new ones must not take
+fn taken_ports(services: &BTreeMap<String, Service>) -> Vec<u32> {
+    services
+        .values()
+        .filter_map(|service| service.port)
+        .collect()
+}
+
+/// Start a service, giving it a port or socket the first time
+fn start_service(app: &AppHandle, name: &str, service: &mut Service, taken: &[u32]) {
+    match service.spec.listen {
+        Listen::Port if service.port.is_none() => match ports::pick(app, None, taken) {
+            Ok(port) => service.port = Some(port),
+            Err(e) => {
+                log_error!("No port for service {}: {}", name, e);
+                service.status = ServiceStatus::Failed;
+                return;
+            }
+        },
+        Listen::Socket if service.socket.is_none() => {
+            let Ok(dir) = portable::data_dir(app).map(|dir| dir.join(SOCKET_DIR)) else {
+                service.status = ServiceStatus::Failed;
+                return;
+            };
+            let _ = std::fs::create_dir_all(&dir);
+            service.socket = Some(dir.join(format!("{}.sock", name)));
+        }
+        _ => {}
+    }
+    // A socket left behind by a crash would stop the service binding it
+    if let Some(socket) = &service.socket {
+        let _ = std::fs::remove_file(socket);
+    }
+
+    service.generation += 1;
+    service.exit_code = None;
+    match spawn(app, name, service, service.generation) {
+        Ok(child) => {
+            log_info!("Started service {} (pid {})", name, child.pid());
+            service.child = Some(child);
+            service.status = ServiceStatus::Running;
+            service.started_at = Some(Instant::now());
+        }
+        Err(e) => {
+            log_error!("Failed to start service {}: {}", name, e);
+            service.status = ServiceStatus::Failed;
+        }
+    }
+    emit_status(app, name, service);
+}
+
+/// Handle a service's process exiting: restart it with a backoff if its
+/// policy says so, unless it keeps crashing
+async fn exited(app: &AppHandle, name: &str, generation: u64, code: Option<i32>) {
+    let delay = {
+        let state = app.state::<ServiceState>();
+        let Ok(mut services) = state.0.lock() else {
+            return;
+        };
+        let Some(service) = services.get_mut(name) else {
+            return;
+        };
+        // Stopped or restarted on purpose
+        if service.generation != generation || service.status != ServiceStatus::Running {
+            return;
+        }
+        service.child = None;
+        service.exit_code = code;
+        log_info!("Service {} exited with code {:?}", name, code);
+        telemetry::record(
+            app,
+            "service-exit",
+            serde_json::json!({ "service": name, "code": code }),
+        );
+
+        let stable = service
+            .started_at
+            .is_some_and(|started| started.elapsed() >= Duration::from_secs(STABLE_SECS));
+        service.failures = if stable { 1 } else { service.failures + 1 };
+
+        let delay = if !should_restart(service.spec.restart, code) {
+            service.status = ServiceStatus::Exited;
+            None
+        } else if service.failures > MAX_FAILURES {
+            log_error!(
+                "Service {} crashed {} times in a row; not restarting it",
+                name,
+                MAX_FAILURES
+            );
+            service.status = ServiceStatus::Failed;
+            None
+        } else {
+            service.status = ServiceStatus::Restarting;
+            Some(backoff(service.failures))
+        };
+        emit_status(app, name, service);
+        delay
+    };
+
+    let Some(delay) = delay else {
+        return;
+    };
+    tokio::time::sleep(delay).await;
+    let state = app.state::<ServiceState>();
+    let Ok(mut services) = state.0.lock() else {
+        return;
+    };
+    let taken = taken_ports(&services);
+    if let Some(service) = services.get_mut(name).filter(|service| {
+        service.generation == generation && service.status == ServiceStatus::Restarting
+    }) {
+        service.restarts += 1;
+        start_service(app, name, service, &taken);
+    }
+}
+
+/// Load the manifest and start the enabled services
+pub fn start(app: &AppHandle) {
+    let manifest = load_manifest(app);
+    if manifest.is_empty() {
+        return;
+    }
+    let state = app.state::<ServiceState>();
+    let Ok(mut services) = state.0.lock() else {
+        return;
+    };
+    cleanup::register(app, "services", stop_all);
+    for (name, spec) in manifest {
+        let taken = taken_ports(&services);
+        let service = services.entry(name.clone()).or_insert(Service::new(spec));
+        if service.status == ServiceStatus::Stopped {
+            start_service(app, &name, service, &taken);
+        }
+    }
+}
+
+/// Stop all services, on the way out
+fn stop_all(app: &AppHandle) {
+    let Some(state) = app.try_state::<ServiceState>() else {
+        return;
+    };
+    let Ok(mut services) = state.0.lock() else {
+        return;
+    };
+    for (name, service) in services.iter_mut() {
+        if service.child.is_some() {
+            service.status = ServiceStatus::Stopped;
+            service.kill();
+            log_info!("Stopped service {}", name);
+        }
+        if let Some(socket) = &service.socket {
+            let _ = std::fs::remove_file(socket);
+        }
+    }
+}
+
+/// The services declared in the manifest and how they are doing
+#[tauri::command]
+pub fn list_services(app: AppHandle) -> Vec<ServiceInfo> {
+    app.state::<ServiceState>()
+        .0
+        .lock()
+        .map(|services| {
+            services
+                .iter()
+                .map(|(name, service)| info(name, service))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Stop a service and start it again, with its declaration re-read from the
+/// manifest. Also starts one that failed, exited or was added since launch.
+#[tauri::command]
+pub fn restart_service(app: AppHandle, name: String) -> CommandResult<ServiceInfo> {
+    let spec = load_manifest(&app).remove(&name).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("No service '{}' in {}", name, MANIFEST_FILE),
+        )
+    })?;
+
+    let state = app.state::<ServiceState>();
+    let mut services = state
+        .0
+        .lock()
+        .map_err(|_| AppError::new(ErrorCode::Internal, "Service state unavailable"))?;
+    let taken = taken_ports(&services);
+    let service = services
+        .entry(name.clone())
+        .or_insert_with(|| Service::new(spec.clone()));
+    // The old process's exit must not trigger a restart of its own
+    service.status = ServiceStatus::Stopped;
+    service.kill();
+    // Listening differently means a new port or socket
+    if service.spec.listen != spec.listen {
+        service.port = None;
+        service.socket = None;
+    }
+    service.spec = spec;
+    service.failures = 0;
+
+    if !service.spec.enabled {
+        service.status = ServiceStatus::Disabled;
+        emit_status(&app, &name, service);
+        return Ok(info(&name, service));
+    }
+    if service.started_at.is_some() {
+        service.restarts += 1;
+    }
+    log_info!("Restarting service {}", name);
+    start_service(&app, &name, service, &taken);
+    Ok(info(&name, service))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_manifest() {
+        let manifest = parse_manifest(
+            r#"{
+                "lfs-proxy": { "command": "lfs-proxy --listen 127.0.0.1:{port}", "listen": "port" },
+                "helper": { "command": "helper", "restart": "always", "enabled": false }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest["lfs-proxy"].listen, Listen::Port);
+        assert_eq!(manifest["lfs-proxy"].restart, RestartPolicy::OnFailure);
+        assert!(manifest["lfs-proxy"].enabled);
+        assert_eq!(manifest["helper"].restart, RestartPolicy::Always);
+        assert!(!manifest["helper"].enabled);
+
+        assert!(parse_manifest(r#"{ "x": { "cmd": "x" } }"#).is_err());
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let socket = PathBuf::from("/tmp/helper.sock");
+        assert_eq!(
+            expand("127.0.0.1:{port}", Some(4100), None),
+            "127.0.0.1:4100"
+        );
+        assert_eq!(
+            expand("--socket={socket}", None, Some(&socket)),
+            "--socket=/tmp/helper.sock"
+        );
+        assert_eq!(expand("{port}", None, None), "{port}");
+    }
+
+    #[test]
+    fn follows_the_restart_policy() {
+        assert!(!should_restart(RestartPolicy::Never, Some(1)));
+        assert!(!should_restart(RestartPolicy::OnFailure, Some(0)));
+        assert!(should_restart(RestartPolicy::OnFailure, Some(1)));
+        assert!(should_restart(RestartPolicy::OnFailure, None));
+        assert!(should_restart(RestartPolicy::Always, Some(0)));
+    }
+
+    #[test]
+    fn backs_off_up_to_a_limit() {
+        assert_eq!(backoff(1), Duration::from_secs(1));
+        assert_eq!(backoff(2), Duration::from_secs(2));
+        assert_eq!(backoff(4), Duration::from_secs(8));
+        assert_eq!(backoff(20), Duration::from_secs(MAX_BACKOFF_SECS));
+    }
+}
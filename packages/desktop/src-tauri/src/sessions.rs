@@ -0,0 +1,234 @@
+//! Named workspace sessions: the repositories open in windows and where those
+//! windows sit, saved on request and automatically on quit, then reopened.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::broadcast::{repo_key, WindowRegistry};
+use crate::compact::WindowGeometry;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::portable;
+use crate::settings;
+use crate::sidecar::ServerState;
+
+const SESSIONS_FILE: &str = "sessions.json";
+/// Session saved when the app quits and reopened on the next launch
+pub const LAST_SESSION: &str = "last";
+
+/// A window in a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionWindow {
+    pub repo_path: String,
+    pub geometry: Option<WindowGeometry>,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// Open repository windows at the time the session was saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Seconds since the Unix epoch
+    pub saved_at: u64,
+    /// Windows in the order they were opened, main window first
+    pub windows: Vec<SessionWindow>,
+}
+
+/// A saved session, as listed by `list_sessions`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    name: String,
+    saved_at: u64,
+    repos: Vec<String>,
+}
+
+/// Path of the sessions file
+pub fn sessions_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::data_dir(app)
+        .ok()
+        .map(|dir| dir.join(SESSIONS_FILE))
+}
+
+/// Saved sessions by name
+fn all(app: &AppHandle) -> BTreeMap<String, Session> {
+    sessions_path(app)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_all(app: &AppHandle, sessions: &BTreeMap<String, Session>) -> Result<(), String> {
+    let path = sessions_path(app).ok_or("Data directory not available")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(sessions)
+        .map_err(|e| format!("Failed to serialize sessions: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write sessions: {}", e))
+}
+
+/// The repository windows open right now
+fn capture(app: &AppHandle) -> Session {
+    let registry = app.state::<WindowRegistry>();
+    let windows = app
+        .state::<ServerState>()
+        .all()
+        .into_iter()
+        .flat_map(|sidecar| {
+            registry
+                .windows_for(&sidecar.repo_path)
+                .into_iter()
+                .map(move |label| (label, sidecar.repo_path.clone()))
+        })
+        .filter_map(|(label, repo_path)| {
+            let window = app.get_webview_window(&label)?;
+            Some(SessionWindow {
+                repo_path,
+                geometry: WindowGeometry::of(&window).ok(),
+                maximized: window.is_maximized().unwrap_or(false),
+            })
+        })
+        .collect();
+
+    Session {
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default(),
+        windows,
+    }
+}
+
+/// Save the open windows under a name, replacing any session with that name
+fn save(app: &AppHandle, name: &str) -> Result<Session, String> {
+    let session = capture(app);
+    let mut sessions = all(app);
+    sessions.insert(name.to_string(), session.clone());
+    write_all(app, &sessions)?;
+    log_info!(
+        "Saved session {} with {} window(s)",
+        name,
+        session.windows.len()
+    );
+    Ok(session)
+}
+
+/// Remember the open windows for the next launch. Called while quitting,
+/// before the windows are destroyed.
+pub fn save_last(app: &AppHandle) {
+    if capture(app).windows.is_empty() {
+        return;
+    }
+    if let Err(e) = save(app, LAST_SESSION) {
+        log_error!("Failed to save session: {}", e);
+    }
+}
+
+/// Open a session's windows and move them where they were. Repositories that
+/// are already open keep their windows, and gain more if the session had more.
+async fn restore(app: &AppHandle, session: &Session) {
+    let mut opened: HashMap<String, usize> = HashMap::new();
+    for entry in &session.windows {
+        if !std::path::Path::new(&entry.repo_path).is_dir() {
+            log_error!("Skipping missing repository {}", entry.repo_path);
+            continue;
+        }
+
+        let key = repo_key(&entry.repo_path);
+        let index = *opened.get(&key).unwrap_or(&0);
+        let existing = app.state::<WindowRegistry>().windows_for(&entry.repo_path);
+        let result = if index == 0 {
+            crate::window::open_repo_in_window(app, &entry.repo_path).await
+        } else if existing.len() <= index {
+            crate::window::open_window_for_repo(app, &entry.repo_path).map_err(AppError::from)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            log_error!("Failed to reopen {}: {}", entry.repo_path, e);
+            continue;
+        }
+        opened.insert(key, index + 1);
+
+        let Some(window) = app
+            .state::<WindowRegistry>()
+            .windows_for(&entry.repo_path)
+            .get(index)
+            .and_then(|label| app.get_webview_window(label))
+        else {
+            continue;
+        };
+        if let Some(geometry) = entry.geometry {
+            let _ = geometry.apply(&window);
+        }
+        if entry.maximized {
+            let _ = window.maximize();
+        }
+    }
+}
+
+/// Reopen the windows from when the app last quit, if the user wants that
+pub async fn restore_last(app: &AppHandle) {
+    if !settings::current(app).restore_session {
+        return;
+    }
+    if let Some(session) = all(app).remove(LAST_SESSION) {
+        restore(app, &session).await;
+    }
+}
+
+/// Save the open repositories and window positions as a named session
+#[tauri::command]
+pub fn save_session(app: AppHandle, name: String) -> CommandResult<Session> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            "Session name cannot be empty",
+        ));
+    }
+    Ok(save(&app, name)?)
+}
+
+/// Open the windows of a saved session, alongside any already open
+#[tauri::command]
+pub async fn load_session(app: AppHandle, name: String) -> CommandResult<()> {
+    let session = all(&app).remove(&name).ok_or_else(|| {
+        AppError::new(ErrorCode::NotFound, format!("No session named {}", name))
+            .with_metadata(serde_json::json!({ "name": name }))
+    })?;
+    restore(&app, &session).await;
+    Ok(())
+}
+
+/// Saved sessions, most recently saved first
+#[tauri::command]
+pub fn list_sessions(app: AppHandle) -> Vec<SessionSummary> {
+    let mut summaries: Vec<SessionSummary> = all(&app)
+        .into_iter()
+        .map(|(name, session)| {
+            let mut repos: Vec<String> = Vec::new();
+            for window in session.windows {
+                if !repos
+                    .iter()
+                    .any(|repo| repo_key(repo) == repo_key(&window.repo_path))
+                {
+                    repos.push(window.repo_path);
+                }
+            }
+            SessionSummary {
+                name,
+                saved_at: session.saved_at,
+                repos,
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+    summaries
+}
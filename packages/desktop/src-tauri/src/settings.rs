@@ -0,0 +1,422 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::accelerators;
+use crate::auto_fetch::AutoFetchSettings;
+use crate::compact::WindowGeometry;
+use crate::dev_frontend;
+use crate::display;
+use crate::environment::EnvironmentSettings;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::hotkey;
+use crate::i18n;
+use crate::large_repo::LargeRepoSettings;
+use crate::logs::{self, LogLevel, LogSettings};
+use crate::loopback::AddressFamily;
+use crate::navigation;
+use crate::pairing::PairedDevice;
+use crate::portable;
+use crate::ports::PortRange;
+use crate::profiles::RepoProfile;
+use crate::proxy::ProxySettings;
+use crate::safe_mode;
+use crate::standby;
+use crate::theme::{self, ThemePreference};
+use crate::timeout::CommandTimeouts;
+use crate::window::WindowSettings;
+
+const SETTINGS_FILE: &str = "settings.json";
+/// Label of the settings window
+const WINDOW_LABEL: &str = "settings";
+
+/// What closing the last window does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CloseBehavior {
+    /// Quit the app and stop the sidecar
+    #[default]
+    Quit,
+    /// Hide the window and keep the sidecar alive in the tray
+    Tray,
+    /// Close the window but keep the sidecar fetching in the background
+    Background,
+}
+
+/// User settings persisted as JSON in the app config directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Settings {
+    /// Ask the sidecar to drop its caches when the OS reports memory pressure
+    pub drop_sidecar_caches_on_memory_pressure: bool,
+    /// What closing the last window does
+    pub on_close: CloseBehavior,
+    /// Interval between fetches while running in the background
+    pub background_fetch_interval_secs: u64,
+    /// Zoom factor applied to windows on each monitor, keyed by monitor name
+    pub monitor_zoom: HashMap<String, f64>,
+    /// Global shortcut that shows or hides the main window; `None` disables it
+    pub toggle_window_shortcut: Option<String>,
+    /// Global shortcut that shows the repository switcher; `None` disables it
+    pub switcher_shortcut: Option<String>,
+    /// User keybindings for webview actions, mapping action to accelerator
+    pub keybindings: HashMap<String, String>,
+    /// Number of pre-warmed sidecars kept ready for opening repositories
+    pub standby_sidecars: usize,
+    /// Keep the dock/taskbar badge showing the active repo's changed file count
+    pub auto_badge: bool,
+    /// Timeouts applied to commands, by class
+    pub command_timeouts: CommandTimeouts,
+    /// Command for opening files in an editor, with `{file}` and `{line}` placeholders
+    pub editor_command: Option<String>,
+    /// Command for opening a terminal, with a `{dir}` placeholder
+    pub terminal_command: Option<String>,
+    /// Command for comparing files, with git's `$LOCAL`, `$REMOTE`, `$BASE` and
+    /// `$MERGED` placeholders; git's `diff.tool` is used if unset
+    pub diff_tool_command: Option<String>,
+    /// Command for resolving conflicts, with the same placeholders; git's
+    /// `merge.tool` is used if unset
+    pub merge_tool_command: Option<String>,
+    /// Proxy for the sidecar's network access
+    pub proxy: ProxySettings,
+    /// Extra and filtered environment variables for the sidecar
+    pub environment: EnvironmentSettings,
+    /// Extra sidecar arguments and environment, keyed by repository path
+    pub repo_profiles: HashMap<String, RepoProfile>,
+    /// Light or dark theme, or follow the OS
+    pub theme: ThemePreference,
+    /// Draw the titlebar in the UI instead of using native window decorations.
+    /// Applies to windows opened after the change.
+    pub custom_titlebar: bool,
+    /// Zoom set from the View menu, keyed by repository path or window label
+    pub window_zoom: HashMap<String, f64>,
+    /// Windows in compact mode and the geometry to restore them to, keyed by
+    /// repository path or window label
+    pub compact_windows: HashMap<String, WindowGeometry>,
+    /// Record app events to a local journal for troubleshooting. Nothing is
+    /// sent anywhere; the journal is only shared by exporting it.
+    pub telemetry: bool,
+    /// How much sidecar output is kept in memory and on disk
+    pub logs: LogSettings,
+    /// Level the sidecars log at; their own default, `info`, if unset
+    pub log_level: Option<LogLevel>,
+    /// Reopen the repository windows from the last session on launch
+    pub restore_session: bool,
+    /// Let sidecars listen on all interfaces instead of loopback only.
+    /// Applies to sidecars started after the change.
+    pub expose_on_lan: bool,
+    /// Whether sidecars listen on IPv4 or IPv6. Applies to sidecars started
+    /// after the change.
+    pub server_address: AddressFamily,
+    /// Serve the UI's connection to the sidecars over HTTPS with a self-signed
+    /// certificate. Applies after restarting the app.
+    pub tls: bool,
+    /// Let devices on the LAN pair with a code shown in the app and use the
+    /// UI as a second screen. Sidecars then listen on all interfaces.
+    pub lan_pairing: bool,
+    /// Devices that redeemed a pairing code, with their tokens. Managed by
+    /// the shell and kept from the webviews, see `Settings::for_ui`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paired_devices: Vec<PairedDevice>,
+    /// Ids of paired devices whose tokens are no longer accepted
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub revoked_devices: Vec<String>,
+    /// Ports sidecars are started on; any free port if unset.
+    /// `OPENTUI_PORT_RANGE` takes precedence.
+    pub port_range: Option<PortRange>,
+    /// Give each repository the same port within `port_range` on every launch
+    pub stable_ports: bool,
+    /// Command running the server under a separately installed runtime, e.g.
+    /// `/opt/bun/bin/bun /opt/opentui/server.js`, tried when launching through
+    /// the shell and directly both fail
+    pub fallback_runtime: Option<String>,
+    /// Pause watchers and slow down periodic checks while the windows are
+    /// hidden or the OS is saving battery
+    pub power_saver: bool,
+    /// How long the windows must stay hidden before saving power
+    pub power_saver_delay_secs: u64,
+    /// Also ask the sidecars to cut down their own background work
+    pub idle_sidecars_when_saving: bool,
+    /// Restart a sidecar whose resident memory stays above this many MB;
+    /// no limit if unset
+    pub sidecar_memory_limit_mb: Option<u64>,
+    /// Consecutive samples over the limit before restarting
+    pub sidecar_memory_limit_samples: u32,
+    /// Size and startup behavior of repository windows
+    pub window: WindowSettings,
+    /// Whether the startup check has shown its warnings; errors are shown on
+    /// every launch
+    pub doctor_warnings_shown: bool,
+    /// Language of menus and dialogs, e.g. `de`; follows the OS if unset
+    pub locale: Option<String>,
+    /// Repository opened when the app is launched without one
+    pub default_repo: Option<String>,
+    /// Hosts whose links open in the browser without asking, e.g.
+    /// `github.com` or `*.atlassian.net` for all its subdomains
+    pub external_link_hosts: Vec<String>,
+    /// Fetching the remotes of open repositories on a schedule
+    pub auto_fetch: AutoFetchSettings,
+    /// Notify when a merge, rebase, cherry-pick or revert is started outside
+    /// the app while it is in the background
+    pub notify_repo_state: bool,
+    /// Offer to stash uncommitted changes when a window is closed
+    pub stash_on_close: bool,
+    /// Repositories listed first in the switcher
+    pub bookmarked_repos: Vec<String>,
+    /// Repositories whose `.opentui.toml` may set the sidecar and editor,
+    /// mapped to the blob id of the trusted contents. Managed by the shell.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub trusted_repo_configs: HashMap<String, String>,
+    /// When a repository counts as large and gets lighter treatment
+    pub large_repo: LargeRepoSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            drop_sidecar_caches_on_memory_pressure: true,
+            on_close: CloseBehavior::default(),
+            background_fetch_interval_secs: 300,
+            monitor_zoom: HashMap::new(),
+            toggle_window_shortcut: Some(hotkey::DEFAULT_TOGGLE_SHORTCUT.to_string()),
+            switcher_shortcut: Some(hotkey::DEFAULT_SWITCHER_SHORTCUT.to_string()),
+            keybindings: HashMap::new(),
+            standby_sidecars: 1,
+            auto_badge: false,
+            command_timeouts: CommandTimeouts::default(),
+            editor_command: None,
+            terminal_command: None,
+            diff_tool_command: None,
+            merge_tool_command: None,
+            proxy: ProxySettings::default(),
+            environment: EnvironmentSettings::default(),
+            repo_profiles: HashMap::new(),
+            theme: ThemePreference::default(),
+            custom_titlebar: false,
+            window_zoom: HashMap::new(),
+            compact_windows: HashMap::new(),
+            telemetry: false,
+            logs: LogSettings::default(),
+            log_level: None,
+            restore_session: true,
+            expose_on_lan: false,
+            server_address: AddressFamily::default(),
+            tls: false,
+            lan_pairing: false,
+            paired_devices: Vec::new(),
+            revoked_devices: Vec::new(),
+            port_range: None,
+            stable_ports: false,
+            fallback_runtime: None,
+            power_saver: true,
+            power_saver_delay_secs: 60,
+            idle_sidecars_when_saving: false,
+            sidecar_memory_limit_mb: None,
+            sidecar_memory_limit_samples: 3,
+            window: WindowSettings::default(),
+            doctor_warnings_shown: false,
+            locale: None,
+            default_repo: None,
+            external_link_hosts: ["github.com", "gitlab.com", "bitbucket.org", "codeberg.org"]
+                .map(str::to_string)
+                .to_vec(),
+            auto_fetch: AutoFetchSettings::default(),
+            notify_repo_state: true,
+            stash_on_close: false,
+            bookmarked_repos: Vec::new(),
+            trusted_repo_configs: HashMap::new(),
+            large_repo: LargeRepoSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Copy for the webviews, without device tokens, revocations and trusted
+    /// configs, which only the shell's own commands may read or change
+    pub fn for_ui(&self) -> Settings {
+        Settings {
+            paired_devices: Vec::new(),
+            revoked_devices: Vec::new(),
+            trusted_repo_configs: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Take the shell-managed fields from `current`, so a stale copy from
+    /// the UI can't drop a revocation or a trust decision, or revert window
+    /// state, bookmarks and the default repository
+    fn keep_managed(&mut self, current: &Settings) {
+        self.paired_devices = current.paired_devices.clone();
+        self.revoked_devices = current.revoked_devices.clone();
+        self.trusted_repo_configs = current.trusted_repo_configs.clone();
+        self.window_zoom = current.window_zoom.clone();
+        self.compact_windows = current.compact_windows.clone();
+        self.window.last_size = current.window.last_size;
+        self.bookmarked_repos = current.bookmarked_repos.clone();
+        self.default_repo = current.default_repo.clone();
+    }
+}
+
+/// State holding the current settings
+#[derive(Clone, Default)]
+pub struct SettingsState(pub Arc<Mutex<Settings>>);
+
+/// Path of the settings file
+pub fn settings_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::config_dir(app)
+        .ok()
+        .map(|dir| dir.join(SETTINGS_FILE))
+}
+
+/// Load settings from disk, falling back to defaults if missing or invalid
+pub fn load(app: &AppHandle) -> Settings {
+    let Some(path) = settings_path(app) else {
+        return Settings::default();
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log_error!("Invalid settings file {}: {}", path.display(), e);
+            Settings::default()
+        }),
+        Err(_) => Settings::default(),
+    }
+}
+
+/// Write settings to disk. In safe mode the defaults are in use, so nothing
+/// is written and changes last until quit, leaving the user's file intact.
+fn save(app: &AppHandle, settings: &Settings) -> Result<(), String> {
+    if safe_mode::is_active(app) {
+        return Ok(());
+    }
+    let path = settings_path(app).ok_or("Config directory not available")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let contents = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write settings: {}", e))
+}
+
+/// Change the current settings in place and persist them
+pub fn update(app: &AppHandle, change: impl FnOnce(&mut Settings)) -> Result<(), String> {
+    let state = app.state::<SettingsState>();
+    let mut guard = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on settings: {}", e))?;
+    change(&mut guard);
+    save(app, &guard)
+}
+
+/// Snapshot of the current settings
+pub fn current(app: &AppHandle) -> Settings {
+    app.try_state::<SettingsState>()
+        .and_then(|state| state.0.lock().ok().map(|s| s.clone()))
+        .unwrap_or_default()
+}
+
+/// Tell every window to live-apply the current settings
+pub fn emit_changed(app: &AppHandle) {
+    let _ = app.emit("settings-changed", current(app).for_ui());
+}
+
+/// Get the current settings, without the shell-managed fields
+#[tauri::command]
+pub fn get_settings(app: AppHandle) -> Settings {
+    current(&app).for_ui()
+}
+
+/// Replace the settings and persist them, or only until quit in safe mode.
+/// Paired devices, revocations and trusted configs are kept as they are.
+#[tauri::command]
+pub fn set_settings(app: AppHandle, mut settings: Settings) -> CommandResult<()> {
+    for (field, accelerator) in [
+        ("toggleWindowShortcut", &settings.toggle_window_shortcut),
+        ("switcherShortcut", &settings.switcher_shortcut),
+    ] {
+        if let Some(accelerator) = accelerator {
+            hotkey::parse(accelerator).map_err(|e| {
+                AppError::new(ErrorCode::InvalidInput, e)
+                    .with_metadata(serde_json::json!({ "field": field }))
+            })?;
+        }
+    }
+    // The menu is built from these, and one that fails to parse loses it all
+    for (id, accelerator) in settings.keybindings.iter().filter(|(_, a)| !a.is_empty()) {
+        hotkey::parse(accelerator).map_err(|e| {
+            AppError::new(ErrorCode::InvalidInput, e)
+                .with_metadata(serde_json::json!({ "field": "keybindings", "id": id }))
+        })?;
+    }
+    accelerators::report_conflicts(&settings);
+    let state = app.state::<SettingsState>();
+    let mut guard = state
+        .0
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock on settings: {}", e))?;
+    // Held across the save, so a pairing or trust change can't slip in between
+    settings.keep_managed(&guard);
+    save(&app, &settings)?;
+    if safe_mode::is_active(&app) {
+        log_info!("Safe mode: settings changed for this session only, not saved");
+    }
+    *guard = settings;
+    drop(guard);
+
+    display::refresh(&app);
+    theme::refresh(&app);
+    logs::refresh(&app);
+    standby::fill(&app);
+    i18n::refresh(&app);
+    // Parsing succeeded above, so failing here means another app holds the shortcut
+    hotkey::register(&app).map_err(|e| {
+        AppError::new(ErrorCode::Conflict, e.message)
+            .with_metadata(serde_json::json!({ "field": e.field }))
+    })?;
+
+    // Running sidecars pick up log level, environment and profile changes
+    crate::sidecar::reload_config(&app);
+
+    // Let every window live-apply the new preferences
+    emit_changed(&app);
+    Ok(())
+}
+
+/// Show the settings window, creating it if needed
+pub fn open_window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(window);
+    }
+
+    WebviewWindow::builder(app, WINDOW_LABEL, dev_frontend::webview_url())
+        .on_navigation(navigation::handler(app))
+        .title(i18n::t(app, "title.settings"))
+        .inner_size(720.0, 560.0)
+        .min_inner_size(560.0, 400.0)
+        .resizable(true)
+        .theme(theme::native(app))
+        .initialization_script(&format!(
+            r#"
+            window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+            window.__OPENTUI__.view = "settings";
+            window.__OPENTUI__.theme = "{}";
+            "#,
+            theme::name(app)
+        ))
+        .build()
+        .map_err(|e| format!("Failed to create settings window: {}", e))
+}
+
+/// Open the settings window, or focus it if already open
+#[tauri::command]
+pub async fn open_settings(app: AppHandle) -> CommandResult<()> {
+    open_window(&app)?;
+    Ok(())
+}
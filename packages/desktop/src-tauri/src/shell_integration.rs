@@ -0,0 +1,339 @@
+use std::path::PathBuf;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+
+const MENU_LABEL: &str = "Open in opentui-git";
+
+/// Executable the file manager should launch. Launching it while the app is
+/// running hands the folder to the existing instance.
+fn app_executable() -> CommandResult<PathBuf> {
+    // AppImages run from a temporary mount; the image itself is the stable path
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    tauri::utils::platform::current_exe().map_err(|e| {
+        AppError::new(
+            ErrorCode::Internal,
+            format!("Failed to locate executable: {}", e),
+        )
+    })
+}
+
+#[cfg(unix)]
+fn home_dir() -> CommandResult<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "Home directory not found"))
+}
+
+fn io_error(context: &str, e: std::io::Error) -> AppError {
+    AppError::new(ErrorCode::Internal, format!("{}: {}", context, e))
+}
+
+/// Finder Quick Action that runs the app with the selected folders
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    fn workflow_dir() -> CommandResult<PathBuf> {
+        Ok(home_dir()?
+            .join("Library/Services")
+            .join(format!("{}.workflow", MENU_LABEL)))
+    }
+
+    fn xml_escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn info_plist() -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.folder</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#,
+            MENU_LABEL
+        )
+    }
+
+    fn document_wflow(executable: &str) -> String {
+        let script = xml_escape(&format!(
+            "for f in \"$@\"; do \"{}\" \"$f\" & done",
+            executable.replace('"', "\\\"")
+        ));
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMProvides</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{}</string>
+					<key>CheckedForUserDefaultShell</key>
+					<true/>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+					<key>source</key>
+					<string></string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+			</dict>
+		</dict>
+	</array>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject.folder</string>
+		<key>serviceOutputTypeIdentifier</key>
+		<string>com.apple.Automator.nothing</string>
+		<key>serviceProcessesInput</key>
+		<integer>0</integer>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#,
+            script
+        )
+    }
+
+    /// Ask macOS to pick up added or removed services
+    fn refresh_services() {
+        let _ = std::process::Command::new("/System/Library/CoreServices/pbs")
+            .arg("-update")
+            .status();
+    }
+
+    pub fn install(executable: &str) -> CommandResult<()> {
+        let contents = workflow_dir()?.join("Contents");
+        std::fs::create_dir_all(&contents).map_err(|e| io_error("Failed to create workflow", e))?;
+        std::fs::write(contents.join("Info.plist"), info_plist())
+            .map_err(|e| io_error("Failed to write workflow", e))?;
+        std::fs::write(contents.join("document.wflow"), document_wflow(executable))
+            .map_err(|e| io_error("Failed to write workflow", e))?;
+        refresh_services();
+        Ok(())
+    }
+
+    pub fn uninstall() -> CommandResult<()> {
+        let dir = workflow_dir()?;
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).map_err(|e| io_error("Failed to remove workflow", e))?;
+        }
+        refresh_services();
+        Ok(())
+    }
+
+    pub fn is_installed() -> bool {
+        workflow_dir().is_ok_and(|dir| dir.exists())
+    }
+}
+
+/// Explorer context menu entries for folders and folder backgrounds, per user
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    const KEYS: [(&str, &str); 2] = [
+        (r"HKCU\Software\Classes\Directory\shell\opentui-git", "%1"),
+        (
+            r"HKCU\Software\Classes\Directory\Background\shell\opentui-git",
+            "%V",
+        ),
+    ];
+
+    fn reg(args: &[&str]) -> CommandResult<()> {
+        let status = Command::new("reg")
+            .args(args)
+            .status()
+            .map_err(|e| io_error("Failed to run reg", e))?;
+        if !status.success() {
+            return Err(AppError::new(
+                ErrorCode::Internal,
+                format!("reg {} failed with {}", args.join(" "), status),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn install(executable: &str) -> CommandResult<()> {
+        for (key, placeholder) in KEYS {
+            let command = format!("\"{}\" \"{}\"", executable, placeholder);
+            let icon = format!("\"{}\",0", executable);
+            reg(&["add", key, "/ve", "/d", MENU_LABEL, "/f"])?;
+            reg(&["add", key, "/v", "Icon", "/d", &icon, "/f"])?;
+            reg(&[
+                "add",
+                &format!(r"{}\command", key),
+                "/ve",
+                "/d",
+                &command,
+                "/f",
+            ])?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall() -> CommandResult<()> {
+        for (key, _) in KEYS {
+            if Command::new("reg")
+                .args(["query", key])
+                .status()
+                .is_ok_and(|status| status.success())
+            {
+                reg(&["delete", key, "/f"])?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_installed() -> bool {
+        Command::new("reg")
+            .args(["query", KEYS[0].0])
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+/// Nautilus script listed under Scripts in the folder context menu
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script_path() -> CommandResult<PathBuf> {
+        let data_dir = std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .map_or_else(|| home_dir().map(|home| home.join(".local/share")), Ok)?;
+        Ok(data_dir.join("nautilus/scripts").join(MENU_LABEL))
+    }
+
+    pub fn install(executable: &str) -> CommandResult<()> {
+        let path = script_path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| io_error("Failed to create scripts directory", e))?;
+        }
+
+        let script = r#"#!/bin/sh
+# Installed by opentui-git
+printf '%s' "$NAUTILUS_SCRIPT_SELECTED_FILE_PATHS" | while IFS= read -r f; do
+    [ -d "$f" ] && 'EXECUTABLE' "$f" &
+done
+"#
+        .replace("EXECUTABLE", &executable.replace('\'', r"'\''"));
+        std::fs::write(&path, script).map_err(|e| io_error("Failed to write script", e))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| io_error("Failed to make script executable", e))?;
+        Ok(())
+    }
+
+    pub fn uninstall() -> CommandResult<()> {
+        let path = script_path()?;
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| io_error("Failed to remove script", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_installed() -> bool {
+        script_path().is_ok_and(|path| path.exists())
+    }
+}
+
+/// Add an "Open in opentui-git" entry to the file manager's folder context menu
+#[tauri::command]
+pub fn install_shell_integration() -> CommandResult<()> {
+    let executable = app_executable()?;
+    platform::install(&executable.to_string_lossy())?;
+    log_info!("Installed shell integration for {}", executable.display());
+    Ok(())
+}
+
+/// Remove the file manager context menu entry
+#[tauri::command]
+pub fn uninstall_shell_integration() -> CommandResult<()> {
+    platform::uninstall()?;
+    log_info!("Removed shell integration");
+    Ok(())
+}
+
+/// Whether the file manager context menu entry is installed
+#[tauri::command]
+pub fn is_shell_integration_installed() -> bool {
+    platform::is_installed()
+}
@@ -0,0 +1,114 @@
+//! Exit codes and the reason the app is exiting. Every deliberate exit goes
+//! through `exit`, which writes a final log record first, so wrapper scripts
+//! and crash reports can tell failure modes apart. Code 1 is left for
+//! failures before the app is built and for panics.
+
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::telemetry;
+
+/// Why the app is exiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The user quit
+    UserQuit,
+    /// Interrupted with Ctrl+C while headless
+    Interrupted,
+    /// Restarting into an installed update; the restart itself exits with 0
+    UpdateRestart,
+    /// The launch folder is not a git repository
+    NotARepository,
+    /// No port could be found for the server
+    NoPort,
+    /// The server binary is missing or not executable
+    MissingBinary,
+    /// No launch method could spawn the server
+    SpawnFailed,
+    /// The server was spawned but never reported ready
+    ReadinessTimeout,
+    /// The server speaks a protocol this app does not
+    IncompatibleServer,
+    /// The first window could not be created
+    WindowFailed,
+    /// No secret could be generated for the local server
+    NoSecret,
+}
+
+impl ExitReason {
+    /// Process exit code
+    pub fn code(self) -> i32 {
+        match self {
+            Self::UserQuit | Self::UpdateRestart => 0,
+            Self::NotARepository => 2,
+            Self::NoPort => 3,
+            Self::MissingBinary => 4,
+            Self::SpawnFailed => 5,
+            Self::ReadinessTimeout => 6,
+            Self::IncompatibleServer => 7,
+            Self::WindowFailed => 8,
+            Self::NoSecret => 9,
+            // 128 + SIGINT, as shells report it
+            Self::Interrupted => 130,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::UserQuit => "user-quit",
+            Self::Interrupted => "interrupted",
+            Self::UpdateRestart => "update-restart",
+            Self::NotARepository => "not-a-repository",
+            Self::NoPort => "no-port",
+            Self::MissingBinary => "missing-binary",
+            Self::SpawnFailed => "spawn-failed",
+            Self::ReadinessTimeout => "readiness-timeout",
+            Self::IncompatibleServer => "incompatible-server",
+            Self::WindowFailed => "window-failed",
+            Self::NoSecret => "no-secret",
+        }
+    }
+}
+
+/// The reason given for the exit in progress, if any
+#[derive(Clone, Default)]
+pub struct ShutdownState(Arc<Mutex<Option<ExitReason>>>);
+
+/// Record why the app is about to exit, without exiting
+pub fn record(app: &AppHandle, reason: ExitReason) {
+    log_info!(
+        "Shutting down: {} (exit code {})",
+        reason.name(),
+        reason.code()
+    );
+    telemetry::record(
+        app,
+        "exit",
+        serde_json::json!({ "reason": reason.name(), "code": reason.code() }),
+    );
+    if let Some(state) = app.try_state::<ShutdownState>() {
+        if let Ok(mut current) = state.0.lock() {
+            *current = Some(reason);
+        }
+    }
+}
+
+/// Exit with the code for a reason, after logging it
+pub fn exit(app: &AppHandle, reason: ExitReason) {
+    record(app, reason);
+    app.exit(reason.code());
+}
+
+/// The recorded reason for the exit in progress
+pub fn reason(app: &AppHandle) -> Option<ExitReason> {
+    app.try_state::<ShutdownState>()
+        .and_then(|state| *state.0.lock().ok()?)
+}
+
+/// Final log line once the app is exiting, naming the recorded reason
+pub fn log_exit(app: &AppHandle) {
+    match reason(app) {
+        Some(reason) => log_info!("Exiting: {}", reason.name()),
+        None => log_info!("Exiting without a recorded reason"),
+    }
+}
@@ -0,0 +1,781 @@
+//! The sidecar processes, one per open repository: spawning them, waiting
+//! until they are ready, respawning them, and killing them on the way out.
+
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, WebviewWindow};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::net::TcpSocket;
+
+use crate::broadcast::{self, WindowRegistry};
+use crate::config::{get_shell_flags, get_user_shell};
+use crate::control::{self, ControlMessage, Secret, SidecarEvent};
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::logs::{self, LogLevel, LogStream};
+use crate::profiles::RepoProfile;
+use crate::spawn::{self, SpawnMethod};
+use crate::startup_metrics::{self, StartupPhase};
+use crate::timeout::{self, CommandClass};
+use crate::window::{sidecar_for_window, sync_server_info};
+use crate::{
+    askpass, cleanup, credential_helper, environment, git, ipc, large_repo, launch, loopback,
+    mock_server, notifications, pairing, ports, profiles, progress, proxy, settings,
+    sidecar_binary, signing, standby, telemetry, tls, version, worktrees,
+};
+
+/// A sidecar process serving one repository
+#[derive(Clone)]
+pub struct Sidecar {
+    pub child: Arc<Mutex<Option<CommandChild>>>,
+    pub port: u32,
+    pub repo_path: String,
+    /// Times the process has been respawned on this port
+    pub restarts: Arc<AtomicUsize>,
+}
+
+impl Sidecar {
+    /// Kill the sidecar process, if this app spawned it
+    pub fn kill(&self) {
+        let Some(child) = self.child.lock().ok().and_then(|mut child| child.take()) else {
+            log_info!("Server state missing for {}", self.repo_path);
+            return;
+        };
+
+        let _ = child.kill();
+        log_info!("Killed sidecar server for {}", self.repo_path);
+    }
+
+    /// Whether this app spawned the sidecar, and so can talk to it over stdio
+    pub fn is_managed(&self) -> bool {
+        self.child.lock().is_ok_and(|child| child.is_some())
+    }
+}
+
+/// State to track the sidecar processes, one per open repository.
+/// The first entry serves the repository the app was launched with.
+#[derive(Clone, Default)]
+pub struct ServerState(Arc<Mutex<Vec<Sidecar>>>);
+
+impl ServerState {
+    /// All sidecars, primary first
+    pub fn all(&self) -> Vec<Sidecar> {
+        self.0.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Sidecar serving the repository the app was launched with
+    pub fn primary(&self) -> Option<Sidecar> {
+        self.0.lock().ok()?.first().cloned()
+    }
+
+    /// Sidecar serving the given repository
+    pub fn for_repo(&self, repo_path: &str) -> Option<Sidecar> {
+        let key = broadcast::repo_key(repo_path);
+        self.0
+            .lock()
+            .ok()?
+            .iter()
+            .find(|s| broadcast::repo_key(&s.repo_path) == key)
+            .cloned()
+    }
+
+    /// Sidecar listening on the given port
+    pub fn for_port(&self, port: u32) -> Option<Sidecar> {
+        self.0.lock().ok()?.iter().find(|s| s.port == port).cloned()
+    }
+
+    pub fn insert(&self, sidecar: Sidecar) {
+        if let Ok(mut sidecars) = self.0.lock() {
+            sidecars.push(sidecar);
+        }
+    }
+
+    pub fn remove(&self, repo_path: &str) -> Option<Sidecar> {
+        let key = broadcast::repo_key(repo_path);
+        let mut sidecars = self.0.lock().ok()?;
+        let index = sidecars
+            .iter()
+            .position(|s| broadcast::repo_key(&s.repo_path) == key)?;
+        Some(sidecars.remove(index))
+    }
+}
+
+/// Payload of the `server-reconnected` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerReconnectedEvent {
+    port: u32,
+    repo_path: String,
+    respawned: bool,
+}
+
+/// The sidecar serving a window, as returned by `get_server_info`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInfo {
+    pub port: u32,
+    /// Base URL to connect to, `https` when TLS is on
+    pub url: String,
+    /// Address the sidecar is reached at, e.g. `127.0.0.1` or `::1`
+    pub host: String,
+    pub repo_path: String,
+    /// `None` for a sidecar started outside the app, or one not answering
+    pub pid: Option<u32>,
+    pub uptime_ms: Option<u64>,
+    pub restart_count: usize,
+    pub protocol_version: Option<u32>,
+    /// How the sidecar was launched; `None` if started outside the app
+    pub spawn_method: Option<SpawnMethod>,
+}
+
+/// Response of the server's `/operations` endpoint
+#[derive(Debug, Default, Deserialize)]
+struct OperationsResponse {
+    #[serde(default)]
+    operations: Vec<RunningOperation>,
+}
+
+/// A git operation the server reports as in flight
+#[derive(Debug, Deserialize)]
+struct RunningOperation {
+    kind: String,
+}
+
+/// How long a sidecar has to report ready
+pub const SERVER_TIMEOUT_SECS: u64 = 10;
+const OPERATIONS_TIMEOUT_MS: u64 = 1500;
+
+/// Kill all sidecar processes
+#[tauri::command]
+pub fn kill_sidecar(app: AppHandle) {
+    standby::drain(&app);
+
+    let sidecars = app.state::<ServerState>().all();
+    if sidecars.is_empty() {
+        log_info!("Server not running");
+        return;
+    }
+
+    for sidecar in sidecars {
+        sidecar.kill();
+    }
+}
+
+/// Ask the sidecars this app spawned to shut down cleanly, waiting for them
+/// until the cleanup deadline, then kill whatever is left. Called on exit.
+pub fn shutdown_sidecars(app: &AppHandle) {
+    let sidecars: Vec<Sidecar> = app
+        .state::<ServerState>()
+        .all()
+        .into_iter()
+        .filter(Sidecar::is_managed)
+        .collect();
+    if !sidecars.is_empty() {
+        let app = app.clone();
+        cleanup::run_now("sidecars", move || {
+            tauri::async_runtime::block_on(async move {
+                let stopping: Vec<_> = sidecars
+                    .into_iter()
+                    .map(|sidecar| {
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if ipc::shutdown(&app, &sidecar).await.is_ok() {
+                                while is_server_running(sidecar.port).await {
+                                    tokio::time::sleep(Duration::from_millis(100)).await;
+                                }
+                            }
+                        })
+                    })
+                    .collect();
+                for task in stopping {
+                    let _ = task.await;
+                }
+            });
+        });
+    }
+    kill_sidecar(app.clone());
+}
+
+/// Put app-wide sidecar arguments ahead of a profile's own. The sidecar takes
+/// the last value given for an option, so one set for the repository wins.
+fn with_defaults(profile: &mut RepoProfile, defaults: Vec<String>) {
+    profile.args.splice(0..0, defaults);
+}
+
+/// `reload-config` message with the configuration a sidecar would now be
+/// spawned with, for the parts it can apply while running
+pub fn current_config(app: &AppHandle, sidecar: &Sidecar) -> ControlMessage {
+    let mut profile = profiles::for_repo(app, &sidecar.repo_path);
+    if let Err(e) = profile.check_args() {
+        log_error!("Ignoring spawn profile for {}: {}", sidecar.repo_path, e);
+        profile.args.clear();
+    }
+    with_defaults(
+        &mut profile,
+        large_repo::sidecar_args(app, &sidecar.repo_path),
+    );
+    with_defaults(&mut profile, logs::sidecar_args(app));
+
+    // In the order spawning applies them, so later sources win
+    let env = proxy::env(app)
+        .into_iter()
+        .chain(environment::extra(app))
+        .chain(profile.env.clone())
+        .map(|(name, value)| (name, Secret(value)))
+        .collect();
+    ControlMessage::ReloadConfig {
+        log_level: profile
+            .option("log-level")
+            .and_then(LogLevel::from_name)
+            .unwrap_or(LogLevel::Info),
+        history_limit: profile
+            .option("history-limit")
+            .and_then(|limit| limit.parse().ok()),
+        env,
+    }
+}
+
+/// Send every sidecar the current configuration, e.g. after the settings change
+pub fn reload_config(app: &AppHandle) {
+    for sidecar in app.state::<ServerState>().all() {
+        if let Err(e) = control::send_to(&sidecar, &current_config(app, &sidecar)) {
+            log_error!("Failed to reload config of {}: {}", sidecar.repo_path, e);
+        }
+    }
+}
+
+/// Ask the server which git operations are currently running.
+/// An unreachable server is treated as having nothing in flight.
+pub async fn get_running_operations(app: &AppHandle, sidecar: &Sidecar) -> Vec<String> {
+    if sidecar.is_managed() {
+        return ipc::operations(app, sidecar)
+            .await
+            .map(|operations| operations.into_iter().map(|op| op.kind).collect())
+            .unwrap_or_default();
+    }
+
+    // Sidecars started outside the app (in development) are only reachable over HTTP
+    let client = match loopback::client(app, Duration::from_millis(OPERATIONS_TIMEOUT_MS)) {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+
+    let response = match client
+        .get(format!("{}/operations", tls::base_url(app, sidecar.port)))
+        .send()
+        .await
+    {
+        Ok(r) if r.status().is_success() => r,
+        _ => return Vec::new(),
+    };
+
+    response
+        .json::<OperationsResponse>()
+        .await
+        .map(|r| r.operations.into_iter().map(|op| op.kind).collect())
+        .unwrap_or_default()
+}
+
+/// Check if the server is running by attempting a TCP connection, on the
+/// IPv4 loopback and then the IPv6 one, as it may listen on either
+pub async fn is_server_running(port: u32) -> bool {
+    let Ok(port) = u16::try_from(port) else {
+        return false;
+    };
+
+    for address in [
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ] {
+        let socket = match address {
+            IpAddr::V4(_) => TcpSocket::new_v4(),
+            IpAddr::V6(_) => TcpSocket::new_v6(),
+        };
+        let Ok(socket) = socket else {
+            continue;
+        };
+        if socket.connect(SocketAddr::new(address, port)).await.is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Wait until the sidecar reports it is serving, up to `SERVER_TIMEOUT_SECS`
+pub async fn wait_for_server(app: &AppHandle, port: u32) -> bool {
+    let start = Instant::now();
+    if !ipc::wait_ready(app, port, Duration::from_secs(SERVER_TIMEOUT_SECS)).await {
+        log_error!(
+            "Server failed to start within {} seconds",
+            SERVER_TIMEOUT_SECS
+        );
+        return false;
+    }
+    if let Err(e) = loopback::verify_bind(app, port) {
+        log_error!("Refusing server: {}", e);
+        return false;
+    }
+    log_info!("Server ready after {:?}", start.elapsed());
+    true
+}
+
+/// Kill and respawn a sidecar on its existing port
+pub async fn respawn_sidecar(app: &AppHandle, sidecar: &Sidecar) -> bool {
+    sidecar.kill();
+
+    let child = spawn::start(app, sidecar.port, &sidecar.repo_path)
+        .await
+        .ok();
+    telemetry::record(
+        app,
+        "sidecar-restart",
+        serde_json::json!({ "port": sidecar.port, "ready": child.is_some() }),
+    );
+    let Some(child) = child else {
+        return false;
+    };
+
+    if let Ok(mut guard) = sidecar.child.lock() {
+        *guard = Some(child);
+    }
+    sidecar.restarts.fetch_add(1, Ordering::SeqCst);
+    sync_server_info(app, sidecar).await;
+    true
+}
+
+/// Tell the windows on a sidecar's repo to resync with it
+pub fn emit_reconnected(app: &AppHandle, sidecar: &Sidecar, respawned: bool) {
+    broadcast::emit_to_repo(
+        app,
+        &sidecar.repo_path,
+        "server-reconnected",
+        ServerReconnectedEvent {
+            port: sidecar.port,
+            repo_path: sidecar.repo_path.clone(),
+            respawned,
+        },
+    );
+}
+
+/// Re-probe the sidecars, respawning any that have died on the same port,
+/// and tell the frontend to resync
+pub async fn reconnect_sidecar(app: &AppHandle) {
+    let sidecars = app.state::<ServerState>().all();
+    for sidecar in sidecars {
+        // Sidecars started outside the app (in development) can only be probed
+        let reachable = if sidecar.is_managed() {
+            ipc::ping(app, &sidecar).await.is_ok()
+        } else {
+            is_server_running(sidecar.port).await
+        };
+        let respawned = if reachable {
+            log_info!("Server still reachable on port {}", sidecar.port);
+            false
+        } else {
+            log_info!("Server for {} unreachable, respawning", sidecar.repo_path);
+            if !respawn_sidecar(app, &sidecar).await {
+                continue;
+            }
+            true
+        };
+
+        emit_reconnected(app, &sidecar, respawned);
+    }
+}
+
+/// Restart the sidecar serving the calling window's repository
+#[tauri::command]
+pub async fn restart_sidecar(
+    app: AppHandle,
+    window: WebviewWindow,
+    request_id: Option<String>,
+) -> CommandResult<()> {
+    let sidecar = sidecar_for_window(&app, window.label())
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "No server for this window"))?;
+
+    timeout::run(&app, CommandClass::Local, request_id, |_| async {
+        log_info!("Restarting sidecar for {}", sidecar.repo_path);
+        if !respawn_sidecar(&app, &sidecar).await {
+            return Err(AppError::new(
+                ErrorCode::Internal,
+                format!("Server for {} failed to restart", sidecar.repo_path),
+            ));
+        }
+
+        emit_reconnected(&app, &sidecar, true);
+        Ok(())
+    })
+    .await
+}
+
+/// Current state of a sidecar
+pub async fn server_info(app: &AppHandle, sidecar: &Sidecar) -> ServerInfo {
+    let status = if sidecar.is_managed() {
+        ipc::status(app, sidecar).await.ok()
+    } else {
+        None
+    };
+    ServerInfo {
+        port: sidecar.port,
+        url: tls::base_url(app, sidecar.port),
+        host: loopback::connect_host(app, sidecar.port),
+        repo_path: sidecar.repo_path.clone(),
+        pid: status.as_ref().map(|status| status.pid),
+        uptime_ms: status.as_ref().map(|status| status.uptime_ms),
+        restart_count: sidecar.restarts.load(Ordering::SeqCst),
+        protocol_version: ipc::ready_info(app, sidecar.port).map(|ready| ready.protocol),
+        spawn_method: sidecar
+            .is_managed()
+            .then(|| spawn::method(app, sidecar.port))
+            .flatten(),
+    }
+}
+
+/// Current state of the sidecar serving the calling window's repository.
+/// Unlike `window.__OPENTUI__`, which is set once when the page loads, this
+/// stays correct after the sidecar restarts.
+#[tauri::command]
+pub async fn get_server_info(app: AppHandle, window: WebviewWindow) -> CommandResult<ServerInfo> {
+    let sidecar = sidecar_for_window(&app, window.label())
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "No server for this window"))?;
+    Ok(server_info(&app, &sidecar).await)
+}
+
+/// Start a sidecar for a repository and wait until it is ready.
+/// Binds a pre-warmed standby sidecar if one is available.
+pub async fn start_sidecar(app: &AppHandle, repo_path: &str) -> Result<Sidecar, String> {
+    let size = large_repo::prescan(app, repo_path, false).await;
+    // Standby sidecars are already running, too late for a profile's or a
+    // large repository's arguments
+    let standby = if profiles::for_repo(app, repo_path).is_empty() && !size.is_large() {
+        standby::take(app, repo_path)
+    } else {
+        None
+    };
+    let sidecar = match standby {
+        Some(sidecar) => {
+            if !wait_for_server(app, sidecar.port).await {
+                sidecar.kill();
+                return Err(format!("Server for {} failed to start", repo_path));
+            }
+            sidecar
+        }
+        None => {
+            let port = ports::pick(app, Some(repo_path), &[])?;
+            let child = spawn::start(app, port, repo_path)
+                .await
+                .map_err(|e| format!("Server for {} failed to start: {}", repo_path, e))?;
+            Sidecar {
+                child: Arc::new(Mutex::new(Some(child))),
+                port,
+                repo_path: repo_path.to_string(),
+                restarts: Arc::default(),
+            }
+        }
+    };
+
+    if let Err(message) = version::handshake(app, &sidecar).await {
+        sidecar.kill();
+        return Err(message);
+    }
+
+    app.state::<ServerState>().insert(sidecar.clone());
+    Ok(sidecar)
+}
+
+/// Stop a repository's sidecar once its last window has closed.
+/// The primary sidecar stays up so the main window can be reopened.
+pub fn release_sidecar_if_unused(app: &AppHandle, repo_path: &str) {
+    if !app
+        .state::<WindowRegistry>()
+        .windows_for(repo_path)
+        .is_empty()
+    {
+        return;
+    }
+
+    let server_state = app.state::<ServerState>();
+    let is_primary = server_state
+        .primary()
+        .is_some_and(|p| broadcast::repo_key(&p.repo_path) == broadcast::repo_key(repo_path));
+    if is_primary {
+        return;
+    }
+
+    if let Some(sidecar) = server_state.remove(repo_path) {
+        sidecar.kill();
+    }
+}
+
+/// Spawn the sidecar server process with the launch method that last worked.
+/// Without a repo path the sidecar starts in standby and waits for a `bind-repo` message.
+pub fn spawn_sidecar(
+    app: &AppHandle,
+    port: u32,
+    repo_path: Option<&str>,
+) -> Result<CommandChild, String> {
+    let method = spawn::preferred(app);
+    let child = spawn_sidecar_with(app, port, repo_path, method)?;
+    spawn::record(app, port, method);
+    Ok(child)
+}
+
+/// Spawn the sidecar server process with a given launch method
+pub fn spawn_sidecar_with(
+    app: &AppHandle,
+    port: u32,
+    repo_path: Option<&str>,
+    method: SpawnMethod,
+) -> Result<CommandChild, String> {
+    let started = Instant::now();
+    // Don't mistake a previous sidecar's ready notification on this port for the new one's
+    let launch = ipc::forget(app, port);
+    let event_app = app.clone();
+    let mut profile = repo_path
+        .map(|repo_path| profiles::for_repo(app, repo_path))
+        .unwrap_or_default();
+    profile.check_args().map_err(|e| {
+        format!(
+            "Invalid spawn profile for {}: {}",
+            repo_path.unwrap_or_default(),
+            e
+        )
+    })?;
+    if let Some(repo_path) = repo_path {
+        with_defaults(&mut profile, large_repo::sidecar_args(app, repo_path));
+    }
+    with_defaults(&mut profile, logs::sidecar_args(app));
+
+    let location = repo_path.and_then(worktrees::locate);
+    let sidecar_args = spawn::sidecar_args(
+        port,
+        repo_path,
+        location.as_ref(),
+        loopback::host(app),
+        profile.args,
+    );
+
+    let command = match method {
+        // End-to-end tests: this binary stands in for the server
+        _ if mock_server::enabled() => mock_server::command(app)?.args(&sidecar_args),
+        // On macOS/Linux: Execute through user's shell with login flags
+        // This ensures the user's PATH and environment is loaded
+        SpawnMethod::Shell => {
+            let sidecar_path = sidecar_binary::path()?;
+
+            let shell = get_user_shell();
+            let command_str = spawn::shell_command_line(&sidecar_path, &sidecar_args);
+
+            let mut args: Vec<&str> = get_shell_flags(&shell);
+            args.push(&command_str);
+            environment::inherit(app, app.shell().command(&shell)).args(&args)
+        }
+        // On Windows, or when the shell is broken: Direct sidecar execution
+        SpawnMethod::Direct => {
+            let command = app
+                .shell()
+                .sidecar("opentui-git-server")
+                .map_err(|e| format!("Failed to create sidecar command: {}", e))?;
+            environment::inherit(app, command)
+                .envs(environment::cached_login_env(app))
+                .args(&sidecar_args)
+        }
+        // Last resort: the server under a separately installed runtime
+        SpawnMethod::Runtime => {
+            let template = settings::current(app)
+                .fallback_runtime
+                .ok_or("No fallback runtime configured")?;
+            let words = launch::split_words(&template);
+            let (program, runtime_args) = words
+                .split_first()
+                .ok_or("Fallback runtime command is empty")?;
+            environment::inherit(app, app.shell().command(program))
+                .envs(environment::cached_login_env(app))
+                .args(runtime_args)
+                .args(&sidecar_args)
+        }
+    };
+
+    let command = command
+        .envs(askpass::env(app))
+        .envs(signing::env(app))
+        .envs(git::config_env(
+            &signing::git_config(app)
+                .into_iter()
+                .chain(credential_helper::git_config())
+                .collect::<Vec<_>>(),
+        ))
+        .envs(proxy::env(app))
+        .envs(environment::extra(app))
+        .envs(profile.env)
+        .envs(loopback::env(app))
+        .envs(pairing::env(app))
+        .envs(tls::env(app));
+    startup_metrics::record(app, StartupPhase::EnvCapture, started);
+
+    let started = Instant::now();
+    let (mut rx, child) = command
+        .spawn()
+        .map_err(|e| format!("Failed to spawn sidecar: {}", e))?;
+    startup_metrics::record(app, StartupPhase::Spawn, started);
+
+    log_info!(
+        "Spawned sidecar on port {} via {:?} for repo: {}",
+        port,
+        method,
+        repo_path.unwrap_or("(standby)")
+    );
+
+    // Collect stdout/stderr asynchronously
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line_bytes) => {
+                    let line = String::from_utf8_lossy(&line_bytes);
+                    print!("{}", line);
+
+                    if ipc::handle_line(&event_app, port, &line) {
+                        continue;
+                    }
+
+                    // Look the repo up at event time, since standby sidecars bind later
+                    if let Some(event) = control::parse_event(&line) {
+                        if let Some(sidecar) = event_app.state::<ServerState>().for_port(port) {
+                            if let SidecarEvent::PairRequest { id, code, name } = event {
+                                pairing::handle_request(&event_app, sidecar, id, code, name);
+                            } else {
+                                progress::handle_sidecar_event(
+                                    &event_app,
+                                    &sidecar.repo_path,
+                                    &event,
+                                );
+                                notifications::handle_sidecar_event(
+                                    &event_app,
+                                    &sidecar.repo_path,
+                                    event,
+                                );
+                            }
+                        }
+                    }
+
+                    logs::push(&event_app, port, LogStream::Stdout, &line);
+                }
+                CommandEvent::Stderr(line_bytes) => {
+                    let line = String::from_utf8_lossy(&line_bytes);
+                    eprint!("{}", line);
+
+                    logs::push(&event_app, port, LogStream::Stderr, &line);
+                }
+                CommandEvent::Error(err) => {
+                    log_error!("Sidecar error: {}", err);
+                }
+                CommandEvent::Terminated(status) => {
+                    log_info!("Sidecar terminated with status: {:?}", status);
+                    telemetry::record(
+                        &event_app,
+                        "sidecar-exit",
+                        serde_json::json!({
+                            "port": port,
+                            "code": status.code,
+                            "signal": status.signal,
+                        }),
+                    );
+                    ipc::mark_exited(&event_app, port, launch);
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+
+    Ok(child)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sidecar(port: u32, repo_path: &str) -> Sidecar {
+        Sidecar {
+            child: Arc::new(Mutex::new(None)),
+            port,
+            repo_path: repo_path.to_string(),
+            restarts: Arc::default(),
+        }
+    }
+
+    fn profile(args: &[&str]) -> RepoProfile {
+        RepoProfile {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn profile_log_level_wins_over_the_global_one() {
+        let mut repo = profile(&["--log-level", "debug"]);
+        with_defaults(&mut repo, vec!["--log-level".into(), "warn".into()]);
+        assert_eq!(repo.option("log-level"), Some("debug"));
+
+        let mut repo = profile(&[]);
+        with_defaults(&mut repo, vec!["--log-level".into(), "warn".into()]);
+        assert_eq!(repo.option("log-level"), Some("warn"));
+    }
+
+    #[test]
+    fn profile_history_limit_wins_over_the_large_repo_one() {
+        let mut repo = profile(&["--history-limit=5000"]);
+        with_defaults(&mut repo, vec!["--history-limit".into(), "500".into()]);
+        assert_eq!(repo.option("history-limit"), Some("5000"));
+
+        let mut repo = profile(&["--log-level", "debug"]);
+        with_defaults(&mut repo, vec!["--history-limit".into(), "500".into()]);
+        assert_eq!(repo.option("history-limit"), Some("500"));
+    }
+
+    #[test]
+    fn looks_up_sidecars_by_repo_and_port() {
+        let state = ServerState::default();
+        assert!(state.primary().is_none());
+        state.insert(sidecar(4096, "/nonexistent/first"));
+        state.insert(sidecar(4097, "/nonexistent/second"));
+
+        assert_eq!(state.primary().map(|s| s.port), Some(4096));
+        assert_eq!(
+            state.for_repo("/nonexistent/second/").map(|s| s.port),
+            Some(4097)
+        );
+        assert_eq!(
+            state.for_port(4096).map(|s| s.repo_path),
+            Some("/nonexistent/first".to_string())
+        );
+        assert!(state.for_repo("/nonexistent/third").is_none());
+        assert!(state.for_port(4098).is_none());
+    }
+
+    #[test]
+    fn removing_the_primary_promotes_the_next() {
+        let state = ServerState::default();
+        state.insert(sidecar(4096, "/nonexistent/first"));
+        state.insert(sidecar(4097, "/nonexistent/second"));
+
+        assert_eq!(
+            state.remove("/nonexistent/first").map(|s| s.port),
+            Some(4096)
+        );
+        assert!(state.remove("/nonexistent/first").is_none());
+        assert_eq!(state.primary().map(|s| s.port), Some(4097));
+        assert_eq!(state.all().len(), 1);
+    }
+
+    #[test]
+    fn a_sidecar_without_a_child_is_unmanaged() {
+        let sidecar = sidecar(4096, "/nonexistent/first");
+        assert!(!sidecar.is_managed());
+        // Nothing to kill, which is only logged
+        sidecar.kill();
+    }
+}
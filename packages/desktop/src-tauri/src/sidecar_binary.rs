@@ -0,0 +1,135 @@
+//! Startup check for the `opentui-git-server` binary shipped next to the app.
+//! Security software and incomplete installs remove it or strip its execute
+//! bit, so the app explains what is wrong instead of failing to spawn it.
+
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::{i18n, mock_server};
+
+const BINARY_NAME: &str = "opentui-git-server";
+
+/// Where the sidecar binary should be
+pub fn path() -> Result<PathBuf, String> {
+    let exe = tauri::utils::platform::current_exe()
+        .map_err(|e| format!("Failed to get current exe: {}", e))?;
+    let dir = exe.parent().ok_or("Failed to get parent dir")?;
+    Ok(dir.join(format!("{}{}", BINARY_NAME, std::env::consts::EXE_SUFFIX)))
+}
+
+/// What is wrong with the binary at `path`, if anything
+fn problem(path: &Path) -> Option<String> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Some("The file does not exist.".to_string())
+        }
+        Err(e) => return Some(format!("The file cannot be read: {}.", e)),
+    };
+    if !metadata.is_file() {
+        return Some("The path is not a file.".to_string());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Some("The file is not executable.".to_string());
+        }
+    }
+    None
+}
+
+/// Causes worth checking on this platform
+fn likely_causes() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "macOS may have quarantined the app after download. Move it to \
+         Applications, or run `xattr -dr com.apple.quarantine` on the app bundle."
+    } else if cfg!(target_os = "windows") {
+        "Antivirus software may have quarantined or deleted the file. Restore it \
+         from quarantine and add an exclusion, or reinstall the app."
+    } else {
+        "The package may be incomplete, the file may have lost its execute \
+         permission, or the app may be on a filesystem mounted noexec."
+    }
+}
+
+fn diagnostics(app: &AppHandle, path: &Path, problem: &str) -> String {
+    format!(
+        "opentui-git {}\nPlatform: {} {}\nExpected server binary: {}\nProblem: {}",
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        path.display(),
+        problem
+    )
+}
+
+/// Ask a two-button question, returning whether the first button was chosen
+async fn ask(app: &AppHandle, message: String, first: &str, second: &str) -> bool {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(message)
+        .title(i18n::t(app, "startup.missing-binary"))
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            first.to_string(),
+            second.to_string(),
+        ))
+        .show(move |chosen| {
+            let _ = tx.send(chosen);
+        });
+    rx.await.unwrap_or(false)
+}
+
+/// Check the sidecar binary before launching it. If it is missing or not
+/// executable, log why and, unless headless, explain it in a dialog that can
+/// open its folder and copy diagnostics. Returns whether it is usable.
+pub async fn check(app: &AppHandle, headless: bool) -> bool {
+    // The mock server is this binary, which is there by definition
+    if mock_server::enabled() {
+        return true;
+    }
+    let path = match path() {
+        Ok(path) => path,
+        Err(e) => {
+            log_error!("{}", e);
+            return false;
+        }
+    };
+    let Some(problem) = problem(&path) else {
+        return true;
+    };
+    let report = diagnostics(app, &path, &problem);
+    log_error!("Cannot start the server:\n{}", report);
+    log_error!("{}", likely_causes());
+    if headless {
+        return false;
+    }
+
+    let message = format!(
+        "{}\n\nExpected at: {}\nPlatform: {} {}\n{}\n\n{}",
+        i18n::t(app, "sidecar-binary.message"),
+        path.display(),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        problem,
+        likely_causes()
+    );
+    // Opening the folder leaves the dialog up so diagnostics can still be copied
+    let open_folder = i18n::t(app, "sidecar-binary.open-folder");
+    let copy_diagnostics = i18n::t(app, "sidecar-binary.copy-diagnostics");
+    while ask(app, message.clone(), &open_folder, &copy_diagnostics).await {
+        if let Some(dir) = path.parent() {
+            if let Err(e) = app.opener().open_path(dir.to_string_lossy(), None::<&str>) {
+                log_error!("Failed to open {}: {}", dir.display(), e);
+            }
+        }
+    }
+    if let Err(e) = app.clipboard().write_text(report) {
+        log_error!("Failed to copy diagnostics: {}", e);
+    }
+    false
+}
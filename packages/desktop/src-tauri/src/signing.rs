@@ -0,0 +1,196 @@
+//! Signed commits without a terminal.
+//!
+//! Launched from the Dock or a desktop entry there is no TTY for gpg's
+//! pinentry, so signing fails. The sidecar's git is pointed at this binary as
+//! `gpg.program`; run that way it first tries gpg as usual and, if a
+//! passphrase is needed, asks the app for it and retries with gpg's loopback
+//! pinentry. SSH signing prompts go through the askpass bridge instead.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::askpass::{self, PromptKind};
+
+/// The gpg program git would have run, passed to helper runs
+const GPG_PROGRAM_VAR: &str = "OPENTUI_GIT_GPG_PROGRAM";
+/// Passphrase attempts before giving up on signing
+const MAX_ATTEMPTS: usize = 3;
+
+/// Signing key passphrases entered this session, keyed by key id
+#[derive(Clone, Default)]
+pub struct SigningState(Arc<Mutex<HashMap<String, String>>>);
+
+/// Passphrase for a signing key, from this session's cache or a native dialog
+pub fn passphrase(app: &AppHandle, key: &str) -> Option<String> {
+    let state = app.state::<SigningState>();
+    if let Some(cached) = state
+        .0
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(key).cloned())
+    {
+        return Some(cached);
+    }
+
+    let message = if key.is_empty() {
+        "Enter the passphrase of your signing key".to_string()
+    } else {
+        format!("Enter the passphrase of signing key {}", key)
+    };
+    let passphrase = askpass::native_prompt(&message, true)?;
+    if let Ok(mut cache) = state.0.lock() {
+        cache.insert(key.to_string(), passphrase.clone());
+    }
+    Some(passphrase)
+}
+
+/// Drop a cached passphrase that gpg rejected
+pub fn forget(app: &AppHandle, key: &str) {
+    if let Ok(mut cache) = app.state::<SigningState>().0.lock() {
+        cache.remove(key);
+    }
+}
+
+/// gpg program from the user's git config, defaulting to `gpg`
+fn configured_gpg() -> String {
+    Command::new("git")
+        .args(["config", "--get", "gpg.program"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|program| !program.is_empty())
+        .unwrap_or_else(|| "gpg".to_string())
+}
+
+/// Git config that makes git sign through this binary
+pub fn git_config(app: &AppHandle) -> Vec<(String, String)> {
+    if app.try_state::<askpass::AskpassState>().is_none() {
+        return Vec::new();
+    }
+    let Ok(exe) = std::env::current_exe() else {
+        return Vec::new();
+    };
+    vec![("gpg.program".to_string(), exe.to_string_lossy().to_string())]
+}
+
+/// Environment for runs of this binary as git's gpg program
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    if app.try_state::<askpass::AskpassState>().is_none() {
+        return Vec::new();
+    }
+    vec![(GPG_PROGRAM_VAR.to_string(), configured_gpg())]
+}
+
+/// Key git asked to sign with, from `-u <key>`, `-bsau <key>` or `--local-user`
+fn signing_key(args: &[String]) -> String {
+    args.iter()
+        .position(|arg| {
+            arg == "--local-user"
+                || (arg.starts_with('-') && !arg.starts_with("--") && arg.ends_with('u'))
+        })
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn run_gpg(
+    program: &str,
+    extra: &[&str],
+    args: &[String],
+    input: &[u8],
+) -> std::io::Result<Output> {
+    let mut child = Command::new(program)
+        .args(extra)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input)?;
+    }
+    child.wait_with_output()
+}
+
+/// Pass gpg's output on to git, which reads the signature from stdout and
+/// status lines from stderr
+fn forward(output: &Output) -> i32 {
+    let _ = std::io::stdout().write_all(&output.stdout);
+    let _ = std::io::stderr().write_all(&output.stderr);
+    output.status.code().unwrap_or(1)
+}
+
+/// When git started this binary as its gpg program, sign or verify through
+/// the real gpg and return the exit code; otherwise `None`.
+pub fn run_helper() -> Option<i32> {
+    let program = std::env::var(GPG_PROGRAM_VAR).ok()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // git always passes options; an askpass prompt never starts with a dash
+    if !args.first().is_some_and(|arg| arg.starts_with('-')) {
+        return None;
+    }
+
+    let mut payload = Vec::new();
+    if let Err(e) = std::io::stdin().read_to_end(&mut payload) {
+        eprintln!("opentui-git gpg: {}", e);
+        return Some(2);
+    }
+
+    // Keys without a passphrase, or with one cached by the agent, need no prompt
+    let mut last = match run_gpg(&program, &["--pinentry-mode", "error"], &args, &payload) {
+        Ok(output) if output.status.success() => return Some(forward(&output)),
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("opentui-git gpg: failed to run {}: {}", program, e);
+            return Some(2);
+        }
+    };
+    if args.iter().any(|arg| arg == "--verify") {
+        return Some(forward(&last));
+    }
+
+    let key = signing_key(&args);
+    for _ in 0..MAX_ATTEMPTS {
+        let passphrase = match askpass::relay(PromptKind::SigningPassphrase { key: key.clone() }) {
+            Ok(Some(passphrase)) => passphrase,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("opentui-git gpg: {}", e);
+                break;
+            }
+        };
+
+        // The passphrase is the first line on stdin, followed by the payload
+        let mut input = passphrase.into_bytes();
+        input.push(b'\n');
+        input.extend_from_slice(&payload);
+        let loopback = [
+            "--batch",
+            "--pinentry-mode",
+            "loopback",
+            "--passphrase-fd",
+            "0",
+        ];
+        last = match run_gpg(&program, &loopback, &args, &input) {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("opentui-git gpg: failed to run {}: {}", program, e);
+                return Some(2);
+            }
+        };
+        if last.status.success() {
+            return Some(forward(&last));
+        }
+
+        let stderr = String::from_utf8_lossy(&last.stderr).to_lowercase();
+        if !stderr.contains("bad passphrase") && !stderr.contains("bad_passphrase") {
+            break;
+        }
+        let _ = askpass::relay(PromptKind::ForgetPassphrase { key: key.clone() });
+    }
+    Some(forward(&last))
+}
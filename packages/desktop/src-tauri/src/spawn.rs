@@ -0,0 +1,279 @@
+//! How sidecars are launched. On macOS/Linux they normally run through the
+//! user's login shell so they see the same PATH as a terminal, but a broken
+//! rc file can make that shell fail or exit at once. Launching then falls
+//! back to running the sidecar directly with the environment the shell last
+//! produced, and finally to a bundled runtime if one is configured.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_shell::process::CommandChild;
+
+use crate::startup_metrics::{self, StartupPhase};
+use crate::worktrees::RepoLocation;
+use crate::{environment, launch, mock_server, settings, telemetry};
+
+/// A way of launching the sidecar, in the order they are tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpawnMethod {
+    /// Through `$SHELL -il -c`, picking up the user's profile
+    Shell,
+    /// The sidecar binary itself, with the cached login environment
+    Direct,
+    /// The configured `fallbackRuntime` command
+    Runtime,
+}
+
+/// Why no launch method started a sidecar
+#[derive(Debug)]
+pub enum SpawnError {
+    /// No method could even spawn the process; holds the last error
+    Launch(String),
+    /// A process was spawned but never reported ready
+    NotReady,
+}
+
+impl std::fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Launch(message) => f.write_str(message),
+            Self::NotReady => write!(
+                f,
+                "The server did not report ready within {} seconds",
+                crate::sidecar::SERVER_TIMEOUT_SECS
+            ),
+        }
+    }
+}
+
+/// The method each sidecar was started with, by port, and the last one that
+/// worked, which standby sidecars use since nothing waits for them to start
+#[derive(Clone, Default)]
+pub struct SpawnState(Arc<Mutex<SpawnInner>>);
+
+#[derive(Default)]
+struct SpawnInner {
+    by_port: HashMap<u32, SpawnMethod>,
+    preferred: Option<SpawnMethod>,
+}
+
+/// Methods to try, in order
+fn chain(app: &AppHandle) -> Vec<SpawnMethod> {
+    // The mock server is this binary, which needs no shell
+    if mock_server::enabled() {
+        return vec![SpawnMethod::Direct];
+    }
+    let mut methods = Vec::new();
+    if cfg!(not(target_os = "windows")) {
+        methods.push(SpawnMethod::Shell);
+    }
+    methods.push(SpawnMethod::Direct);
+    if settings::current(app).fallback_runtime.is_some() {
+        methods.push(SpawnMethod::Runtime);
+    }
+    methods
+}
+
+/// The method that last started a sidecar, or the first to try
+pub fn preferred(app: &AppHandle) -> SpawnMethod {
+    app.try_state::<SpawnState>()
+        .and_then(|state| state.0.lock().ok()?.preferred)
+        .unwrap_or_else(|| chain(app)[0])
+}
+
+/// How the sidecar on a port was started
+pub fn method(app: &AppHandle, port: u32) -> Option<SpawnMethod> {
+    let state = app.try_state::<SpawnState>()?;
+    let inner = state.0.lock().ok()?;
+    inner.by_port.get(&port).copied()
+}
+
+/// Remember how the sidecar on a port was started
+pub fn record(app: &AppHandle, port: u32, method: SpawnMethod) {
+    if let Some(state) = app.try_state::<SpawnState>() {
+        if let Ok(mut inner) = state.0.lock() {
+            inner.by_port.insert(port, method);
+            inner.preferred = Some(method);
+        }
+    }
+}
+
+/// Arguments the sidecar is started with. Without a repo path it starts in
+/// standby and waits for a `bind-repo` message.
+pub fn sidecar_args(
+    port: u32,
+    repo_path: Option<&str>,
+    location: Option<&RepoLocation>,
+    host: &str,
+    extra: Vec<String>,
+) -> Vec<String> {
+    let mut args = vec!["--port".to_string(), port.to_string()];
+    match repo_path {
+        Some(repo_path) => args.extend(["--repo".to_string(), repo_path.to_string()]),
+        None => args.push("--standby".to_string()),
+    }
+    // Linked worktrees share refs and config with their main repository, and
+    // bare repositories have no working tree for the server to read
+    if let Some(location) = location {
+        if location.bare {
+            args.push("--bare".to_string());
+        }
+        if let Some(main_repo) = &location.main_repo {
+            args.extend(["--main-repo".to_string(), main_repo.clone()]);
+        }
+    }
+    args.extend(["--host".to_string(), host.to_string()]);
+    args.extend(extra);
+    args
+}
+
+/// Command line run through the login shell to start the sidecar
+pub fn shell_command_line(sidecar_path: &Path, args: &[String]) -> String {
+    std::iter::once(sidecar_path.to_string_lossy().to_string())
+        .chain(args.iter().cloned())
+        .map(|word| launch::shell_quote(&word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Start a sidecar and wait until it serves the repository, trying each
+/// launch method in turn until one works
+pub async fn start(
+    app: &AppHandle,
+    port: u32,
+    repo_path: &str,
+) -> Result<CommandChild, SpawnError> {
+    let mut error = SpawnError::Launch("No launch method available".to_string());
+    for method in chain(app) {
+        let child = match crate::sidecar::spawn_sidecar_with(app, port, Some(repo_path), method) {
+            Ok(child) => child,
+            Err(e) => {
+                log_error!("Failed to launch sidecar via {:?}: {}", method, e);
+                // A timeout says more about what went wrong than a later launch error
+                if !matches!(error, SpawnError::NotReady) {
+                    error = SpawnError::Launch(e);
+                }
+                continue;
+            }
+        };
+        let started = Instant::now();
+        let ready = crate::sidecar::wait_for_server(app, port).await;
+        startup_metrics::record(app, StartupPhase::Readiness, started);
+        if ready {
+            log_info!("Sidecar on port {} started via {:?}", port, method);
+            record(app, port, method);
+            telemetry::record(
+                app,
+                "sidecar-spawn",
+                serde_json::json!({ "port": port, "method": method }),
+            );
+            if method == SpawnMethod::Shell {
+                // Keep the environment the shell produced for when it breaks
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    environment::cache_login_env(&app).await;
+                });
+            }
+            return Ok(child);
+        }
+        log_error!(
+            "Sidecar launched via {:?} did not start, trying the next method",
+            method
+        );
+        let _ = child.kill();
+        error = SpawnError::NotReady;
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profiles::RepoProfile;
+
+    #[test]
+    fn standby_sidecars_get_no_repo() {
+        let args = sidecar_args(4000, None, None, "127.0.0.1", Vec::new());
+        assert_eq!(args, ["--port", "4000", "--standby", "--host", "127.0.0.1"]);
+    }
+
+    #[test]
+    fn worktrees_pass_their_main_repo() {
+        let location = RepoLocation {
+            workdir: "/work/feature".to_string(),
+            main_repo: Some("/work/main".to_string()),
+            bare: false,
+        };
+        let args = sidecar_args(
+            4000,
+            Some("/work/feature"),
+            Some(&location),
+            "0.0.0.0",
+            vec!["--log-level=debug".to_string()],
+        );
+        assert_eq!(
+            args,
+            [
+                "--port",
+                "4000",
+                "--repo",
+                "/work/feature",
+                "--main-repo",
+                "/work/main",
+                "--host",
+                "0.0.0.0",
+                "--log-level=debug"
+            ]
+        );
+    }
+
+    #[test]
+    fn profile_args_follow_the_shells() {
+        let profile = RepoProfile {
+            args: vec!["--history-limit".to_string(), "500".to_string()],
+            ..Default::default()
+        };
+        profile.check_args().unwrap();
+        let args = sidecar_args(4000, Some("/repo"), None, "127.0.0.1", profile.args);
+        assert_eq!(
+            args,
+            [
+                "--port",
+                "4000",
+                "--repo",
+                "/repo",
+                "--host",
+                "127.0.0.1",
+                "--history-limit",
+                "500"
+            ]
+        );
+    }
+
+    #[test]
+    fn bare_repos_are_flagged() {
+        let location = RepoLocation {
+            workdir: "/srv/repo.git".to_string(),
+            main_repo: None,
+            bare: true,
+        };
+        let args = sidecar_args(1, Some("/srv/repo.git"), Some(&location), "::1", Vec::new());
+        assert!(args.contains(&"--bare".to_string()));
+    }
+
+    #[test]
+    fn shell_command_line_quotes_every_word() {
+        let line = shell_command_line(
+            Path::new("/Applications/Open TUI.app/server"),
+            &["--repo".to_string(), "/tmp/it's".to_string()],
+        );
+        assert_eq!(
+            line,
+            r"'/Applications/Open TUI.app/server' '--repo' '/tmp/it'\''s'"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+use crate::control::{self, ControlMessage};
+use crate::sidecar::{spawn_sidecar, Sidecar};
+use crate::{ports, settings};
+
+/// Pre-warmed sidecars that are running but not yet bound to a repository,
+/// so opening another repository skips the shell and runtime startup cost
+#[derive(Clone, Default)]
+pub struct StandbyPool(Arc<Mutex<Vec<Sidecar>>>);
+
+/// Spawn standby sidecars until the pool reaches its configured size,
+/// killing any beyond it
+pub fn fill(app: &AppHandle) {
+    let size = settings::current(app).standby_sidecars;
+    let pool = app.state::<StandbyPool>();
+    let Ok(mut standby) = pool.0.lock() else {
+        return;
+    };
+
+    while standby.len() > size {
+        if let Some(sidecar) = standby.pop() {
+            sidecar.kill();
+        }
+    }
+
+    while standby.len() < size {
+        // Not listening yet, so the pool's own ports must be skipped explicitly
+        let pooled: Vec<u32> = standby.iter().map(|sidecar| sidecar.port).collect();
+        let port = match ports::pick(app, None, &pooled) {
+            Ok(port) => port,
+            Err(e) => {
+                log_error!("Failed to find a port for a standby sidecar: {}", e);
+                return;
+            }
+        };
+        let child = match spawn_sidecar(app, port, None) {
+            Ok(child) => child,
+            Err(e) => {
+                log_error!("Failed to spawn standby sidecar: {}", e);
+                return;
+            }
+        };
+        standby.push(Sidecar {
+            child: Arc::new(Mutex::new(Some(child))),
+            port,
+            repo_path: String::new(),
+            restarts: Arc::default(),
+        });
+    }
+}
+
+/// Bind a standby sidecar to a repository, refilling the pool in the background.
+/// Returns None if the pool is empty or binding failed.
+pub fn take(app: &AppHandle, repo_path: &str) -> Option<Sidecar> {
+    let mut sidecar = app.state::<StandbyPool>().0.lock().ok()?.pop()?;
+    sidecar.repo_path = repo_path.to_string();
+
+    let bind = ControlMessage::BindRepo {
+        repo_path: repo_path.to_string(),
+    };
+    if let Err(e) = control::send_to(&sidecar, &bind) {
+        log_error!("Failed to bind standby sidecar: {}", e);
+        sidecar.kill();
+        return None;
+    }
+    log_info!(
+        "Bound standby sidecar on port {} to {}",
+        sidecar.port,
+        repo_path
+    );
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { fill(&app) });
+    Some(sidecar)
+}
+
+/// Kill all standby sidecars
+pub fn drain(app: &AppHandle) {
+    let Some(pool) = app.try_state::<StandbyPool>() else {
+        return;
+    };
+    let standby = pool
+        .0
+        .lock()
+        .map(|mut standby| std::mem::take(&mut *standby))
+        .unwrap_or_default();
+    for sidecar in standby {
+        sidecar.kill();
+    }
+}
@@ -0,0 +1,170 @@
+//! Failures while starting up. Each kind exits with its own code (see
+//! `shutdown`) so scripts and bug reports can tell them apart. Launched from
+//! the GUI, the user first sees a dialog and can keep the app open with the
+//! log window, where the logs and diagnostics bundle are still available.
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::shutdown::{self, ExitReason};
+use crate::spawn::SpawnError;
+use crate::{i18n, logs, telemetry};
+
+/// Why the app could not start
+#[derive(Debug, Clone)]
+pub enum StartupError {
+    /// The launch folder is not a git repository (headless only; windowed
+    /// launches show onboarding instead)
+    NotARepository(String),
+    /// No port could be found for the server
+    Port(String),
+    /// The server binary is missing or not executable
+    MissingBinary,
+    /// No launch method could spawn the server
+    Spawn(String),
+    /// The server was spawned but did not report ready in time
+    Timeout(String),
+    /// The server speaks a protocol this app does not
+    Incompatible(String),
+    /// The first window could not be created
+    Window(String),
+    /// No secret could be generated for the local server
+    Secret(String),
+}
+
+impl From<SpawnError> for StartupError {
+    fn from(error: SpawnError) -> Self {
+        match error {
+            SpawnError::Launch(message) => Self::Spawn(message),
+            SpawnError::NotReady => Self::Timeout(error.to_string()),
+        }
+    }
+}
+
+impl StartupError {
+    fn reason(&self) -> ExitReason {
+        match self {
+            Self::NotARepository(_) => ExitReason::NotARepository,
+            Self::Port(_) => ExitReason::NoPort,
+            Self::MissingBinary => ExitReason::MissingBinary,
+            Self::Spawn(_) => ExitReason::SpawnFailed,
+            Self::Timeout(_) => ExitReason::ReadinessTimeout,
+            Self::Incompatible(_) => ExitReason::IncompatibleServer,
+            Self::Window(_) => ExitReason::WindowFailed,
+            Self::Secret(_) => ExitReason::NoSecret,
+        }
+    }
+
+    /// Translation key of the title
+    fn title_key(&self) -> &'static str {
+        match self {
+            Self::NotARepository(_) => "startup.not-a-repository",
+            Self::Port(_) => "startup.no-port",
+            Self::MissingBinary => "startup.missing-binary",
+            Self::Spawn(_) | Self::Timeout(_) => "startup.spawn-failed",
+            Self::Incompatible(_) => "startup.incompatible",
+            Self::Window(_) => "startup.window-failed",
+            Self::Secret(_) => "startup.no-secret",
+        }
+    }
+
+    /// Title in the given language
+    fn title(&self, locale: &str) -> String {
+        i18n::translate(locale, self.title_key(), &[])
+    }
+
+    fn message(&self, locale: &str) -> String {
+        match self {
+            Self::NotARepository(path) => i18n::translate(
+                locale,
+                "startup.not-a-repository.message",
+                &[("path", path.as_str())],
+            ),
+            Self::MissingBinary => i18n::translate(locale, "startup.missing-binary.message", &[]),
+            Self::Port(message)
+            | Self::Spawn(message)
+            | Self::Timeout(message)
+            | Self::Incompatible(message)
+            | Self::Window(message)
+            | Self::Secret(message) => message.clone(),
+        }
+    }
+}
+
+/// A startup failure as reported by `get_startup_error`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupFailure {
+    title: String,
+    message: String,
+    exit_code: i32,
+}
+
+/// The failure the app is staying open after, if any
+#[derive(Clone, Default)]
+pub struct StartupState(Arc<Mutex<Option<StartupFailure>>>);
+
+fn report(app: &AppHandle, error: &StartupError) {
+    // Logs stay in English for bug reports
+    log_error!("{}: {}", error.title("en"), error.message("en"));
+    telemetry::record(
+        app,
+        "startup-failed",
+        serde_json::json!({ "code": error.reason().code(), "message": error.message("en") }),
+    );
+}
+
+/// Log the failure and exit with its code, for launches without any UI
+pub fn exit(app: &AppHandle, error: StartupError) {
+    report(app, &error);
+    shutdown::exit(app, error.reason());
+}
+
+/// Explain the failure in a dialog, then either exit with its code or stay
+/// open in an error state with the log window
+pub async fn fail(app: &AppHandle, error: StartupError) {
+    report(app, &error);
+    // The binary check has already shown its own dialog
+    if matches!(error, StartupError::MissingBinary) {
+        shutdown::exit(app, error.reason());
+        return;
+    }
+
+    let locale = i18n::current(app);
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(error.message(locale))
+        .title(error.title(locale))
+        .kind(MessageDialogKind::Error)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            i18n::t(app, "startup.quit"),
+            i18n::t(app, "startup.show-logs"),
+        ))
+        .show(move |quit| {
+            let _ = tx.send(quit);
+        });
+    if rx.await.unwrap_or(true) {
+        shutdown::exit(app, error.reason());
+        return;
+    }
+
+    if let Ok(mut failure) = app.state::<StartupState>().0.lock() {
+        *failure = Some(StartupFailure {
+            title: error.title(locale),
+            message: error.message(locale),
+            exit_code: error.reason().code(),
+        });
+    }
+    if let Err(e) = logs::open_log_window(app.clone()).await {
+        log_error!("Failed to open log window: {}", e.message);
+        shutdown::exit(app, error.reason());
+    }
+}
+
+/// Why startup failed, if the app stayed open after a failure
+#[tauri::command]
+pub fn get_startup_error(app: AppHandle) -> Option<StartupFailure> {
+    app.state::<StartupState>().0.lock().ok()?.clone()
+}
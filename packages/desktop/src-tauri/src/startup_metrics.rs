@@ -0,0 +1,151 @@
+//! Timing of the startup phases, for "it takes 8 seconds to open" reports.
+//! Spans are recorded until the first window is up (or the headless server is
+//! serving), then exposed through `get_startup_metrics`. Launching with
+//! `--profile-startup` also prints a summary to the log.
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Command-line flag printing the startup summary
+const PROFILE_FLAG: &str = "--profile-startup";
+
+/// When `run` was entered, which the spans are measured from
+static LAUNCHED_AT: OnceLock<Instant> = OnceLock::new();
+
+/// A phase of starting up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartupPhase {
+    /// From launch until the app's setup hook runs
+    Setup,
+    PortSelection,
+    /// Assembling the sidecar's environment. Through the login shell, the
+    /// profile itself loads as part of readiness.
+    EnvCapture,
+    Spawn,
+    /// From spawning until the sidecar reports ready
+    Readiness,
+    /// Checking the sidecar's protocol version
+    Handshake,
+    WindowCreation,
+}
+
+/// One timed phase; a phase retried with another launch method appears once
+/// per attempt
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupSpan {
+    phase: StartupPhase,
+    /// Since launch
+    start_ms: u64,
+    duration_ms: u64,
+}
+
+/// Result of `get_startup_metrics`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupMetrics {
+    spans: Vec<StartupSpan>,
+    /// Launch until the app was usable; `None` while still starting
+    total_ms: Option<u64>,
+}
+
+/// Spans recorded so far, closed once startup has finished
+#[derive(Clone)]
+pub struct StartupTimings(Arc<Mutex<TimingsInner>>);
+
+struct TimingsInner {
+    spans: Vec<StartupSpan>,
+    total: Option<Duration>,
+}
+
+impl Default for StartupTimings {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(TimingsInner {
+            spans: Vec::new(),
+            total: None,
+        })))
+    }
+}
+
+/// When the app was launched
+pub fn launched_at() -> Instant {
+    *LAUNCHED_AT.get_or_init(Instant::now)
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// Note the launch time; call as early as possible
+pub fn mark_launch() {
+    launched_at();
+}
+
+/// Whether the app was launched with `--profile-startup`
+fn requested() -> bool {
+    std::env::args().skip(1).any(|arg| arg == PROFILE_FLAG)
+}
+
+/// Record a phase that began at `started` and ends now. Ignored once startup
+/// has finished, so restarts and standby sidecars don't show up.
+pub fn record(app: &AppHandle, phase: StartupPhase, started: Instant) {
+    let Some(timings) = app.try_state::<StartupTimings>() else {
+        return;
+    };
+    let Ok(mut inner) = timings.0.lock() else {
+        return;
+    };
+    if inner.total.is_some() {
+        return;
+    }
+    inner.spans.push(StartupSpan {
+        phase,
+        start_ms: millis(started.saturating_duration_since(launched_at())),
+        duration_ms: millis(started.elapsed()),
+    });
+}
+
+/// Close the spans once the app is usable, printing the summary if asked to
+pub fn finish(app: &AppHandle) {
+    let Some(timings) = app.try_state::<StartupTimings>() else {
+        return;
+    };
+    let Ok(mut inner) = timings.0.lock() else {
+        return;
+    };
+    if inner.total.is_some() {
+        return;
+    }
+    let total = launched_at().elapsed();
+    inner.total = Some(total);
+    log_info!("Started in {} ms", millis(total));
+
+    if requested() {
+        log_info!("Startup profile:");
+        for span in &inner.spans {
+            log_info!(
+                "  {:<16} +{:>6} ms  {:>6} ms",
+                format!("{:?}", span.phase),
+                span.start_ms,
+                span.duration_ms
+            );
+        }
+    }
+}
+
+/// Timing of the startup phases
+#[tauri::command]
+pub fn get_startup_metrics(app: AppHandle) -> StartupMetrics {
+    let timings = app.state::<StartupTimings>();
+    let inner = timings.0.lock().ok();
+    StartupMetrics {
+        spans: inner
+            .as_ref()
+            .map(|inner| inner.spans.clone())
+            .unwrap_or_default(),
+        total_ms: inner.and_then(|inner| inner.total).map(millis),
+    }
+}
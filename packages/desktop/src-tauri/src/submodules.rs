@@ -0,0 +1,129 @@
+//! Submodules of a repository, and opening one in its own window.
+
+use git2::{Repository, SubmoduleIgnore, SubmoduleStatus};
+use serde::Serialize;
+use std::path::Path;
+use std::process::Stdio;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::timeout::{self, CommandClass};
+use crate::window::open_repo_in_window;
+use crate::{askpass, clone, git, native};
+
+/// A submodule and whether it is checked out
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmoduleInfo {
+    name: String,
+    /// Path relative to the parent repository
+    path: String,
+    url: Option<String>,
+    branch: Option<String>,
+    /// Commit the parent repository records for it
+    head_id: Option<String>,
+    /// Commit checked out in its working tree
+    workdir_id: Option<String>,
+    /// Whether it has been cloned into its working tree
+    initialized: bool,
+    /// Whether its checkout or contents differ from what the parent records
+    modified: bool,
+}
+
+fn list(repo: &Repository) -> Result<Vec<SubmoduleInfo>, git2::Error> {
+    let mut submodules = Vec::new();
+    for submodule in repo.submodules()? {
+        let name = submodule.name().unwrap_or_default().to_string();
+        let status = repo.submodule_status(&name, SubmoduleIgnore::Unspecified)?;
+        submodules.push(SubmoduleInfo {
+            path: submodule.path().to_string_lossy().replace('\\', "/"),
+            url: submodule.url().map(str::to_string),
+            branch: submodule.branch().map(str::to_string),
+            head_id: submodule.head_id().map(|id| id.to_string()),
+            workdir_id: submodule.workdir_id().map(|id| id.to_string()),
+            initialized: !status.contains(SubmoduleStatus::WD_UNINITIALIZED),
+            modified: status.intersects(
+                SubmoduleStatus::WD_MODIFIED
+                    | SubmoduleStatus::WD_INDEX_MODIFIED
+                    | SubmoduleStatus::WD_WD_MODIFIED
+                    | SubmoduleStatus::WD_UNTRACKED,
+            ),
+            name,
+        });
+    }
+    Ok(submodules)
+}
+
+/// List the submodules of the window's repository, or of `repo_path`
+#[tauri::command]
+pub async fn list_submodules(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo_path: Option<String>,
+) -> CommandResult<Vec<SubmoduleInfo>> {
+    native::with_repo(&app, &window, repo_path, list).await
+}
+
+/// Run `git submodule update --init` for one submodule, emitting
+/// `submodule-progress` as it clones
+async fn init_submodule(app: &AppHandle, repo_path: &str, path: &str) -> CommandResult<()> {
+    let mut child = git::command(
+        repo_path,
+        &["submodule", "update", "--init", "--progress", "--", path],
+    )
+    .env("GIT_TERMINAL_PROMPT", "0")
+    .envs(askpass::env(app))
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true)
+    .spawn()
+    .map_err(|e| AppError::new(ErrorCode::Internal, format!("Failed to run git: {}", e)))?;
+    let stderr = child.stderr.take().ok_or("Failed to read git output")?;
+    let errors = clone::read_progress(app, "submodule-progress", path, stderr).await?;
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to wait for git: {}", e))?;
+    if !status.success() {
+        return Err(AppError::from(format!(
+            "git submodule update: {}",
+            clone::error_tail(&errors)
+        )));
+    }
+    Ok(())
+}
+
+/// Open a submodule of the window's repository in its own window, cloning
+/// and checking it out first if it has not been initialized.
+/// `path` is relative to the parent repository.
+#[tauri::command]
+pub async fn open_submodule(
+    app: AppHandle,
+    window: WebviewWindow,
+    path: String,
+    request_id: Option<String>,
+) -> CommandResult<()> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let submodule = native::with_repo(&app, &window, None, list)
+        .await?
+        .into_iter()
+        .find(|submodule| submodule.path == path.replace('\\', "/"))
+        .ok_or_else(|| {
+            AppError::new(ErrorCode::NotFound, format!("No submodule at {}", path))
+                .with_metadata(serde_json::json!({ "path": path }))
+        })?;
+
+    if !submodule.initialized {
+        log_info!("Initializing submodule {} in {}", path, repo_path);
+        timeout::run(&app, CommandClass::Long, request_id, |_| {
+            init_submodule(&app, &repo_path, &submodule.path)
+        })
+        .await?;
+    }
+
+    let submodule_path = Path::new(&repo_path).join(&submodule.path);
+    open_repo_in_window(&app, &submodule_path.to_string_lossy()).await?;
+    Ok(())
+}
@@ -0,0 +1,254 @@
+//! A small always-on-top window, summoned by a global shortcut, for jumping
+//! to a bookmarked or recent repository. The list is filtered here with a
+//! fuzzy match so the webview stays a text box and a list; picking a
+//! repository focuses its window or opens one.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::broadcast::{repo_key, WindowRegistry};
+use crate::error::CommandResult;
+use crate::window::open_repo_in_window;
+use crate::{dev_frontend, i18n, navigation, recent, settings, theme};
+
+pub const WINDOW_LABEL: &str = "switcher";
+
+/// A repository offered by the switcher
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitcherEntry {
+    path: String,
+    name: String,
+    bookmarked: bool,
+    /// Whether a window already shows it
+    open: bool,
+}
+
+/// Whether a character starts a word of a path or name
+fn starts_word(previous: Option<char>) -> bool {
+    previous.is_none_or(|c| matches!(c, '/' | '\\' | '-' | '_' | '.' | ' '))
+}
+
+/// How well a query matches, higher being better, or `None` if its
+/// characters don't all appear in order. Runs of characters and matches at
+/// the start of words score extra; gaps cost a little.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut score = 0;
+    let mut previous = None;
+    let mut last_match: Option<usize> = None;
+    for (i, c) in candidate.chars().enumerate() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            score += 1;
+            match last_match {
+                Some(last) if last + 1 == i => score += 5,
+                Some(last) => score -= (i - last - 1).min(5) as i64,
+                None => {}
+            }
+            if starts_word(previous) {
+                score += 8;
+            }
+            last_match = Some(i);
+            query.next();
+        }
+        previous = Some(c);
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// Score of an entry, preferring matches on the repository name to ones that
+/// need the rest of the path
+fn entry_score(query: &str, entry: &SwitcherEntry) -> Option<i64> {
+    let by_name = fuzzy_score(query, &entry.name).map(|score| score + 20);
+    by_name.max(fuzzy_score(query, &entry.path))
+}
+
+fn repo_name(repo_path: &str) -> String {
+    Path::new(repo_path).file_name().map_or_else(
+        || repo_path.to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// Bookmarked repositories, then recent ones, without duplicates
+fn entries(app: &AppHandle) -> Vec<SwitcherEntry> {
+    let bookmarks = settings::current(app).bookmarked_repos;
+    let registry = app.state::<WindowRegistry>();
+    let mut seen = HashSet::new();
+    bookmarks
+        .iter()
+        .map(|path| (path.clone(), true))
+        .chain(recent::list(app).into_iter().map(|path| (path, false)))
+        .filter(|(path, _)| seen.insert(repo_key(path)))
+        .map(|(path, bookmarked)| SwitcherEntry {
+            name: repo_name(&path),
+            open: !registry.windows_for(&path).is_empty(),
+            path,
+            bookmarked,
+        })
+        .collect()
+}
+
+/// Entries matching a query, best match first. Ties keep bookmarks and
+/// recency order.
+fn filter(entries: Vec<SwitcherEntry>, query: &str) -> Vec<SwitcherEntry> {
+    let mut scored: Vec<_> = entries
+        .into_iter()
+        .filter_map(|entry| Some((entry_score(query, &entry)?, entry)))
+        .collect();
+    scored.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn create_window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    WebviewWindow::builder(app, WINDOW_LABEL, dev_frontend::webview_url())
+        .on_navigation(navigation::handler(app))
+        .title(i18n::t(app, "title.switch-repository"))
+        .inner_size(560.0, 360.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .theme(theme::native(app))
+        .initialization_script(&format!(
+            r#"
+            window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+            window.__OPENTUI__.view = "switcher";
+            window.__OPENTUI__.theme = "{}";
+            "#,
+            theme::name(app)
+        ))
+        .build()
+        .map_err(|e| format!("Failed to create switcher window: {}", e))
+}
+
+/// Show the switcher, or hide it if it is in front. It is kept around hidden
+/// so it comes up instantly; `switcher-shown` tells it to start afresh.
+pub fn toggle(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        if window.is_visible().unwrap_or(false) && window.is_focused().unwrap_or(false) {
+            let _ = window.hide();
+            return;
+        }
+        let _ = window.center();
+        let _ = window.show();
+        let _ = window.set_focus();
+        let _ = window.emit_to(WINDOW_LABEL, "switcher-shown", ());
+        return;
+    }
+
+    // Build off the event loop thread to avoid deadlocking webview creation
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match create_window(&app) {
+            Ok(window) => {
+                let _ = window.set_focus();
+            }
+            Err(e) => log_error!("{}", e),
+        }
+    });
+}
+
+/// Hide the switcher, e.g. when it loses focus
+pub fn hide(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.hide();
+    }
+}
+
+/// Bookmarked and recent repositories matching the query, best first
+#[tauri::command]
+pub fn get_switcher_repos(app: AppHandle, query: Option<String>) -> Vec<SwitcherEntry> {
+    filter(entries(&app), query.as_deref().unwrap_or_default())
+}
+
+/// Switch to a repository picked in the switcher, focusing its window or
+/// opening one
+#[tauri::command]
+pub async fn activate_switcher_repo(app: AppHandle, repo_path: String) -> CommandResult<()> {
+    hide(&app);
+    open_repo_in_window(&app, &repo_path).await?;
+    Ok(())
+}
+
+/// Close the switcher without picking anything
+#[tauri::command]
+pub fn close_switcher(app: AppHandle) {
+    hide(&app);
+}
+
+/// Add a repository to the bookmarks, which the switcher lists first, or
+/// remove it
+#[tauri::command]
+pub fn set_repo_bookmarked(
+    app: AppHandle,
+    repo_path: String,
+    bookmarked: bool,
+) -> CommandResult<()> {
+    let key = repo_key(&repo_path);
+    settings::update(&app, |settings| {
+        settings
+            .bookmarked_repos
+            .retain(|path| repo_key(path) != key);
+        if bookmarked {
+            settings.bookmarked_repos.push(repo_path);
+        }
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str) -> SwitcherEntry {
+        SwitcherEntry {
+            path: path.to_string(),
+            name: repo_name(path),
+            bookmarked: false,
+            open: false,
+        }
+    }
+
+    #[test]
+    fn matches_characters_in_order() {
+        assert!(fuzzy_score("otg", "opentui-git").is_some());
+        assert!(fuzzy_score("OTG", "opentui-git").is_some());
+        assert!(fuzzy_score("gto", "opentui-git").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn prefers_word_starts_and_runs() {
+        let word_starts = fuzzy_score("og", "opentui-git").unwrap();
+        let scattered = fuzzy_score("og", "prologue").unwrap();
+        assert!(word_starts > scattered);
+        let run = fuzzy_score("open", "opentui").unwrap();
+        let gaps = fuzzy_score("open", "oxpxexn").unwrap();
+        assert!(run > gaps);
+    }
+
+    #[test]
+    fn ranks_name_matches_first() {
+        let entries = vec![
+            entry("/nonexistent/web/frontend"),
+            entry("/nonexistent/api"),
+            entry("/nonexistent/webapp"),
+        ];
+        let names: Vec<_> = filter(entries, "web")
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["webapp", "frontend"]);
+    }
+}
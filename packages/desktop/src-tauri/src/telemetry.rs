@@ -0,0 +1,217 @@
+//! Opt-in local event journal: startup time, sidecar restarts and exits,
+//! crashes and feature usage, appended to a file in the app data directory.
+//! Nothing leaves the machine unless the user exports the journal and shares
+//! it, e.g. when reporting a performance problem.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::i18n;
+use crate::portable;
+use crate::settings;
+
+const JOURNAL_FILE: &str = "telemetry.jsonl";
+/// Once the journal grows past this it is moved aside and a new one started
+const MAX_JOURNAL_BYTES: u64 = 2 * 1024 * 1024;
+
+/// When the app started, and a lock serializing journal writes
+#[derive(Clone)]
+pub struct Telemetry {
+    started: Instant,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            write_lock: Arc::default(),
+        }
+    }
+}
+
+/// One journal line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    /// Milliseconds since the Unix epoch
+    time: u128,
+    event: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    data: Value,
+}
+
+/// The file written by `export_telemetry`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TelemetryExport {
+    app_version: String,
+    os: &'static str,
+    arch: &'static str,
+    events: Vec<JournalEntry>,
+}
+
+fn journal_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::data_dir(app)
+        .ok()
+        .map(|dir| dir.join(JOURNAL_FILE))
+}
+
+/// Journal moved aside when the current one got too big
+fn previous_journal_path(path: &std::path::Path) -> PathBuf {
+    path.with_extension("old.jsonl")
+}
+
+/// Journal files on disk, oldest first
+pub fn journal_files(app: &AppHandle) -> Vec<PathBuf> {
+    journal_path(app)
+        .map(|path| vec![previous_journal_path(&path), path])
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn is_enabled(app: &AppHandle) -> bool {
+    settings::current(app).telemetry
+}
+
+/// Append an event to the journal, if the user opted in
+pub fn record(app: &AppHandle, event: &str, data: Value) {
+    if !is_enabled(app) {
+        return;
+    }
+    let (Some(state), Some(path)) = (app.try_state::<Telemetry>(), journal_path(app)) else {
+        return;
+    };
+    let entry = JournalEntry {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default(),
+        event: event.to_string(),
+        data,
+    };
+    let Ok(line) = serde_json::to_string(&entry) else {
+        return;
+    };
+
+    let _guard = state.write_lock.lock();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    if std::fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_JOURNAL_BYTES) {
+        let _ = std::fs::rename(&path, previous_journal_path(&path));
+    }
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        log_error!("Failed to write telemetry journal: {}", e);
+    }
+}
+
+/// Record how long the app took from launch to its first window
+pub fn record_startup(app: &AppHandle) {
+    let Some(state) = app.try_state::<Telemetry>() else {
+        return;
+    };
+    let elapsed = state.started.elapsed().as_millis();
+    record(app, "startup", serde_json::json!({ "ms": elapsed }));
+}
+
+/// Record panics as crashes, then hand them to the default hook
+pub fn install_panic_hook(app: &AppHandle) {
+    let app = app.clone();
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        record(
+            &app,
+            "crash",
+            serde_json::json!({
+                "message": message,
+                "location": info.location().map(|location| location.to_string()),
+            }),
+        );
+        default_hook(info);
+    }));
+}
+
+/// Record that a UI feature was used, e.g. `blame` or `interactive-rebase`
+#[tauri::command]
+pub fn record_feature_usage(app: AppHandle, feature: String, data: Option<Value>) {
+    record(
+        &app,
+        "feature",
+        serde_json::json!({ "feature": feature, "data": data }),
+    );
+}
+
+/// Save the journal as one JSON file, chosen with a save dialog, to attach to
+/// a bug report. Returns the file's path, or `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_telemetry(app: AppHandle) -> CommandResult<Option<String>> {
+    let mut events = Vec::new();
+    for journal in journal_files(&app) {
+        let Ok(contents) = std::fs::read_to_string(&journal) else {
+            continue;
+        };
+        events.extend(
+            contents
+                .lines()
+                .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok()),
+        );
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title(i18n::t(&app, "title.export-telemetry"))
+        .set_file_name("opentui-git-telemetry.json")
+        .add_filter("JSON", &["json"])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let Some(dest) = rx.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let dest = dest
+        .into_path()
+        .map_err(|e| format!("Invalid path: {}", e))?;
+
+    let export = TelemetryExport {
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        events,
+    };
+    let contents = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize telemetry: {}", e))?;
+    std::fs::write(&dest, contents).map_err(|e| {
+        AppError::new(
+            ErrorCode::Internal,
+            format!("Failed to write {}: {}", dest.display(), e),
+        )
+    })?;
+
+    log_info!(
+        "Exported {} telemetry events to {}",
+        export.events.len(),
+        dest.display()
+    );
+    Ok(Some(dest.to_string_lossy().to_string()))
+}
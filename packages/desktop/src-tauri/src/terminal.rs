@@ -0,0 +1,196 @@
+use std::path::Path;
+use std::process::{Child, Command};
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+#[cfg(not(target_os = "macos"))]
+use crate::launch::find_executable;
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::launch::program_name;
+#[cfg(target_os = "macos")]
+use crate::launch::shell_quote;
+use crate::launch::split_words;
+use crate::{git, launch, settings};
+
+/// Process creation flag giving cmd a console window of its own
+#[cfg(target_os = "windows")]
+const CREATE_NEW_CONSOLE: u32 = 0x0000_0010;
+
+/// Run a command in a new terminal window
+pub fn run_in(words: &[String], cwd: &str) -> std::io::Result<Child> {
+    #[cfg(target_os = "macos")]
+    {
+        let script = format!(
+            "cd {} && {}",
+            shell_quote(cwd),
+            words
+                .iter()
+                .map(|w| shell_quote(w))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let escaped = script.replace('\\', "\\\\").replace('"', "\\\"");
+        Command::new("osascript")
+            .args([
+                "-e",
+                &format!("tell application \"Terminal\" to do script \"{}\"", escaped),
+                "-e",
+                "tell application \"Terminal\" to activate",
+            ])
+            .spawn()
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let terminal = std::env::var("TERMINAL")
+            .ok()
+            .into_iter()
+            .chain(
+                ["x-terminal-emulator", "gnome-terminal", "konsole", "xterm"]
+                    .iter()
+                    .map(|t| t.to_string()),
+            )
+            .find(|t| find_executable(t).is_some())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "No terminal emulator found")
+            })?;
+        // gnome-terminal takes the command after `--`, the others after `-e`
+        let separator = if program_name(&terminal) == "gnome-terminal" {
+            "--"
+        } else {
+            "-e"
+        };
+        Command::new(&terminal)
+            .arg(separator)
+            .args(words)
+            .current_dir(cwd)
+            .spawn()
+    }
+
+    // The new console's cmd parses the line, so every word is escaped for it
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        let line = words
+            .iter()
+            .map(|w| launch::cmd_quote(w))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Command::new("cmd")
+            .arg("/K")
+            .raw_arg(line)
+            .creation_flags(CREATE_NEW_CONSOLE)
+            .current_dir(cwd)
+            .spawn()
+    }
+}
+
+/// Command line for a user-configured template with a `{dir}` placeholder
+fn from_template(template: &str, dir: &str) -> Vec<String> {
+    let mut words = split_words(template);
+    if !template.contains("{dir}") {
+        words.push(dir.to_string());
+    }
+    words
+        .into_iter()
+        .map(|word| word.replace("{dir}", dir))
+        .collect()
+}
+
+/// Open the platform's terminal at a directory
+fn open_at(dir: &str) -> std::io::Result<Child> {
+    #[cfg(target_os = "macos")]
+    {
+        let terminal = if Path::new("/Applications/iTerm.app").exists() {
+            "iTerm"
+        } else {
+            "Terminal"
+        };
+        Command::new("open").args(["-a", terminal, dir]).spawn()
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let terminal = std::env::var("TERMINAL")
+            .ok()
+            .into_iter()
+            .chain(
+                ["x-terminal-emulator", "gnome-terminal", "konsole", "xterm"]
+                    .iter()
+                    .map(|t| t.to_string()),
+            )
+            .find(|t| find_executable(t).is_some())
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "No terminal emulator found")
+            })?;
+        let mut command = Command::new(&terminal);
+        match program_name(&terminal).as_str() {
+            "gnome-terminal" => {
+                command.arg(format!("--working-directory={}", dir));
+            }
+            "konsole" => {
+                command.args(["--workdir", dir]);
+            }
+            _ => {}
+        }
+        command.current_dir(dir).spawn()
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if find_executable("wt").is_some() {
+            Command::new("wt").args(["-d", dir]).spawn()
+        } else {
+            use std::os::windows::process::CommandExt;
+            Command::new("cmd")
+                .creation_flags(CREATE_NEW_CONSOLE)
+                .current_dir(dir)
+                .spawn()
+        }
+    }
+}
+
+/// Open a terminal at the window's repository, or a subdirectory of it
+#[tauri::command]
+pub fn open_in_terminal(
+    app: AppHandle,
+    window: WebviewWindow,
+    subdir: Option<String>,
+) -> CommandResult<()> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    let dir = match &subdir {
+        Some(subdir) => Path::new(&repo_path).join(subdir),
+        None => Path::new(&repo_path).to_path_buf(),
+    };
+    if !dir.is_dir() {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("Directory not found: {}", dir.display()),
+        ));
+    }
+    let dir = dir.to_string_lossy().to_string();
+
+    let template = settings::current(&app)
+        .terminal_command
+        .filter(|template| !template.trim().is_empty());
+    let child = match &template {
+        Some(template) => {
+            let words = from_template(template, &dir);
+            Command::new(&words[0])
+                .args(&words[1..])
+                .current_dir(&dir)
+                .spawn()
+        }
+        None => open_at(&dir),
+    }
+    .map_err(|e| {
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("Failed to open terminal: {}", e),
+        )
+    })?;
+
+    log_info!("Opened terminal at {}", dir);
+    launch::reap(child);
+    Ok(())
+}
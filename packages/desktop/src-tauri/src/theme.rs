@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, Theme};
+
+use crate::settings;
+
+/// Theme the user chose in settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemePreference {
+    /// Follow the OS
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Last theme the OS reported
+#[derive(Clone)]
+pub struct ThemeState(Arc<Mutex<Theme>>);
+
+impl Default for ThemeState {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(detect())))
+    }
+}
+
+/// Payload of the `theme-changed` event
+#[derive(Debug, Clone, Serialize)]
+struct ThemeChangedEvent {
+    theme: &'static str,
+}
+
+fn is_dark(output: std::io::Result<std::process::Output>, needle: &str) -> Option<bool> {
+    let output = output.ok()?;
+    Some(
+        output.status.success()
+            && String::from_utf8_lossy(&output.stdout)
+                .to_lowercase()
+                .contains(needle),
+    )
+}
+
+/// Ask the OS for its theme before any window exists to report it
+fn detect() -> Theme {
+    #[cfg(target_os = "macos")]
+    let dark = is_dark(
+        Command::new("defaults")
+            .args(["read", "-g", "AppleInterfaceStyle"])
+            .output(),
+        "dark",
+    );
+
+    #[cfg(target_os = "windows")]
+    let dark = is_dark(
+        Command::new("reg")
+            .args([
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ])
+            .output(),
+        "0x0",
+    );
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let dark = is_dark(
+        Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+            .output(),
+        "dark",
+    );
+
+    if dark.unwrap_or(false) {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+/// Theme windows should use, after the user's override
+pub fn effective(app: &AppHandle) -> Theme {
+    match settings::current(app).theme {
+        ThemePreference::Light => Theme::Light,
+        ThemePreference::Dark => Theme::Dark,
+        ThemePreference::System => app
+            .try_state::<ThemeState>()
+            .and_then(|state| state.0.lock().ok().map(|theme| *theme))
+            .unwrap_or_else(detect),
+    }
+}
+
+/// Name of the effective theme, as injected into `window.__OPENTUI__.theme`
+pub fn name(app: &AppHandle) -> &'static str {
+    match effective(app) {
+        Theme::Dark => "dark",
+        _ => "light",
+    }
+}
+
+/// Native theme for window titlebars; `None` follows the OS
+pub fn native(app: &AppHandle) -> Option<Theme> {
+    match settings::current(app).theme {
+        ThemePreference::System => None,
+        _ => Some(effective(app)),
+    }
+}
+
+/// Apply the current theme to every window and tell the UI
+pub fn refresh(app: &AppHandle) {
+    let native = native(app);
+    for window in app.webview_windows().values() {
+        let _ = window.set_theme(native);
+    }
+    let _ = app.emit("theme-changed", ThemeChangedEvent { theme: name(app) });
+}
+
+/// Record a theme change reported by the OS through a window
+pub fn handle_os_change(app: &AppHandle, theme: Theme) {
+    let Some(state) = app.try_state::<ThemeState>() else {
+        return;
+    };
+    // Every window reports the same change
+    let Ok(mut current) = state.0.lock() else {
+        return;
+    };
+    if *current == theme {
+        return;
+    }
+    *current = theme;
+    drop(current);
+
+    if settings::current(app).theme == ThemePreference::System {
+        log_info!("OS theme changed to {}", name(app));
+        let _ = app.emit("theme-changed", ThemeChangedEvent { theme: name(app) });
+    }
+}
+
+/// Get the theme the UI should render with, "light" or "dark"
+#[tauri::command]
+pub fn get_theme(app: AppHandle) -> &'static str {
+    name(&app)
+}
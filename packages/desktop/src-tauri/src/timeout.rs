@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::settings;
+
+/// How long a class of command may run before it is abandoned
+#[derive(Debug, Clone, Copy)]
+pub enum CommandClass {
+    /// Local work such as starting a server or opening a window
+    Local,
+    /// Requests to remotes or update servers
+    Network,
+    /// Multi-stage operations such as installing an update
+    Long,
+}
+
+/// Per-class timeouts, configurable in settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CommandTimeouts {
+    pub local_secs: u64,
+    pub network_secs: u64,
+    pub long_secs: u64,
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        Self {
+            local_secs: 30,
+            network_secs: 120,
+            long_secs: 600,
+        }
+    }
+}
+
+impl CommandTimeouts {
+    fn limit(&self, class: CommandClass) -> Duration {
+        Duration::from_secs(match class {
+            CommandClass::Local => self.local_secs,
+            CommandClass::Network => self.network_secs,
+            CommandClass::Long => self.long_secs,
+        })
+    }
+}
+
+#[derive(Default)]
+struct CancelSignal {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cooperative cancellation signal handed to a running command
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<CancelSignal>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fail with a cancellation error if the token was cancelled
+    pub fn check(&self) -> CommandResult<()> {
+        if self.is_cancelled() {
+            return Err(AppError::new(ErrorCode::Cancelled, "Cancelled"));
+        }
+        Ok(())
+    }
+
+    /// Resolve once the token is cancelled
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking so a concurrent cancel is not missed
+            let notified = self.0.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Tokens of commands started with a request id, so the frontend can cancel them
+#[derive(Clone, Default)]
+pub struct CancelRegistry(Arc<Mutex<HashMap<String, CancelToken>>>);
+
+/// Run a command body under its class timeout, cancellable by request id.
+///
+/// The body is dropped on timeout or cancellation, which aborts in-flight
+/// HTTP requests. Work that must not be interrupted midway should check the
+/// token between stages and run its final stage in a detached task.
+pub async fn run<T, F, Fut>(
+    app: &AppHandle,
+    class: CommandClass,
+    request_id: Option<String>,
+    body: F,
+) -> CommandResult<T>
+where
+    F: FnOnce(CancelToken) -> Fut,
+    Fut: Future<Output = CommandResult<T>>,
+{
+    let token = CancelToken::default();
+    let registry = app.state::<CancelRegistry>().inner().clone();
+    if let Some(id) = &request_id {
+        if let Ok(mut tokens) = registry.0.lock() {
+            tokens.insert(id.clone(), token.clone());
+        }
+    }
+
+    let limit = settings::current(app).command_timeouts.limit(class);
+    let result = tokio::select! {
+        result = body(token.clone()) => result,
+        _ = token.cancelled() => Err(AppError::new(ErrorCode::Cancelled, "Cancelled")),
+        _ = tokio::time::sleep(limit) => {
+            // Let any detached stages see the timeout too
+            token.cancel();
+            Err(AppError::new(
+                ErrorCode::Timeout,
+                format!("Timed out after {} seconds", limit.as_secs()),
+            )
+            .with_metadata(serde_json::json!({ "timeoutSecs": limit.as_secs() })))
+        }
+    };
+
+    if let Some(id) = &request_id {
+        if let Ok(mut tokens) = registry.0.lock() {
+            tokens.remove(id);
+        }
+    }
+    result
+}
+
+/// Cancel a running command by the request id it was started with.
+/// Returns false if no such command is running.
+#[tauri::command]
+pub fn cancel_command(app: AppHandle, request_id: String) -> bool {
+    let token = app
+        .state::<CancelRegistry>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|mut tokens| tokens.remove(&request_id));
+
+    match token {
+        Some(token) => {
+            log_info!("Cancelling command {}", request_id);
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,96 @@
+//! Window controls for the integrated titlebar the UI draws when native
+//! decorations are turned off.
+
+use tauri::{AppHandle, Runtime, WebviewWindow, WebviewWindowBuilder};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::settings;
+
+fn window_error(e: tauri::Error) -> AppError {
+    AppError::new(
+        ErrorCode::Internal,
+        format!("Window operation failed: {}", e),
+    )
+}
+
+/// Whether windows are created without native decorations
+pub fn is_custom(app: &AppHandle) -> bool {
+    settings::current(app).custom_titlebar
+}
+
+/// Drop native decorations from a window being built when the custom titlebar
+/// is on. macOS keeps its traffic lights over the webview instead.
+pub fn configure<'a, R: Runtime, M: tauri::Manager<R>>(
+    app: &AppHandle,
+    builder: WebviewWindowBuilder<'a, R, M>,
+) -> WebviewWindowBuilder<'a, R, M> {
+    if !is_custom(app) {
+        return builder;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        builder
+            .title_bar_style(tauri::TitleBarStyle::Overlay)
+            .hidden_title(true)
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        builder.decorations(false)
+    }
+}
+
+/// What double-clicking a titlebar does on macOS, from System Settings
+#[cfg(target_os = "macos")]
+fn double_click_action() -> String {
+    std::process::Command::new("defaults")
+        .args(["read", "-g", "AppleActionOnDoubleClick"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| "Maximize".to_string())
+}
+
+fn toggle(window: &WebviewWindow) -> tauri::Result<()> {
+    if window.is_maximized()? {
+        window.unmaximize()
+    } else {
+        window.maximize()
+    }
+}
+
+/// Minimize the calling window
+#[tauri::command]
+pub fn minimize(window: WebviewWindow) -> CommandResult<()> {
+    window.minimize().map_err(window_error)
+}
+
+/// Maximize the calling window, or restore it if already maximized
+#[tauri::command]
+pub fn toggle_maximize(window: WebviewWindow) -> CommandResult<()> {
+    toggle(&window).map_err(window_error)
+}
+
+/// Close the calling window, going through the usual close handling
+#[tauri::command]
+pub fn close_window(window: WebviewWindow) -> CommandResult<()> {
+    window.close().map_err(window_error)
+}
+
+/// Start moving the calling window with the mouse, called on mousedown in the titlebar
+#[tauri::command]
+pub fn start_drag(window: WebviewWindow) -> CommandResult<()> {
+    window.start_dragging().map_err(window_error)
+}
+
+/// Handle a double click on the titlebar the way the OS would
+#[tauri::command]
+pub fn titlebar_double_click(window: WebviewWindow) -> CommandResult<()> {
+    #[cfg(target_os = "macos")]
+    match double_click_action().as_str() {
+        "Minimize" => return window.minimize().map_err(window_error),
+        "None" => return Ok(()),
+        _ => {}
+    }
+    toggle(&window).map_err(window_error)
+}
@@ -0,0 +1,312 @@
+//! Optional TLS between the webviews and the sidecars, for security policies
+//! that forbid plaintext services even on loopback. The app keeps a local CA
+//! in its data directory and hands each sidecar it starts a certificate
+//! issued by it. That certificate is replaced at startup once it is due, or
+//! on request, and only ever while the sidecars are restarted with it.
+//!
+//! Webviews only connect if the OS trusts the CA, so `get_tls_info` reports
+//! where it is for the user to trust it once.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::sidecar::ServerState;
+use crate::{loopback, portable, settings, standby};
+
+const TLS_DIR: &str = "tls";
+const CA_CERT_FILE: &str = "ca.crt";
+const CA_KEY_FILE: &str = "ca.key";
+const CERT_FILE: &str = "server.crt";
+const KEY_FILE: &str = "server.key";
+/// Environment variables passing the certificate and key paths to the sidecar
+const CERT_VAR: &str = "OPENTUI_TLS_CERT";
+const KEY_VAR: &str = "OPENTUI_TLS_KEY";
+/// How long the CA is valid. It is trusted by hand, so it lasts.
+const CA_VALIDITY: Duration = Duration::from_secs(10 * 365 * 24 * 60 * 60);
+const CA_NAME: &str = "opentui-git local CA";
+/// How long a server certificate is valid
+const VALIDITY: Duration = Duration::from_secs(90 * 24 * 60 * 60);
+/// Age at which a certificate is replaced, well before it expires
+const ROTATE_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+/// Names the certificate is valid for
+const SUBJECT_NAMES: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+/// Whether this launch serves over TLS. Read from the settings once at startup,
+/// since every sidecar and window of a launch must agree on the scheme.
+#[derive(Clone)]
+pub struct TlsState {
+    enabled: bool,
+    /// Serializes certificate generation
+    lock: Arc<Mutex<()>>,
+}
+
+impl TlsState {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            lock: Arc::default(),
+        }
+    }
+}
+
+/// Certificate details returned by `get_tls_info`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsInfo {
+    /// Whether the sidecars of this launch serve HTTPS
+    enabled: bool,
+    /// Whether the setting differs from this launch, pending a restart
+    restart_required: bool,
+    /// The CA, for trusting it in the OS
+    cert_path: Option<String>,
+    cert_pem: Option<String>,
+    /// When the server certificate was issued, in seconds since the Unix epoch
+    created_at: Option<u64>,
+    expires_at: Option<u64>,
+}
+
+fn tls_dir(app: &AppHandle) -> Option<PathBuf> {
+    portable::data_dir(app).ok().map(|dir| dir.join(TLS_DIR))
+}
+
+/// Whether sidecars of this launch serve HTTPS
+pub fn enabled(app: &AppHandle) -> bool {
+    app.try_state::<TlsState>()
+        .is_some_and(|state| state.enabled)
+}
+
+/// Base URL of the sidecar on a port
+pub fn base_url(app: &AppHandle, port: u32) -> String {
+    let scheme = if enabled(app) { "https" } else { "http" };
+    let host = loopback::connect_host(app, port);
+    format!("{}://{}:{}", scheme, loopback::url_host(&host), port)
+}
+
+/// When the certificate on disk was written
+fn created_at(cert_path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(cert_path).ok()?.modified().ok()
+}
+
+fn to_offset(at: SystemTime) -> Result<time::OffsetDateTime, String> {
+    let secs = at
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| e.to_string())?
+        .as_secs();
+    time::OffsetDateTime::from_unix_timestamp(secs as i64).map_err(|e| e.to_string())
+}
+
+/// Parameters of the CA. Signing needs only its name and key, so they are
+/// rebuilt from these rather than parsed from the certificate on disk.
+fn ca_params(now: SystemTime) -> Result<rcgen::CertificateParams, String> {
+    let mut params = rcgen::CertificateParams::default();
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, CA_NAME);
+    params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Constrained(0));
+    params.key_usages = vec![
+        rcgen::KeyUsagePurpose::KeyCertSign,
+        rcgen::KeyUsagePurpose::CrlSign,
+    ];
+    params.not_before = to_offset(now - Duration::from_secs(60 * 60))?;
+    params.not_after = to_offset(now + CA_VALIDITY)?;
+    Ok(params)
+}
+
+/// Write a key, readable only by the user where the OS allows
+fn write_key(path: &std::path::Path, key_pair: &rcgen::KeyPair) -> Result<(), String> {
+    std::fs::write(path, key_pair.serialize_pem())
+        .map_err(|e| format!("Failed to write TLS key: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+/// Write a new CA certificate and key
+fn generate_ca(dir: &std::path::Path) -> Result<(), String> {
+    let key_pair =
+        rcgen::KeyPair::generate().map_err(|e| format!("Failed to generate key: {}", e))?;
+    let cert = ca_params(SystemTime::now())?
+        .self_signed(&key_pair)
+        .map_err(|e| format!("Failed to sign CA certificate: {}", e))?;
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create TLS directory: {}", e))?;
+    write_key(&dir.join(CA_KEY_FILE), &key_pair)?;
+    std::fs::write(dir.join(CA_CERT_FILE), cert.pem())
+        .map_err(|e| format!("Failed to write CA certificate: {}", e))?;
+    log_info!("Generated TLS CA in {}", dir.display());
+    Ok(())
+}
+
+/// Write a new server certificate and key, issued by the CA
+fn issue(dir: &std::path::Path) -> Result<(), String> {
+    let now = SystemTime::now();
+    let ca_key_pem = std::fs::read_to_string(dir.join(CA_KEY_FILE))
+        .map_err(|e| format!("Failed to read CA key: {}", e))?;
+    let ca_key =
+        rcgen::KeyPair::from_pem(&ca_key_pem).map_err(|e| format!("Invalid CA key: {}", e))?;
+    let ca = ca_params(now)?
+        .self_signed(&ca_key)
+        .map_err(|e| format!("Failed to load CA: {}", e))?;
+
+    let mut params = rcgen::CertificateParams::new(
+        SUBJECT_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>(),
+    )
+    .map_err(|e| format!("Invalid certificate parameters: {}", e))?;
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "localhost");
+    params.use_authority_key_identifier_extension = true;
+    params.key_usages = vec![rcgen::KeyUsagePurpose::DigitalSignature];
+    params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ServerAuth];
+    // Allow for clocks that are slightly behind
+    params.not_before = to_offset(now - Duration::from_secs(60 * 60))?;
+    params.not_after = to_offset(now + VALIDITY)?;
+
+    let key_pair =
+        rcgen::KeyPair::generate().map_err(|e| format!("Failed to generate key: {}", e))?;
+    let cert = params
+        .signed_by(&key_pair, &ca, &ca_key)
+        .map_err(|e| format!("Failed to sign certificate: {}", e))?;
+
+    write_key(&dir.join(KEY_FILE), &key_pair)?;
+    std::fs::write(dir.join(CERT_FILE), cert.pem())
+        .map_err(|e| format!("Failed to write TLS certificate: {}", e))?;
+    log_info!("Issued TLS certificate in {}", dir.display());
+    Ok(())
+}
+
+/// Whether the server certificate is old enough to be replaced
+fn rotation_due(cert_path: &std::path::Path) -> bool {
+    !matches!(
+        created_at(cert_path).and_then(|created| created.elapsed().ok()),
+        Some(age) if age < ROTATE_AFTER
+    )
+}
+
+/// Certificate and key paths, creating the CA and a certificate if missing.
+/// `rotate` replaces the certificate, which running sidecars would go on
+/// serving, so it is only set while none run or all are restarted.
+fn ensure(app: &AppHandle, rotate: bool) -> Result<(PathBuf, PathBuf), String> {
+    let state = app.state::<TlsState>();
+    let _guard = state.lock.lock();
+    let dir = tls_dir(app).ok_or("Data directory not available")?;
+    let (cert_path, key_path) = (dir.join(CERT_FILE), dir.join(KEY_FILE));
+
+    let new_ca = !dir.join(CA_KEY_FILE).is_file() || !dir.join(CA_CERT_FILE).is_file();
+    if new_ca {
+        generate_ca(&dir)?;
+    }
+    if rotate || new_ca || !key_path.is_file() || !cert_path.is_file() {
+        issue(&dir)?;
+    }
+    Ok((cert_path, key_path))
+}
+
+/// Prepare the certificates at startup, before any sidecar serves them,
+/// replacing the server certificate if it is due
+pub fn prepare(app: &AppHandle) {
+    if !enabled(app) {
+        return;
+    }
+    let due = tls_dir(app).is_some_and(|dir| rotation_due(&dir.join(CERT_FILE)));
+    if let Err(e) = ensure(app, due) {
+        log_error!("{}", e);
+    }
+}
+
+/// Environment passing the certificate to a sidecar, if this launch uses TLS
+pub fn env(app: &AppHandle) -> Vec<(String, String)> {
+    if !enabled(app) {
+        return Vec::new();
+    }
+    match ensure(app, false) {
+        Ok((cert_path, key_path)) => vec![
+            (
+                CERT_VAR.to_string(),
+                cert_path.to_string_lossy().to_string(),
+            ),
+            (KEY_VAR.to_string(), key_path.to_string_lossy().to_string()),
+        ],
+        Err(e) => {
+            log_error!("{}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// CA to trust when connecting to sidecars, if this launch uses TLS. Any
+/// certificate it issued is accepted, so a rotation doesn't cut off sidecars
+/// still serving the previous one.
+pub fn root_certificate(app: &AppHandle) -> Option<reqwest::Certificate> {
+    if !enabled(app) {
+        return None;
+    }
+    ensure(app, false).ok()?;
+    let pem = std::fs::read(tls_dir(app)?.join(CA_CERT_FILE)).ok()?;
+    reqwest::Certificate::from_pem(&pem).ok()
+}
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// Whether TLS is on and where its CA is, for trusting it in the OS
+#[tauri::command]
+pub fn get_tls_info(app: AppHandle) -> TlsInfo {
+    let enabled = enabled(&app);
+    let dir = tls_dir(&app);
+    let cert_path = dir
+        .as_ref()
+        .map(|dir| dir.join(CA_CERT_FILE))
+        .filter(|path| path.is_file());
+    let created = dir.and_then(|dir| created_at(&dir.join(CERT_FILE)));
+
+    TlsInfo {
+        enabled,
+        restart_required: settings::current(&app).tls != enabled,
+        cert_pem: cert_path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok()),
+        cert_path: cert_path.map(|path| path.to_string_lossy().to_string()),
+        created_at: created.and_then(unix_secs),
+        expires_at: created.and_then(|created| unix_secs(created + VALIDITY)),
+    }
+}
+
+/// Replace the server certificate now and restart the sidecars to serve the
+/// new one. The CA stays, so the OS keeps trusting it.
+#[tauri::command]
+pub async fn rotate_tls_certificate(app: AppHandle) -> CommandResult<TlsInfo> {
+    if !enabled(&app) {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            "TLS is not enabled for this session",
+        ));
+    }
+    ensure(&app, true)?;
+
+    // Standby sidecars would come up with the old certificate
+    standby::drain(&app);
+    for sidecar in app.state::<ServerState>().all() {
+        if !crate::sidecar::respawn_sidecar(&app, &sidecar).await {
+            log_error!(
+                "Server for {} failed to restart with the new certificate",
+                sidecar.repo_path
+            );
+        }
+    }
+    standby::fill(&app);
+    Ok(get_tls_info(app))
+}
@@ -0,0 +1,79 @@
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Wry};
+
+use crate::i18n;
+
+const TRAY_ID: &str = "main";
+
+/// Create the tray icon if it doesn't exist yet
+pub fn ensure(app: &AppHandle) {
+    if app.tray_by_id(TRAY_ID).is_some() {
+        return;
+    }
+
+    if let Err(e) = build(app) {
+        log_error!("Failed to create tray icon: {}", e);
+    }
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let show = MenuItem::with_id(
+        app,
+        "tray-show",
+        i18n::t(app, "tray.show"),
+        true,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(
+        app,
+        "tray-quit",
+        i18n::t(app, "tray.quit"),
+        true,
+        None::<&str>,
+    )?;
+    Menu::with_items(app, &[&show, &PredefinedMenuItem::separator(app)?, &quit])
+}
+
+/// Rebuild the tray menu, e.g. after the language changed
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(e) = build_menu(app).and_then(|menu| tray.set_menu(Some(menu))) {
+        log_error!("Failed to refresh tray menu: {}", e);
+    }
+}
+
+fn build(app: &AppHandle) -> tauri::Result<()> {
+    let menu = build_menu(app)?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("opentui-git")
+        .menu(&menu)
+        .on_menu_event(|app, event| match event.id().as_ref() {
+            "tray-show" => crate::window::show_main_window(app),
+            "tray-quit" => {
+                tauri::async_runtime::spawn(crate::request_quit(app.clone()));
+            }
+            _ => {}
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                crate::window::show_main_window(tray.app_handle());
+            }
+        });
+
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+
+    builder.build(app)?;
+    log_info!("Tray icon created");
+    Ok(())
+}
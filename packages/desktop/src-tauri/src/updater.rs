@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_updater::UpdaterExt;
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::shutdown::{self, ExitReason};
+use crate::sidecar::{
+    emit_reconnected, get_running_operations, is_server_running, kill_sidecar, respawn_sidecar,
+    ServerState, Sidecar,
+};
+use crate::timeout::{self, CancelToken, CommandClass};
+use crate::{ipc, portable, standby, QuitState};
+
+const RESTART_STATE_FILE: &str = "restart-state.json";
+/// How long to wait for in-flight git operations before giving up on updating
+const DRAIN_TIMEOUT_SECS: u64 = 30;
+/// How long the old server gets to exit on its own before it is killed
+const SHUTDOWN_TIMEOUT_SECS: u64 = 3;
+
+/// Result of an update check
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    available: bool,
+    current_version: String,
+    version: Option<String>,
+    notes: Option<String>,
+}
+
+/// Payload of the `update-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateProgressEvent {
+    stage: &'static str,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+/// State carried across the post-update restart
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RestartState {
+    /// Open repositories, primary first
+    repo_paths: Vec<String>,
+}
+
+fn restart_state_path(app: &AppHandle) -> Option<PathBuf> {
+    portable::data_dir(app)
+        .ok()
+        .map(|dir| dir.join(RESTART_STATE_FILE))
+}
+
+/// Take the repo paths saved before an update restart, primary first
+pub fn take_restart_repos(app: &AppHandle) -> Vec<String> {
+    let Some(path) = restart_state_path(app) else {
+        return Vec::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let _ = std::fs::remove_file(&path);
+
+    serde_json::from_str::<RestartState>(&contents)
+        .map(|state| state.repo_paths)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|repo| !repo.is_empty())
+        .collect()
+}
+
+fn save_restart_state(app: &AppHandle, repo_paths: Vec<String>) -> Result<(), String> {
+    let path = restart_state_path(app).ok_or("App data directory not available")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+    let contents = serde_json::to_string(&RestartState { repo_paths })
+        .map_err(|e| format!("Failed to serialize restart state: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write restart state: {}", e))
+}
+
+/// Wrap an updater error, flagging connectivity problems as network errors
+fn updater_error(context: &str, e: tauri_plugin_updater::Error) -> AppError {
+    let code = match e {
+        tauri_plugin_updater::Error::Reqwest(_) => ErrorCode::Network,
+        _ => ErrorCode::Internal,
+    };
+    AppError::new(code, format!("{}: {}", context, e))
+}
+
+/// Whether this build can install updates
+#[derive(Clone, Copy)]
+pub struct UpdatesEnabled(bool);
+
+/// Public key update bundles are verified with. Only release builds, made
+/// with `tauri.release.conf.json`, configure one.
+fn public_key(app: &AppHandle) -> Option<String> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")?
+        .get("pubkey")?
+        .as_str()
+        .filter(|key| !key.trim().is_empty())
+        .map(str::to_string)
+}
+
+/// Register the updater plugin, if this build has a public key to verify
+/// updates with
+pub fn register(app: &AppHandle) -> tauri::Result<()> {
+    let enabled = public_key(app).is_some();
+    if enabled {
+        app.plugin(tauri_plugin_updater::Builder::new().build())?;
+    } else {
+        log_info!("Updates disabled: no update public key configured");
+    }
+    app.manage(UpdatesEnabled(enabled));
+    Ok(())
+}
+
+/// The updater, or an error if this build can't verify updates
+fn updater(app: &AppHandle) -> CommandResult<tauri_plugin_updater::Updater> {
+    if !app
+        .try_state::<UpdatesEnabled>()
+        .is_some_and(|enabled| enabled.0)
+    {
+        return Err(AppError::new(
+            ErrorCode::Conflict,
+            "Updates are not available in this build",
+        ));
+    }
+    app.updater()
+        .map_err(|e| updater_error("Updater not available", e))
+}
+
+/// Wait for the server to finish in-flight git operations
+async fn drain_operations(
+    app: &AppHandle,
+    sidecar: &Sidecar,
+    token: &CancelToken,
+) -> CommandResult<()> {
+    let start = Instant::now();
+    loop {
+        token.check()?;
+        let operations = get_running_operations(app, sidecar).await;
+        if operations.is_empty() {
+            return Ok(());
+        }
+        if start.elapsed() > Duration::from_secs(DRAIN_TIMEOUT_SECS) {
+            return Err(AppError::new(
+                ErrorCode::Lock,
+                format!(
+                    "Cannot update while git operations are running: {}",
+                    operations.join(", ")
+                ),
+            )
+            .with_metadata(serde_json::json!({ "operations": operations })));
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Check whether a newer version is available
+#[tauri::command]
+pub async fn check_for_updates(
+    app: AppHandle,
+    request_id: Option<String>,
+) -> CommandResult<UpdateInfo> {
+    let current_version = app.package_info().version.to_string();
+    let update = timeout::run(&app, CommandClass::Network, request_id, |_| async {
+        updater(&app)?
+            .check()
+            .await
+            .map_err(|e| updater_error("Failed to check for updates", e))
+    })
+    .await?;
+
+    Ok(match update {
+        Some(update) => UpdateInfo {
+            available: true,
+            current_version,
+            version: Some(update.version.clone()),
+            notes: update.body.clone(),
+        },
+        None => UpdateInfo {
+            available: false,
+            current_version,
+            version: None,
+            notes: None,
+        },
+    })
+}
+
+/// Bring the servers back after a failed install, since the app keeps
+/// running the old version on the repositories it had open
+async fn recover(app: &AppHandle, sidecars: &[Sidecar], size: u64) {
+    if let Some(path) = restart_state_path(app) {
+        let _ = std::fs::remove_file(path);
+    }
+    for sidecar in sidecars {
+        if respawn_sidecar(app, sidecar).await {
+            emit_reconnected(app, sidecar, true);
+        } else {
+            log_error!("Server for {} failed to restart", sidecar.repo_path);
+        }
+    }
+    standby::fill(app);
+    let _ = app.emit(
+        "update-progress",
+        UpdateProgressEvent {
+            stage: "error",
+            downloaded: size,
+            total: Some(size),
+        },
+    );
+}
+
+/// Download and install the latest update, replacing the app and its sidecar,
+/// then restart into the same repositories
+#[tauri::command]
+pub async fn install_update(app: AppHandle, request_id: Option<String>) -> CommandResult<()> {
+    let app = &app;
+    timeout::run(app, CommandClass::Long, request_id, |token| async move {
+        let update = updater(app)?
+            .check()
+            .await
+            .map_err(|e| updater_error("Failed to check for updates", e))?
+            .ok_or_else(|| AppError::new(ErrorCode::NotFound, "No update available"))?;
+
+        log_info!("Downloading update {}", update.version);
+
+        let progress_app = app.clone();
+        let mut downloaded: u64 = 0;
+        let bytes = update
+            .download(
+                move |chunk_length, content_length| {
+                    downloaded += chunk_length as u64;
+                    let _ = progress_app.emit(
+                        "update-progress",
+                        UpdateProgressEvent {
+                            stage: "downloading",
+                            downloaded,
+                            total: content_length,
+                        },
+                    );
+                },
+                || log_info!("Update download finished"),
+            )
+            .await
+            .map_err(|e| updater_error("Failed to download update", e))?;
+
+        // Let the old servers finish what they are doing before they are replaced
+        let sidecars = app.state::<ServerState>().all();
+        for sidecar in &sidecars {
+            drain_operations(app, sidecar, &token).await?;
+        }
+        token.check()?;
+        save_restart_state(app, sidecars.iter().map(|s| s.repo_path.clone()).collect())?;
+
+        let _ = app.emit(
+            "update-progress",
+            UpdateProgressEvent {
+                stage: "installing",
+                downloaded: bytes.len() as u64,
+                total: Some(bytes.len() as u64),
+            },
+        );
+
+        // Past this point the servers go down, so finish even if the caller stops waiting
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            // Ask the old servers to shut down cleanly before they are replaced
+            for sidecar in &sidecars {
+                if ipc::shutdown(&app, sidecar).await.is_ok() {
+                    let start = Instant::now();
+                    while is_server_running(sidecar.port).await
+                        && start.elapsed() < Duration::from_secs(SHUTDOWN_TIMEOUT_SECS)
+                    {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                }
+            }
+            kill_sidecar(app.clone());
+
+            let size = bytes.len() as u64;
+            if let Err(e) = update.install(bytes) {
+                let error = updater_error("Failed to install update", e);
+                recover(&app, &sidecars, size).await;
+                return Err::<(), _>(error);
+            }
+
+            log_info!("Update installed, restarting");
+            shutdown::record(&app, ExitReason::UpdateRestart);
+            app.state::<QuitState>().0.store(true, Ordering::SeqCst);
+            app.restart();
+        })
+        .await
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Update task failed: {}", e)))?
+    })
+    .await
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+use crate::sidecar::Sidecar;
+use crate::{i18n, ipc, loopback, tls};
+
+/// Sidecar versions this build of the desktop shell can talk to
+const SIDECAR_VERSION_REQ: &str = ">=0.1.7, <0.2.0";
+const VERSION_TIMEOUT_MS: u64 = 2000;
+
+/// Response of the server's `/version` endpoint
+#[derive(Debug, Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+/// Sidecar version reported during the startup handshake
+#[derive(Clone, Default)]
+pub struct VersionState(Arc<Mutex<Option<String>>>);
+
+/// Versions of the app's components, for the About screen
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Versions {
+    app: String,
+    sidecar: Option<String>,
+    sidecar_requirement: &'static str,
+    tauri: &'static str,
+    webview: Option<String>,
+    /// Version of the `git` on the PATH, which the sidecar and the shell run
+    git: Option<String>,
+    /// Version of the libgit2 built into the shell
+    libgit2: String,
+    os: String,
+}
+
+/// Version of the `git` on the PATH, looked up once since it does not change
+/// while the app runs
+fn git_version() -> Option<String> {
+    static GIT_VERSION: OnceLock<Option<String>> = OnceLock::new();
+    GIT_VERSION
+        .get_or_init(|| {
+            let output = std::process::Command::new("git")
+                .arg("--version")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())?;
+            let version = String::from_utf8_lossy(&output.stdout);
+            Some(
+                version
+                    .trim()
+                    .trim_start_matches("git version ")
+                    .to_string(),
+            )
+        })
+        .clone()
+}
+
+/// Ask the server for its version
+async fn fetch_sidecar_version(app: &AppHandle, port: u32) -> Result<String, String> {
+    let client = loopback::client(app, Duration::from_millis(VERSION_TIMEOUT_MS))?;
+
+    let response = client
+        .get(format!("{}/version", tls::base_url(app, port)))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to query server version: {}", e))?;
+
+    // Servers predating the handshake have no /version route
+    if !response.status().is_success() {
+        return Err(format!(
+            "Server did not report a version ({})",
+            response.status()
+        ));
+    }
+
+    response
+        .json::<VersionResponse>()
+        .await
+        .map(|r| r.version)
+        .map_err(|e| format!("Invalid version response: {}", e))
+}
+
+/// User-facing explanation of a sidecar this app cannot talk to
+fn incompatible(app_version: &str, found: &str) -> String {
+    format!(
+        "opentui-git v{} requires server {}, but found {}.\n\nReinstall the app to restore a matching server.",
+        app_version, SIDECAR_VERSION_REQ, found
+    )
+}
+
+/// Check a sidecar version against the range this build supports. Returns a
+/// user-facing explanation naming both versions if it is outside it.
+fn check_version(app_version: &str, sidecar_version: &str) -> Result<(), String> {
+    let requirement = semver::VersionReq::parse(SIDECAR_VERSION_REQ)
+        .map_err(|e| format!("Invalid sidecar version requirement: {}", e))?;
+    let found = match semver::Version::parse(sidecar_version) {
+        Ok(parsed) if requirement.matches(&parsed) => return Ok(()),
+        Ok(_) => format!("v{}", sidecar_version),
+        Err(_) => format!("an unrecognized version {:?}", sidecar_version),
+    };
+    Err(incompatible(app_version, &found))
+}
+
+/// Check that the running sidecar is compatible with this app.
+/// Returns a user-facing explanation if it is not.
+pub async fn handshake(app: &AppHandle, sidecar: &Sidecar) -> Result<(), String> {
+    let app_version = app.package_info().version.to_string();
+
+    // Sidecars started outside the app (in development) are only reachable over HTTP
+    let version = if sidecar.is_managed() {
+        ipc::version(app, sidecar)
+            .await
+            .map(|version| (version.version, Some(version.protocol)))
+    } else {
+        fetch_sidecar_version(app, sidecar.port)
+            .await
+            .map(|version| (version, None))
+    };
+    let (version, protocol) = version.map_err(|e| {
+        log_error!("Version handshake failed: {}", e);
+        incompatible(&app_version, "an unknown version")
+    })?;
+
+    if let Ok(mut guard) = app.state::<VersionState>().0.lock() {
+        *guard = Some(version.clone());
+    }
+
+    if let Some(protocol) = protocol.filter(|protocol| *protocol != ipc::PROTOCOL_VERSION) {
+        return Err(incompatible(
+            &app_version,
+            &format!(
+                "v{} speaking protocol {} instead of {}",
+                version,
+                protocol,
+                ipc::PROTOCOL_VERSION
+            ),
+        ));
+    }
+    check_version(&app_version, &version)?;
+
+    log_info!("Sidecar v{} is compatible", version);
+    Ok(())
+}
+
+/// Get the versions of the app and its components
+#[tauri::command]
+pub fn get_versions(app: AppHandle) -> Versions {
+    let sidecar = app
+        .try_state::<VersionState>()
+        .and_then(|state| state.0.lock().ok().and_then(|v| v.clone()));
+
+    Versions {
+        app: app.package_info().version.to_string(),
+        sidecar,
+        sidecar_requirement: SIDECAR_VERSION_REQ,
+        tauri: tauri::VERSION,
+        webview: tauri::webview_version().ok(),
+        git: git_version(),
+        libgit2: {
+            let (major, minor, patch) = git2::Version::get().libgit2_version();
+            format!("{}.{}.{}", major, minor, patch)
+        },
+        os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+    }
+}
+
+/// Human-readable version summary for the About dialog
+pub fn summary(app: &AppHandle) -> String {
+    let versions = get_versions(app.clone());
+    let unknown = || "unknown".to_string();
+    format!(
+        "opentui-git v{}\nServer: {}\nGit: {}\nlibgit2: {}\nTauri: {}\nWebview: {}\nOS: {}",
+        versions.app,
+        versions
+            .sidecar
+            .map(|v| format!("v{}", v))
+            .unwrap_or_else(unknown),
+        versions.git.unwrap_or_else(unknown),
+        versions.libgit2,
+        versions.tauri,
+        versions.webview.unwrap_or_else(unknown),
+        versions.os
+    )
+}
+
+/// Show the versions of the app's components in a native dialog, with a
+/// button copying them for a bug report
+#[tauri::command]
+pub async fn show_about(app: AppHandle) {
+    let summary = summary(&app);
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(summary.clone())
+        .title(i18n::t(&app, "about.title"))
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            i18n::t(&app, "about.copy"),
+            i18n::t(&app, "about.close"),
+        ))
+        .show(move |copy| {
+            let _ = tx.send(copy);
+        });
+    if !rx.await.unwrap_or(false) {
+        return;
+    }
+    if let Err(e) = app.clipboard().write_text(summary) {
+        log_error!("Failed to copy versions: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_versions_in_range() {
+        assert_eq!(check_version("0.1.7", "0.1.7"), Ok(()));
+        assert_eq!(check_version("0.1.7", "0.1.12"), Ok(()));
+    }
+
+    #[test]
+    fn names_both_versions_when_out_of_range() {
+        for found in ["0.2.0", "0.1.6", "1.0.0"] {
+            let message = check_version("0.1.7", found).unwrap_err();
+            assert!(message.contains("opentui-git v0.1.7"), "{}", message);
+            assert!(
+                message.contains(&format!("found v{}", found)),
+                "{}",
+                message
+            );
+            assert!(message.contains(SIDECAR_VERSION_REQ), "{}", message);
+        }
+    }
+
+    #[test]
+    fn names_unparsable_versions() {
+        let message = check_version("0.1.7", "dev-build").unwrap_err();
+        assert!(message.contains("opentui-git v0.1.7"), "{}", message);
+        assert!(message.contains("\"dev-build\""), "{}", message);
+    }
+}
@@ -0,0 +1,505 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::broadcast::{self, repo_key, WindowRegistry};
+use crate::cleanup;
+use crate::large_repo;
+use crate::repo_config;
+use crate::repo_state::{self, Operation};
+use crate::sidecar::ServerState;
+use crate::{notifications, settings, window_title};
+
+/// Quiet period after the last change before `repo-changed` is emitted
+const DEBOUNCE_MS: u64 = 150;
+/// Quiet period for working tree changes, which come in larger bursts
+const WORKDIR_DEBOUNCE_MS: u64 = 300;
+/// Most paths listed in one `workdir-dirty` event
+const MAX_DIRTY_PATHS: usize = 200;
+
+/// Files in the git directory that mean an operation started or finished
+const OPERATION_FILES: &[&str] = &[
+    "MERGE_HEAD",
+    "CHERRY_PICK_HEAD",
+    "REVERT_HEAD",
+    "BISECT_LOG",
+    "rebase-merge",
+    "rebase-apply",
+];
+
+/// What changed in the repository's git directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// HEAD moved, e.g. a checkout
+    Head,
+    /// Branches or tags changed, e.g. a commit or fetch
+    Refs,
+    /// The index changed, e.g. staging
+    Index,
+    /// A merge, rebase, cherry-pick, revert or bisect started or finished
+    Operation,
+}
+
+/// Payload of the `repo-state-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RepoStateChangedEvent {
+    repo_path: String,
+    /// The operation now in progress, `None` once it finished or was aborted
+    operation: Option<Operation>,
+}
+
+/// Payload of the `repo-changed` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RepoChangedEvent {
+    repo_path: String,
+    kinds: Vec<ChangeKind>,
+}
+
+/// Payload of the `workdir-dirty` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkdirDirtyEvent {
+    repo_path: String,
+    /// Changed paths relative to the repository root
+    paths: Vec<String>,
+    /// More paths changed than are listed
+    truncated: bool,
+}
+
+/// Watchers of one open repository; dropping them stops the events
+struct RepoWatch {
+    _git_dir: RecommendedWatcher,
+    _workdir: Option<RecommendedWatcher>,
+}
+
+/// Watchers of open repositories, keyed by repo
+#[derive(Clone, Default)]
+pub struct RepoWatchers(Arc<Mutex<HashMap<String, RepoWatch>>>);
+
+/// Decides which working tree paths git ignores, honoring nested `.gitignore`
+/// files, `.git/info/exclude` and the global excludes file, plus the
+/// watcher excludes of `.opentui.toml`
+struct IgnoreFilter {
+    root: PathBuf,
+    /// Parsed `.gitignore` of each directory seen so far
+    by_dir: HashMap<PathBuf, Gitignore>,
+    exclude: Gitignore,
+    global: Gitignore,
+    configured: Gitignore,
+}
+
+impl IgnoreFilter {
+    fn new(root: PathBuf, git_dir: &Path, configured: &[String]) -> Self {
+        let (exclude, _) = Gitignore::new(git_dir.join("info").join("exclude"));
+        let (global, _) = Gitignore::global();
+        let mut builder = GitignoreBuilder::new(&root);
+        for pattern in configured {
+            if let Err(e) = builder.add_line(None, pattern) {
+                log_error!("Ignoring watcher exclude '{}': {}", pattern, e);
+            }
+        }
+        let configured = builder.build().unwrap_or_else(|_| Gitignore::empty());
+        Self {
+            root,
+            by_dir: HashMap::new(),
+            exclude,
+            global,
+            configured,
+        }
+    }
+
+    /// Forget a directory's cached rules after its `.gitignore` changed
+    fn invalidate(&mut self, dir: &Path) {
+        self.by_dir.remove(dir);
+    }
+
+    fn gitignore(&mut self, dir: &Path) -> &Gitignore {
+        self.by_dir
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| Gitignore::new(dir.join(".gitignore")).0)
+    }
+
+    /// Whether rules match this exact path, nearest `.gitignore` first
+    fn matches(&mut self, path: &Path, relative: &Path, is_dir: bool) -> bool {
+        let root = self.root.clone();
+        for dir in path.ancestors().skip(1) {
+            match self.gitignore(dir).matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+            if dir == root {
+                break;
+            }
+        }
+        for rules in [&self.configured, &self.exclude, &self.global] {
+            match rules.matched(relative, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    /// Whether git ignores a path, either directly or through an ignored parent
+    fn is_ignored(&mut self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root).map(Path::to_path_buf) else {
+            return true;
+        };
+        let components: Vec<_> = relative.components().collect();
+        if components
+            .first()
+            .is_some_and(|first| first.as_os_str() == ".git")
+        {
+            return true;
+        }
+
+        let mut current = self.root.clone();
+        let mut current_relative = PathBuf::new();
+        for (i, component) in components.iter().enumerate() {
+            current.push(component);
+            current_relative.push(component);
+            let is_dir = i + 1 < components.len() || current.is_dir();
+            if self.matches(&current, &current_relative, is_dir) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Git directory of a repository and the common directory holding its refs,
+/// which differ for linked worktrees
+pub fn git_dirs(repo_path: &str) -> Option<(PathBuf, PathBuf)> {
+    let dot_git = Path::new(repo_path).join(".git");
+    let git_dir = if dot_git.is_dir() {
+        dot_git
+    } else {
+        // Worktrees and submodules have a `.git` file pointing at the real directory
+        let contents = std::fs::read_to_string(&dot_git).ok()?;
+        let target = contents.trim().strip_prefix("gitdir:")?.trim();
+        Path::new(repo_path).join(target)
+    };
+
+    let common_dir = std::fs::read_to_string(git_dir.join("commondir"))
+        .ok()
+        .map(|common| git_dir.join(common.trim()))
+        .unwrap_or_else(|| git_dir.clone());
+    Some((git_dir, common_dir))
+}
+
+/// Classify a changed path, ignoring lock files and unrelated git internals
+fn classify(path: &Path, git_dir: &Path, common_dir: &Path) -> Option<ChangeKind> {
+    let name = path.file_name()?.to_str()?;
+    if name.ends_with(".lock") {
+        return None;
+    }
+    if path.starts_with(common_dir.join("refs")) || name == "packed-refs" {
+        return Some(ChangeKind::Refs);
+    }
+
+    let relative = path.strip_prefix(git_dir).ok()?;
+    let top = relative.components().next()?.as_os_str().to_str()?;
+    match top {
+        "HEAD" => Some(ChangeKind::Head),
+        "index" => Some(ChangeKind::Index),
+        top if OPERATION_FILES.contains(&top) => Some(ChangeKind::Operation),
+        _ => None,
+    }
+}
+
+/// Collect changes until a quiet period passes, then hand them over as one
+/// batch. Ends when the watcher feeding the channel is dropped.
+fn debounce<T, F>(changes: mpsc::Receiver<T>, quiet: Duration, on_batch: F)
+where
+    T: Ord + Send + 'static,
+    F: Fn(BTreeSet<T>) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        while let Ok(first) = changes.recv() {
+            let mut batch = BTreeSet::from([first]);
+            loop {
+                match changes.recv_timeout(quiet) {
+                    Ok(change) => {
+                        batch.insert(change);
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            on_batch(batch);
+        }
+    });
+}
+
+/// Emit `repo-state-changed` if an operation started or finished since the
+/// last check, e.g. a merge run in a terminal, and notify about a new one
+fn check_state(app: &AppHandle, repo_path: &str, git_dir: &Path, last: &Mutex<Option<Operation>>) {
+    let Ok(mut last) = last.lock() else {
+        return;
+    };
+    let operation = repo_state::operation(git_dir);
+    // Steps of a rebase or pick move on without changing the state
+    if operation.as_ref().map(std::mem::discriminant) == last.as_ref().map(std::mem::discriminant) {
+        return;
+    }
+    let started = last.is_none();
+    *last = operation.clone();
+    drop(last);
+
+    log_info!("{} is now in {:?}", repo_path, operation);
+    if let Some(operation) = operation.as_ref().filter(|_| started) {
+        if settings::current(app).notify_repo_state {
+            notifications::operation_started(app, repo_path, operation);
+        }
+    }
+    broadcast::emit_to_repo(
+        app,
+        repo_path,
+        "repo-state-changed",
+        RepoStateChangedEvent {
+            repo_path: repo_path.to_string(),
+            operation,
+        },
+    );
+}
+
+/// Watch the git directory for HEAD, ref, index and operation changes
+fn watch_git_dir(
+    app: &AppHandle,
+    repo_path: &str,
+    git_dir: &Path,
+    common_dir: &Path,
+) -> notify::Result<RecommendedWatcher> {
+    let (sender, changes) = mpsc::channel();
+    let (event_git_dir, event_common_dir) = (git_dir.to_path_buf(), common_dir.to_path_buf());
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in &event.paths {
+            if let Some(kind) = classify(path, &event_git_dir, &event_common_dir) {
+                let _ = sender.send(kind);
+            }
+        }
+    })?;
+
+    // HEAD, index and operation files sit at the top of the git directory
+    let mut paths = vec![
+        (git_dir.to_path_buf(), RecursiveMode::NonRecursive),
+        (common_dir.join("refs"), RecursiveMode::Recursive),
+    ];
+    if common_dir != git_dir {
+        paths.push((common_dir.to_path_buf(), RecursiveMode::NonRecursive));
+    }
+    for (path, mode) in paths {
+        if let Err(e) = watcher.watch(&path, mode) {
+            log_error!("Failed to watch {}: {}", path.display(), e);
+        }
+    }
+
+    let (app, repo_path) = (app.clone(), repo_path.to_string());
+    let (git_dir, state) = (
+        git_dir.to_path_buf(),
+        Mutex::new(repo_state::operation(git_dir)),
+    );
+    debounce(changes, Duration::from_millis(DEBOUNCE_MS), move |kinds| {
+        if kinds.contains(&ChangeKind::Operation) {
+            check_state(&app, &repo_path, &git_dir, &state);
+        }
+        if kinds.iter().any(|kind| {
+            matches!(
+                kind,
+                ChangeKind::Head | ChangeKind::Refs | ChangeKind::Index
+            )
+        }) {
+            window_title::refresh(&app, &repo_path);
+        }
+        broadcast::emit_to_repo(
+            &app,
+            &repo_path,
+            "repo-changed",
+            RepoChangedEvent {
+                repo_path: repo_path.clone(),
+                kinds: kinds.into_iter().collect(),
+            },
+        );
+    });
+    Ok(watcher)
+}
+
+/// Watch the working tree for changes to files git does not ignore
+fn watch_workdir(
+    app: &AppHandle,
+    repo_path: &str,
+    git_dir: &Path,
+) -> notify::Result<RecommendedWatcher> {
+    let root = PathBuf::from(repo_path);
+    let (sender, changes) = mpsc::channel();
+    let mut filter = IgnoreFilter::new(
+        root.clone(),
+        git_dir,
+        &repo_config::watcher_excludes(repo_path),
+    );
+    let event_root = root.clone();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in &event.paths {
+            if path.file_name().is_some_and(|name| name == ".gitignore") {
+                if let Some(dir) = path.parent() {
+                    filter.invalidate(dir);
+                }
+            }
+            if filter.is_ignored(path) {
+                continue;
+            }
+            if let Ok(relative) = path.strip_prefix(&event_root) {
+                let _ = sender.send(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    let (app, repo_path) = (app.clone(), repo_path.to_string());
+    debounce(
+        changes,
+        Duration::from_millis(WORKDIR_DEBOUNCE_MS),
+        move |paths| {
+            let truncated = paths.len() > MAX_DIRTY_PATHS;
+            window_title::refresh(&app, &repo_path);
+            broadcast::emit_to_repo(
+                &app,
+                &repo_path,
+                "workdir-dirty",
+                WorkdirDirtyEvent {
+                    repo_path: repo_path.clone(),
+                    paths: paths.into_iter().take(MAX_DIRTY_PATHS).collect(),
+                    truncated,
+                },
+            );
+        },
+    );
+    Ok(watcher)
+}
+
+/// Start watching a repository's git directory and working tree, if not already watched
+pub fn watch(app: &AppHandle, repo_path: &str) {
+    let watchers = app.state::<RepoWatchers>();
+    let Ok(mut watchers) = watchers.0.lock() else {
+        return;
+    };
+    let key = repo_key(repo_path);
+    if watchers.contains_key(&key) {
+        return;
+    }
+
+    let Some((git_dir, common_dir)) = git_dirs(repo_path) else {
+        log_error!("No git directory to watch in {}", repo_path);
+        return;
+    };
+
+    let git_watcher = match watch_git_dir(app, repo_path, &git_dir, &common_dir) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            log_error!("Failed to watch {}: {}", git_dir.display(), e);
+            return;
+        }
+    };
+    // Status still refreshes on repo-changed without this, so keep going if it
+    // fails, and skip it for large repositories, where it costs too much
+    let workdir_watcher = if large_repo::is_large_repo(app, repo_path) {
+        None
+    } else {
+        watch_workdir(app, repo_path, &git_dir)
+            .map_err(|e| log_error!("Failed to watch working tree {}: {}", repo_path, e))
+            .ok()
+    };
+
+    watchers.insert(
+        key,
+        RepoWatch {
+            _git_dir: git_watcher,
+            _workdir: workdir_watcher,
+        },
+    );
+    drop(watchers);
+    cleanup::register(app, "watchers", stop_all);
+    log_info!("Watching {}", repo_path);
+}
+
+/// Stop watching a repository once no window shows it
+pub fn release(app: &AppHandle, repo_path: &str) {
+    if !app
+        .state::<WindowRegistry>()
+        .windows_for(repo_path)
+        .is_empty()
+    {
+        return;
+    }
+    if let Ok(mut watchers) = app.state::<RepoWatchers>().0.lock() {
+        // Dropping the watchers closes their channels, which ends the debounce threads
+        watchers.remove(&repo_key(repo_path));
+    }
+}
+
+/// Stop all watchers when the app exits
+fn stop_all(app: &AppHandle) {
+    if let Ok(mut watchers) = app.state::<RepoWatchers>().0.lock() {
+        watchers.clear();
+    }
+}
+
+/// Stop all watchers, e.g. while saving power. `resume` starts them again.
+pub fn pause(app: &AppHandle) {
+    if let Ok(mut watchers) = app.state::<RepoWatchers>().0.lock() {
+        if !watchers.is_empty() {
+            log_info!("Pausing {} repository watcher(s)", watchers.len());
+        }
+        watchers.clear();
+    }
+}
+
+/// Watch every repository shown in a window again, and have the windows
+/// refresh, since changes made while paused went unreported
+pub fn resume(app: &AppHandle) {
+    let registry = app.state::<WindowRegistry>();
+    for sidecar in app.state::<ServerState>().all() {
+        if registry.windows_for(&sidecar.repo_path).is_empty() {
+            continue;
+        }
+        watch(app, &sidecar.repo_path);
+        broadcast::emit_to_repo(
+            app,
+            &sidecar.repo_path,
+            "repo-changed",
+            RepoChangedEvent {
+                repo_path: sidecar.repo_path.clone(),
+                kinds: vec![
+                    ChangeKind::Head,
+                    ChangeKind::Refs,
+                    ChangeKind::Index,
+                    ChangeKind::Operation,
+                ],
+            },
+        );
+    }
+}
@@ -0,0 +1,472 @@
+//! Repository windows: creating them with their sidecar's details injected,
+//! keeping those details current, and opening repositories in new windows.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::webview::PageLoadEvent;
+use tauri::{AppHandle, Emitter, EventTarget, LogicalSize, Manager, WebviewWindow};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::a11y::{self, A11yPrefs};
+use crate::broadcast::{self, WindowRegistry};
+use crate::config::get_repo_path;
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::safe_mode::SafeMode;
+use crate::sidecar::{server_info, start_sidecar, ServerInfo, ServerState, Sidecar};
+use crate::timeout::{self, CommandClass};
+use crate::{
+    background, compact, dev_frontend, display, i18n, lfs, loopback, navigation, onboarding,
+    recent, settings, theme, titlebar, tls, watcher, window_title, worktrees,
+};
+
+/// Counter for labels of windows opened after the main one
+static NEXT_WINDOW_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Size and startup behavior of repository windows, in logical pixels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WindowSettings {
+    pub width: f64,
+    pub height: f64,
+    /// Smallest size windows can be resized to
+    pub min_width: f64,
+    pub min_height: f64,
+    /// Open repository windows maximized
+    pub start_maximized: bool,
+    /// Launch with the main window hidden and only the tray icon showing
+    pub start_minimized_to_tray: bool,
+    /// Open windows at the size the last one was closed at
+    pub remember_last_size: bool,
+    /// Size the last repository window was closed at
+    pub last_size: Option<WindowSize>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 1200.0,
+            height: 800.0,
+            min_width: 800.0,
+            min_height: 600.0,
+            start_maximized: false,
+            start_minimized_to_tray: false,
+            remember_last_size: false,
+            last_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl WindowSettings {
+    /// Smallest size windows can be resized to
+    pub fn min_size(&self) -> LogicalSize<f64> {
+        LogicalSize::new(self.min_width.max(0.0), self.min_height.max(0.0))
+    }
+
+    /// Size to open a window at: the remembered one if enabled, never below
+    /// the minimum
+    pub fn initial_size(&self) -> LogicalSize<f64> {
+        let size = self
+            .last_size
+            .filter(|_| self.remember_last_size)
+            .unwrap_or(WindowSize {
+                width: self.width,
+                height: self.height,
+            });
+        let min = self.min_size();
+        LogicalSize::new(size.width.max(min.width), size.height.max(min.height))
+    }
+}
+
+/// Note the size of a repository window about to close, for
+/// `rememberLastSize`. Maximized, minimized and compact windows keep the
+/// previous size.
+pub fn remember_size(window: &WebviewWindow) {
+    let app = window.app_handle();
+    let current = settings::current(app);
+    if !current.window.remember_last_size
+        || app
+            .state::<WindowRegistry>()
+            .repo_for(window.label())
+            .is_none()
+        || current
+            .compact_windows
+            .contains_key(&broadcast::window_key(window))
+        || window.is_maximized().unwrap_or(false)
+        || window.is_minimized().unwrap_or(false)
+    {
+        return;
+    }
+    let (Ok(size), Ok(scale)) = (window.inner_size(), window.scale_factor()) else {
+        return;
+    };
+    let size = size.to_logical::<f64>(scale);
+    let size = WindowSize {
+        width: size.width,
+        height: size.height,
+    };
+    if current.window.last_size == Some(size) {
+        return;
+    }
+    if let Err(e) = settings::update(app, |settings| settings.window.last_size = Some(size)) {
+        log_error!("Failed to remember the window size: {}", e);
+    }
+}
+
+/// Sidecar serving the repository shown in a window
+pub fn sidecar_for_window(app: &AppHandle, label: &str) -> Option<Sidecar> {
+    let repo_path = app.state::<WindowRegistry>().repo_for(label)?;
+    app.state::<ServerState>().for_repo(&repo_path)
+}
+
+/// Overwrite the server details injected into a window when it was created,
+/// then tell the page to reconnect if it is already using stale ones
+pub fn sync_window(window: &WebviewWindow, info: &ServerInfo) {
+    let script = format!(
+        r#"
+        window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+        window.__OPENTUI__.port = {};
+        window.__OPENTUI__.serverUrl = "{}";
+        window.__OPENTUI__.host = {};
+        window.__OPENTUI__.repoPath = {};
+        "#,
+        info.port,
+        info.url,
+        serde_json::to_string(&info.host).unwrap_or_else(|_| "\"\"".to_string()),
+        serde_json::to_string(&info.repo_path).unwrap_or_else(|_| "\"\"".to_string()),
+    );
+    if let Err(e) = window.eval(&script) {
+        log_error!("Failed to update server info in {}: {}", window.label(), e);
+    }
+    let _ = window.emit_to(
+        EventTarget::webview_window(window.label()),
+        "server-info-changed",
+        info,
+    );
+}
+
+/// Push a sidecar's current details into every window on its repository
+pub async fn sync_server_info(app: &AppHandle, sidecar: &Sidecar) {
+    let info = server_info(app, sidecar).await;
+    for label in app
+        .state::<WindowRegistry>()
+        .windows_for(&sidecar.repo_path)
+    {
+        if let Some(window) = app.get_webview_window(&label) {
+            sync_window(&window, &info);
+        }
+    }
+}
+
+/// What a repository window is told about its sidecar when it is created
+struct InjectedDetails<'a> {
+    port: u32,
+    server_url: String,
+    /// Address the sidecar is reached at
+    host: String,
+    repo_path: &'a str,
+    secret: Option<String>,
+    safe_mode: bool,
+    is_bare: bool,
+    theme: &'static str,
+    custom_titlebar: bool,
+    a11y: A11yPrefs,
+}
+
+/// Script setting `window.__OPENTUI__` before the page's own scripts run
+fn injection_script(details: &InjectedDetails) -> String {
+    format!(
+        r#"
+            window.__OPENTUI__ = window.__OPENTUI__ || {{}};
+            window.__OPENTUI__.port = {};
+            window.__OPENTUI__.serverUrl = "{}";
+            window.__OPENTUI__.host = {};
+            window.__OPENTUI__.repoPath = {};
+            window.__OPENTUI__.secret = {};
+            window.__OPENTUI__.safeMode = {};
+            window.__OPENTUI__.isBare = {};
+            window.__OPENTUI__.theme = "{}";
+            window.__OPENTUI__.customTitlebar = {};
+            window.__OPENTUI__.a11y = {};
+            "#,
+        details.port,
+        details.server_url,
+        serde_json::to_string(&details.host).unwrap_or_else(|_| "\"\"".to_string()),
+        serde_json::to_string(details.repo_path).unwrap_or_else(|_| "\"\"".to_string()),
+        serde_json::to_string(&details.secret).unwrap_or_else(|_| "null".to_string()),
+        details.safe_mode,
+        details.is_bare,
+        details.theme,
+        details.custom_titlebar,
+        serde_json::to_string(&details.a11y).unwrap_or_else(|_| "{}".to_string())
+    )
+}
+
+/// Create a window for a repository with its server's port and repo path
+/// injected, hidden until shown if `visible` is false
+pub fn create_repo_window(
+    app: &AppHandle,
+    label: &str,
+    sidecar: &Sidecar,
+    visible: bool,
+) -> tauri::Result<WebviewWindow> {
+    let safe_mode = app.state::<SafeMode>();
+    let config = settings::current(app).window;
+    let size = config.initial_size();
+    let min_size = config.min_size();
+
+    let builder = WebviewWindow::builder(app, label, dev_frontend::webview_url())
+        .on_navigation(navigation::handler(app))
+        .title(window_title::initial(&sidecar.repo_path))
+        .inner_size(size.width, size.height)
+        .min_inner_size(min_size.width, min_size.height)
+        .maximized(config.start_maximized)
+        .visible(visible)
+        .resizable(true)
+        .theme(theme::native(app))
+        // The injected details date from the window's creation, so refresh them
+        // after a reload in case the sidecar has restarted since
+        .on_page_load(|window, payload| {
+            if payload.event() != PageLoadEvent::Finished {
+                return;
+            }
+            let app = window.app_handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Some(sidecar) = sidecar_for_window(&app, window.label()) {
+                    sync_window(&window, &server_info(&app, &sidecar).await);
+                }
+            });
+        });
+    let details = InjectedDetails {
+        port: sidecar.port,
+        server_url: tls::base_url(app, sidecar.port),
+        host: loopback::connect_host(app, sidecar.port),
+        repo_path: &sidecar.repo_path,
+        secret: loopback::secret(app),
+        safe_mode: safe_mode.0,
+        is_bare: worktrees::is_bare(&sidecar.repo_path),
+        theme: theme::name(app),
+        custom_titlebar: titlebar::is_custom(app),
+        a11y: a11y::prefs(app),
+    };
+    let window = titlebar::configure(app, builder)
+        .initialization_script(&injection_script(&details))
+        .build()?;
+
+    broadcast::register(app, window.label(), &sidecar.repo_path);
+    window_title::attach(app, &window, &sidecar.repo_path);
+    // Safe mode runs no background monitors
+    if !safe_mode.0 {
+        watcher::watch(app, &sidecar.repo_path);
+    }
+    display::update(&window);
+    compact::restore(&window);
+    lfs::check(app, &window, &sidecar.repo_path);
+    Ok(window)
+}
+
+/// Create the main window for the primary sidecar, hidden if `visible` is false
+pub fn create_main_window(app: &AppHandle, visible: bool) -> Result<WebviewWindow, String> {
+    let Some(sidecar) = app.state::<ServerState>().primary() else {
+        // Started without a repository and none opened since
+        return onboarding::show(app, &get_repo_path(app));
+    };
+    create_repo_window(app, "main", &sidecar, visible).map_err(|e| e.to_string())
+}
+
+/// Show and focus the main window, recreating it if it was closed
+pub fn show_main_window(app: &AppHandle) {
+    background::stop(app);
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+
+    // Build off the event loop thread to avoid deadlocking webview creation
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = create_main_window(&app, true) {
+            log_error!("Failed to reopen window: {}", e);
+        }
+    });
+}
+
+/// Open a repository in its own window, spawning a sidecar for it if needed.
+/// Focuses the existing window if the repository is already open. Folders
+/// outside a repository are refused, since the server can't run there.
+pub async fn open_repo_in_window(app: &AppHandle, repo_path: &str) -> CommandResult<()> {
+    // Open the top of the working tree, even when given a subfolder
+    let location = worktrees::locate(repo_path).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("{} is not a git repository", repo_path),
+        )
+        .with_metadata(serde_json::json!({ "repoPath": repo_path, "notARepository": true }))
+    })?;
+    let repo_path = location.workdir.as_str();
+
+    let existing = app.state::<WindowRegistry>().windows_for(repo_path);
+    if let Some(window) = existing
+        .first()
+        .and_then(|label| app.get_webview_window(label))
+    {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let sidecar = match app.state::<ServerState>().for_repo(repo_path) {
+        Some(sidecar) => sidecar,
+        None => start_sidecar(app, repo_path).await?,
+    };
+
+    let label = format!("repo-{}", NEXT_WINDOW_ID.fetch_add(1, Ordering::SeqCst));
+    create_repo_window(app, &label, &sidecar, true)
+        .map_err(|e| format!("Failed to create window: {}", e))?;
+
+    recent::add(app, repo_path);
+    onboarding::finish(app);
+    log_info!("Opened {} in window {}", repo_path, label);
+    Ok(())
+}
+
+/// Open another window on a repository that already has a sidecar
+pub fn open_window_for_repo(app: &AppHandle, repo_path: &str) -> Result<(), String> {
+    let sidecar = app
+        .state::<ServerState>()
+        .for_repo(repo_path)
+        .ok_or_else(|| format!("No server running for {}", repo_path))?;
+
+    let label = format!("repo-{}", NEXT_WINDOW_ID.fetch_add(1, Ordering::SeqCst));
+    create_repo_window(app, &label, &sidecar, true)
+        .map_err(|e| format!("Failed to create window: {}", e))?;
+
+    log_info!("Opened another window on {} ({})", repo_path, label);
+    Ok(())
+}
+
+/// Open a repository by path in its own window
+#[tauri::command]
+pub async fn open_repo(
+    app: AppHandle,
+    repo_path: String,
+    request_id: Option<String>,
+) -> CommandResult<()> {
+    if !PathBuf::from(&repo_path).is_dir() {
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!("Repository not found: {}", repo_path),
+        )
+        .with_metadata(serde_json::json!({ "repoPath": repo_path })));
+    }
+
+    if worktrees::is_bare(&repo_path) {
+        return Err(worktrees::bare_repo_error(&repo_path));
+    }
+
+    timeout::run(&app, CommandClass::Local, request_id, |_| async {
+        open_repo_in_window(&app, &repo_path).await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Pick a folder with a native dialog and open it as a repository.
+/// Returns the chosen path, or None if the dialog was cancelled.
+#[tauri::command]
+pub async fn open_repo_dialog(app: AppHandle) -> CommandResult<Option<String>> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title(i18n::t(&app, "title.open-repository"))
+        .pick_folder(move |folder| {
+            let _ = tx.send(folder);
+        });
+
+    let Some(folder) = rx.await.ok().flatten() else {
+        return Ok(None);
+    };
+    let repo_path = folder
+        .into_path()
+        .map_err(|e| format!("Invalid folder: {}", e))?
+        .to_string_lossy()
+        .to_string();
+
+    if worktrees::is_bare(&repo_path) {
+        return Err(worktrees::bare_repo_error(&repo_path));
+    }
+    open_repo_in_window(&app, &repo_path).await?;
+    Ok(Some(repo_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(repo_path: &str, secret: Option<&str>) -> InjectedDetails<'_> {
+        InjectedDetails {
+            port: 4096,
+            server_url: "http://127.0.0.1:4096".to_string(),
+            host: "127.0.0.1".to_string(),
+            repo_path,
+            secret: secret.map(str::to_string),
+            safe_mode: false,
+            is_bare: false,
+            theme: "dark",
+            custom_titlebar: true,
+            a11y: A11yPrefs::default(),
+        }
+    }
+
+    #[test]
+    fn injects_the_sidecar_details() {
+        let script = injection_script(&details("/home/me/repo", Some("s3cret")));
+        assert!(script.contains("window.__OPENTUI__.port = 4096;"));
+        assert!(script.contains(r#"window.__OPENTUI__.serverUrl = "http://127.0.0.1:4096";"#));
+        assert!(script.contains(r#"window.__OPENTUI__.host = "127.0.0.1";"#));
+        assert!(script.contains(r#"window.__OPENTUI__.repoPath = "/home/me/repo";"#));
+        assert!(script.contains(r#"window.__OPENTUI__.secret = "s3cret";"#));
+        assert!(script.contains("window.__OPENTUI__.safeMode = false;"));
+        assert!(script.contains(r#"window.__OPENTUI__.theme = "dark";"#));
+        assert!(script.contains("window.__OPENTUI__.customTitlebar = true;"));
+        assert!(script.contains(
+            r#"window.__OPENTUI__.a11y = {"reducedMotion":false,"highContrast":false,"fontScale":1.0};"#
+        ));
+    }
+
+    #[test]
+    fn opens_at_the_remembered_size_when_enabled() {
+        let mut config = WindowSettings {
+            last_size: Some(WindowSize {
+                width: 1600.0,
+                height: 1000.0,
+            }),
+            ..WindowSettings::default()
+        };
+        assert_eq!(config.initial_size(), LogicalSize::new(1200.0, 800.0));
+        config.remember_last_size = true;
+        assert_eq!(config.initial_size(), LogicalSize::new(1600.0, 1000.0));
+        config.last_size = Some(WindowSize {
+            width: 300.0,
+            height: 200.0,
+        });
+        assert_eq!(config.initial_size(), LogicalSize::new(800.0, 600.0));
+    }
+
+    #[test]
+    fn escapes_the_repo_path() {
+        let script = injection_script(&details(r#"C:\repos\"quoted""#, None));
+        assert!(script.contains(r#"window.__OPENTUI__.repoPath = "C:\\repos\\\"quoted\"";"#));
+        assert!(script.contains("window.__OPENTUI__.secret = null;"));
+    }
+}
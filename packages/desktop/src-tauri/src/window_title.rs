@@ -0,0 +1,164 @@
+//! Native window titles of repository windows, `repo (branch) — opentui-git`
+//! with a marker while the working tree has changes. They are kept up to
+//! date from the repository watcher, so the taskbar, window switchers and
+//! Mission Control show the right context even before the page renders; the
+//! UI can pass on what the server knows through `set_title_info`.
+
+use git2::Repository;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::broadcast::{repo_key, WindowRegistry};
+use crate::error::CommandResult;
+use crate::{git, large_repo, native};
+
+const APP_NAME: &str = "opentui-git";
+/// Marker after the branch while the working tree has changes
+const DIRTY_MARKER: &str = "●";
+
+/// What a repository window's title shows besides the repository name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TitleInfo {
+    /// Branch HEAD is on, or the short commit when detached
+    branch: Option<String>,
+    dirty: bool,
+}
+
+/// Title details by repo key
+#[derive(Clone, Default)]
+pub struct TitleState(Arc<Mutex<HashMap<String, TitleInfo>>>);
+
+fn repo_name(repo_path: &str) -> String {
+    Path::new(repo_path).file_name().map_or_else(
+        || repo_path.to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+fn format_title(repo_path: &str, info: &TitleInfo) -> String {
+    let mut title = repo_name(repo_path);
+    if let Some(branch) = &info.branch {
+        title.push_str(&format!(" ({})", branch));
+    }
+    if info.dirty {
+        title.push(' ');
+        title.push_str(DIRTY_MARKER);
+    }
+    format!("{} — {}", title, APP_NAME)
+}
+
+/// Title of a window on a repository before anything is known about it
+pub fn initial(repo_path: &str) -> String {
+    format_title(repo_path, &TitleInfo::default())
+}
+
+fn read(repo_path: &str, check_dirty: bool) -> Result<TitleInfo, git2::Error> {
+    let repo = Repository::open(repo_path)?;
+    let branch = native::head_branch(&repo).or_else(|| {
+        let head = repo.head().ok()?.peel_to_commit().ok()?;
+        Some(head.id().to_string()[..7].to_string())
+    });
+    let dirty = check_dirty && !repo.is_bare() && native::is_dirty(&repo)?;
+    Ok(TitleInfo { branch, dirty })
+}
+
+/// Set the title of every window on a repository
+fn apply(app: &AppHandle, repo_path: &str, info: &TitleInfo) {
+    let title = format_title(repo_path, info);
+    for label in app.state::<WindowRegistry>().windows_for(repo_path) {
+        if let Some(window) = app.get_webview_window(&label) {
+            if let Err(e) = window.set_title(&title) {
+                log_error!("Failed to set the title of {}: {}", label, e);
+            }
+        }
+    }
+}
+
+/// Store a repository's title details and apply them if they changed
+fn update(app: &AppHandle, repo_path: &str, change: impl FnOnce(&mut TitleInfo)) {
+    let Ok(mut titles) = app.state::<TitleState>().0.lock() else {
+        return;
+    };
+    let info = titles.entry(repo_key(repo_path)).or_default();
+    let previous = info.clone();
+    change(info);
+    if *info != previous {
+        let info = info.clone();
+        drop(titles);
+        apply(app, repo_path, &info);
+    }
+}
+
+/// Read a repository's branch and whether it is dirty in the background, and
+/// retitle its windows. Called when HEAD, the index or the working tree
+/// changes. Large repositories aren't scanned for changes.
+pub fn refresh(app: &AppHandle, repo_path: &str) {
+    let check_dirty = !large_repo::is_large_repo(app, repo_path);
+    let (app, repo_path) = (app.clone(), repo_path.to_string());
+    tauri::async_runtime::spawn_blocking(move || match read(&repo_path, check_dirty) {
+        Ok(read) => update(&app, &repo_path, |info| *info = read),
+        Err(e) => log_error!("Failed to read title details of {}: {}", repo_path, e),
+    });
+}
+
+/// Title a new window on a repository with what is already known, then read
+/// the rest
+pub fn attach(app: &AppHandle, window: &WebviewWindow, repo_path: &str) {
+    let known = app
+        .state::<TitleState>()
+        .0
+        .lock()
+        .ok()
+        .and_then(|titles| titles.get(&repo_key(repo_path)).cloned());
+    if let Some(info) = known {
+        let _ = window.set_title(&format_title(repo_path, &info));
+    }
+    refresh(app, repo_path);
+}
+
+/// Update the title of the windows on the calling window's repository with
+/// what the server reports, e.g. after an operation the watcher can't see.
+/// Omitted fields keep their value.
+#[tauri::command]
+pub fn set_title_info(
+    app: AppHandle,
+    window: WebviewWindow,
+    branch: Option<String>,
+    dirty: Option<bool>,
+) -> CommandResult<()> {
+    let repo_path = git::repo_for_window(&app, &window)?;
+    update(&app, &repo_path, |info| {
+        if let Some(branch) = branch.filter(|branch| !branch.is_empty()) {
+            info.branch = Some(branch);
+        }
+        if let Some(dirty) = dirty {
+            info.dirty = dirty;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_titles() {
+        assert_eq!(initial("/work/opentui-git"), "opentui-git — opentui-git");
+        let info = TitleInfo {
+            branch: Some("main".to_string()),
+            dirty: false,
+        };
+        assert_eq!(format_title("/work/app", &info), "app (main) — opentui-git");
+        let info = TitleInfo {
+            dirty: true,
+            ..info
+        };
+        assert_eq!(
+            format_title("/work/app", &info),
+            "app (main) ● — opentui-git"
+        );
+    }
+}
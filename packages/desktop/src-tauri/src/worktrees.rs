@@ -0,0 +1,210 @@
+use git2::{Repository, WorktreeLockStatus};
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::error::{AppError, CommandResult, ErrorCode};
+use crate::git;
+use crate::native;
+use crate::timeout::{self, CommandClass};
+use crate::window::open_repo_in_window;
+
+/// Where a repository path lives on disk
+#[derive(Debug, Clone)]
+pub struct RepoLocation {
+    /// Top of the working tree containing the path, or the git directory of a
+    /// bare repository
+    pub workdir: String,
+    /// Working tree of the main repository when the path is in a linked worktree
+    pub main_repo: Option<String>,
+    /// A bare repository has no working tree to show status or diffs for
+    pub bare: bool,
+}
+
+/// One working tree of a repository
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeInfo {
+    path: String,
+    /// Name git knows a linked worktree by; `None` for the main working tree
+    name: Option<String>,
+    /// Branch checked out, `None` when detached
+    branch: Option<String>,
+    is_main: bool,
+    is_current: bool,
+    locked: bool,
+    /// Directory is gone and `git worktree prune` would remove it
+    prunable: bool,
+}
+
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .trim_end_matches(['/', '\\'])
+        .to_string()
+}
+
+/// Repository owning the common git directory, i.e. the main working tree
+fn main_repository(repo: &Repository) -> Result<Repository, git2::Error> {
+    if repo.is_worktree() {
+        Repository::open(repo.commondir())
+    } else {
+        Repository::open(repo.path())
+    }
+}
+
+/// Resolve the working tree a path belongs to, following a linked worktree's
+/// `.git` file to its main repository. `None` if the path is not in a repository.
+pub fn locate(path: &str) -> Option<RepoLocation> {
+    let repo = Repository::discover(path).ok()?;
+    if repo.is_bare() {
+        return Some(RepoLocation {
+            workdir: display_path(repo.path()),
+            main_repo: None,
+            bare: true,
+        });
+    }
+    let workdir = display_path(repo.workdir()?);
+    let main_repo = if repo.is_worktree() {
+        let main = main_repository(&repo).ok()?;
+        Some(display_path(main.workdir().unwrap_or_else(|| main.path())))
+    } else {
+        None
+    };
+    Some(RepoLocation {
+        workdir,
+        main_repo,
+        bare: false,
+    })
+}
+
+/// Whether a path is inside a bare repository
+pub fn is_bare(path: &str) -> bool {
+    locate(path).is_some_and(|location| location.bare)
+}
+
+/// Error for opening a bare repository, which the UI answers by offering
+/// to add a worktree
+pub fn bare_repo_error(repo_path: &str) -> AppError {
+    AppError::new(
+        ErrorCode::Conflict,
+        format!(
+            "{} is a bare repository; add a worktree to open it",
+            repo_path
+        ),
+    )
+    .with_metadata(serde_json::json!({ "repoPath": repo_path, "isBare": true }))
+}
+
+fn branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    head.is_branch()
+        .then(|| head.shorthand().map(str::to_string))
+        .flatten()
+}
+
+fn list(repo: &Repository) -> Result<Vec<WorktreeInfo>, git2::Error> {
+    let current = repo.workdir().map(display_path);
+    let main = main_repository(repo)?;
+    let mut worktrees = Vec::new();
+
+    if let Some(workdir) = main.workdir() {
+        let path = display_path(workdir);
+        worktrees.push(WorktreeInfo {
+            is_current: current.as_deref() == Some(path.as_str()),
+            path,
+            name: None,
+            branch: branch(&main),
+            is_main: true,
+            locked: false,
+            prunable: false,
+        });
+    }
+
+    for name in main.worktrees()?.iter().flatten() {
+        let worktree = main.find_worktree(name)?;
+        let path = display_path(worktree.path());
+        let prunable = worktree.validate().is_err();
+        worktrees.push(WorktreeInfo {
+            is_current: current.as_deref() == Some(path.as_str()),
+            branch: if prunable {
+                None
+            } else {
+                Repository::open_from_worktree(&worktree)
+                    .ok()
+                    .and_then(|repo| branch(&repo))
+            },
+            path,
+            name: Some(name.to_string()),
+            is_main: false,
+            locked: matches!(worktree.is_locked(), Ok(WorktreeLockStatus::Locked(_))),
+            prunable,
+        });
+    }
+    Ok(worktrees)
+}
+
+/// List the main working tree and linked worktrees of the window's repository,
+/// or of `repo_path`
+#[tauri::command]
+pub async fn list_worktrees(
+    app: AppHandle,
+    window: WebviewWindow,
+    repo_path: Option<String>,
+) -> CommandResult<Vec<WorktreeInfo>> {
+    native::with_repo(&app, &window, repo_path, list).await
+}
+
+/// Add a worktree to a repository, typically a bare one, and open it in a new
+/// window. Checks out `branch`, creating it if it does not exist yet.
+#[tauri::command]
+pub async fn add_worktree(
+    app: AppHandle,
+    repo_path: String,
+    path: String,
+    branch: Option<String>,
+    request_id: Option<String>,
+) -> CommandResult<()> {
+    if path.trim().is_empty() || path.starts_with('-') {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("Invalid worktree path: {:?}", path),
+        ));
+    }
+
+    // Relative paths are relative to the repository, as for git itself
+    let path = Path::new(&repo_path)
+        .join(&path)
+        .to_string_lossy()
+        .to_string();
+
+    timeout::run(&app, CommandClass::Local, request_id, |_| async {
+        let mut args = vec!["worktree", "add"];
+        if let Some(branch) = &branch {
+            git::validate_rev(branch)?;
+            let exists = git::run(
+                &repo_path,
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "--quiet",
+                    &format!("refs/heads/{}", branch),
+                ],
+            )
+            .await
+            .is_ok();
+            if exists {
+                args.extend(["--", path.as_str(), branch.as_str()]);
+            } else {
+                args.extend(["-b", branch.as_str(), "--", path.as_str()]);
+            }
+        } else {
+            args.extend(["--", path.as_str()]);
+        }
+        git::run(&repo_path, &args).await?;
+
+        log_info!("Added worktree {} to {}", path, repo_path);
+        open_repo_in_window(&app, &path).await?;
+        Ok(())
+    })
+    .await
+}
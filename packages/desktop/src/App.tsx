@@ -1,5 +1,6 @@
-import { createSignal, createResource, Show, onMount, onCleanup } from "solid-js";
-import { createClient, type GitClient } from "@opentui-git/sdk";
+import { createSignal, createResource, For, Show, onMount, onCleanup } from "solid-js";
+import { invoke } from "@tauri-apps/api/core";
+import { createClient, pairDevice, type GitClient } from "@opentui-git/sdk";
 import type { DiffMode } from "@opentui-git/core/git/types";
 import { Header } from "./components/Header.js";
 import { FileTree } from "./components/FileTree.js";
@@ -15,6 +16,10 @@ import { useGitStatus, useGitDiff } from "./hooks/index.js";
  */
 function App() {
   const [serverUrl, setServerUrl] = createSignal("http://localhost:5050");
+  // Secret the server requires, injected by Tauri or given as ?secret= in a browser
+  const [secret, setSecret] = createSignal<string | undefined>(
+    new URLSearchParams(window.location.search).get("secret") ?? undefined
+  );
   const [connected, setConnected] = createSignal(false);
   const [error, setError] = createSignal<string | null>(null);
   const [repoPath, setRepoPath] = createSignal("");
@@ -25,20 +30,33 @@ function App() {
   const [diffMode, setDiffMode] = createSignal<DiffMode>("unstaged");
   const [compareBranch, setCompareBranch] = createSignal<string | null>(null);
 
+  // Companion app on a phone or tablet, connecting to a server elsewhere
+  const mobile = !!(window as Window).__OPENTUI__?.mobile;
+  const [knownServers, { refetch: refetchServers }] = createResource(
+    async () => mobile ? invoke<KnownServer[]>("list_servers") : []
+  );
+
   // Create client when connected
-  const client = (): GitClient | null => connected() ? createClient(serverUrl()) : null;
+  const client = (): GitClient | null => connected() ? createClient(serverUrl(), { secret: secret() }) : null;
 
   // Auto-connect on mount using injected port from Tauri
   onMount(async () => {
     const tauriConfig = (window as Window).__OPENTUI__;
-    if (tauriConfig?.port) {
-      const url = `http://127.0.0.1:${tauriConfig.port}`;
-      console.log("[app] Using Tauri-injected port:", tauriConfig.port);
+    if (tauriConfig?.port || tauriConfig?.serverUrl) {
+      // Older shells inject only the port
+      const url = tauriConfig.serverUrl ?? `http://127.0.0.1:${tauriConfig.port}`;
+      console.log("[app] Using Tauri-injected server:", url);
       setServerUrl(url);
+    } else if (mobile) {
+      // Nothing to connect to until the user picks a server
+      return;
     }
     if (tauriConfig?.repoPath) {
       setRepoPath(tauriConfig.repoPath);
     }
+    if (tauriConfig?.secret) {
+      setSecret(tauriConfig.secret);
+    }
     
     // Auto-connect after a brief delay to let server start
     setTimeout(async () => {
@@ -48,10 +66,19 @@ function App() {
 
   const handleConnect = async () => {
     try {
-      const testClient = createClient(serverUrl());
+      // A device opened with a pairing code trades it for a token once
+      const pairCode = new URLSearchParams(window.location.search).get("pair");
+      if (pairCode && !secret()) {
+        setSecret(await pairDevice(serverUrl(), pairCode, navigator.userAgent));
+      }
+      const testClient = createClient(serverUrl(), { secret: secret() });
       await testClient.health();
       setConnected(true);
       setError(null);
+      if (mobile) {
+        await invoke("save_server", { url: serverUrl(), token: secret() ?? null });
+        refetchServers();
+      }
       
       // Initialize compare branch to default branch
       try {
@@ -68,7 +95,22 @@ function App() {
   return (
     <Show
       when={connected() && client()}
-      fallback={<ConnectPanel serverUrl={serverUrl} setServerUrl={setServerUrl} onConnect={handleConnect} error={error} />}
+      fallback={
+        <ConnectPanel
+          serverUrl={serverUrl}
+          setServerUrl={setServerUrl}
+          onConnect={handleConnect}
+          error={error}
+          mobile={mobile}
+          secret={secret}
+          setSecret={setSecret}
+          knownServers={() => knownServers() ?? []}
+          onForget={async (url) => {
+            await invoke("forget_server", { url });
+            refetchServers();
+          }}
+        />
+      }
     >
       {(c) => (
         <AppContent 
@@ -340,11 +382,25 @@ function AppContent(props: AppContentProps) {
   );
 }
 
+/**
+ * A server the mobile app has connected to before
+ */
+interface KnownServer {
+  url: string;
+  token: string | null;
+  lastUsed: number;
+}
+
 interface ConnectPanelProps {
   serverUrl: () => string;
   setServerUrl: (url: string) => void;
   onConnect: () => void;
   error: () => string | null;
+  mobile: boolean;
+  secret: () => string | undefined;
+  setSecret: (secret: string | undefined) => void;
+  knownServers: () => KnownServer[];
+  onForget: (url: string) => void;
 }
 
 function ConnectPanel(props: ConnectPanelProps) {
@@ -358,7 +414,37 @@ function ConnectPanel(props: ConnectPanelProps) {
         </div>
       </Show>
 
-      <p class="text-app-text-muted mb-6">Connecting to server...</p>
+      <p class="text-app-text-muted mb-6">
+        {props.mobile ? "Connect to a server running on your computer" : "Connecting to server..."}
+      </p>
+
+      <Show when={props.mobile && props.knownServers().length > 0}>
+        <div class="flex flex-col gap-2 mb-6 w-[300px]">
+          <For each={props.knownServers()}>
+            {(server) => (
+              <div class="flex gap-2">
+                <button
+                  onClick={() => {
+                    props.setServerUrl(server.url);
+                    props.setSecret(server.token ?? undefined);
+                    props.onConnect();
+                  }}
+                  class="flex-1 px-4 py-2 rounded-lg border border-app-border bg-app-surface text-app-text text-left truncate hover:border-app-accent"
+                >
+                  {server.url}
+                </button>
+                <button
+                  onClick={() => props.onForget(server.url)}
+                  class="px-3 py-2 rounded-lg text-app-text-muted hover:text-red-400"
+                  title="Forget server"
+                >
+                  ×
+                </button>
+              </div>
+            )}
+          </For>
+        </div>
+      </Show>
       
       <div class="flex gap-3">
         <input
@@ -368,6 +454,15 @@ function ConnectPanel(props: ConnectPanelProps) {
           class="px-4 py-2 rounded-lg border border-app-border bg-app-surface text-app-text w-[300px] focus:outline-none focus:border-app-accent"
           placeholder="Server URL"
         />
+        <Show when={props.mobile}>
+          <input
+            type="password"
+            value={props.secret() ?? ""}
+            onInput={(e) => props.setSecret(e.currentTarget.value || undefined)}
+            class="px-4 py-2 rounded-lg border border-app-border bg-app-surface text-app-text w-[160px] focus:outline-none focus:border-app-accent"
+            placeholder="Token"
+          />
+        </Show>
         <button
           onClick={props.onConnect}
           class="px-5 py-2 rounded-lg bg-app-accent text-white font-medium hover:bg-app-accent/80 transition-colors"
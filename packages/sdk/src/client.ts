@@ -72,46 +72,73 @@ async function fetchJson<T>(url: string, options?: RequestInit): Promise<T> {
   return response.json() as Promise<T>;
 }
 
+/**
+ * Header carrying the secret the desktop shell gives its server
+ */
+export const SECRET_HEADER = "X-OpenTUI-Secret";
+
+export interface ClientOptions {
+  /**
+   * Secret the server requires on every request, if any
+   */
+  secret?: string;
+}
+
+/**
+ * Redeem a pairing code shown by the desktop app for a device token,
+ * to be used as the client's secret from then on
+ */
+export async function pairDevice(baseUrl: string, code: string, name: string) {
+  const data = await fetchJson<{ token: string }>(`${baseUrl}/pair`, {
+    method: "POST",
+    body: JSON.stringify({ code, name }),
+  });
+  return data.token;
+}
+
 /**
  * Create a type-safe API client for opentui-git server
  */
-export function createClient(baseUrl: string = "http://localhost:5050") {
+export function createClient(baseUrl: string = "http://localhost:5050", options: ClientOptions = {}) {
   const url = (path: string) => `${baseUrl}${path}`;
+  const authHeaders: Record<string, string> = options.secret ? { [SECRET_HEADER]: options.secret } : {};
+  const send = <T>(target: string, init?: RequestInit) =>
+    fetchJson<T>(target, { ...init, headers: { ...authHeaders, ...init?.headers } });
   
   return {
     /**
      * Health check
      */
     health: async () => {
-      return fetchJson<{ status: "ok" }>(url("/health"));
+      return send<{ status: "ok" }>(url("/health"));
     },
 
     /**
      * Get repository status
      */
     getStatus: async () => {
-      return fetchJson<GitStatusSummary>(url("/status"));
+      return send<GitStatusSummary>(url("/status"));
     },
 
     /**
      * Check if current directory is a git repository
      */
     getRepoInfo: async () => {
-      return fetchJson<{ isRepo: boolean; repoRoot: string | null }>(url("/status/repo"));
+      return send<{ isRepo: boolean; repoRoot: string | null }>(url("/status/repo"));
     },
 
     /**
      * Get all branches
      */
     getBranches: async () => {
-      return fetchJson<GitBranchInfo>(url("/branches"));
+      return send<GitBranchInfo>(url("/branches"));
     },
 
     /**
      * Get default branch name
      */
     getDefaultBranch: async () => {
-      const data = await fetchJson<{ defaultBranch: string }>(url("/branches/default"));
+      const data = await send<{ defaultBranch: string }>(url("/branches/default"));
       return data.defaultBranch;
     },
 
@@ -119,7 +146,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Create a new branch
      */
     createBranch: async (name: string) => {
-      return fetchJson<{ success: true; branch: string }>(url("/branches"), {
+      return send<{ success: true; branch: string }>(url("/branches"), {
         method: "POST",
         body: JSON.stringify({ name }),
       });
@@ -129,7 +156,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Checkout a branch
      */
     checkoutBranch: async (name: string) => {
-      return fetchJson<{ success: true }>(url(`/branches/${encodeURIComponent(name)}/checkout`), {
+      return send<{ success: true }>(url(`/branches/${encodeURIComponent(name)}/checkout`), {
         method: "POST",
       });
     },
@@ -138,7 +165,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Merge a branch into current branch
      */
     mergeBranch: async (name: string) => {
-      return fetchJson<{ success: true; result: MergeResult }>(url(`/branches/${encodeURIComponent(name)}/merge`), {
+      return send<{ success: true; result: MergeResult }>(url(`/branches/${encodeURIComponent(name)}/merge`), {
         method: "POST",
       });
     },
@@ -147,7 +174,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Delete a branch
      */
     deleteBranch: async (name: string, force: boolean = false) => {
-      return fetchJson<{ success: true }>(url(`/branches/${encodeURIComponent(name)}?force=${force}`), {
+      return send<{ success: true }>(url(`/branches/${encodeURIComponent(name)}?force=${force}`), {
         method: "DELETE",
       });
     },
@@ -156,7 +183,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Get all tags
      */
     getTags: async () => {
-      const data = await fetchJson<{ tags: string[] }>(url("/tags"));
+      const data = await send<{ tags: string[] }>(url("/tags"));
       return data.tags;
     },
 
@@ -164,7 +191,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Create a new tag
      */
     createTag: async (name: string) => {
-      return fetchJson<{ success: true; tag: string }>(url("/tags"), {
+      return send<{ success: true; tag: string }>(url("/tags"), {
         method: "POST",
         body: JSON.stringify({ name }),
       });
@@ -174,7 +201,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Push a tag to remote
      */
     pushTag: async (name: string, remote: string = "origin") => {
-      return fetchJson<{ success: true }>(url(`/tags/${encodeURIComponent(name)}/push`), {
+      return send<{ success: true }>(url(`/tags/${encodeURIComponent(name)}/push`), {
         method: "POST",
         body: JSON.stringify({ remote }),
       });
@@ -184,7 +211,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Stage a file
      */
     stageFile: async (path: string) => {
-      return fetchJson<{ success: true }>(url("/files/stage"), {
+      return send<{ success: true }>(url("/files/stage"), {
         method: "POST",
         body: JSON.stringify({ path }),
       });
@@ -194,7 +221,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Unstage a file
      */
     unstageFile: async (path: string) => {
-      return fetchJson<{ success: true }>(url("/files/unstage"), {
+      return send<{ success: true }>(url("/files/unstage"), {
         method: "POST",
         body: JSON.stringify({ path }),
       });
@@ -204,7 +231,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Stage all files
      */
     stageAll: async () => {
-      return fetchJson<{ success: true }>(url("/files/stage-all"), {
+      return send<{ success: true }>(url("/files/stage-all"), {
         method: "POST",
       });
     },
@@ -213,7 +240,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Unstage all files
      */
     unstageAll: async () => {
-      return fetchJson<{ success: true }>(url("/files/unstage-all"), {
+      return send<{ success: true }>(url("/files/unstage-all"), {
         method: "POST",
       });
     },
@@ -222,14 +249,14 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Read a file with metadata
      */
     readFile: async (path: string) => {
-      return fetchJson<FileReadResponse>(url(`/files/read?path=${encodeURIComponent(path)}`));
+      return send<FileReadResponse>(url(`/files/read?path=${encodeURIComponent(path)}`));
     },
 
     /**
      * Write a file with conflict detection
      */
     writeFile: async (path: string, content: string, expectedMtime?: Date) => {
-      return fetchJson<FileWriteResponse>(url("/files/write"), {
+      return send<FileWriteResponse>(url("/files/write"), {
         method: "POST",
         body: JSON.stringify({
           path,
@@ -243,7 +270,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Get commit history
      */
     getCommits: async (limit: number = 50) => {
-      const data = await fetchJson<{ commits: GitCommitInfo[] }>(url(`/commits?limit=${limit}`));
+      const data = await send<{ commits: GitCommitInfo[] }>(url(`/commits?limit=${limit}`));
       return data.commits;
     },
 
@@ -251,7 +278,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Get current commit hash
      */
     getCurrentCommitHash: async () => {
-      const data = await fetchJson<{ hash: string }>(url("/commits/current"));
+      const data = await send<{ hash: string }>(url("/commits/current"));
       return data.hash;
     },
 
@@ -259,7 +286,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Create a commit
      */
     commit: async (message: string) => {
-      const data = await fetchJson<{ success: true; hash: string }>(url("/commits"), {
+      const data = await send<{ success: true; hash: string }>(url("/commits"), {
         method: "POST",
         body: JSON.stringify({ message }),
       });
@@ -276,7 +303,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
       } else if (options.staged !== undefined) {
         params.set("staged", options.staged.toString());
       }
-      const data = await fetchJson<{ diff: string }>(url(`/diff?${params}`));
+      const data = await send<{ diff: string }>(url(`/diff?${params}`));
       return data.diff;
     },
 
@@ -284,7 +311,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Get files changed against a branch
      */
     getFilesChangedAgainstBranch: async (branch: string) => {
-      const data = await fetchJson<{ files: GitFileStatus[] }>(url(`/diff/files?branch=${encodeURIComponent(branch)}`));
+      const data = await send<{ files: GitFileStatus[] }>(url(`/diff/files?branch=${encodeURIComponent(branch)}`));
       return data.files;
     },
 
@@ -292,7 +319,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Pull from remote
      */
     pull: async () => {
-      return fetchJson<{ success: true }>(url("/remote/pull"), {
+      return send<{ success: true }>(url("/remote/pull"), {
         method: "POST",
       });
     },
@@ -301,7 +328,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Push to remote
      */
     push: async () => {
-      return fetchJson<{ success: true }>(url("/remote/push"), {
+      return send<{ success: true }>(url("/remote/push"), {
         method: "POST",
       });
     },
@@ -311,7 +338,7 @@ export function createClient(baseUrl: string = "http://localhost:5050") {
      * Returns a fetch response for SSE stream
      */
     subscribe: async () => {
-      return fetch(`${baseUrl}/events`);
+      return fetch(`${baseUrl}/events`, { headers: authHeaders });
     },
   };
 }
@@ -5,6 +5,9 @@
 
 export { 
   createClient, 
+  pairDevice,
+  SECRET_HEADER,
+  type ClientOptions,
   type GitClient,
   type FileReadResponse,
   type FileWriteResponse,